@@ -4,6 +4,7 @@ use ethers::contract::Contract;
 use ethers::prelude::{AbiError, Address, ContractError, Http, Middleware, Provider};
 use ethers::providers::ProviderError;
 use ethers::types::{I256, U256};
+use serde::{Deserialize, Serialize};
 use starknet_api::block::{BlockHash, BlockNumber};
 use starknet_api::hash::StarkHash;
 use starknet_api::StarknetApiError;
@@ -29,6 +30,7 @@ pub enum EthereumBaseLayerError {
     StarknetApi(#[from] StarknetApiError),
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct EthereumBaseLayerConfig {
     // TODO(yair): consider using types.
     pub node_url: String,
@@ -0,0 +1,342 @@
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use indexmap::IndexMap;
+use papyrus_storage::body::BodyStorageWriter;
+use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_storage::state::StateStorageWriter;
+use papyrus_storage::test_utils::get_test_storage;
+use papyrus_storage::{StorageReader, StorageWriter};
+use starknet_api::block::{BlockBody, BlockHeader, BlockNumber, GasPrice};
+use starknet_api::core::{
+    ChainId, ClassHash, ContractAddress, EntryPointSelector, Nonce, PatriciaKey,
+};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{ContractClass, EntryPointType, StateDiff, StateNumber, StorageKey};
+use starknet_api::transaction::{
+    Calldata, DeployTransaction, DeployTransactionOutput, Fee, InvokeTransaction,
+    L1HandlerTransaction, L1HandlerTransactionOutput, Transaction, TransactionHash,
+    TransactionOffsetInBlock, TransactionOutput, TransactionSignature, TransactionVersion,
+};
+use starknet_api::{patricia_key, stark_felt};
+use test_utils::read_json_file;
+
+use crate::objects::CallType;
+use crate::{
+    estimate_fee, execute_call, trace_block_transactions, ExecutionConfig, ExecutionError,
+};
+
+// The selector of the get_value entry point of the test contract, starknet_keccak("get_value").
+const GET_VALUE_SELECTOR: &str =
+    "0x26813d396fdb198e9ead934e4f7a592a8b88a059e45ab0eb6ee53494e8d45b0";
+// The selector of the deposit L1 handler of the test contract, starknet_keccak("deposit").
+const DEPOSIT_SELECTOR: &str = "0xc73f681176fc7b3f9693986fd7b14581e8d540519e27400e88b8713932be01";
+
+// Returns a storage with a single block, which declares the test contract class and deploys it at
+// the address with the value in the storage key.
+fn prepare_storage(
+    contract_address: ContractAddress,
+    key: StorageKey,
+    value: StarkFelt,
+) -> (StorageReader, StorageWriter) {
+    let (storage_reader, mut storage_writer) = get_test_storage();
+    let class_hash = ClassHash(stark_felt!("0x1"));
+    let class: ContractClass =
+        serde_json::from_value(read_json_file("test_contract.json")).unwrap();
+    let state_diff = StateDiff {
+        deployed_contracts: IndexMap::from([(contract_address, class_hash)]),
+        storage_diffs: IndexMap::from([(contract_address, IndexMap::from([(key, value)]))]),
+        declared_classes: IndexMap::from([(class_hash, class)]),
+        nonces: IndexMap::new(),
+    };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &BlockHeader::default())
+        .unwrap()
+        .append_state_diff(BlockNumber(0), state_diff, IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+    (storage_reader, storage_writer)
+}
+
+#[test]
+fn execute_call_reads_storage() {
+    let contract_address = ContractAddress(patricia_key!("0x100"));
+    let key = StorageKey(patricia_key!("0x10"));
+    let (storage_reader, _) = prepare_storage(contract_address, key, stark_felt!("0x1234"));
+    let txn = storage_reader.begin_ro_txn().unwrap();
+
+    let retdata = execute_call(
+        &txn,
+        &ChainId("SN_GOERLI".to_owned()),
+        &ExecutionConfig::default(),
+        StateNumber::right_after_block(BlockNumber(0)),
+        contract_address,
+        EntryPointSelector(stark_felt!(GET_VALUE_SELECTOR)),
+        Calldata(Arc::new(vec![*key.0.key()])),
+    )
+    .unwrap();
+    assert_eq!(retdata, vec![stark_felt!("0x1234")]);
+
+    // Before the block, the contract isn't deployed yet.
+    let err = execute_call(
+        &txn,
+        &ChainId("SN_GOERLI".to_owned()),
+        &ExecutionConfig::default(),
+        StateNumber::right_before_block(BlockNumber(0)),
+        contract_address,
+        EntryPointSelector(stark_felt!(GET_VALUE_SELECTOR)),
+        Calldata(Arc::new(vec![*key.0.key()])),
+    )
+    .unwrap_err();
+    assert_matches!(err, ExecutionError::ContractNotFound { .. });
+}
+
+#[test]
+fn execute_call_unknown_entry_point() {
+    let contract_address = ContractAddress(patricia_key!("0x100"));
+    let (storage_reader, _) =
+        prepare_storage(contract_address, StorageKey(patricia_key!("0x10")), stark_felt!("0x1"));
+    let txn = storage_reader.begin_ro_txn().unwrap();
+
+    let err = execute_call(
+        &txn,
+        &ChainId("SN_GOERLI".to_owned()),
+        &ExecutionConfig::default(),
+        StateNumber::right_after_block(BlockNumber(0)),
+        contract_address,
+        EntryPointSelector(stark_felt!("0x1")),
+        Calldata::default(),
+    )
+    .unwrap_err();
+    assert_matches!(err, ExecutionError::ExecutionFailed(_));
+}
+
+#[test]
+fn estimate_fee_of_unknown_sender() {
+    let (storage_reader, _) = prepare_storage(
+        ContractAddress(patricia_key!("0x100")),
+        StorageKey(patricia_key!("0x10")),
+        stark_felt!("0x1"),
+    );
+    let txn = storage_reader.begin_ro_txn().unwrap();
+
+    let sender_address = ContractAddress(patricia_key!("0x101"));
+    let tx = InvokeTransaction {
+        transaction_hash: TransactionHash::default(),
+        max_fee: Fee(100),
+        version: TransactionVersion(stark_felt!("0x1")),
+        signature: TransactionSignature::default(),
+        nonce: Nonce::default(),
+        sender_address,
+        entry_point_selector: None,
+        calldata: Calldata::default(),
+    };
+    let err = estimate_fee(
+        &txn,
+        &ChainId("SN_GOERLI".to_owned()),
+        &ExecutionConfig::default(),
+        StateNumber::right_after_block(BlockNumber(0)),
+        tx,
+    )
+    .unwrap_err();
+    assert_matches!(
+        err,
+        ExecutionError::ContractNotFound { contract_address, .. }
+            if contract_address == sender_address
+    );
+}
+
+#[test]
+fn estimate_fee_of_invoke() {
+    // The __validate__ and __execute__ entry points of the account return without checking
+    // anything, so any invoke transaction of it is valid.
+    let (storage_reader, mut storage_writer) = get_test_storage();
+    let class_hash = ClassHash(stark_felt!("0x2"));
+    let class: ContractClass =
+        serde_json::from_value(read_json_file("account_without_validations.json")).unwrap();
+    let account_address = ContractAddress(patricia_key!("0x200"));
+    let state_diff = StateDiff {
+        deployed_contracts: IndexMap::from([(account_address, class_hash)]),
+        storage_diffs: IndexMap::new(),
+        declared_classes: IndexMap::from([(class_hash, class)]),
+        nonces: IndexMap::new(),
+    };
+    let gas_price = GasPrice(100);
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &BlockHeader { gas_price, ..BlockHeader::default() })
+        .unwrap()
+        .append_state_diff(BlockNumber(0), state_diff, IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let txn = storage_reader.begin_ro_txn().unwrap();
+
+    let tx = InvokeTransaction {
+        transaction_hash: TransactionHash::default(),
+        max_fee: Fee(1000000),
+        version: TransactionVersion(stark_felt!("0x1")),
+        signature: TransactionSignature::default(),
+        nonce: Nonce::default(),
+        sender_address: account_address,
+        entry_point_selector: None,
+        calldata: Calldata::default(),
+    };
+    let fee_estimation = estimate_fee(
+        &txn,
+        &ChainId("SN_GOERLI".to_owned()),
+        &ExecutionConfig::default(),
+        StateNumber::right_after_block(BlockNumber(0)),
+        tx,
+    )
+    .unwrap();
+    // The fee is estimated with the gas price of the latest block.
+    assert_eq!(fee_estimation.gas_price, gas_price);
+    assert!(fee_estimation.gas_consumed > 0);
+    assert_eq!(fee_estimation.overall_fee, Fee(fee_estimation.gas_consumed * gas_price.0));
+}
+
+// Appends a block after the one of the prepared storage, with the transactions and no state diff.
+fn append_block(
+    storage_writer: &mut StorageWriter,
+    transactions: Vec<Transaction>,
+    transaction_outputs: Vec<TransactionOutput>,
+) {
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(1), &BlockHeader::default())
+        .unwrap()
+        .append_body(BlockNumber(1), BlockBody { transactions, transaction_outputs })
+        .unwrap()
+        .append_state_diff(BlockNumber(1), StateDiff::default(), IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+}
+
+#[test]
+fn trace_l1_handler_transaction() {
+    let contract_address = ContractAddress(patricia_key!("0x100"));
+    let (storage_reader, mut storage_writer) =
+        prepare_storage(contract_address, StorageKey(patricia_key!("0x10")), stark_felt!("0x1"));
+    let calldata = Calldata(Arc::new(vec![stark_felt!("0x200"), stark_felt!("0x7")]));
+    let tx = L1HandlerTransaction {
+        transaction_hash: TransactionHash(stark_felt!("0x1")),
+        version: TransactionVersion::default(),
+        nonce: Nonce::default(),
+        contract_address,
+        entry_point_selector: EntryPointSelector(stark_felt!(DEPOSIT_SELECTOR)),
+        calldata: calldata.clone(),
+    };
+    append_block(
+        &mut storage_writer,
+        vec![Transaction::L1Handler(tx)],
+        vec![TransactionOutput::L1Handler(L1HandlerTransactionOutput::default())],
+    );
+    let txn = storage_reader.begin_ro_txn().unwrap();
+
+    let traces = trace_block_transactions(
+        &txn,
+        &ChainId("SN_GOERLI".to_owned()),
+        &ExecutionConfig::default(),
+        BlockNumber(1),
+        None,
+    )
+    .unwrap();
+    assert_eq!(traces.len(), 1);
+    let trace = &traces[0];
+    assert_eq!(trace.transaction_hash, TransactionHash(stark_felt!("0x1")));
+    // An L1 handler isn't validated and doesn't pay a fee on Starknet.
+    assert!(trace.validate_invocation.is_none());
+    assert!(trace.fee_transfer_invocation.is_none());
+    let invocation = trace.execute_invocation.as_ref().unwrap();
+    assert_eq!(invocation.contract_address, contract_address);
+    assert_eq!(invocation.entry_point_type, EntryPointType::L1Handler);
+    assert_eq!(invocation.call_type, CallType::Call);
+    assert_eq!(invocation.calldata, calldata);
+}
+
+#[test]
+fn trace_block_with_failed_transactions() {
+    let contract_address = ContractAddress(patricia_key!("0x100"));
+    let (storage_reader, mut storage_writer) =
+        prepare_storage(contract_address, StorageKey(patricia_key!("0x10")), stark_felt!("0x1"));
+    let deployed_address = ContractAddress(patricia_key!("0x101"));
+    let deploy_tx = DeployTransaction {
+        transaction_hash: TransactionHash(stark_felt!("0x1")),
+        class_hash: ClassHash(stark_felt!("0x1")),
+        contract_address: deployed_address,
+        ..DeployTransaction::default()
+    };
+    let l1_handler_tx = |tx_hash, entry_point_selector| {
+        Transaction::L1Handler(L1HandlerTransaction {
+            transaction_hash: TransactionHash(stark_felt!(tx_hash)),
+            version: TransactionVersion::default(),
+            nonce: Nonce::default(),
+            contract_address: deployed_address,
+            entry_point_selector: EntryPointSelector(stark_felt!(entry_point_selector)),
+            calldata: Calldata(Arc::new(vec![stark_felt!("0x200"), stark_felt!("0x7")])),
+        })
+    };
+    append_block(
+        &mut storage_writer,
+        vec![
+            Transaction::Deploy(deploy_tx),
+            l1_handler_tx("0x2", "0x1"),
+            l1_handler_tx("0x3", DEPOSIT_SELECTOR),
+        ],
+        vec![
+            TransactionOutput::Deploy(DeployTransactionOutput::default()),
+            TransactionOutput::L1Handler(L1HandlerTransactionOutput::default()),
+            TransactionOutput::L1Handler(L1HandlerTransactionOutput::default()),
+        ],
+    );
+    let txn = storage_reader.begin_ro_txn().unwrap();
+
+    let traces = trace_block_transactions(
+        &txn,
+        &ChainId("SN_GOERLI".to_owned()),
+        &ExecutionConfig::default(),
+        BlockNumber(1),
+        None,
+    )
+    .unwrap();
+    assert_eq!(traces.len(), 3);
+
+    // The deploy transaction isn't executed, but the contract is deployed for the transactions
+    // after it.
+    assert_eq!(traces[0].transaction_hash, TransactionHash(stark_felt!("0x1")));
+    assert!(traces[0].failure_reason.is_some());
+    assert!(traces[0].execute_invocation.is_none());
+    assert_eq!(
+        traces[0].state_diff.deployed_contracts,
+        IndexMap::from([(deployed_address, ClassHash(stark_felt!("0x1")))])
+    );
+
+    // The call of an unknown entry point fails without failing the block.
+    assert_eq!(traces[1].transaction_hash, TransactionHash(stark_felt!("0x2")));
+    assert!(traces[1].failure_reason.is_some());
+    assert!(traces[1].execute_invocation.is_none());
+
+    assert_eq!(traces[2].transaction_hash, TransactionHash(stark_felt!("0x3")));
+    assert!(traces[2].failure_reason.is_none());
+    assert_eq!(traces[2].execute_invocation.as_ref().unwrap().contract_address, deployed_address);
+
+    // Tracing up to the failed transaction returns its trace last.
+    let traces = trace_block_transactions(
+        &txn,
+        &ChainId("SN_GOERLI".to_owned()),
+        &ExecutionConfig::default(),
+        BlockNumber(1),
+        Some(TransactionOffsetInBlock(1)),
+    )
+    .unwrap();
+    assert_eq!(traces.len(), 2);
+    assert_eq!(traces[1].transaction_hash, TransactionHash(stark_felt!("0x2")));
+    assert!(traces[1].failure_reason.is_some());
+}
@@ -0,0 +1,316 @@
+//! Execution of Cairo 0 contract classes against the state of the storage, for serving calls, fee
+//! estimations and transaction traces without a sequencer.
+//!
+//! The classes are executed by the blockifier over a [`ExecutionStateReader`], which reads the
+//! state at a given state number. Changes made by the execution are kept in a cache on top of the
+//! storage and are discarded when the execution ends.
+
+#[cfg(test)]
+#[path = "execution_test.rs"]
+mod execution_test;
+pub mod objects;
+mod state_reader;
+
+use std::collections::HashMap;
+
+use blockifier::block_context::BlockContext;
+use blockifier::execution::entry_point::{CallEntryPoint, CallType};
+use blockifier::fee::fee_utils::calculate_tx_fee;
+use blockifier::state::cached_state::{CachedState, MutRefState};
+use blockifier::state::state_api::State;
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::objects::AccountTransactionContext;
+use blockifier::transaction::transaction_execution::Transaction as BlockifierTransaction;
+use blockifier::transaction::transactions::ExecutableTransaction;
+use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
+use papyrus_storage::body::BodyStorageReader;
+use papyrus_storage::db::RO;
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::state::data::ThinStateDiff;
+use papyrus_storage::state::StateStorageReader;
+use papyrus_storage::{StorageError, StorageTxn};
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{BlockHeader, BlockNumber, GasPrice};
+use starknet_api::core::{ChainId, ClassHash, ContractAddress, EntryPointSelector, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::patricia_key;
+use starknet_api::state::{EntryPointType, StateNumber};
+use starknet_api::transaction::{
+    Calldata, Fee, InvokeTransaction, Transaction, TransactionHash, TransactionOffsetInBlock,
+};
+
+use crate::objects::TransactionTrace;
+use crate::state_reader::to_blockifier_class;
+pub use crate::state_reader::ExecutionStateReader;
+
+// The weights of the Cairo resources in the fee, in gas units per use.
+const CAIRO_RESOURCE_FEE_WEIGHTS: [(&str, f64); 7] = [
+    ("n_steps", 0.01),
+    ("pedersen_builtin", 0.32),
+    ("range_check_builtin", 0.16),
+    ("ecdsa_builtin", 20.48),
+    ("bitwise_builtin", 0.64),
+    ("ec_op_builtin", 10.24),
+    ("output_builtin", 0.0),
+];
+
+// The failure reason in the trace of a deploy transaction.
+const DEPLOY_FAILURE_REASON: &str = "Deploy transactions can't be executed anymore.";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionConfig {
+    /// The address of the token contract the fee is paid in.
+    pub fee_contract_address: ContractAddress,
+    /// The maximal number of Cairo steps in the execution of a call or a transaction.
+    pub invoke_tx_max_n_steps: u32,
+    /// The maximal number of Cairo steps in the validation of a transaction by its account.
+    pub validate_max_n_steps: u32,
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            fee_contract_address: ContractAddress(patricia_key!(
+                "0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7"
+            )),
+            invoke_tx_max_n_steps: 1_000_000,
+            validate_max_n_steps: 1_000_000,
+        }
+    }
+}
+
+pub type ExecutionResult<T> = Result<T, ExecutionError>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExecutionError {
+    #[error("Contract {contract_address:?} isn't deployed at state {state_number:?}.")]
+    ContractNotFound { contract_address: ContractAddress, state_number: StateNumber },
+    #[error("Missing the header of block {block_number}.")]
+    MissingHeader { block_number: BlockNumber },
+    #[error("Missing the transactions of block {block_number}.")]
+    MissingTransactions { block_number: BlockNumber },
+    #[error("Missing the class {class_hash:?}, declared by a transaction of the block.")]
+    MissingClass { class_hash: ClassHash },
+    #[error("Transaction {tx_hash:?} is of a type that can't be executed anymore.")]
+    UnsupportedTransaction { tx_hash: TransactionHash },
+    #[error("Execution failed: {0}.")]
+    ExecutionFailed(String),
+    #[error(transparent)]
+    ClassSerdeError(#[from] serde_json::Error),
+    #[error(transparent)]
+    StorageError(#[from] StorageError),
+}
+
+/// The fee an invoke transaction would be charged.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeeEstimation {
+    pub gas_consumed: u128,
+    pub gas_price: GasPrice,
+    pub overall_fee: Fee,
+}
+
+/// Executes the entry point of the contract with the calldata, on top of the state at the state
+/// number, and returns the data it returned.
+pub fn execute_call(
+    txn: &StorageTxn<'_, RO>,
+    chain_id: &ChainId,
+    config: &ExecutionConfig,
+    state_number: StateNumber,
+    contract_address: ContractAddress,
+    entry_point_selector: EntryPointSelector,
+    calldata: Calldata,
+) -> ExecutionResult<Vec<StarkFelt>> {
+    verify_contract_exists(txn, state_number, contract_address)?;
+    let block_context = create_next_block_context(txn, chain_id, config, state_number)?;
+    let mut cached_state = CachedState::new(ExecutionStateReader::new(txn, state_number)?);
+
+    let call_entry_point = CallEntryPoint {
+        class_hash: None,
+        entry_point_type: EntryPointType::External,
+        entry_point_selector,
+        calldata,
+        storage_address: contract_address,
+        caller_address: ContractAddress::default(),
+        call_type: CallType::Call,
+    };
+    let call_info = call_entry_point
+        .execute(
+            &mut cached_state,
+            &mut ExecutionResources::default(),
+            &block_context,
+            &AccountTransactionContext::default(),
+        )
+        .map_err(|err| ExecutionError::ExecutionFailed(err.to_string()))?;
+
+    Ok(call_info.execution.retdata.0)
+}
+
+/// Estimates the fee of the invoke transaction, if it were included in a block on top of the state
+/// at the state number. The transaction is executed as if its max fee was zero, so the account
+/// doesn't have to hold the fee.
+pub fn estimate_fee(
+    txn: &StorageTxn<'_, RO>,
+    chain_id: &ChainId,
+    config: &ExecutionConfig,
+    state_number: StateNumber,
+    tx: InvokeTransaction,
+) -> ExecutionResult<FeeEstimation> {
+    verify_contract_exists(txn, state_number, tx.sender_address)?;
+    let block_context = create_next_block_context(txn, chain_id, config, state_number)?;
+    let mut cached_state = CachedState::new(ExecutionStateReader::new(txn, state_number)?);
+
+    let tx = AccountTransaction::Invoke(InvokeTransaction { max_fee: Fee(0), ..tx });
+    let execution_info = tx
+        .execute(&mut cached_state, &block_context)
+        .map_err(|err| ExecutionError::ExecutionFailed(err.to_string()))?;
+    let overall_fee = calculate_tx_fee(&execution_info.actual_resources, &block_context)
+        .map_err(|err| ExecutionError::ExecutionFailed(err.to_string()))?;
+
+    let gas_price = GasPrice(block_context.gas_price);
+    let gas_consumed = overall_fee.0.checked_div(gas_price.0).unwrap_or_default();
+    Ok(FeeEstimation { gas_consumed, gas_price, overall_fee })
+}
+
+/// Re-executes the transactions of the block over the state before it and returns their traces. If
+/// an offset is given, only the transactions up to and including it are executed. A transaction
+/// that can't be executed doesn't fail the block, the reason is recorded in its trace.
+pub fn trace_block_transactions(
+    txn: &StorageTxn<'_, RO>,
+    chain_id: &ChainId,
+    config: &ExecutionConfig,
+    block_number: BlockNumber,
+    last_offset: Option<TransactionOffsetInBlock>,
+) -> ExecutionResult<Vec<TransactionTrace>> {
+    let header = txn
+        .get_block_header(block_number)?
+        .ok_or(ExecutionError::MissingHeader { block_number })?;
+    let transactions = txn
+        .get_block_transactions(block_number)?
+        .ok_or(ExecutionError::MissingTransactions { block_number })?;
+    let n_transactions = last_offset.map_or(transactions.len(), |offset| offset.0 + 1);
+    let block_context = create_block_context(chain_id, config, block_number, &header);
+    let mut state = CachedState::new(ExecutionStateReader::new(
+        txn,
+        StateNumber::right_before_block(block_number),
+    )?);
+
+    let mut traces = Vec::with_capacity(n_transactions);
+    for tx in transactions.into_iter().take(n_transactions) {
+        let tx_hash = tx.transaction_hash();
+        // Each transaction is executed on a cache of its own, to take the state diff it produced
+        // before committing it to the state of the block.
+        let mut tx_state = CachedState::new(MutRefState::new(&mut state));
+        let trace = match tx {
+            // Deploy transactions were removed from Starknet, so they can't be executed. The
+            // contract is still deployed for the transactions after it, without the storage its
+            // constructor wrote.
+            Transaction::Deploy(tx) => {
+                tx_state
+                    .set_class_hash_at(tx.contract_address, tx.class_hash)
+                    .map_err(|err| ExecutionError::ExecutionFailed(err.to_string()))?;
+                let state_diff = ThinStateDiff::from(tx_state.to_state_diff());
+                tx_state.commit();
+                TransactionTrace::failed(tx_hash, DEPLOY_FAILURE_REASON.to_owned(), state_diff)
+            }
+            tx => {
+                let (tx, declared_class_hash) = to_blockifier_transaction(txn, block_number, tx)?;
+                match tx.execute(&mut tx_state, &block_context) {
+                    Ok(execution_info) => {
+                        let mut state_diff = ThinStateDiff::from(tx_state.to_state_diff());
+                        state_diff.declared_contract_hashes.extend(declared_class_hash);
+                        tx_state.commit();
+                        TransactionTrace::new(tx_hash, execution_info, state_diff)
+                    }
+                    // The changes of a failed transaction are discarded with its cache, and the
+                    // transactions after it are executed as if it wasn't in the block.
+                    Err(err) => {
+                        TransactionTrace::failed(tx_hash, err.to_string(), ThinStateDiff::default())
+                    }
+                }
+            }
+        };
+        traces.push(trace);
+    }
+
+    Ok(traces)
+}
+
+// Returns the transaction as the blockifier executes it, and the hash of the class it declares.
+// Deploy transactions were removed from Starknet, so they can't be converted.
+fn to_blockifier_transaction(
+    txn: &StorageTxn<'_, RO>,
+    block_number: BlockNumber,
+    tx: Transaction,
+) -> ExecutionResult<(BlockifierTransaction, Option<ClassHash>)> {
+    Ok(match tx {
+        Transaction::Declare(tx) => {
+            let class_hash = tx.class_hash;
+            let class = txn
+                .get_state_reader()?
+                .get_class_definition_at(StateNumber::right_after_block(block_number), &class_hash)?
+                .ok_or(ExecutionError::MissingClass { class_hash })?;
+            let tx = AccountTransaction::Declare(tx, to_blockifier_class(class)?);
+            (BlockifierTransaction::AccountTransaction(tx), Some(class_hash))
+        }
+        Transaction::DeployAccount(tx) => {
+            (BlockifierTransaction::AccountTransaction(AccountTransaction::DeployAccount(tx)), None)
+        }
+        Transaction::Invoke(tx) => {
+            (BlockifierTransaction::AccountTransaction(AccountTransaction::Invoke(tx)), None)
+        }
+        Transaction::L1Handler(tx) => (BlockifierTransaction::L1HandlerTransaction(tx), None),
+        Transaction::Deploy(tx) => {
+            return Err(ExecutionError::UnsupportedTransaction { tx_hash: tx.transaction_hash });
+        }
+    })
+}
+
+fn verify_contract_exists(
+    txn: &StorageTxn<'_, RO>,
+    state_number: StateNumber,
+    contract_address: ContractAddress,
+) -> ExecutionResult<()> {
+    txn.get_state_reader()?
+        .get_class_hash_at(state_number, &contract_address)?
+        .ok_or(ExecutionError::ContractNotFound { contract_address, state_number })?;
+    Ok(())
+}
+
+// Calls and fee estimations are executed as if in the block after the state number, with the
+// sequencer, timestamp and gas price of the last block before it.
+fn create_next_block_context(
+    txn: &StorageTxn<'_, RO>,
+    chain_id: &ChainId,
+    config: &ExecutionConfig,
+    state_number: StateNumber,
+) -> ExecutionResult<BlockContext> {
+    let block_number = state_number.block_after();
+    let header = match block_number.prev() {
+        Some(prev_block_number) => txn
+            .get_block_header(prev_block_number)?
+            .ok_or(ExecutionError::MissingHeader { block_number: prev_block_number })?,
+        None => return Err(ExecutionError::MissingHeader { block_number }),
+    };
+    Ok(create_block_context(chain_id, config, block_number, &header))
+}
+
+fn create_block_context(
+    chain_id: &ChainId,
+    config: &ExecutionConfig,
+    block_number: BlockNumber,
+    header: &BlockHeader,
+) -> BlockContext {
+    BlockContext {
+        chain_id: chain_id.clone(),
+        block_number,
+        block_timestamp: header.timestamp,
+        sequencer_address: header.sequencer,
+        fee_token_address: config.fee_contract_address,
+        cairo_resource_fee_weights: HashMap::from(
+            CAIRO_RESOURCE_FEE_WEIGHTS.map(|(resource, weight)| (resource.to_owned(), weight)),
+        ),
+        gas_price: header.gas_price.0,
+        invoke_tx_max_n_steps: config.invoke_tx_max_n_steps,
+        validate_max_n_steps: config.validate_max_n_steps,
+    }
+}
@@ -0,0 +1,120 @@
+//! The traces of executed transactions, converted from the objects of the blockifier.
+
+use blockifier::execution::entry_point::{CallInfo, CallType as BlockifierCallType};
+use blockifier::transaction::objects::TransactionExecutionInfo;
+use papyrus_storage::state::data::ThinStateDiff;
+use serde::{Deserialize, Serialize};
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::EntryPointType;
+use starknet_api::transaction::{Calldata, EventContent, MessageToL1, TransactionHash};
+
+/// The trace of the execution of a transaction, with the state diff it produced.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TransactionTrace {
+    pub transaction_hash: TransactionHash,
+    /// The validation of the transaction by its account, None for L1 handler transactions.
+    pub validate_invocation: Option<FunctionInvocation>,
+    /// The execution of the transaction, None for declare transactions.
+    pub execute_invocation: Option<FunctionInvocation>,
+    /// The transfer of the fee to the sequencer, None for L1 handler transactions.
+    pub fee_transfer_invocation: Option<FunctionInvocation>,
+    pub state_diff: ThinStateDiff,
+    /// Why the transaction couldn't be executed, None if it was. A transaction that wasn't
+    /// executed has no invocations.
+    pub failure_reason: Option<String>,
+}
+
+impl TransactionTrace {
+    pub(crate) fn new(
+        transaction_hash: TransactionHash,
+        execution_info: TransactionExecutionInfo,
+        state_diff: ThinStateDiff,
+    ) -> Self {
+        Self {
+            transaction_hash,
+            validate_invocation: execution_info.validate_call_info.map(FunctionInvocation::from),
+            execute_invocation: execution_info.execute_call_info.map(FunctionInvocation::from),
+            fee_transfer_invocation: execution_info
+                .fee_transfer_call_info
+                .map(FunctionInvocation::from),
+            state_diff,
+            failure_reason: None,
+        }
+    }
+
+    pub(crate) fn failed(
+        transaction_hash: TransactionHash,
+        failure_reason: String,
+        state_diff: ThinStateDiff,
+    ) -> Self {
+        Self {
+            transaction_hash,
+            validate_invocation: None,
+            execute_invocation: None,
+            fee_transfer_invocation: None,
+            state_diff,
+            failure_reason: Some(failure_reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CallType {
+    #[serde(rename = "CALL")]
+    Call,
+    #[serde(rename = "LIBRARY_CALL")]
+    LibraryCall,
+}
+
+impl From<BlockifierCallType> for CallType {
+    fn from(call_type: BlockifierCallType) -> Self {
+        match call_type {
+            BlockifierCallType::Call => CallType::Call,
+            BlockifierCallType::Delegate => CallType::LibraryCall,
+        }
+    }
+}
+
+/// A call to an entry point, with the calls it made and the events and messages it emitted.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FunctionInvocation {
+    pub contract_address: ContractAddress,
+    pub entry_point_selector: EntryPointSelector,
+    pub calldata: Calldata,
+    pub caller_address: ContractAddress,
+    /// The class hash of a library call, None for a call of the class of the contract.
+    pub class_hash: Option<ClassHash>,
+    pub entry_point_type: EntryPointType,
+    pub call_type: CallType,
+    pub result: Vec<StarkFelt>,
+    pub calls: Vec<FunctionInvocation>,
+    pub events: Vec<EventContent>,
+    pub messages: Vec<MessageToL1>,
+}
+
+impl From<CallInfo> for FunctionInvocation {
+    fn from(call_info: CallInfo) -> Self {
+        Self {
+            contract_address: call_info.call.storage_address,
+            entry_point_selector: call_info.call.entry_point_selector,
+            calldata: call_info.call.calldata,
+            caller_address: call_info.call.caller_address,
+            class_hash: call_info.call.class_hash,
+            entry_point_type: call_info.call.entry_point_type,
+            call_type: CallType::from(call_info.call.call_type),
+            result: call_info.execution.retdata.0,
+            calls: call_info.inner_calls.into_iter().map(FunctionInvocation::from).collect(),
+            events: call_info.execution.events.into_iter().map(|event| event.event).collect(),
+            messages: call_info
+                .execution
+                .l2_to_l1_messages
+                .into_iter()
+                .map(|message| MessageToL1 {
+                    to_address: message.message.to_address,
+                    payload: message.message.payload,
+                })
+                .collect(),
+        }
+    }
+}
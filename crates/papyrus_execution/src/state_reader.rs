@@ -0,0 +1,73 @@
+use blockifier::execution::contract_class::ContractClass as BlockifierContractClass;
+use blockifier::state::errors::StateError;
+use blockifier::state::state_api::{StateReader as BlockifierStateReader, StateResult};
+use papyrus_storage::db::RO;
+use papyrus_storage::state::{StateReader, StateStorageReader};
+use papyrus_storage::{StorageResult, StorageTxn};
+use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::{ContractClass, StateNumber, StorageKey};
+
+/// A view of the state at a state number, as read by the blockifier. Contracts that aren't deployed
+/// have the default class hash, nonce and storage values.
+pub struct ExecutionStateReader<'env> {
+    state_reader: StateReader<'env, RO>,
+    state_number: StateNumber,
+}
+
+impl<'env> ExecutionStateReader<'env> {
+    pub fn new(txn: &'env StorageTxn<'env, RO>, state_number: StateNumber) -> StorageResult<Self> {
+        Ok(Self { state_reader: txn.get_state_reader()?, state_number })
+    }
+}
+
+impl<'env> BlockifierStateReader for ExecutionStateReader<'env> {
+    fn get_storage_at(
+        &mut self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<StarkFelt> {
+        self.state_reader
+            .get_storage_at(self.state_number, &contract_address, &key)
+            .map_err(storage_error)
+    }
+
+    fn get_nonce_at(&mut self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        Ok(self
+            .state_reader
+            .get_nonce_at(self.state_number, &contract_address)
+            .map_err(storage_error)?
+            .unwrap_or_default())
+    }
+
+    fn get_class_hash_at(&mut self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        Ok(self
+            .state_reader
+            .get_class_hash_at(self.state_number, &contract_address)
+            .map_err(storage_error)?
+            .unwrap_or_default())
+    }
+
+    fn get_contract_class(
+        &mut self,
+        class_hash: &ClassHash,
+    ) -> StateResult<BlockifierContractClass> {
+        let class = self
+            .state_reader
+            .get_class_definition_at(self.state_number, class_hash)
+            .map_err(storage_error)?
+            .ok_or(StateError::UndeclaredClassHash(*class_hash))?;
+        to_blockifier_class(class).map_err(storage_error)
+    }
+}
+
+// The classes are stored in the format of the feeder gateway, which the blockifier parses.
+pub(crate) fn to_blockifier_class(
+    class: ContractClass,
+) -> Result<BlockifierContractClass, serde_json::Error> {
+    serde_json::from_value(serde_json::to_value(class)?)
+}
+
+fn storage_error(err: impl std::fmt::Display) -> StateError {
+    StateError::StateReadError(err.to_string())
+}
@@ -0,0 +1,159 @@
+use indexmap::IndexMap;
+use papyrus_storage::body::BodyStorageWriter;
+use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_storage::state::data::{Cairo1StateDiff, CompiledClassHash, SierraContractClass};
+use papyrus_storage::state::StateStorageWriter;
+use papyrus_storage::test_utils::get_test_storage;
+use papyrus_storage::StorageReader;
+use starknet_api::block::{BlockHeader, BlockNumber};
+use starknet_api::core::{ClassHash, GlobalRoot};
+use starknet_api::hash::StarkFelt;
+use starknet_api::stark_felt;
+use starknet_api::state::StateDiff;
+use starknet_client::{
+    Block, BlockStatus, ContractClass, GenericContractClass, RetryConfig, StarknetClient,
+    StarknetClientTrait,
+};
+use test_utils::{get_test_block_with_events, get_test_state_diff};
+
+use crate::{run_server, FeederGatewayConfig};
+
+// Runs the feeder gateway and returns a starknet client that reads from it.
+async fn get_test_client(storage_reader: StorageReader) -> StarknetClient {
+    let config = FeederGatewayConfig { server_address: String::from("127.0.0.1:0") };
+    let (addr, server_future) = run_server(config, storage_reader).await.unwrap();
+    tokio::spawn(server_future);
+    let retry_config =
+        RetryConfig { retry_base_millis: 30, retry_max_delay_millis: 30000, max_retries: 0 };
+    StarknetClient::new(&format!("http://{addr}/"), None, retry_config).unwrap()
+}
+
+#[tokio::test]
+async fn get_block() {
+    let (storage_reader, mut storage_writer) = get_test_storage();
+    let block = get_test_block_with_events(2, 2);
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block.header.block_number, &block.header)
+        .unwrap()
+        .append_body(block.header.block_number, block.body.clone())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let client = get_test_client(storage_reader).await;
+
+    let expected_block = Block::from_starknet_api_block(block, BlockStatus::AcceptedOnL2);
+    let res = client.block(BlockNumber(0)).await.unwrap().unwrap();
+    // Compared as json since an empty builtin instance counter isn't deserialized to its default.
+    assert_eq!(serde_json::to_value(res).unwrap(), serde_json::to_value(expected_block).unwrap());
+    assert_eq!(client.block_number().await.unwrap(), Some(BlockNumber(0)));
+    assert!(client.block(BlockNumber(1)).await.unwrap().is_none());
+    assert!(client.pending_block().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn get_state_update() {
+    let (storage_reader, mut storage_writer) = get_test_storage();
+    let header =
+        BlockHeader { state_root: GlobalRoot(stark_felt!("0x1")), ..BlockHeader::default() };
+    let diff = get_test_state_diff();
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(header.block_number, &header)
+        .unwrap()
+        .append_state_diff(header.block_number, diff.clone(), IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let client = get_test_client(storage_reader).await;
+
+    let res = client.state_update(BlockNumber(0)).await.unwrap().unwrap();
+    assert_eq!(res.block_hash, header.block_hash);
+    assert_eq!(GlobalRoot::from(res.new_root), header.state_root);
+    assert_eq!(GlobalRoot::from(res.old_root), GlobalRoot::default());
+    assert_eq!(
+        res.state_diff.declared_contracts,
+        diff.declared_classes.keys().copied().collect::<Vec<_>>()
+    );
+    assert_eq!(res.state_diff.nonces, diff.nonces);
+    let (address, class_hash) = diff.deployed_contracts.get_index(0).unwrap();
+    assert_eq!(res.state_diff.deployed_contracts[0].address, *address);
+    assert_eq!(res.state_diff.deployed_contracts[0].class_hash, *class_hash);
+    let (address, storage_entries) = diff.storage_diffs.get_index(0).unwrap();
+    let (key, value) = storage_entries.get_index(0).unwrap();
+    let res_storage_entries = res.state_diff.storage_diffs.get(address).unwrap();
+    assert_eq!(res_storage_entries[0].key, *key);
+    assert_eq!(res_storage_entries[0].value, *value);
+
+    assert!(client.state_update(BlockNumber(1)).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn get_class_by_hash() {
+    let (storage_reader, mut storage_writer) = get_test_storage();
+    let header = BlockHeader::default();
+    let diff = get_test_state_diff();
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(header.block_number, &header)
+        .unwrap()
+        .append_state_diff(header.block_number, diff.clone(), IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let client = get_test_client(storage_reader).await;
+
+    let (class_hash, class) = diff.declared_classes.get_index(0).unwrap();
+    let res = client.class_by_hash(*class_hash).await.unwrap().unwrap();
+    assert_eq!(res, GenericContractClass::Cairo0ContractClass(ContractClass::from(class.clone())));
+    assert!(client.class_by_hash(ClassHash(stark_felt!("0x123"))).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn get_sierra_class_by_hash() {
+    let (storage_reader, mut storage_writer) = get_test_storage();
+    let class_hash = ClassHash(stark_felt!("0x5"));
+    let compiled_class_hash = CompiledClassHash(stark_felt!("0x6"));
+    let class = SierraContractClass {
+        sierra_program: vec![stark_felt!("0x1")],
+        contract_class_version: "0.1.0".to_owned(),
+        ..SierraContractClass::default()
+    };
+    let cairo1_diff = Cairo1StateDiff {
+        declared_classes: IndexMap::from([(class_hash, (compiled_class_hash, class.clone()))]),
+        replaced_classes: IndexMap::new(),
+    };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &BlockHeader::default())
+        .unwrap()
+        .append_state_diff_with_cairo1(
+            BlockNumber(0),
+            StateDiff::default(),
+            cairo1_diff,
+            IndexMap::new(),
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+    let client = get_test_client(storage_reader).await;
+
+    let res = client.class_by_hash(class_hash).await.unwrap().unwrap();
+    let res = match res {
+        GenericContractClass::Cairo1ContractClass(class) => class,
+        class => panic!("Expected a Sierra class, got {class:?}."),
+    };
+    assert_eq!(res.sierra_program, class.sierra_program);
+    assert_eq!(res.contract_class_version, class.contract_class_version);
+
+    let state_update = client.state_update(BlockNumber(0)).await.unwrap().unwrap();
+    assert_eq!(state_update.state_diff.declared_classes[0].class_hash, class_hash);
+    assert_eq!(
+        state_update.state_diff.declared_classes[0].compiled_class_hash,
+        compiled_class_hash.0
+    );
+}
@@ -0,0 +1,349 @@
+//! A server of the [`starknet`] feeder gateway endpoints used by the sync, read from the storage,
+//! so that other nodes can sync from this node instead of from the starknet feeder gateway.
+//!
+//! [`starknet`]: https://starknet.io/
+
+#[cfg(test)]
+mod gateway_test;
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use papyrus_storage::base_layer::BaseLayerStorageReader;
+use papyrus_storage::body::events::ThinTransactionOutput;
+use papyrus_storage::body::BodyStorageReader;
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::state::data::SierraContractClass as StorageSierraContractClass;
+use papyrus_storage::state::StateStorageReader;
+use papyrus_storage::{StorageError, StorageReader, TransactionIndex};
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{BlockBody, BlockNumber};
+use starknet_api::core::ClassHash;
+use starknet_api::state::StateNumber;
+use starknet_api::transaction::{
+    DeclareTransactionOutput, DeployAccountTransactionOutput, DeployTransactionOutput, Event,
+    InvokeTransactionOutput, L1HandlerTransactionOutput, TransactionOffsetInBlock,
+    TransactionOutput,
+};
+use starknet_client::{
+    Block, BlockStatus, ContractClass, DeclaredClassHashEntry, DeployedContract,
+    GenericContractClass, GlobalRoot, ReplacedClass, SierraContractClass, SierraEntryPoint,
+    StarknetError, StarknetErrorCode, StateDiff, StateUpdate, StorageEntry,
+};
+use tracing::{debug, error, info, instrument};
+
+const GET_BLOCK_PATH: &str = "/feeder_gateway/get_block";
+const GET_CLASS_BY_HASH_PATH: &str = "/feeder_gateway/get_class_by_hash";
+const GET_STATE_UPDATE_PATH: &str = "/feeder_gateway/get_state_update";
+const BLOCK_NUMBER_QUERY: &str = "blockNumber";
+const CLASS_HASH_QUERY: &str = "classHash";
+const LATEST_BLOCK_ID: &str = "latest";
+const PENDING_BLOCK_ID: &str = "pending";
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FeederGatewayConfig {
+    pub server_address: String,
+}
+
+impl Default for FeederGatewayConfig {
+    fn default() -> Self {
+        FeederGatewayConfig { server_address: String::from("0.0.0.0:8084") }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+enum FeederGatewayError {
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    #[error(transparent)]
+    Starknet(#[from] StarknetError),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+type FeederGatewayResult<T> = Result<T, FeederGatewayError>;
+
+/// Runs an HTTP server that serves the `get_block`, `get_state_update` and `get_class_by_hash`
+/// endpoints of the starknet feeder gateway from the storage. The pending block isn't served, and
+/// the receipts leave out the fields the storage doesn't keep: the consumed L1 to L2 message, the
+/// execution resources and the senders of the L2 to L1 messages.
+#[instrument(skip(storage_reader), level = "debug", err)]
+pub async fn run_server(
+    config: FeederGatewayConfig,
+    storage_reader: StorageReader,
+) -> anyhow::Result<(SocketAddr, impl Future<Output = hyper::Result<()>>)> {
+    debug!("Starting feeder gateway.");
+    let addr = config.server_address.parse()?;
+    let make_service = make_service_fn(move |_connection| {
+        let storage_reader = storage_reader.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let response = route(&storage_reader, &request);
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    let addr = server.local_addr();
+    info!(local_address = %addr, "Feeder gateway is running.");
+    Ok((addr, server))
+}
+
+fn route(storage_reader: &StorageReader, request: &Request<Body>) -> Response<Body> {
+    if request.method() != Method::GET {
+        return response_with_status(StatusCode::NOT_FOUND, "Not found.");
+    }
+    let query: HashMap<String, String> = request
+        .uri()
+        .query()
+        .map(|query| url::form_urlencoded::parse(query.as_bytes()).into_owned().collect())
+        .unwrap_or_default();
+    let result = match request.uri().path() {
+        GET_BLOCK_PATH => get_block(storage_reader, &query),
+        GET_STATE_UPDATE_PATH => get_state_update(storage_reader, &query),
+        GET_CLASS_BY_HASH_PATH => get_class_by_hash(storage_reader, &query),
+        _ => return response_with_status(StatusCode::NOT_FOUND, "Not found."),
+    };
+    match result {
+        Ok(body) => response_with_status(StatusCode::OK, body),
+        // The starknet feeder gateway returns its errors with an internal server error status.
+        Err(FeederGatewayError::Starknet(err)) => match serde_json::to_string(&err) {
+            Ok(body) => response_with_status(StatusCode::INTERNAL_SERVER_ERROR, body),
+            Err(err) => internal_server_error_response(err.into()),
+        },
+        Err(err) => internal_server_error_response(err),
+    }
+}
+
+fn get_block(
+    storage_reader: &StorageReader,
+    query: &HashMap<String, String>,
+) -> FeederGatewayResult<String> {
+    let txn = storage_reader.begin_ro_txn()?;
+    let block_marker = txn.get_header_marker()?.min(txn.get_body_marker()?);
+    let block_number = get_block_number(query, block_marker)?;
+
+    let header = txn.get_block_header(block_number)?.ok_or_else(|| inconsistency(block_number))?;
+    let transactions =
+        txn.get_block_transactions(block_number)?.ok_or_else(|| inconsistency(block_number))?;
+    let thin_transaction_outputs = txn
+        .get_block_transaction_outputs(block_number)?
+        .ok_or_else(|| inconsistency(block_number))?;
+    let mut transaction_outputs = vec![];
+    for (i, thin_transaction_output) in thin_transaction_outputs.into_iter().enumerate() {
+        let transaction_index = TransactionIndex(block_number, TransactionOffsetInBlock(i));
+        let events = txn
+            .get_transaction_events(transaction_index)?
+            .ok_or_else(|| inconsistency(block_number))?;
+        transaction_outputs.push(to_transaction_output(thin_transaction_output, events));
+    }
+    let block = starknet_api::block::Block {
+        header,
+        body: BlockBody { transactions, transaction_outputs },
+    };
+
+    let status = if block_number < txn.get_base_layer_block_marker()? {
+        BlockStatus::AcceptedOnL1
+    } else {
+        BlockStatus::AcceptedOnL2
+    };
+    Ok(serde_json::to_string(&Block::from_starknet_api_block(block, status))?)
+}
+
+fn get_state_update(
+    storage_reader: &StorageReader,
+    query: &HashMap<String, String>,
+) -> FeederGatewayResult<String> {
+    let txn = storage_reader.begin_ro_txn()?;
+    let state_marker = txn.get_header_marker()?.min(txn.get_state_marker()?);
+    let block_number = get_block_number(query, state_marker)?;
+
+    let header = txn.get_block_header(block_number)?.ok_or_else(|| inconsistency(block_number))?;
+    // The state root before the genesis block is zero.
+    let old_root = match block_number.prev() {
+        Some(parent_block_number) => {
+            txn.get_block_header(parent_block_number)?
+                .ok_or_else(|| inconsistency(parent_block_number))?
+                .state_root
+        }
+        None => starknet_api::core::GlobalRoot::default(),
+    };
+    let diff = txn.get_state_diff(block_number)?.ok_or_else(|| inconsistency(block_number))?;
+    let state_diff = StateDiff {
+        storage_diffs: diff
+            .storage_diffs
+            .into_iter()
+            .map(|(address, entries)| {
+                let entries =
+                    entries.into_iter().map(|(key, value)| StorageEntry { key, value }).collect();
+                (address, entries)
+            })
+            .collect(),
+        deployed_contracts: diff
+            .deployed_contracts
+            .into_iter()
+            .map(|(address, class_hash)| DeployedContract { address, class_hash })
+            .collect(),
+        declared_contracts: diff.declared_contract_hashes,
+        nonces: diff.nonces,
+        declared_classes: diff
+            .declared_classes
+            .into_iter()
+            .map(|(class_hash, compiled_class_hash)| DeclaredClassHashEntry {
+                class_hash,
+                compiled_class_hash: compiled_class_hash.0,
+            })
+            .collect(),
+        replaced_classes: diff
+            .replaced_classes
+            .into_iter()
+            .map(|(address, class_hash)| ReplacedClass { address, class_hash })
+            .collect(),
+    };
+    let state_update = StateUpdate {
+        block_hash: header.block_hash,
+        new_root: GlobalRoot::from(header.state_root),
+        old_root: GlobalRoot::from(old_root),
+        state_diff,
+    };
+    Ok(serde_json::to_string(&state_update)?)
+}
+
+fn get_class_by_hash(
+    storage_reader: &StorageReader,
+    query: &HashMap<String, String>,
+) -> FeederGatewayResult<String> {
+    let class_hash = query
+        .get(CLASS_HASH_QUERY)
+        .and_then(|class_hash| {
+            serde_json::from_value::<ClassHash>(serde_json::Value::String(class_hash.clone())).ok()
+        })
+        .ok_or_else(|| {
+            starknet_error(StarknetErrorCode::MalformedRequest, "Invalid class hash.")
+        })?;
+
+    let txn = storage_reader.begin_ro_txn()?;
+    let state_number = StateNumber::right_before_block(txn.get_state_marker()?);
+    let state_reader = txn.get_state_reader()?;
+    let class = match state_reader.get_class_definition_at(state_number, &class_hash)? {
+        Some(class) => GenericContractClass::Cairo0ContractClass(ContractClass::from(class)),
+        None => state_reader
+            .get_sierra_class_definition_at(state_number, &class_hash)?
+            .map(|class| GenericContractClass::Cairo1ContractClass(to_sierra_contract_class(class)))
+            .ok_or_else(|| {
+                starknet_error(
+                    StarknetErrorCode::UndeclaredClass,
+                    &format!("Class with hash {class_hash:?} is not declared."),
+                )
+            })?,
+    };
+    Ok(serde_json::to_string(&class)?)
+}
+
+fn to_sierra_contract_class(class: StorageSierraContractClass) -> SierraContractClass {
+    SierraContractClass {
+        sierra_program: class.sierra_program,
+        contract_class_version: class.contract_class_version,
+        entry_points_by_type: class
+            .entry_points_by_type
+            .into_iter()
+            .map(|(entry_point_type, entry_points)| {
+                let entry_points = entry_points.into_iter().map(|entry_point| SierraEntryPoint {
+                    function_idx: entry_point.function_idx,
+                    selector: entry_point.selector,
+                });
+                (entry_point_type, entry_points.collect())
+            })
+            .collect(),
+        abi: class.abi,
+    }
+}
+
+// Returns the requested block number, which is the latest block when it's not given. Only the
+// blocks below the marker are served.
+fn get_block_number(
+    query: &HashMap<String, String>,
+    marker: BlockNumber,
+) -> FeederGatewayResult<BlockNumber> {
+    let block_not_found =
+        || starknet_error(StarknetErrorCode::BlockNotFound, "Block number not found.");
+    let block_number = match query.get(BLOCK_NUMBER_QUERY).map(String::as_str) {
+        None | Some(LATEST_BLOCK_ID) => marker.prev().ok_or_else(block_not_found)?,
+        Some(PENDING_BLOCK_ID) => return Err(block_not_found().into()),
+        Some(block_number) => BlockNumber(block_number.parse().map_err(|_| {
+            starknet_error(StarknetErrorCode::MalformedRequest, "Invalid block number.")
+        })?),
+    };
+    if block_number >= marker {
+        return Err(block_not_found().into());
+    }
+    Ok(block_number)
+}
+
+// Builds the transaction output from its thin output in the storage and its events.
+fn to_transaction_output(
+    thin_transaction_output: ThinTransactionOutput,
+    events: Vec<Event>,
+) -> TransactionOutput {
+    match thin_transaction_output {
+        ThinTransactionOutput::Declare(output) => {
+            TransactionOutput::Declare(DeclareTransactionOutput {
+                actual_fee: output.actual_fee,
+                messages_sent: output.messages_sent,
+                events,
+            })
+        }
+        ThinTransactionOutput::Deploy(output) => {
+            TransactionOutput::Deploy(DeployTransactionOutput {
+                actual_fee: output.actual_fee,
+                messages_sent: output.messages_sent,
+                events,
+            })
+        }
+        ThinTransactionOutput::DeployAccount(output) => {
+            TransactionOutput::DeployAccount(DeployAccountTransactionOutput {
+                actual_fee: output.actual_fee,
+                messages_sent: output.messages_sent,
+                events,
+            })
+        }
+        ThinTransactionOutput::Invoke(output) => {
+            TransactionOutput::Invoke(InvokeTransactionOutput {
+                actual_fee: output.actual_fee,
+                messages_sent: output.messages_sent,
+                events,
+            })
+        }
+        ThinTransactionOutput::L1Handler(output) => {
+            TransactionOutput::L1Handler(L1HandlerTransactionOutput {
+                actual_fee: output.actual_fee,
+                messages_sent: output.messages_sent,
+                events,
+            })
+        }
+    }
+}
+
+fn starknet_error(code: StarknetErrorCode, message: &str) -> StarknetError {
+    StarknetError { code, message: message.to_owned() }
+}
+
+// The blocks below the markers are expected to be fully stored.
+fn inconsistency(block_number: BlockNumber) -> StorageError {
+    StorageError::DBInconsistency { msg: format!("Missing data of block {block_number}.") }
+}
+
+fn internal_server_error_response(err: FeederGatewayError) -> Response<Body> {
+    error!("Feeder gateway request failed: {err}.");
+    response_with_status(StatusCode::INTERNAL_SERVER_ERROR, "Internal error.")
+}
+
+fn response_with_status(status: StatusCode, body: impl Into<Body>) -> Response<Body> {
+    let mut response = Response::new(body.into());
+    *response.status_mut() = status;
+    response
+}
@@ -0,0 +1,176 @@
+//! The transactions of the `starknet_add*Transaction` methods, which the node doesn't execute but
+//! forwards to the gateway of the sequencer.
+
+use jsonrpsee::core::Error;
+use papyrus_storage::compression_utils::{CompressionError, GzEncoded};
+use papyrus_storage::db::serialization::StorageSerdeError;
+use papyrus_storage::state::data::CompiledClassHash;
+use serde::{Deserialize, Serialize};
+use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::transaction::{
+    Calldata, ContractAddressSalt, Fee, TransactionHash, TransactionSignature, TransactionVersion,
+};
+use starknet_client::{
+    AddDeclareTransaction, AddDeployAccountTransaction, AddInvokeTransaction, AddTransaction,
+    ClientError, CompressedCairo0ContractClass, CompressedContractClass,
+    CompressedSierraContractClass, SierraEntryPoint, StarknetError, StarknetErrorCode,
+};
+use tracing::debug;
+
+use crate::api::JsonRpcError;
+use crate::internal_server_error;
+use crate::state::{GatewayContractClass, Program};
+use crate::transaction::BroadcastedInvokeTransaction;
+
+/// A declare transaction that wasn't included in a block, of a Cairo 0 class or, in version 2, of
+/// a Sierra class.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct BroadcastedDeclareTransaction {
+    pub contract_class: GatewayContractClass,
+    pub sender_address: ContractAddress,
+    pub max_fee: Fee,
+    pub version: TransactionVersion,
+    pub signature: TransactionSignature,
+    pub nonce: Nonce,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiled_class_hash: Option<CompiledClassHash>,
+}
+
+/// A deploy account transaction that wasn't included in a block.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct BroadcastedDeployAccountTransaction {
+    pub class_hash: ClassHash,
+    pub contract_address_salt: ContractAddressSalt,
+    pub constructor_calldata: Calldata,
+    pub max_fee: Fee,
+    pub version: TransactionVersion,
+    pub signature: TransactionSignature,
+    pub nonce: Nonce,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct AddInvokeTransactionResult {
+    pub transaction_hash: TransactionHash,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct AddDeclareTransactionResult {
+    pub transaction_hash: TransactionHash,
+    pub class_hash: ClassHash,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct AddDeployAccountTransactionResult {
+    pub transaction_hash: TransactionHash,
+    pub contract_address: ContractAddress,
+}
+
+impl From<BroadcastedInvokeTransaction> for AddTransaction {
+    fn from(tx: BroadcastedInvokeTransaction) -> Self {
+        AddTransaction::Invoke(AddInvokeTransaction {
+            sender_address: tx.sender_address,
+            calldata: tx.calldata,
+            max_fee: tx.max_fee,
+            signature: tx.signature,
+            nonce: tx.nonce,
+            version: tx.version,
+        })
+    }
+}
+
+impl From<BroadcastedDeployAccountTransaction> for AddTransaction {
+    fn from(tx: BroadcastedDeployAccountTransaction) -> Self {
+        AddTransaction::DeployAccount(AddDeployAccountTransaction {
+            class_hash: tx.class_hash,
+            contract_address_salt: tx.contract_address_salt,
+            constructor_calldata: tx.constructor_calldata,
+            max_fee: tx.max_fee,
+            signature: tx.signature,
+            nonce: tx.nonce,
+            version: tx.version,
+        })
+    }
+}
+
+// The gateway of the sequencer takes the Sierra program compressed, like the program of a Cairo 0
+// class.
+impl TryFrom<BroadcastedDeclareTransaction> for AddTransaction {
+    type Error = CompressionError;
+    fn try_from(tx: BroadcastedDeclareTransaction) -> Result<Self, Self::Error> {
+        let contract_class = match tx.contract_class {
+            GatewayContractClass::Cairo0(class) => {
+                CompressedContractClass::Cairo0(CompressedCairo0ContractClass {
+                    abi: serde_json::to_value(class.abi).map_err(|err| {
+                        CompressionError::StorageSerde(StorageSerdeError::Serde(err))
+                    })?,
+                    program: class.program,
+                    entry_points_by_type: class.entry_points_by_type,
+                })
+            }
+            GatewayContractClass::Sierra(class) => {
+                let sierra_program = serde_json::to_value(class.sierra_program)
+                    .map_err(|err| CompressionError::StorageSerde(StorageSerdeError::Serde(err)))?;
+                CompressedContractClass::Sierra(CompressedSierraContractClass {
+                    sierra_program: base64::encode(GzEncoded::encode(Program(sierra_program))?),
+                    contract_class_version: class.contract_class_version,
+                    entry_points_by_type: class
+                        .entry_points_by_type
+                        .into_iter()
+                        .map(|(entry_point_type, entry_points)| {
+                            let entry_points =
+                                entry_points.into_iter().map(|entry_point| SierraEntryPoint {
+                                    function_idx: entry_point.function_idx,
+                                    selector: entry_point.selector,
+                                });
+                            (entry_point_type, entry_points.collect())
+                        })
+                        .collect(),
+                    abi: class.abi,
+                })
+            }
+        };
+        Ok(AddTransaction::Declare(AddDeclareTransaction {
+            contract_class,
+            sender_address: tx.sender_address,
+            max_fee: tx.max_fee,
+            signature: tx.signature,
+            nonce: tx.nonce,
+            version: tx.version,
+            compiled_class_hash: tx
+                .compiled_class_hash
+                .map(|compiled_class_hash| compiled_class_hash.0),
+        }))
+    }
+}
+
+// The sequencer rejects invalid transactions with a starknet error, which is mapped to the JSON-RPC
+// error of the spec. Other failures, including unknown starknet errors, are internal.
+pub(crate) fn add_transaction_error(err: ClientError) -> Error {
+    let (code, message) = match err {
+        ClientError::StarknetError(StarknetError { code, message }) => (code, message),
+        err => return internal_server_error(err),
+    };
+    let json_rpc_error = match code {
+        StarknetErrorCode::ClassAlreadyDeclared => JsonRpcError::ClassAlreadyDeclared,
+        StarknetErrorCode::CompilationFailed => JsonRpcError::CompilationFailed,
+        StarknetErrorCode::ContractBytecodeSizeTooLarge => {
+            JsonRpcError::ContractClassSizeIsTooLarge
+        }
+        StarknetErrorCode::DuplicatedTransaction => JsonRpcError::DuplicateTx,
+        StarknetErrorCode::InsufficientAccountBalance => JsonRpcError::InsufficientAccountBalance,
+        StarknetErrorCode::InsufficientMaxFee => JsonRpcError::InsufficientMaxFee,
+        StarknetErrorCode::InvalidCompiledClassHash => JsonRpcError::CompiledClassHashMismatch,
+        StarknetErrorCode::InvalidContractClassVersion => {
+            JsonRpcError::UnsupportedContractClassVersion
+        }
+        StarknetErrorCode::InvalidTransactionNonce => JsonRpcError::InvalidTransactionNonce,
+        StarknetErrorCode::InvalidTransactionVersion => JsonRpcError::UnsupportedTxVersion,
+        StarknetErrorCode::OutOfRangeClassHash | StarknetErrorCode::UndeclaredClass => {
+            JsonRpcError::ClassHashNotFound
+        }
+        StarknetErrorCode::ValidateFailure => JsonRpcError::ValidationFailure,
+        code => return internal_server_error(format!("{code:?}: {message}")),
+    };
+    debug!("The sequencer rejected the transaction: {}", message);
+    Error::from(json_rpc_error)
+}
@@ -4,14 +4,22 @@ use jsonrpsee::core::Error;
 use jsonrpsee::proc_macros::rpc;
 use serde::{Deserialize, Serialize};
 use starknet_api::block::{BlockHash, BlockNumber};
-use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
-use starknet_api::transaction::{EventKey, TransactionHash, TransactionOffsetInBlock};
+use starknet_api::transaction::{Calldata, EventKey, TransactionHash, TransactionOffsetInBlock};
 
-use crate::block::Block;
-use crate::state::{ContractClass, StateUpdate};
-use crate::transaction::{Event, TransactionReceiptWithStatus, TransactionWithType};
+use crate::add_transaction::{
+    AddDeclareTransactionResult, AddDeployAccountTransactionResult, AddInvokeTransactionResult,
+    BroadcastedDeclareTransaction, BroadcastedDeployAccountTransaction,
+};
+use crate::block::MaybePendingBlock;
+use crate::proof::GetProofResult;
+use crate::state::{GatewayContractClass, MaybePendingStateUpdate};
+use crate::trace::{TransactionTrace, TransactionTraceWithHash};
+use crate::transaction::{
+    BroadcastedInvokeTransaction, Event, TransactionReceiptWithStatus, TransactionWithType,
+};
 
 #[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Tag {
@@ -21,6 +29,9 @@ pub enum Tag {
     /// Currently constructed block
     #[serde(rename = "pending")]
     Pending,
+    /// The most recent block that was proved on the base layer
+    #[serde(rename = "l1")]
+    L1,
 }
 
 #[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -42,6 +53,8 @@ pub enum BlockId {
 pub enum JsonRpcError {
     #[error("There are no blocks.")]
     NoBlocks,
+    #[error("No trace available for transaction.")]
+    NoTraceAvailable = 10,
     #[error("Contract not found.")]
     ContractNotFound = 20,
     #[error("Block not found.")]
@@ -58,6 +71,38 @@ pub enum JsonRpcError {
     InvalidContinuationToken = 33,
     #[error("Too many keys provided in a filter.")]
     TooManyKeysInFilter = 34,
+    #[error("Contract error.")]
+    ContractError = 40,
+    #[error("Class already declared.")]
+    ClassAlreadyDeclared = 51,
+    #[error("Invalid transaction nonce.")]
+    InvalidTransactionNonce = 52,
+    #[error(
+        "Max fee is smaller than the minimal transaction cost (validation plus fee transfer)."
+    )]
+    InsufficientMaxFee = 53,
+    #[error("Account balance is smaller than the transaction's max_fee.")]
+    InsufficientAccountBalance = 54,
+    #[error("Account validation failed.")]
+    ValidationFailure = 55,
+    #[error("Compilation failed.")]
+    CompilationFailed = 56,
+    #[error("Contract class size is too large.")]
+    ContractClassSizeIsTooLarge = 57,
+    #[error("A transaction with the same hash already exists in the mempool.")]
+    DuplicateTx = 59,
+    #[error("The compiled class hash did not match the one supplied in the transaction.")]
+    CompiledClassHashMismatch = 60,
+    #[error("The transaction version is not supported.")]
+    UnsupportedTxVersion = 61,
+    #[error("The contract class version is not supported.")]
+    UnsupportedContractClassVersion = 62,
+    #[error("The state commitment of the block is not available.")]
+    StateCommitmentNotAvailable = 10000,
+    #[error("The state of the block was pruned.")]
+    BlockPruned = 10001,
+    #[error("Too many keys provided in a proof request.")]
+    TooManyKeysInProof = 10002,
 }
 
 #[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -77,6 +122,21 @@ pub struct EventFilter {
     pub keys: Vec<HashSet<EventKey>>,
 }
 
+/// A call to a function of a contract, which doesn't create a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FunctionCall {
+    pub contract_address: ContractAddress,
+    pub entry_point_selector: EntryPointSelector,
+    pub calldata: Calldata,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FeeEstimate {
+    pub gas_consumed: StarkFelt,
+    pub gas_price: StarkFelt,
+    pub overall_fee: StarkFelt,
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ContinuationToken(pub String);
 
@@ -86,6 +146,9 @@ pub struct EventsChunk {
     pub continuation_token: Option<ContinuationToken>,
 }
 
+/// The version of the specification of the latest methods, served by [`JsonRpc`].
+pub const SPEC_VERSION: &str = "0.3.0";
+
 #[rpc(server, client, namespace = "starknet")]
 pub trait JsonRpc {
     /// Gets the most recent accepted block number.
@@ -98,11 +161,12 @@ pub trait JsonRpc {
 
     /// Gets block information with transaction hashes given a block identifier.
     #[method(name = "getBlockWithTxHashes")]
-    fn get_block_w_transaction_hashes(&self, block_id: BlockId) -> Result<Block, Error>;
+    fn get_block_w_transaction_hashes(&self, block_id: BlockId)
+    -> Result<MaybePendingBlock, Error>;
 
     /// Gets block information with full transactions given a block identifier.
     #[method(name = "getBlockWithTxs")]
-    fn get_block_w_full_transactions(&self, block_id: BlockId) -> Result<Block, Error>;
+    fn get_block_w_full_transactions(&self, block_id: BlockId) -> Result<MaybePendingBlock, Error>;
 
     /// Gets the value of the storage at the given address, key, and block.
     #[method(name = "getStorageAt")]
@@ -134,7 +198,7 @@ pub trait JsonRpc {
 
     /// Gets the information about the result of executing the requested block.
     #[method(name = "getStateUpdate")]
-    fn get_state_update(&self, block_id: BlockId) -> Result<StateUpdate, Error>;
+    fn get_state_update(&self, block_id: BlockId) -> Result<MaybePendingStateUpdate, Error>;
 
     /// Gets the transaction receipt by the transaction hash.
     #[method(name = "getTransactionReceipt")]
@@ -145,7 +209,11 @@ pub trait JsonRpc {
 
     /// Gets the contract class definition associated with the given hash.
     #[method(name = "getClass")]
-    fn get_class(&self, block_id: BlockId, class_hash: ClassHash) -> Result<ContractClass, Error>;
+    fn get_class(
+        &self,
+        block_id: BlockId,
+        class_hash: ClassHash,
+    ) -> Result<GatewayContractClass, Error>;
 
     /// Gets the contract class definition in the given block at the given address.
     #[method(name = "getClassAt")]
@@ -153,7 +221,7 @@ pub trait JsonRpc {
         &self,
         block_id: BlockId,
         contract_address: ContractAddress,
-    ) -> Result<ContractClass, Error>;
+    ) -> Result<GatewayContractClass, Error>;
 
     /// Gets the contract class hash in the given block for the contract deployed at the given
     /// address.
@@ -176,7 +244,71 @@ pub trait JsonRpc {
     #[method(name = "chainId")]
     fn chain_id(&self) -> Result<String, Error>;
 
+    /// Returns the version of the Starknet JSON-RPC specification the methods follow.
+    #[method(name = "specVersion")]
+    fn spec_version(&self) -> Result<String, Error>;
+
     /// Returns all events matching the given filter.
     #[method(name = "getEvents")]
     fn get_events(&self, filter: EventFilter) -> Result<EventsChunk, Error>;
+
+    /// Gets Merkle proofs of the state of the contract and of its storage at the given keys
+    /// against the state commitment of the given block.
+    #[method(name = "getProof")]
+    fn get_proof(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+        keys: Vec<StorageKey>,
+    ) -> Result<GetProofResult, Error>;
+
+    /// Calls a function of a contract on top of the state of the given block and returns the data
+    /// it returned, without creating a transaction.
+    #[method(name = "call")]
+    fn call(&self, request: FunctionCall, block_id: BlockId) -> Result<Vec<StarkFelt>, Error>;
+
+    /// Estimates the fee of the invoke transaction, if it were included on top of the given block.
+    #[method(name = "estimateFee")]
+    fn estimate_fee(
+        &self,
+        request: BroadcastedInvokeTransaction,
+        block_id: BlockId,
+    ) -> Result<FeeEstimate, Error>;
+
+    /// Gets the trace of the transaction, by re-executing the transactions of its block up to it
+    /// over the state before the block.
+    #[method(name = "traceTransaction")]
+    fn trace_transaction(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionTrace, Error>;
+
+    /// Gets the traces of the transactions of the block, by re-executing them over the state before
+    /// the block.
+    #[method(name = "traceBlockTransactions")]
+    fn trace_block_transactions(
+        &self,
+        block_id: BlockId,
+    ) -> Result<Vec<TransactionTraceWithHash>, Error>;
+
+    /// Sends the invoke transaction to the sequencer, to be added to its mempool.
+    #[method(name = "addInvokeTransaction")]
+    async fn add_invoke_transaction(
+        &self,
+        invoke_transaction: BroadcastedInvokeTransaction,
+    ) -> Result<AddInvokeTransactionResult, Error>;
+
+    /// Sends the declare transaction to the sequencer, to be added to its mempool.
+    #[method(name = "addDeclareTransaction")]
+    async fn add_declare_transaction(
+        &self,
+        declare_transaction: BroadcastedDeclareTransaction,
+    ) -> Result<AddDeclareTransactionResult, Error>;
+
+    /// Sends the deploy account transaction to the sequencer, to be added to its mempool.
+    #[method(name = "addDeployAccountTransaction")]
+    async fn add_deploy_account_transaction(
+        &self,
+        deploy_account_transaction: BroadcastedDeployAccountTransaction,
+    ) -> Result<AddDeployAccountTransactionResult, Error>;
 }
@@ -34,3 +34,20 @@ pub struct Block {
     pub header: BlockHeader,
     pub transactions: Transactions,
 }
+
+/// The block that is currently being constructed by the sequencer. It has no hash, number or state
+/// root yet.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct PendingBlock {
+    pub parent_hash: BlockHash,
+    pub sequencer_address: ContractAddress,
+    pub timestamp: BlockTimestamp,
+    pub transactions: Transactions,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+#[serde(untagged)]
+pub enum MaybePendingBlock {
+    Block(Block),
+    Pending(PendingBlock),
+}
@@ -1,47 +1,69 @@
 use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::ops::Index;
+use std::sync::{Arc, RwLock};
 
 use assert_matches::assert_matches;
 use indexmap::IndexMap;
 use jsonrpsee::core::Error;
 use jsonrpsee::http_client::HttpClientBuilder;
 use jsonrpsee::http_server::types::error::CallError;
-use jsonrpsee::types::error::ErrorObject;
+use jsonrpsee::types::error::{ErrorCode, ErrorObject};
 use jsonrpsee::types::EmptyParams;
 use jsonschema::JSONSchema;
+use mockito::{mock, Matcher};
+use papyrus_storage::base_layer::BaseLayerStorageWriter;
+use papyrus_storage::body::bloom::EventBloom;
 use papyrus_storage::body::BodyStorageWriter;
 use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_storage::pruning::{PruningConfig, PruningStorageWriter};
+use papyrus_storage::state::data::{Cairo1StateDiff, CompiledClassHash, SierraContractClass};
 use papyrus_storage::state::StateStorageWriter;
-use papyrus_storage::test_utils::get_test_storage;
+use papyrus_storage::test_utils::{get_test_storage, set_event_bloom};
 use papyrus_storage::{EventIndex, TransactionIndex};
-use starknet_api::block::{BlockHash, BlockHeader, BlockNumber, BlockStatus};
-use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
+use papyrus_sync::{PendingBlockHeader, PendingData};
+use starknet_api::block::{
+    BlockBody, BlockHash, BlockHeader, BlockNumber, BlockStatus, BlockTimestamp,
+};
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector, Nonce, PatriciaKey};
 use starknet_api::hash::{StarkFelt, StarkHash};
-use starknet_api::state::StateDiff;
+use starknet_api::state::{StateDiff, StorageKey};
 use starknet_api::transaction::{
-    EventIndexInTransactionOutput, EventKey, Transaction, TransactionHash, TransactionOffsetInBlock,
+    Calldata, DeclareTransaction, DeclareTransactionOutput, DeployTransaction,
+    DeployTransactionOutput, EventIndexInTransactionOutput, EventKey, Fee, Transaction,
+    TransactionHash, TransactionOffsetInBlock, TransactionSignature, TransactionVersion,
 };
 use starknet_api::{patricia_key, stark_felt};
 use test_utils::{
     get_rand_test_block_with_events, get_rand_test_body_with_events, get_rng, get_test_block,
-    get_test_body, get_test_state_diff, send_request, GetTestInstance,
+    get_test_body, get_test_state_diff, read_json_file, send_request, GetTestInstance,
 };
 
+use crate::add_transaction::{
+    AddDeclareTransactionResult, AddDeployAccountTransactionResult, AddInvokeTransactionResult,
+    BroadcastedDeclareTransaction, BroadcastedDeployAccountTransaction,
+};
 use crate::api::{
     BlockHashAndNumber, BlockHashOrNumber, BlockId, ContinuationToken, EventFilter, EventsChunk,
-    JsonRpcClient, JsonRpcError, Tag,
+    FeeEstimate, FunctionCall, JsonRpcClient, JsonRpcError, Tag, SPEC_VERSION,
+};
+use crate::block::{Block, PendingBlock};
+use crate::metrics::get_rpc_requests_count;
+use crate::proof::{verify_proof, GetProofResult};
+use crate::state::{
+    ClassHashes, ContractClass, GatewayContractClass, PendingStateUpdate, ReplacedClass,
+    StateUpdate, ThinStateDiff,
 };
-use crate::block::Block;
-use crate::state::{ContractClass, StateUpdate, ThinStateDiff};
 use crate::test_utils::{
     get_starknet_spec_api_schema, get_test_gateway_config, get_test_rpc_server_and_storage_writer,
+    get_test_rpc_server_with_pending_data,
 };
+use crate::trace::{TransactionTrace, TransactionTraceWithHash};
 use crate::transaction::{
-    Event, TransactionOutput, TransactionReceipt, TransactionReceiptWithStatus, TransactionStatus,
-    TransactionWithType, Transactions,
+    BroadcastedInvokeTransaction, Event, TransactionOutput, TransactionReceipt,
+    TransactionReceiptWithStatus, TransactionStatus, TransactionWithType, Transactions,
 };
-use crate::{run_server, ContinuationTokenAsStruct};
+use crate::{run_server, ContinuationTokenAsStruct, GatewayConfig};
 
 #[tokio::test]
 async fn block_number() {
@@ -268,6 +290,61 @@ async fn get_block_w_full_transactions() {
     ));
 }
 
+#[tokio::test]
+async fn get_block_accepted_on_l1() {
+    let (module, mut storage_writer) = get_test_rpc_server_and_storage_writer();
+
+    let block = get_test_block(1);
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block.header.block_number, &block.header)
+        .unwrap()
+        .append_body(block.header.block_number, block.body.clone())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let transaction_hash = block.body.transactions.index(0).transaction_hash();
+
+    // No block was proved on the base layer yet.
+    let block_id = BlockId::HashOrNumber(BlockHashOrNumber::Number(block.header.block_number));
+    let res = module.call::<_, Block>("starknet_getBlockWithTxHashes", [block_id]).await.unwrap();
+    assert_eq!(res.status, BlockStatus::AcceptedOnL2);
+    let err = module
+        .call::<_, Block>("starknet_getBlockWithTxHashes", [BlockId::Tag(Tag::L1)])
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::BlockNotFound as i32,
+        JsonRpcError::BlockNotFound.to_string(),
+        None::<()>,
+    ));
+
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .update_base_layer_block_marker(&block.header.block_number.next())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let res = module.call::<_, Block>("starknet_getBlockWithTxHashes", [block_id]).await.unwrap();
+    assert_eq!(res.status, BlockStatus::AcceptedOnL1);
+    let res = module
+        .call::<_, Block>("starknet_getBlockWithTxHashes", [BlockId::Tag(Tag::L1)])
+        .await
+        .unwrap();
+    assert_eq!(res.header.block_hash, block.header.block_hash);
+    let res = module
+        .call::<_, TransactionReceiptWithStatus>(
+            "starknet_getTransactionReceipt",
+            [transaction_hash],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status, TransactionStatus::AcceptedOnL1);
+}
+
 #[tokio::test]
 async fn get_storage_at() {
     let (module, mut storage_writer) = get_test_rpc_server_and_storage_writer();
@@ -535,6 +612,147 @@ async fn get_nonce() {
     ));
 }
 
+#[tokio::test]
+async fn get_proof() {
+    let (module, mut storage_writer) = get_test_rpc_server_and_storage_writer();
+    let header = BlockHeader::default();
+    let address = ContractAddress(patricia_key!("0x11"));
+    let class_hash = ClassHash(stark_felt!("0x4"));
+    let key = StorageKey(patricia_key!("0x101"));
+    let missing_key = StorageKey(patricia_key!("0x100"));
+    let diff = StateDiff {
+        deployed_contracts: IndexMap::from([(address, class_hash)]),
+        storage_diffs: IndexMap::from([(address, IndexMap::from([(key, stark_felt!("0x201"))]))]),
+        declared_classes: IndexMap::new(),
+        nonces: IndexMap::from([(address, Nonce(stark_felt!("0x1")))]),
+    };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(header.block_number, &header)
+        .unwrap()
+        .append_state_diff(header.block_number, diff, IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let keys = vec![key, missing_key];
+    let res = module
+        .call::<_, GetProofResult>(
+            "starknet_getProof",
+            (BlockId::HashOrNumber(BlockHashOrNumber::Number(header.block_number)), address, &keys),
+        )
+        .await
+        .unwrap();
+    let contract_data = res.contract_data.as_ref().unwrap();
+    assert_eq!(contract_data.class_hash, class_hash);
+    assert_eq!(contract_data.nonce, Nonce(stark_felt!("0x1")));
+    assert_eq!(
+        verify_proof(&res.state_commitment, &address, &keys, &res).unwrap(),
+        vec![stark_felt!("0x201"), StarkFelt::default()]
+    );
+
+    // The proof survives a JSON round trip.
+    let res: GetProofResult = serde_json::from_value(serde_json::to_value(&res).unwrap()).unwrap();
+    verify_proof(&res.state_commitment, &address, &keys, &res).unwrap();
+
+    // Ask for an invalid block number.
+    let err = module
+        .call::<_, GetProofResult>(
+            "starknet_getProof",
+            (BlockId::HashOrNumber(BlockHashOrNumber::Number(BlockNumber(1))), address, &keys),
+        )
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::BlockNotFound as i32,
+        JsonRpcError::BlockNotFound.to_string(),
+        None::<()>,
+    ));
+
+    // Ask for too many keys.
+    let keys = vec![key; get_test_gateway_config().max_proof_keys + 1];
+    let err = module
+        .call::<_, GetProofResult>(
+            "starknet_getProof",
+            (BlockId::HashOrNumber(BlockHashOrNumber::Number(header.block_number)), address, &keys),
+        )
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::TooManyKeysInProof as i32,
+        JsonRpcError::TooManyKeysInProof.to_string(),
+        None::<()>,
+    ));
+}
+
+#[tokio::test]
+async fn get_pruned_state() {
+    let (module, mut storage_writer) = get_test_rpc_server_and_storage_writer();
+    let address = ContractAddress(patricia_key!("0x11"));
+    let key = StorageKey(patricia_key!("0x101"));
+    for block_number in 0..3 {
+        let header = BlockHeader {
+            block_hash: BlockHash(StarkHash::from(block_number + 1)),
+            block_number: BlockNumber(block_number),
+            ..BlockHeader::default()
+        };
+        let mut diff = StateDiff {
+            storage_diffs: IndexMap::from([(
+                address,
+                IndexMap::from([(key, StarkFelt::from(block_number + 1))]),
+            )]),
+            ..StateDiff::default()
+        };
+        if block_number == 0 {
+            diff.deployed_contracts = IndexMap::from([(address, ClassHash(stark_felt!("0x4")))]);
+        }
+        storage_writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_header(header.block_number, &header)
+            .unwrap()
+            .append_state_diff(header.block_number, diff, IndexMap::new())
+            .unwrap()
+            .commit()
+            .unwrap();
+    }
+    let pruning_config = PruningConfig { history_length: 1, ..PruningConfig::default() };
+    let (txn, _) = storage_writer.begin_rw_txn().unwrap().prune(&pruning_config).unwrap();
+    txn.commit().unwrap();
+
+    // The state after block 1 is kept.
+    let res = module
+        .call::<_, StarkFelt>(
+            "starknet_getStorageAt",
+            (address, key, BlockId::HashOrNumber(BlockHashOrNumber::Number(BlockNumber(1)))),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res, StarkFelt::from(2));
+
+    // The state after block 0 was pruned.
+    let block_id = BlockId::HashOrNumber(BlockHashOrNumber::Number(BlockNumber(0)));
+    let err = module
+        .call::<_, StarkFelt>("starknet_getStorageAt", (address, key, block_id))
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::BlockPruned as i32,
+        JsonRpcError::BlockPruned.to_string(),
+        None::<()>,
+    ));
+    let err = module
+        .call::<_, GetProofResult>("starknet_getProof", (block_id, address, vec![key]))
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::BlockPruned as i32,
+        JsonRpcError::BlockPruned.to_string(),
+        None::<()>,
+    ));
+}
+
 #[tokio::test]
 async fn get_transaction_by_hash() {
     let (module, mut storage_writer) = get_test_rpc_server_and_storage_writer();
@@ -1113,6 +1331,119 @@ async fn get_class_at() {
     ));
 }
 
+#[tokio::test]
+async fn get_sierra_class() {
+    let (module, mut storage_writer) = get_test_rpc_server_and_storage_writer();
+    let address = ContractAddress(patricia_key!("0x11"));
+    let class_hash = ClassHash(stark_felt!("0x4"));
+    let sierra_class_hash = ClassHash(stark_felt!("0x5"));
+    let compiled_class_hash = CompiledClassHash(stark_felt!("0x6"));
+    let sierra_class = SierraContractClass {
+        sierra_program: vec![stark_felt!("0x1")],
+        contract_class_version: "0.1.0".to_owned(),
+        ..SierraContractClass::default()
+    };
+    let declare_tx = DeclareTransaction {
+        class_hash: sierra_class_hash,
+        ..DeclareTransaction::get_test_instance(&mut get_rng())
+    };
+    let state_diff = StateDiff {
+        deployed_contracts: IndexMap::from([(address, class_hash)]),
+        declared_classes: IndexMap::from([(
+            class_hash,
+            starknet_api::state::ContractClass::default(),
+        )]),
+        ..StateDiff::default()
+    };
+    let cairo1_state_diff = Cairo1StateDiff {
+        declared_classes: IndexMap::from([(
+            sierra_class_hash,
+            (compiled_class_hash, sierra_class.clone()),
+        )]),
+        replaced_classes: IndexMap::from([(address, sierra_class_hash)]),
+    };
+    let body = BlockBody {
+        transactions: vec![Transaction::Declare(declare_tx.clone())],
+        transaction_outputs: vec![starknet_api::transaction::TransactionOutput::Declare(
+            DeclareTransactionOutput::default(),
+        )],
+    };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &BlockHeader::default())
+        .unwrap()
+        .append_body(BlockNumber(0), BlockBody::default())
+        .unwrap()
+        .append_state_diff(BlockNumber(0), state_diff, IndexMap::new())
+        .unwrap()
+        .append_header(
+            BlockNumber(1),
+            &BlockHeader { block_number: BlockNumber(1), ..BlockHeader::default() },
+        )
+        .unwrap()
+        .append_body(BlockNumber(1), body)
+        .unwrap()
+        .append_state_diff_with_cairo1(
+            BlockNumber(1),
+            StateDiff::default(),
+            cairo1_state_diff,
+            IndexMap::new(),
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+    let latest = BlockId::Tag(Tag::Latest);
+
+    let res = module
+        .call::<_, GatewayContractClass>("starknet_getClass", (latest, sierra_class_hash))
+        .await
+        .unwrap();
+    assert_eq!(res, GatewayContractClass::Sierra(sierra_class.clone().into()));
+
+    // The class of the contract was replaced by the Sierra class.
+    let res =
+        module.call::<_, ClassHash>("starknet_getClassHashAt", (latest, address)).await.unwrap();
+    assert_eq!(res, sierra_class_hash);
+    let res = module
+        .call::<_, GatewayContractClass>("starknet_getClassAt", (latest, address))
+        .await
+        .unwrap();
+    assert_eq!(res, GatewayContractClass::Sierra(sierra_class.into()));
+    let res = module
+        .call::<_, ClassHash>(
+            "starknet_getClassHashAt",
+            (BlockId::HashOrNumber(BlockHashOrNumber::Number(BlockNumber(0))), address),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res, class_hash);
+
+    // The declare transaction has the compiled class hash of the Sierra class.
+    let res = module
+        .call::<_, TransactionWithType>(
+            "starknet_getTransactionByHash",
+            [declare_tx.transaction_hash],
+        )
+        .await
+        .unwrap();
+    assert_matches!(
+        res.transaction,
+        crate::transaction::Transaction::Declare(tx)
+        if tx.compiled_class_hash == Some(compiled_class_hash)
+    );
+
+    let res = module.call::<_, StateUpdate>("starknet_getStateUpdate", [latest]).await.unwrap();
+    assert_eq!(
+        res.state_diff.declared_classes,
+        vec![ClassHashes { class_hash: sierra_class_hash, compiled_class_hash }]
+    );
+    assert_eq!(
+        res.state_diff.replaced_classes,
+        vec![ReplacedClass { contract_address: address, class_hash: sierra_class_hash }]
+    );
+}
+
 #[tokio::test]
 async fn chain_id() {
     let (module, _) = get_test_rpc_server_and_storage_writer();
@@ -1174,8 +1505,8 @@ async fn get_events_chunk_size_2_with_address() {
             if let Some(key) = event.content.keys.get(0) {
                 if filter_keys.get(key).is_some() && event.from_address == address {
                     emitted_events.push(Event {
-                        block_hash,
-                        block_number,
+                        block_hash: Some(block_hash),
+                        block_number: Some(block_number),
                         transaction_hash,
                         event: event.clone(),
                     });
@@ -1254,8 +1585,8 @@ async fn get_events_chunk_size_2_without_address() {
             if let Some(key) = event.content.keys.get(0) {
                 if filter_keys.get(key).is_some() {
                     emitted_events.push(Event {
-                        block_hash,
-                        block_number,
+                        block_hash: Some(block_hash),
+                        block_number: Some(block_number),
                         transaction_hash,
                         event: event.clone(),
                     });
@@ -1288,6 +1619,58 @@ async fn get_events_chunk_size_2_without_address() {
     }
 }
 
+#[tokio::test]
+async fn get_events_skips_blocks_by_bloom() {
+    let (module, mut storage_writer) = get_test_rpc_server_and_storage_writer();
+    let address = ContractAddress(patricia_key!("0x22"));
+    let key = EventKey(stark_felt!("0x6"));
+    let mut rng = get_rng();
+    for i in 0..2 {
+        let block_number = BlockNumber(i);
+        let header = BlockHeader {
+            block_number,
+            block_hash: BlockHash(StarkHash::from(i + 1)),
+            ..BlockHeader::default()
+        };
+        let body = get_rand_test_body_with_events(
+            &mut rng,
+            2,
+            2,
+            Some(vec![address]),
+            Some(vec![vec![key.clone()]]),
+        );
+        storage_writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_header(block_number, &header)
+            .unwrap()
+            .append_body(block_number, body)
+            .unwrap()
+            .commit()
+            .unwrap();
+    }
+    // A filter without bits can't match any keys, so the events of block 0 aren't read although
+    // they have the key.
+    set_event_bloom(&mut storage_writer, BlockNumber(0), &EventBloom::default());
+
+    let mut filter = EventFilter {
+        from_block: None,
+        to_block: None,
+        continuation_token: None,
+        chunk_size: 10,
+        address: Some(address),
+        keys: vec![HashSet::from([key])],
+    };
+    let res = module.call::<_, EventsChunk>("starknet_getEvents", [filter.clone()]).await.unwrap();
+    assert_eq!(res.events.len(), 4);
+    assert!(res.events.iter().all(|event| event.block_number == Some(BlockNumber(1))));
+
+    // The filters aren't read without keys.
+    filter.keys = vec![];
+    let res = module.call::<_, EventsChunk>("starknet_getEvents", [filter]).await.unwrap();
+    assert_eq!(res.events.len(), 8);
+}
+
 #[tokio::test]
 async fn get_events_page_size_too_big() {
     let (module, _) = get_test_rpc_server_and_storage_writer();
@@ -1426,11 +1809,589 @@ async fn get_events_invalid_ct() {
     ));
 }
 
+#[tokio::test]
+async fn get_block_pending() {
+    let (module, mut storage_writer, pending_data) = get_test_rpc_server_with_pending_data();
+    let block = get_test_block(1);
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block.header.block_number, &block.header)
+        .unwrap()
+        .append_body(block.header.block_number, block.body.clone())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let expected_block = Block {
+        status: BlockStatus::AcceptedOnL2,
+        header: block.header.clone().into(),
+        transactions: Transactions::Hashes(vec![block
+            .body
+            .transactions
+            .index(0)
+            .transaction_hash()]),
+    };
+
+    // Without a pending block, the pending tag refers to the latest block.
+    let res = module
+        .call::<_, Block>("starknet_getBlockWithTxHashes", [BlockId::Tag(Tag::Pending)])
+        .await
+        .unwrap();
+    assert_eq!(res, expected_block);
+
+    // A pending block that doesn't extend the latest block is ignored.
+    let pending_body = get_test_body(2);
+    *pending_data.write().unwrap() = Some(PendingData {
+        header: PendingBlockHeader {
+            parent_hash: BlockHash(stark_felt!("0x1234")),
+            ..PendingBlockHeader::default()
+        },
+        body: pending_body.clone(),
+        ..PendingData::default()
+    });
+    let res = module
+        .call::<_, Block>("starknet_getBlockWithTxHashes", [BlockId::Tag(Tag::Pending)])
+        .await
+        .unwrap();
+    assert_eq!(res, expected_block);
+
+    // Extend the latest block.
+    pending_data.write().unwrap().as_mut().unwrap().header.parent_hash = block.header.block_hash;
+    let res = module
+        .call::<_, PendingBlock>("starknet_getBlockWithTxHashes", [BlockId::Tag(Tag::Pending)])
+        .await
+        .unwrap();
+    assert_eq!(
+        res,
+        PendingBlock {
+            parent_hash: block.header.block_hash,
+            sequencer_address: ContractAddress::default(),
+            timestamp: BlockTimestamp::default(),
+            transactions: Transactions::Hashes(
+                pending_body.transactions.iter().map(|tx| tx.transaction_hash()).collect()
+            ),
+        }
+    );
+
+    let res = module
+        .call::<_, PendingBlock>("starknet_getBlockWithTxs", [BlockId::Tag(Tag::Pending)])
+        .await
+        .unwrap();
+    assert_eq!(
+        res.transactions,
+        Transactions::Full(
+            pending_body.transactions.iter().cloned().map(TransactionWithType::from).collect()
+        )
+    );
+
+    let res = module
+        .call::<_, usize>("starknet_getBlockTransactionCount", [BlockId::Tag(Tag::Pending)])
+        .await
+        .unwrap();
+    assert_eq!(res, pending_body.transactions.len());
+
+    let expected_transaction =
+        TransactionWithType::from(pending_body.transactions.index(1).clone());
+    let res = module
+        .call::<_, TransactionWithType>(
+            "starknet_getTransactionByBlockIdAndIndex",
+            (BlockId::Tag(Tag::Pending), TransactionOffsetInBlock(1)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res, expected_transaction);
+
+    // The transaction exists only in the pending block.
+    let res = module
+        .call::<_, TransactionWithType>(
+            "starknet_getTransactionByHash",
+            [pending_body.transactions.index(1).transaction_hash()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res, expected_transaction);
+}
+
+#[tokio::test]
+async fn get_state_pending() {
+    let (module, mut storage_writer, pending_data) = get_test_rpc_server_with_pending_data();
+    let header = BlockHeader::default();
+    let diff = get_test_state_diff();
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(header.block_number, &header)
+        .unwrap()
+        .append_state_diff(header.block_number, diff.clone(), IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let (address, storage_entries) = diff.storage_diffs.get_index(0).unwrap();
+    let (key, value) = storage_entries.get_index(0).unwrap();
+    let pending_address = ContractAddress(patricia_key!("0x1234"));
+    let pending_class_hash = ClassHash(stark_felt!("0x5678"));
+    let pending_value = stark_felt!("0x9");
+    let pending_nonce = Nonce(stark_felt!("0x3"));
+    let pending_state_diff = StateDiff {
+        deployed_contracts: IndexMap::from([(pending_address, pending_class_hash)]),
+        storage_diffs: IndexMap::from([(*address, IndexMap::from([(*key, pending_value)]))]),
+        declared_classes: IndexMap::new(),
+        nonces: IndexMap::from([(*address, pending_nonce)]),
+    };
+    *pending_data.write().unwrap() = Some(PendingData {
+        header: PendingBlockHeader {
+            parent_hash: header.block_hash,
+            ..PendingBlockHeader::default()
+        },
+        state_diff: pending_state_diff.clone(),
+        ..PendingData::default()
+    });
+
+    // The latest block is not affected by the pending data.
+    let res = module
+        .call::<_, StarkFelt>("starknet_getStorageAt", (*address, *key, BlockId::Tag(Tag::Latest)))
+        .await
+        .unwrap();
+    assert_eq!(res, *value);
+    let err = module
+        .call::<_, ClassHash>(
+            "starknet_getClassHashAt",
+            (BlockId::Tag(Tag::Latest), pending_address),
+        )
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::ContractNotFound as i32,
+        JsonRpcError::ContractNotFound.to_string(),
+        None::<()>,
+    ));
+
+    // The pending data is layered on top of the latest block.
+    let res = module
+        .call::<_, StarkFelt>("starknet_getStorageAt", (*address, *key, BlockId::Tag(Tag::Pending)))
+        .await
+        .unwrap();
+    assert_eq!(res, pending_value);
+    let res = module
+        .call::<_, StarkFelt>(
+            "starknet_getStorageAt",
+            (pending_address, *key, BlockId::Tag(Tag::Pending)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res, StarkFelt::default());
+    let res = module
+        .call::<_, Nonce>("starknet_getNonce", (BlockId::Tag(Tag::Pending), *address))
+        .await
+        .unwrap();
+    assert_eq!(res, pending_nonce);
+    let res = module
+        .call::<_, Nonce>("starknet_getNonce", (BlockId::Tag(Tag::Pending), pending_address))
+        .await
+        .unwrap();
+    assert_eq!(res, Nonce::default());
+    let res = module
+        .call::<_, ClassHash>(
+            "starknet_getClassHashAt",
+            (BlockId::Tag(Tag::Pending), pending_address),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res, pending_class_hash);
+
+    let res = module
+        .call::<_, PendingStateUpdate>("starknet_getStateUpdate", [BlockId::Tag(Tag::Pending)])
+        .await
+        .unwrap();
+    assert_eq!(
+        res,
+        PendingStateUpdate {
+            old_root: header.state_root,
+            state_diff: ThinStateDiff::from(papyrus_storage::state::data::ThinStateDiff::from(
+                pending_state_diff
+            )),
+        }
+    );
+}
+
+#[tokio::test]
+async fn get_events_pending() {
+    let (module, mut storage_writer, pending_data) = get_test_rpc_server_with_pending_data();
+    let mut rng = get_rng();
+    let block = get_rand_test_block_with_events(&mut rng, 1, 2, None, None);
+    let block_number = block.header.block_number;
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block_number, &block.header)
+        .unwrap()
+        .append_body(block_number, block.body.clone())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let pending_body = get_rand_test_body_with_events(&mut rng, 2, 2, None, None);
+    *pending_data.write().unwrap() = Some(PendingData {
+        header: PendingBlockHeader {
+            parent_hash: block.header.block_hash,
+            ..PendingBlockHeader::default()
+        },
+        body: pending_body.clone(),
+        ..PendingData::default()
+    });
+
+    // The events of the stored block are followed by the events of the pending block.
+    let mut emitted_events = vec![];
+    let mut emitted_event_indices = vec![];
+    for (body, maybe_block_number) in [(&block.body, Some(block_number)), (&pending_body, None)] {
+        let event_block_number = maybe_block_number.unwrap_or(block_number.next());
+        for (tx_i, tx_output) in body.transaction_outputs.iter().enumerate() {
+            let transaction_hash = body.transactions.index(tx_i).transaction_hash();
+            for (event_i, event) in tx_output.events().iter().enumerate() {
+                emitted_events.push(Event {
+                    block_hash: maybe_block_number.map(|_| block.header.block_hash),
+                    block_number: maybe_block_number,
+                    transaction_hash,
+                    event: event.clone(),
+                });
+                emitted_event_indices.push(EventIndex(
+                    TransactionIndex(event_block_number, TransactionOffsetInBlock(tx_i)),
+                    EventIndexInTransactionOutput(event_i),
+                ));
+            }
+        }
+    }
+
+    let chunk_size = 4;
+    let mut filter = EventFilter {
+        from_block: None,
+        to_block: Some(BlockId::Tag(Tag::Pending)),
+        continuation_token: None,
+        chunk_size,
+        address: None,
+        keys: vec![],
+    };
+    for (i, chunk) in emitted_events.chunks(chunk_size).enumerate() {
+        let res =
+            module.call::<_, EventsChunk>("starknet_getEvents", [filter.clone()]).await.unwrap();
+        assert_eq!(res.events, chunk);
+        let index = (i + 1) * chunk_size;
+        let expected_continuation_token = if index < emitted_event_indices.len() {
+            Some(
+                ContinuationToken::new(ContinuationTokenAsStruct(
+                    *emitted_event_indices.index(index),
+                ))
+                .unwrap(),
+            )
+        } else {
+            None
+        };
+        assert_eq!(res.continuation_token, expected_continuation_token);
+        filter.continuation_token = res.continuation_token;
+    }
+
+    // Only the pending events.
+    filter.from_block = Some(BlockId::Tag(Tag::Pending));
+    filter.continuation_token = None;
+    filter.chunk_size = emitted_events.len();
+    let res = module.call::<_, EventsChunk>("starknet_getEvents", [filter]).await.unwrap();
+    assert_eq!(res.events, emitted_events[block.body.transaction_outputs[0].events().len()..]);
+}
+
+#[tokio::test]
+async fn call() {
+    let (module, mut storage_writer) = get_test_rpc_server_and_storage_writer();
+    let address = ContractAddress(patricia_key!("0x100"));
+    let key = StorageKey(patricia_key!("0x10"));
+    let class_hash = ClassHash(stark_felt!("0x1"));
+    let class = serde_json::from_value(read_json_file(
+        "../../papyrus_execution/resources/test_contract.json",
+    ))
+    .unwrap();
+    let state_diff = StateDiff {
+        deployed_contracts: IndexMap::from([(address, class_hash)]),
+        storage_diffs: IndexMap::from([(address, IndexMap::from([(key, stark_felt!("0x1234"))]))]),
+        declared_classes: IndexMap::from([(class_hash, class)]),
+        nonces: IndexMap::new(),
+    };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &BlockHeader::default())
+        .unwrap()
+        .append_state_diff(BlockNumber(0), state_diff, IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    // The get_value entry point of the test contract returns the value of the storage key.
+    let request = FunctionCall {
+        contract_address: address,
+        entry_point_selector: EntryPointSelector(stark_felt!(
+            "0x26813d396fdb198e9ead934e4f7a592a8b88a059e45ab0eb6ee53494e8d45b0"
+        )),
+        calldata: Calldata(Arc::new(vec![*key.0.key()])),
+    };
+    let block_id = BlockId::HashOrNumber(BlockHashOrNumber::Number(BlockNumber(0)));
+    let retdata = module
+        .call::<_, Vec<StarkFelt>>("starknet_call", (request.clone(), block_id))
+        .await
+        .unwrap();
+    assert_eq!(retdata, vec![stark_felt!("0x1234")]);
+
+    // Ask for an entry point the contract doesn't have.
+    let err = module
+        .call::<_, Vec<StarkFelt>>(
+            "starknet_call",
+            (
+                FunctionCall {
+                    entry_point_selector: EntryPointSelector::default(),
+                    ..request.clone()
+                },
+                block_id,
+            ),
+        )
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::ContractError as i32,
+        JsonRpcError::ContractError.to_string(),
+        None::<()>,
+    ));
+
+    // Ask for a contract that doesn't exist.
+    let err = module
+        .call::<_, Vec<StarkFelt>>(
+            "starknet_call",
+            (
+                FunctionCall {
+                    contract_address: ContractAddress(patricia_key!("0x101")),
+                    ..request
+                },
+                block_id,
+            ),
+        )
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::ContractNotFound as i32,
+        JsonRpcError::ContractNotFound.to_string(),
+        None::<()>,
+    ));
+}
+
+#[tokio::test]
+async fn estimate_fee_unknown_sender() {
+    let (module, mut storage_writer) = get_test_rpc_server_and_storage_writer();
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &BlockHeader::default())
+        .unwrap()
+        .append_state_diff(BlockNumber(0), StateDiff::default(), IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let request = BroadcastedInvokeTransaction {
+        version: TransactionVersion(stark_felt!("0x1")),
+        sender_address: ContractAddress(patricia_key!("0x100")),
+        ..BroadcastedInvokeTransaction::default()
+    };
+    let err = module
+        .call::<_, FeeEstimate>("starknet_estimateFee", (request, BlockId::Tag(Tag::Latest)))
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::ContractNotFound as i32,
+        JsonRpcError::ContractNotFound.to_string(),
+        None::<()>,
+    ));
+}
+
+#[tokio::test]
+async fn estimate_fee_unsupported_version() {
+    let (module, _) = get_test_rpc_server_and_storage_writer();
+    let request = BroadcastedInvokeTransaction {
+        version: TransactionVersion(stark_felt!("0x0")),
+        sender_address: ContractAddress(patricia_key!("0x100")),
+        ..BroadcastedInvokeTransaction::default()
+    };
+    let err = module
+        .call::<_, FeeEstimate>("starknet_estimateFee", (request, BlockId::Tag(Tag::Latest)))
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::UnsupportedTxVersion as i32,
+        JsonRpcError::UnsupportedTxVersion.to_string(),
+        None::<()>,
+    ));
+}
+
+#[tokio::test]
+async fn add_transactions() {
+    let (module, _) = get_test_rpc_server_and_storage_writer();
+
+    let invoke_tx = BroadcastedInvokeTransaction {
+        version: TransactionVersion(stark_felt!("0x1")),
+        sender_address: ContractAddress(patricia_key!("0x100")),
+        ..BroadcastedInvokeTransaction::default()
+    };
+    let mock_invoke = mock("POST", "/gateway/add_transaction")
+        .match_body(Matcher::PartialJson(serde_json::json!({"type": "INVOKE_FUNCTION"})))
+        .with_status(200)
+        .with_body(r#"{"code": "TRANSACTION_RECEIVED", "transaction_hash": "0x1"}"#)
+        .create();
+    let res = module
+        .call::<_, AddInvokeTransactionResult>("starknet_addInvokeTransaction", [invoke_tx.clone()])
+        .await
+        .unwrap();
+    mock_invoke.assert();
+    assert_eq!(
+        res,
+        AddInvokeTransactionResult { transaction_hash: TransactionHash(stark_felt!("0x1")) }
+    );
+    drop(mock_invoke);
+
+    let declare_tx = BroadcastedDeclareTransaction {
+        contract_class: GatewayContractClass::Sierra(crate::state::SierraContractClass {
+            sierra_program: vec![stark_felt!("0x1")],
+            contract_class_version: "0.1.0".to_owned(),
+            ..crate::state::SierraContractClass::default()
+        }),
+        sender_address: ContractAddress(patricia_key!("0x100")),
+        max_fee: Fee(100),
+        version: TransactionVersion(stark_felt!("0x2")),
+        signature: TransactionSignature::default(),
+        nonce: Nonce::default(),
+        compiled_class_hash: Some(CompiledClassHash(stark_felt!("0x3"))),
+    };
+    let mock_declare = mock("POST", "/gateway/add_transaction")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "type": "DECLARE",
+            "contract_class": {"contract_class_version": "0.1.0"},
+        })))
+        .with_status(200)
+        .with_body(
+            r#"{"code": "TRANSACTION_RECEIVED", "transaction_hash": "0x2", "class_hash": "0x4"}"#,
+        )
+        .create();
+    let res = module
+        .call::<_, AddDeclareTransactionResult>("starknet_addDeclareTransaction", [declare_tx])
+        .await
+        .unwrap();
+    mock_declare.assert();
+    assert_eq!(
+        res,
+        AddDeclareTransactionResult {
+            transaction_hash: TransactionHash(stark_felt!("0x2")),
+            class_hash: ClassHash(stark_felt!("0x4")),
+        }
+    );
+
+    let deploy_account_tx = BroadcastedDeployAccountTransaction {
+        class_hash: ClassHash(stark_felt!("0x4")),
+        version: TransactionVersion(stark_felt!("0x1")),
+        ..BroadcastedDeployAccountTransaction::default()
+    };
+    let mock_deploy_account = mock("POST", "/gateway/add_transaction")
+        .match_body(Matcher::PartialJson(serde_json::json!({"type": "DEPLOY_ACCOUNT"})))
+        .with_status(200)
+        .with_body(
+            r#"{"code": "TRANSACTION_RECEIVED", "transaction_hash": "0x5", "address": "0x6"}"#,
+        )
+        .create();
+    let res = module
+        .call::<_, AddDeployAccountTransactionResult>(
+            "starknet_addDeployAccountTransaction",
+            [deploy_account_tx],
+        )
+        .await
+        .unwrap();
+    mock_deploy_account.assert();
+    assert_eq!(
+        res,
+        AddDeployAccountTransactionResult {
+            transaction_hash: TransactionHash(stark_felt!("0x5")),
+            contract_address: ContractAddress(patricia_key!("0x6")),
+        }
+    );
+
+    // The sequencer's errors on invalid transactions are returned as the errors of the spec.
+    let body =
+        r#"{"code": "StarknetErrorCode.INVALID_TRANSACTION_NONCE", "message": "Invalid nonce."}"#;
+    let mock_rejected =
+        mock("POST", "/gateway/add_transaction").with_status(400).with_body(body).create();
+    let err = module
+        .call::<_, AddInvokeTransactionResult>("starknet_addInvokeTransaction", [invoke_tx])
+        .await
+        .unwrap_err();
+    mock_rejected.assert();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::InvalidTransactionNonce as i32,
+        JsonRpcError::InvalidTransactionNonce.to_string(),
+        None::<()>,
+    ));
+}
+
+#[tokio::test]
+async fn trace_transaction_unknown_hash() {
+    let (module, _) = get_test_rpc_server_and_storage_writer();
+    let err = module
+        .call::<_, TransactionTrace>("starknet_traceTransaction", [TransactionHash::default()])
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::TransactionHashNotFound as i32,
+        JsonRpcError::TransactionHashNotFound.to_string(),
+        None::<()>,
+    ));
+}
+
+#[tokio::test]
+async fn trace_block_transactions_deploy() {
+    let (module, mut storage_writer) = get_test_rpc_server_and_storage_writer();
+    // Deploy transactions were removed from Starknet and can't be re-executed, their trace has the
+    // reason instead of the invocations.
+    let block = starknet_api::block::Block {
+        body: BlockBody {
+            transactions: vec![Transaction::Deploy(DeployTransaction::default())],
+            transaction_outputs: vec![starknet_api::transaction::TransactionOutput::Deploy(
+                DeployTransactionOutput::default(),
+            )],
+        },
+        ..starknet_api::block::Block::default()
+    };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &block.header)
+        .unwrap()
+        .append_body(BlockNumber(0), block.body)
+        .unwrap()
+        .append_state_diff(BlockNumber(0), StateDiff::default(), IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let traces = module
+        .call::<_, Vec<TransactionTraceWithHash>>(
+            "starknet_traceBlockTransactions",
+            [BlockId::Tag(Tag::Latest)],
+        )
+        .await
+        .unwrap();
+    assert_eq!(traces.len(), 1);
+    assert!(traces[0].trace_root.failure_reason.is_some());
+    assert!(traces[0].trace_root.execute_invocation.is_none());
+}
+
 #[tokio::test]
 async fn run_server_no_blocks() {
     let (storage_reader, _) = get_test_storage();
     let gateway_config = get_test_gateway_config();
-    let (addr, _handle) = run_server(&gateway_config, storage_reader).await.unwrap();
+    let (addr, _handle) =
+        run_server(&gateway_config, storage_reader, Arc::new(RwLock::new(None))).await.unwrap();
     let client = HttpClientBuilder::default().build(format!("http://{addr:?}")).unwrap();
     let err = client.block_number().await.unwrap_err();
     assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
@@ -1440,6 +2401,65 @@ async fn run_server_no_blocks() {
     ));
 }
 
+#[tokio::test]
+async fn run_server_records_metrics() {
+    let (storage_reader, _) = get_test_storage();
+    let gateway_config = get_test_gateway_config();
+    let (addr, _handle) =
+        run_server(&gateway_config, storage_reader, Arc::new(RwLock::new(None))).await.unwrap();
+    let client = HttpClientBuilder::default().build(format!("http://{addr:?}")).unwrap();
+
+    // Other tests may run servers in parallel, so the counters are only checked to increase.
+    let successes_before = get_rpc_requests_count("starknet_chainId", "success");
+    let failures_before = get_rpc_requests_count("starknet_blockNumber", "failure");
+    client.chain_id().await.unwrap();
+    client.block_number().await.unwrap_err();
+    assert!(get_rpc_requests_count("starknet_chainId", "success") > successes_before);
+    assert!(get_rpc_requests_count("starknet_blockNumber", "failure") > failures_before);
+}
+
+#[tokio::test]
+async fn run_server_serves_spec_versions() {
+    let (storage_reader, _) = get_test_storage();
+    let gateway_config = get_test_gateway_config();
+    let (addr, _handle) =
+        run_server(&gateway_config, storage_reader, Arc::new(RwLock::new(None))).await.unwrap();
+
+    // The root path serves the latest version.
+    for (path, spec_version) in [
+        ("", SPEC_VERSION),
+        ("/rpc/v0.3", SPEC_VERSION),
+        ("/rpc/v0.3/", SPEC_VERSION),
+        ("/rpc/v0.2", crate::v0_2::SPEC_VERSION),
+    ] {
+        let client = HttpClientBuilder::default().build(format!("http://{addr:?}{path}")).unwrap();
+        assert_eq!(client.spec_version().await.unwrap(), spec_version);
+    }
+
+    // Methods added after a version aren't served on its path.
+    let client = HttpClientBuilder::default().build(format!("http://{addr:?}/rpc/v0.2")).unwrap();
+    let err = client.trace_transaction(TransactionHash::default()).await.unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err))
+        if err.code() == ErrorCode::MethodNotFound.code());
+
+    let client = HttpClientBuilder::default().build(format!("http://{addr:?}/rpc/v0.1")).unwrap();
+    assert!(client.chain_id().await.is_err());
+}
+
+#[tokio::test]
+async fn run_server_on_hostname_and_stop() {
+    let (storage_reader, _) = get_test_storage();
+    let gateway_config =
+        GatewayConfig { server_address: "localhost:0".to_owned(), ..get_test_gateway_config() };
+    let (addr, handle) =
+        run_server(&gateway_config, storage_reader, Arc::new(RwLock::new(None))).await.unwrap();
+    let client = HttpClientBuilder::default().build(format!("http://{addr:?}")).unwrap();
+    client.chain_id().await.unwrap();
+
+    handle.stop().await.unwrap().unwrap();
+    assert!(client.chain_id().await.is_err());
+}
+
 #[tokio::test]
 async fn serialize_returns_valid_json() {
     let (storage_reader, mut storage_writer) = get_test_storage();
@@ -1474,7 +2494,8 @@ async fn serialize_returns_valid_json() {
         .unwrap();
 
     let gateway_config = get_test_gateway_config();
-    let (server_address, _handle) = run_server(&gateway_config, storage_reader).await.unwrap();
+    let (server_address, _handle) =
+        run_server(&gateway_config, storage_reader, Arc::new(RwLock::new(None))).await.unwrap();
 
     let schema = get_starknet_spec_api_schema(&[
         "BLOCK_WITH_TXS",
@@ -1,61 +1,116 @@
+mod add_transaction;
 mod api;
 mod block;
 #[cfg(test)]
 mod gateway_test;
+mod metrics;
+mod papyrus;
+#[cfg(test)]
+mod papyrus_test;
+pub mod proof;
+mod router;
 mod state;
+mod subscription;
+#[cfg(test)]
+mod subscription_test;
 #[cfg(test)]
 mod test_utils;
+mod trace;
 mod transaction;
+mod v0_2;
+#[cfg(test)]
+mod v0_2_test;
 
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
 
 use jsonrpsee::core::{async_trait, Error};
 use jsonrpsee::http_server::types::error::CallError;
-use jsonrpsee::http_server::{HttpServerBuilder, HttpServerHandle};
 use jsonrpsee::types::error::ErrorCode::InternalError;
 use jsonrpsee::types::error::{ErrorObject, INTERNAL_ERROR_MSG};
+use jsonrpsee::ws_server::{WsServerBuilder, WsServerHandle};
+use jsonrpsee::Methods;
+use papyrus_execution::{
+    estimate_fee, execute_call, trace_block_transactions, ExecutionConfig, ExecutionError,
+};
+use papyrus_storage::base_layer::BaseLayerStorageReader;
 use papyrus_storage::body::events::EventsReader;
 use papyrus_storage::body::BodyStorageReader;
 use papyrus_storage::db::TransactionKind;
 use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::pruning::PruningStorageReader;
+use papyrus_storage::state::data::Cairo1StateDiff;
 use papyrus_storage::state::StateStorageReader;
 use papyrus_storage::{EventIndex, StorageReader, StorageTxn, TransactionIndex};
+use papyrus_sync::{PendingData, SyncNotification};
 use serde::{Deserialize, Serialize};
-use starknet_api::block::{BlockNumber, BlockStatus};
+use starknet_api::block::{BlockHash, BlockNumber, BlockStatus};
 use starknet_api::core::{ChainId, ClassHash, ContractAddress, GlobalRoot, Nonce};
 use starknet_api::hash::{StarkFelt, StarkHash, GENESIS_HASH};
 use starknet_api::state::{StateNumber, StorageKey};
 use starknet_api::transaction::{
-    EventIndexInTransactionOutput, TransactionHash, TransactionOffsetInBlock,
+    EventIndexInTransactionOutput, EventKey, TransactionHash, TransactionOffsetInBlock,
 };
+use starknet_client::{AddTransaction, RetryConfig, StarknetClient};
+use tokio::sync::broadcast;
 use tracing::{debug, error, info, instrument};
 
+use crate::add_transaction::{
+    add_transaction_error, AddDeclareTransactionResult, AddDeployAccountTransactionResult,
+    AddInvokeTransactionResult, BroadcastedDeclareTransaction, BroadcastedDeployAccountTransaction,
+};
 use crate::api::{
     BlockHashAndNumber, BlockHashOrNumber, BlockId, ContinuationToken, EventFilter, EventsChunk,
-    JsonRpcError, JsonRpcServer, Tag,
+    FeeEstimate, FunctionCall, JsonRpcError, JsonRpcServer, Tag, SPEC_VERSION,
+};
+use crate::block::{Block, BlockHeader, MaybePendingBlock, PendingBlock};
+use crate::metrics::MetricsMiddleware;
+use crate::papyrus::{JsonRpcPapyrusImpl, JsonRpcPapyrusServer, CHUNK_SIZE};
+use crate::proof::GetProofResult;
+use crate::router::run_router;
+pub use crate::router::ServerHandle;
+use crate::state::{
+    GatewayContractClass, MaybePendingStateUpdate, PendingStateUpdate, StateUpdate,
 };
-use crate::block::{Block, BlockHeader};
-use crate::state::{ContractClass, StateUpdate};
+use crate::subscription::{JsonRpcSubscriptionsImpl, JsonRpcSubscriptionsServer};
+use crate::trace::{TransactionTrace, TransactionTraceWithHash};
 use crate::transaction::{
-    Event, Transaction, TransactionOutput, TransactionReceipt, TransactionReceiptWithStatus,
-    TransactionStatus, TransactionWithType, Transactions,
+    BroadcastedInvokeTransaction, Event, Transaction, TransactionOutput, TransactionReceipt,
+    TransactionReceiptWithStatus, TransactionStatus, TransactionWithType, Transactions,
 };
+use crate::v0_2::{JsonRpcServerV0_2Impl, JsonRpcV0_2Server};
+
+// The paths of the versions of the specification served by the HTTP server.
+const V0_2_PATH: &str = "/rpc/v0.2";
+const V0_3_PATH: &str = "/rpc/v0.3";
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct GatewayConfig {
     pub chain_id: ChainId,
     pub server_address: String,
+    pub ws_server_address: String,
     pub max_events_chunk_size: usize,
     pub max_events_keys: usize,
+    /// The maximal number of storage keys in a starknet_getProof request.
+    pub max_proof_keys: usize,
+    pub execution_config: ExecutionConfig,
+    /// The URL of the sequencer's gateway the added transactions are forwarded to.
+    pub sequencer_url: String,
 }
 
 /// Rpc server.
+#[derive(Clone)]
 struct JsonRpcServerImpl {
     chain_id: ChainId,
     storage_reader: StorageReader,
     max_events_chunk_size: usize,
     max_events_keys: usize,
+    max_proof_keys: usize,
+    execution_config: ExecutionConfig,
+    pending_data: Arc<RwLock<Option<PendingData>>>,
+    sequencer_client: Arc<StarknetClient>,
 }
 
 impl From<JsonRpcError> for Error {
@@ -73,6 +128,40 @@ fn internal_server_error(err: impl Display) -> Error {
     )))
 }
 
+// Failures of the execution of a contract are errors of the contract, other failures are internal.
+fn execution_error(err: ExecutionError) -> Error {
+    match err {
+        ExecutionError::ContractNotFound { .. } => Error::from(JsonRpcError::ContractNotFound),
+        ExecutionError::ExecutionFailed(message) => {
+            debug!("Contract execution failed: {}", message);
+            Error::from(JsonRpcError::ContractError)
+        }
+        err => internal_server_error(err),
+    }
+}
+
+// The transactions of a block whose classes or state are missing have no traces.
+fn trace_error(err: ExecutionError) -> Error {
+    match err {
+        ExecutionError::MissingHeader { .. } | ExecutionError::MissingTransactions { .. } => {
+            Error::from(JsonRpcError::BlockNotFound)
+        }
+        ExecutionError::MissingClass { .. }
+        | ExecutionError::UnsupportedTransaction { .. }
+        | ExecutionError::ExecutionFailed(_) => {
+            debug!("Can't trace the transactions: {}", err);
+            Error::from(JsonRpcError::NoTraceAvailable)
+        }
+        err => internal_server_error(err),
+    }
+}
+
+fn u128_as_felt(value: u128) -> StarkFelt {
+    let mut bytes = [0u8; 32];
+    bytes[16..].copy_from_slice(&value.to_be_bytes());
+    StarkFelt::new(bytes).expect("A u128 is a felt.")
+}
+
 fn get_block_number<Mode: TransactionKind>(
     txn: &StorageTxn<'_, Mode>,
     block_id: BlockId,
@@ -91,15 +180,72 @@ fn get_block_number<Mode: TransactionKind>(
             }
             block_number
         }
-        BlockId::Tag(Tag::Latest) => {
+        // When there's no pending block, the pending tag refers to the latest block.
+        BlockId::Tag(Tag::Latest) | BlockId::Tag(Tag::Pending) => {
             get_latest_block_number(txn)?.ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?
         }
-        BlockId::Tag(Tag::Pending) => {
-            todo!("Pending tag is not supported yet.")
-        }
+        BlockId::Tag(Tag::L1) => txn
+            .get_base_layer_block_marker()
+            .map_err(internal_server_error)?
+            .prev()
+            .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?,
     })
 }
 
+// Blocks up to the latest block proved on the base layer are accepted on L1.
+fn get_block_status<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> Result<BlockStatus, Error> {
+    let base_layer_block_marker =
+        txn.get_base_layer_block_marker().map_err(internal_server_error)?;
+    Ok(if block_number < base_layer_block_marker {
+        BlockStatus::AcceptedOnL1
+    } else {
+        BlockStatus::AcceptedOnL2
+    })
+}
+
+// Returns the state number a query on the given block should read from. The pending data, if
+// given, is applied on top of the state after the latest block.
+fn get_state_number<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_id: BlockId,
+    pending_data: &Option<PendingData>,
+) -> Result<StateNumber, Error> {
+    if pending_data.is_some() {
+        return Ok(StateNumber::right_before_block(
+            txn.get_header_marker().map_err(internal_server_error)?,
+        ));
+    }
+    let block_number = get_block_number(txn, block_id)?;
+    verify_state_not_pruned(txn, block_number)?;
+    Ok(StateNumber::right_after_block(block_number))
+}
+
+// The transactions of the block are re-executed over the state after its parent block.
+fn verify_parent_state_not_pruned<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> Result<(), Error> {
+    match block_number.prev() {
+        Some(parent_block_number) => verify_state_not_pruned(txn, parent_block_number),
+        None => Ok(()),
+    }
+}
+
+// The state after the block can't be read if the storage pruned it.
+fn verify_state_not_pruned<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+) -> Result<(), Error> {
+    let pruned_state_marker = txn.get_pruned_state_marker().map_err(internal_server_error)?;
+    if block_number.next() < pruned_state_marker {
+        return Err(Error::from(JsonRpcError::BlockPruned));
+    }
+    Ok(())
+}
+
 fn get_latest_block_number<Mode: TransactionKind>(
     txn: &StorageTxn<'_, Mode>,
 ) -> Result<Option<BlockNumber>, Error> {
@@ -127,7 +273,124 @@ fn get_block_txs_by_number<Mode: TransactionKind>(
         .map_err(internal_server_error)?
         .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?;
 
-    Ok(transactions.into_iter().map(Transaction::from).collect())
+    transactions
+        .into_iter()
+        .map(|transaction| set_compiled_class_hash(txn, block_number, transaction.into()))
+        .collect()
+}
+
+// The stored declare transactions don't keep the compiled class hash of the Sierra class they
+// declare, so it is read from the state after their block.
+fn set_compiled_class_hash<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+    mut transaction: Transaction,
+) -> Result<Transaction, Error> {
+    if let Transaction::Declare(declare_tx) = &mut transaction {
+        declare_tx.compiled_class_hash = txn
+            .get_state_reader()
+            .map_err(internal_server_error)?
+            .get_compiled_class_hash_at(
+                StateNumber::right_after_block(block_number),
+                &declare_tx.transaction.class_hash,
+            )
+            .map_err(internal_server_error)?;
+    }
+    Ok(transaction)
+}
+
+fn set_pending_compiled_class_hash(
+    cairo1_state_diff: &Cairo1StateDiff,
+    mut transaction: Transaction,
+) -> Transaction {
+    if let Transaction::Declare(declare_tx) = &mut transaction {
+        declare_tx.compiled_class_hash = cairo1_state_diff
+            .declared_classes
+            .get(&declare_tx.transaction.class_hash)
+            .map(|(compiled_class_hash, _)| *compiled_class_hash);
+    }
+    transaction
+}
+
+fn get_pending_class_definition(
+    pending_data: &PendingData,
+    class_hash: &ClassHash,
+) -> Option<starknet_api::state::ContractClass> {
+    pending_data
+        .state_diff
+        .declared_classes
+        .get(class_hash)
+        .or_else(|| pending_data.deployed_contract_class_definitions.get(class_hash))
+        .cloned()
+}
+
+// Returns the Cairo 0 or Sierra class of the hash at the state, looking first at the pending data.
+fn get_class_definition<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    pending_data: &Option<PendingData>,
+    state_number: StateNumber,
+    class_hash: &ClassHash,
+) -> Result<Option<GatewayContractClass>, Error> {
+    if let Some(pending_data) = pending_data {
+        if let Some((_, class)) = pending_data.cairo1_state_diff.declared_classes.get(class_hash) {
+            return Ok(Some(GatewayContractClass::Sierra(class.clone().into())));
+        }
+        if let Some(class) = get_pending_class_definition(pending_data, class_hash) {
+            return Ok(Some(GatewayContractClass::Cairo0(
+                class.try_into().map_err(internal_server_error)?,
+            )));
+        }
+    }
+    let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
+    if let Some(class) = state_reader
+        .get_class_definition_at(state_number, class_hash)
+        .map_err(internal_server_error)?
+    {
+        return Ok(Some(GatewayContractClass::Cairo0(
+            class.try_into().map_err(internal_server_error)?,
+        )));
+    }
+    Ok(state_reader
+        .get_sierra_class_definition_at(state_number, class_hash)
+        .map_err(internal_server_error)?
+        .map(|class| GatewayContractClass::Sierra(class.into())))
+}
+
+// Returns the class hash of the contract in the pending data, either replaced or deployed there.
+fn get_pending_class_hash(
+    pending_data: &PendingData,
+    contract_address: &ContractAddress,
+) -> Option<ClassHash> {
+    pending_data
+        .cairo1_state_diff
+        .replaced_classes
+        .get(contract_address)
+        .or_else(|| pending_data.state_diff.deployed_contracts.get(contract_address))
+        .cloned()
+}
+
+fn is_matching_event(
+    address: Option<ContractAddress>,
+    keys: &[HashSet<EventKey>],
+    from_address: ContractAddress,
+    content: &starknet_api::transaction::EventContent,
+) -> bool {
+    if address.is_some() && from_address != address.unwrap() {
+        return false;
+    }
+    // TODO: Consider changing empty sets in the filer keys to None.
+    keys.iter().enumerate().all(|(i, keys)| {
+        content.keys.len() > i && (keys.is_empty() || keys.contains(&content.keys[i]))
+    })
+}
+
+// Returns the keys of the position in the filter with the fewest keys, as they match the fewest
+// events. An empty set of keys matches any key.
+fn get_most_selective_keys(keys: &[HashSet<EventKey>]) -> Option<Vec<EventKey>> {
+    keys.iter()
+        .filter(|keys| !keys.is_empty())
+        .min_by_key(|keys| keys.len())
+        .map(|keys| keys.iter().cloned().collect())
 }
 
 struct ContinuationTokenAsStruct(EventIndex);
@@ -145,6 +408,37 @@ impl ContinuationToken {
     }
 }
 
+impl JsonRpcServerImpl {
+    // Returns the pending data if it extends the latest block in the storage. Otherwise, the
+    // pending data is outdated (or not synced yet) and is ignored.
+    fn get_pending_data<Mode: TransactionKind>(
+        &self,
+        txn: &StorageTxn<'_, Mode>,
+    ) -> Result<Option<PendingData>, Error> {
+        let latest_block_hash = match get_latest_block_number(txn)? {
+            Some(block_number) => get_block_header_by_number(txn, block_number)?.block_hash,
+            None => BlockHash::default(),
+        };
+        let pending_data = self.pending_data.read().map_err(internal_server_error)?;
+        Ok(pending_data
+            .as_ref()
+            .filter(|pending_data| pending_data.header.parent_hash == latest_block_hash)
+            .cloned())
+    }
+
+    // Returns the pending data if the block id is the pending tag and there is a pending block.
+    fn get_pending_data_by_block_id<Mode: TransactionKind>(
+        &self,
+        txn: &StorageTxn<'_, Mode>,
+        block_id: BlockId,
+    ) -> Result<Option<PendingData>, Error> {
+        match block_id {
+            BlockId::Tag(Tag::Pending) => self.get_pending_data(txn),
+            _ => Ok(None),
+        }
+    }
+}
+
 #[async_trait]
 impl JsonRpcServer for JsonRpcServerImpl {
     #[instrument(skip(self), level = "debug", err, ret)]
@@ -164,35 +458,69 @@ impl JsonRpcServer for JsonRpcServerImpl {
     }
 
     #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_block_w_transaction_hashes(&self, block_id: BlockId) -> Result<Block, Error> {
+    fn get_block_w_transaction_hashes(
+        &self,
+        block_id: BlockId,
+    ) -> Result<MaybePendingBlock, Error> {
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        if let Some(pending_data) = self.get_pending_data_by_block_id(&txn, block_id)? {
+            let transaction_hashes = pending_data
+                .body
+                .transactions
+                .iter()
+                .map(|transaction| transaction.transaction_hash())
+                .collect();
+            return Ok(MaybePendingBlock::Pending(PendingBlock {
+                parent_hash: pending_data.header.parent_hash,
+                sequencer_address: pending_data.header.sequencer,
+                timestamp: pending_data.header.timestamp,
+                transactions: Transactions::Hashes(transaction_hashes),
+            }));
+        }
         let block_number = get_block_number(&txn, block_id)?;
         let header = get_block_header_by_number(&txn, block_number)?;
         let transactions = get_block_txs_by_number(&txn, block_number)?;
         let transaction_hashes: Vec<TransactionHash> =
             transactions.iter().map(|transaction| transaction.transaction_hash()).collect();
 
-        Ok(Block {
-            status: BlockStatus::AcceptedOnL2,
+        Ok(MaybePendingBlock::Block(Block {
+            status: get_block_status(&txn, block_number)?,
             header,
             transactions: Transactions::Hashes(transaction_hashes),
-        })
+        }))
     }
 
     #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_block_w_full_transactions(&self, block_id: BlockId) -> Result<Block, Error> {
+    fn get_block_w_full_transactions(&self, block_id: BlockId) -> Result<MaybePendingBlock, Error> {
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        if let Some(pending_data) = self.get_pending_data_by_block_id(&txn, block_id)? {
+            let cairo1_state_diff = &pending_data.cairo1_state_diff;
+            let transactions = pending_data
+                .body
+                .transactions
+                .into_iter()
+                .map(|transaction| {
+                    set_pending_compiled_class_hash(cairo1_state_diff, transaction.into()).into()
+                })
+                .collect();
+            return Ok(MaybePendingBlock::Pending(PendingBlock {
+                parent_hash: pending_data.header.parent_hash,
+                sequencer_address: pending_data.header.sequencer,
+                timestamp: pending_data.header.timestamp,
+                transactions: Transactions::Full(transactions),
+            }));
+        }
         let block_number = get_block_number(&txn, block_id)?;
         let header = get_block_header_by_number(&txn, block_number)?;
         let transactions = get_block_txs_by_number(&txn, block_number)?;
 
-        Ok(Block {
-            status: BlockStatus::AcceptedOnL2,
+        Ok(MaybePendingBlock::Block(Block {
+            status: get_block_status(&txn, block_number)?,
             header,
             transactions: Transactions::Full(
                 transactions.into_iter().map(TransactionWithType::from).collect(),
             ),
-        })
+        }))
     }
 
     #[instrument(skip(self), level = "debug", err, ret)]
@@ -205,16 +533,26 @@ impl JsonRpcServer for JsonRpcServerImpl {
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
 
         // Check that the block is valid and get the state number.
-        let block_number = get_block_number(&txn, block_id)?;
-        let state = StateNumber::right_after_block(block_number);
+        let pending_data = self.get_pending_data_by_block_id(&txn, block_id)?;
+        let state = get_state_number(&txn, block_id, &pending_data)?;
         let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
 
         // Check that the contract exists.
-        state_reader
-            .get_class_hash_at(state, &contract_address)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))?;
+        let deployed_in_pending = pending_data.as_ref().map_or(false, |pending_data| {
+            pending_data.state_diff.deployed_contracts.contains_key(&contract_address)
+        });
+        if !deployed_in_pending {
+            state_reader
+                .get_class_hash_at(state, &contract_address)
+                .map_err(internal_server_error)?
+                .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))?;
+        }
 
+        if let Some(value) = pending_data.as_ref().and_then(|pending_data| {
+            pending_data.state_diff.storage_diffs.get(&contract_address)?.get(&key)
+        }) {
+            return Ok(*value);
+        }
         state_reader.get_storage_at(state, &contract_address, &key).map_err(internal_server_error)
     }
 
@@ -225,17 +563,39 @@ impl JsonRpcServer for JsonRpcServerImpl {
     ) -> Result<TransactionWithType, Error> {
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
 
-        let transaction_index = txn
+        let transaction_index = match txn
             .get_transaction_idx_by_hash(&transaction_hash)
             .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
+        {
+            Some(transaction_index) => transaction_index,
+            None => {
+                // Look for the transaction in the pending block.
+                let pending_data = self
+                    .get_pending_data(&txn)?
+                    .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
+                let transaction = pending_data
+                    .body
+                    .transactions
+                    .into_iter()
+                    .find(|transaction| transaction.transaction_hash() == transaction_hash)
+                    .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
+                return Ok(TransactionWithType::from(set_pending_compiled_class_hash(
+                    &pending_data.cairo1_state_diff,
+                    transaction.into(),
+                )));
+            }
+        };
 
         let transaction = txn
             .get_transaction(transaction_index)
             .map_err(internal_server_error)?
             .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
 
-        Ok(TransactionWithType::from(transaction))
+        Ok(TransactionWithType::from(set_compiled_class_hash(
+            &txn,
+            transaction_index.0,
+            transaction.into(),
+        )?))
     }
 
     #[instrument(skip(self), level = "debug", err, ret)]
@@ -245,6 +605,18 @@ impl JsonRpcServer for JsonRpcServerImpl {
         index: TransactionOffsetInBlock,
     ) -> Result<TransactionWithType, Error> {
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        if let Some(pending_data) = self.get_pending_data_by_block_id(&txn, block_id)? {
+            let transaction = pending_data
+                .body
+                .transactions
+                .get(index.0)
+                .cloned()
+                .ok_or_else(|| Error::from(JsonRpcError::InvalidTransactionIndex))?;
+            return Ok(TransactionWithType::from(set_pending_compiled_class_hash(
+                &pending_data.cairo1_state_diff,
+                transaction.into(),
+            )));
+        }
         let block_number = get_block_number(&txn, block_id)?;
 
         let transaction = txn
@@ -252,12 +624,19 @@ impl JsonRpcServer for JsonRpcServerImpl {
             .map_err(internal_server_error)?
             .ok_or_else(|| Error::from(JsonRpcError::InvalidTransactionIndex))?;
 
-        Ok(TransactionWithType::from(transaction))
+        Ok(TransactionWithType::from(set_compiled_class_hash(
+            &txn,
+            block_number,
+            transaction.into(),
+        )?))
     }
 
     #[instrument(skip(self), level = "debug", err, ret)]
     fn get_block_transaction_count(&self, block_id: BlockId) -> Result<usize, Error> {
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        if let Some(pending_data) = self.get_pending_data_by_block_id(&txn, block_id)? {
+            return Ok(pending_data.body.transactions.len());
+        }
         let block_number = get_block_number(&txn, block_id)?;
         let transactions = get_block_txs_by_number(&txn, block_number)?;
 
@@ -265,9 +644,26 @@ impl JsonRpcServer for JsonRpcServerImpl {
     }
 
     #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_state_update(&self, block_id: BlockId) -> Result<StateUpdate, Error> {
+    fn get_state_update(&self, block_id: BlockId) -> Result<MaybePendingStateUpdate, Error> {
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
 
+        if let Some(pending_data) = self.get_pending_data_by_block_id(&txn, block_id)? {
+            // The old root of the pending block is the root of the latest block.
+            let old_root = match get_latest_block_number(&txn)? {
+                Some(block_number) => get_block_header_by_number(&txn, block_number)?.new_root,
+                None => {
+                    GlobalRoot(StarkHash::try_from(GENESIS_HASH).map_err(internal_server_error)?)
+                }
+            };
+            let mut thin_state_diff =
+                papyrus_storage::state::data::ThinStateDiff::from(pending_data.state_diff);
+            thin_state_diff.add_cairo1_state_diff(pending_data.cairo1_state_diff);
+            return Ok(MaybePendingStateUpdate::Pending(PendingStateUpdate {
+                old_root,
+                state_diff: thin_state_diff.into(),
+            }));
+        }
+
         // Get the block header for the block hash and state root.
         let block_number = get_block_number(&txn, block_id)?;
         let header = get_block_header_by_number(&txn, block_number)?;
@@ -290,12 +686,12 @@ impl JsonRpcServer for JsonRpcServerImpl {
             .map_err(internal_server_error)?
             .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?;
 
-        Ok(StateUpdate {
+        Ok(MaybePendingStateUpdate::StateUpdate(StateUpdate {
             block_hash: header.block_hash,
             new_root: header.new_root,
             old_root,
             state_diff: thin_state_diff.into(),
-        })
+        }))
     }
 
     #[instrument(skip(self), level = "debug", err, ret)]
@@ -338,23 +734,22 @@ impl JsonRpcServer for JsonRpcServerImpl {
                 header.block_hash,
                 block_number,
             ),
-            status: TransactionStatus::default(),
+            status: TransactionStatus::from(get_block_status(&txn, block_number)?),
         })
     }
 
     #[instrument(skip(self), level = "debug", err, ret)]
-    fn get_class(&self, block_id: BlockId, class_hash: ClassHash) -> Result<ContractClass, Error> {
+    fn get_class(
+        &self,
+        block_id: BlockId,
+        class_hash: ClassHash,
+    ) -> Result<GatewayContractClass, Error> {
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
 
-        let block_number = get_block_number(&txn, block_id)?;
-        let state_number = StateNumber::right_after_block(block_number);
-        let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
-
-        let class = state_reader
-            .get_class_definition_at(state_number, &class_hash)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::ClassHashNotFound))?;
-        class.try_into().map_err(internal_server_error)
+        let pending_data = self.get_pending_data_by_block_id(&txn, block_id)?;
+        let state_number = get_state_number(&txn, block_id, &pending_data)?;
+        get_class_definition(&txn, &pending_data, state_number, &class_hash)?
+            .ok_or_else(|| Error::from(JsonRpcError::ClassHashNotFound))
     }
 
     #[instrument(skip(self), level = "debug", err, ret)]
@@ -362,23 +757,26 @@ impl JsonRpcServer for JsonRpcServerImpl {
         &self,
         block_id: BlockId,
         contract_address: ContractAddress,
-    ) -> Result<ContractClass, Error> {
+    ) -> Result<GatewayContractClass, Error> {
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
 
-        let block_number = get_block_number(&txn, block_id)?;
-        let state_number = StateNumber::right_after_block(block_number);
+        let pending_data = self.get_pending_data_by_block_id(&txn, block_id)?;
+        let state_number = get_state_number(&txn, block_id, &pending_data)?;
         let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
 
-        let class_hash = state_reader
-            .get_class_hash_at(state_number, &contract_address)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))?;
+        let class_hash = match pending_data
+            .as_ref()
+            .and_then(|pending_data| get_pending_class_hash(pending_data, &contract_address))
+        {
+            Some(class_hash) => class_hash,
+            None => state_reader
+                .get_class_hash_at(state_number, &contract_address)
+                .map_err(internal_server_error)?
+                .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))?,
+        };
 
-        let class = state_reader
-            .get_class_definition_at(state_number, &class_hash)
-            .map_err(internal_server_error)?
-            .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))?;
-        class.try_into().map_err(internal_server_error)
+        get_class_definition(&txn, &pending_data, state_number, &class_hash)?
+            .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))
     }
 
     #[instrument(skip(self), level = "debug", err, ret)]
@@ -389,8 +787,14 @@ impl JsonRpcServer for JsonRpcServerImpl {
     ) -> Result<ClassHash, Error> {
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
 
-        let block_number = get_block_number(&txn, block_id)?;
-        let state = StateNumber::right_after_block(block_number);
+        let pending_data = self.get_pending_data_by_block_id(&txn, block_id)?;
+        if let Some(class_hash) = pending_data
+            .as_ref()
+            .and_then(|pending_data| get_pending_class_hash(pending_data, &contract_address))
+        {
+            return Ok(class_hash);
+        }
+        let state = get_state_number(&txn, block_id, &pending_data)?;
         let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
 
         state_reader
@@ -407,8 +811,16 @@ impl JsonRpcServer for JsonRpcServerImpl {
     ) -> Result<Nonce, Error> {
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
 
-        let block_number = get_block_number(&txn, block_id)?;
-        let state = StateNumber::right_after_block(block_number);
+        let pending_data = self.get_pending_data_by_block_id(&txn, block_id)?;
+        if let Some(pending_data) = &pending_data {
+            if let Some(nonce) = pending_data.state_diff.nonces.get(&contract_address) {
+                return Ok(*nonce);
+            }
+            if pending_data.state_diff.deployed_contracts.contains_key(&contract_address) {
+                return Ok(Nonce::default());
+            }
+        }
+        let state = get_state_number(&txn, block_id, &pending_data)?;
         let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
 
         state_reader
@@ -422,6 +834,11 @@ impl JsonRpcServer for JsonRpcServerImpl {
         Ok(self.chain_id.as_hex())
     }
 
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn spec_version(&self) -> Result<String, Error> {
+        Ok(SPEC_VERSION.to_owned())
+    }
+
     #[instrument(skip(self), level = "debug", err, ret)]
     fn get_events(&self, filter: EventFilter) -> Result<EventsChunk, Error> {
         // Check the chunk size.
@@ -433,15 +850,24 @@ impl JsonRpcServer for JsonRpcServerImpl {
             return Err(Error::from(JsonRpcError::TooManyKeysInFilter));
         }
 
-        // Get the requested block numbers.
+        // Get the requested block numbers. If the range ends at the pending block, its events are
+        // given the block number that follows the latest block.
         let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
-        let from_block_number = filter
-            .from_block
-            .map_or(Ok(BlockNumber(0)), |block_id| get_block_number(&txn, block_id))?;
-        let maybe_to_block_number =
-            filter.to_block.map_or(get_latest_block_number(&txn), |block_id| {
-                get_block_number(&txn, block_id).map(Some)
-            })?;
+        let pending_data = match filter.to_block {
+            Some(BlockId::Tag(Tag::Pending)) => self.get_pending_data(&txn)?,
+            _ => None,
+        };
+        let pending_block_number = txn.get_header_marker().map_err(internal_server_error)?;
+        let from_block_number = match filter.from_block {
+            Some(BlockId::Tag(Tag::Pending)) if pending_data.is_some() => pending_block_number,
+            Some(block_id) => get_block_number(&txn, block_id)?,
+            None => BlockNumber(0),
+        };
+        let maybe_to_block_number = match (&pending_data, filter.to_block) {
+            (Some(_), _) => Some(pending_block_number),
+            (None, Some(block_id)) => Some(get_block_number(&txn, block_id)?),
+            (None, None) => get_latest_block_number(&txn)?,
+        };
         if maybe_to_block_number.is_none() {
             // There are no blocks.
             return Ok(EventsChunk { events: vec![], continuation_token: None });
@@ -453,8 +879,8 @@ impl JsonRpcServer for JsonRpcServerImpl {
 
         // Get the event index. If there's a continuation token we take the event index from there.
         // Otherwise, we take the first index in the from_block_number.
-        let event_index = match filter.continuation_token {
-            Some(token) => token.parse()?.0,
+        let start_event_index = match filter.continuation_token {
+            Some(ref token) => token.parse()?.0,
             None => EventIndex(
                 TransactionIndex(from_block_number, TransactionOffsetInBlock(0)),
                 EventIndexInTransactionOutput(0),
@@ -466,10 +892,17 @@ impl JsonRpcServer for JsonRpcServerImpl {
         // corresponding to the requested filter. If there are, we return a continuation token
         // pointing to the next relevant event. Otherwise, we return a continuation token None.
         let mut filtered_events = vec![];
-        for ((from_address, event_index), content) in txn
-            .iter_events(filter.address, event_index, to_block_number)
-            .map_err(internal_server_error)?
-        {
+        // Without an address, the event keys index is used if the filter has keys.
+        let events_iter = match (filter.address, get_most_selective_keys(&filter.keys)) {
+            (None, Some(keys)) => {
+                txn.iter_events_by_keys(&keys, start_event_index, to_block_number)
+            }
+            (address, _) => {
+                txn.iter_events(address, &filter.keys, start_event_index, to_block_number)
+            }
+        }
+        .map_err(internal_server_error)?;
+        for ((from_address, event_index), content) in events_iter {
             let block_number = (event_index.0).0;
             if block_number > to_block_number {
                 break;
@@ -477,10 +910,7 @@ impl JsonRpcServer for JsonRpcServerImpl {
             if filter.address.is_some() && from_address != filter.address.unwrap() {
                 break;
             }
-            // TODO: Consider changing empty sets in the filer keys to None.
-            if filter.keys.iter().enumerate().all(|(i, keys)| {
-                content.keys.len() > i && (keys.is_empty() || keys.contains(&content.keys[i]))
-            }) {
+            if is_matching_event(filter.address, &filter.keys, from_address, &content) {
                 if filtered_events.len() == filter.chunk_size {
                     return Ok(EventsChunk {
                         events: filtered_events,
@@ -496,8 +926,8 @@ impl JsonRpcServer for JsonRpcServerImpl {
                     .map_err(internal_server_error)?
                     .ok_or_else(|| internal_server_error("Unknown internal error."))?;
                 let emitted_event = Event {
-                    block_hash: header.block_hash,
-                    block_number,
+                    block_hash: Some(header.block_hash),
+                    block_number: Some(block_number),
                     transaction_hash: transaction.transaction_hash(),
                     event: starknet_api::transaction::Event { from_address, content },
                 };
@@ -505,27 +935,303 @@ impl JsonRpcServer for JsonRpcServerImpl {
             }
         }
 
+        // Collect the requested events of the pending block.
+        if let Some(pending_data) = pending_data {
+            let start_position =
+                (start_event_index.0 .0, start_event_index.0 .1 .0, start_event_index.1 .0);
+            let transactions_with_outputs = pending_data
+                .body
+                .transactions
+                .iter()
+                .zip(pending_data.body.transaction_outputs.iter())
+                .enumerate();
+            for (transaction_offset, (transaction, output)) in transactions_with_outputs {
+                for (event_offset, event) in output.events().iter().enumerate() {
+                    let event_index = EventIndex(
+                        TransactionIndex(
+                            pending_block_number,
+                            TransactionOffsetInBlock(transaction_offset),
+                        ),
+                        EventIndexInTransactionOutput(event_offset),
+                    );
+                    // Skip the events that precede the continuation token.
+                    if (pending_block_number, transaction_offset, event_offset) < start_position {
+                        continue;
+                    }
+                    if !is_matching_event(
+                        filter.address,
+                        &filter.keys,
+                        event.from_address,
+                        &event.content,
+                    ) {
+                        continue;
+                    }
+                    if filtered_events.len() == filter.chunk_size {
+                        return Ok(EventsChunk {
+                            events: filtered_events,
+                            continuation_token: Some(ContinuationToken::new(
+                                ContinuationTokenAsStruct(event_index),
+                            )?),
+                        });
+                    }
+                    filtered_events.push(Event {
+                        block_hash: None,
+                        block_number: None,
+                        transaction_hash: transaction.transaction_hash(),
+                        event: event.clone(),
+                    });
+                }
+            }
+        }
+
         Ok(EventsChunk { events: filtered_events, continuation_token: None })
     }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_proof(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+        keys: Vec<StorageKey>,
+    ) -> Result<GetProofResult, Error> {
+        if keys.len() > self.max_proof_keys {
+            return Err(Error::from(JsonRpcError::TooManyKeysInProof));
+        }
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+
+        // The state of the pending block isn't committed to.
+        if self.get_pending_data_by_block_id(&txn, block_id)?.is_some() {
+            return Err(Error::from(JsonRpcError::StateCommitmentNotAvailable));
+        }
+        let block_number = get_block_number(&txn, block_id)?;
+        verify_state_not_pruned(&txn, block_number)?;
+        let proof = txn
+            .get_state_proof(block_number, &contract_address, &keys)
+            .map_err(internal_server_error)?
+            .ok_or_else(|| Error::from(JsonRpcError::StateCommitmentNotAvailable))?;
+
+        Ok(GetProofResult::from(proof))
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn call(&self, request: FunctionCall, block_id: BlockId) -> Result<Vec<StarkFelt>, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+
+        // The pending data isn't executed on, so the pending tag refers to the latest block.
+        let state_number = get_state_number(&txn, block_id, &None)?;
+        execute_call(
+            &txn,
+            &self.chain_id,
+            &self.execution_config,
+            state_number,
+            request.contract_address,
+            request.entry_point_selector,
+            request.calldata,
+        )
+        .map_err(execution_error)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn estimate_fee(
+        &self,
+        request: BroadcastedInvokeTransaction,
+        block_id: BlockId,
+    ) -> Result<FeeEstimate, Error> {
+        let tx = request.into_invoke_transaction(&self.chain_id)?;
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+
+        // The pending data isn't executed on, so the pending tag refers to the latest block.
+        let state_number = get_state_number(&txn, block_id, &None)?;
+        let fee_estimation =
+            estimate_fee(&txn, &self.chain_id, &self.execution_config, state_number, tx)
+                .map_err(execution_error)?;
+
+        Ok(FeeEstimate {
+            gas_consumed: u128_as_felt(fee_estimation.gas_consumed),
+            gas_price: u128_as_felt(fee_estimation.gas_price.0),
+            overall_fee: u128_as_felt(fee_estimation.overall_fee.0),
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn trace_transaction(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionTrace, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+
+        let transaction_index = txn
+            .get_transaction_idx_by_hash(&transaction_hash)
+            .map_err(internal_server_error)?
+            .ok_or_else(|| Error::from(JsonRpcError::TransactionHashNotFound))?;
+        let block_number = transaction_index.0;
+        verify_parent_state_not_pruned(&txn, block_number)?;
+        let mut traces = trace_block_transactions(
+            &txn,
+            &self.chain_id,
+            &self.execution_config,
+            block_number,
+            Some(transaction_index.1),
+        )
+        .map_err(trace_error)?;
+        let trace = traces.pop().ok_or_else(|| internal_server_error("Unknown internal error."))?;
+
+        Ok(TransactionTraceWithHash::from(trace).trace_root)
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn trace_block_transactions(
+        &self,
+        block_id: BlockId,
+    ) -> Result<Vec<TransactionTraceWithHash>, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+
+        let block_number = get_block_number(&txn, block_id)?;
+        verify_parent_state_not_pruned(&txn, block_number)?;
+        let traces = trace_block_transactions(
+            &txn,
+            &self.chain_id,
+            &self.execution_config,
+            block_number,
+            None,
+        )
+        .map_err(trace_error)?;
+
+        Ok(traces.into_iter().map(TransactionTraceWithHash::from).collect())
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    async fn add_invoke_transaction(
+        &self,
+        invoke_transaction: BroadcastedInvokeTransaction,
+    ) -> Result<AddInvokeTransactionResult, Error> {
+        let response = self
+            .sequencer_client
+            .add_transaction(&invoke_transaction.into())
+            .await
+            .map_err(add_transaction_error)?;
+        Ok(AddInvokeTransactionResult { transaction_hash: response.transaction_hash })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    async fn add_declare_transaction(
+        &self,
+        declare_transaction: BroadcastedDeclareTransaction,
+    ) -> Result<AddDeclareTransactionResult, Error> {
+        let tx = AddTransaction::try_from(declare_transaction).map_err(internal_server_error)?;
+        let response =
+            self.sequencer_client.add_transaction(&tx).await.map_err(add_transaction_error)?;
+        Ok(AddDeclareTransactionResult {
+            transaction_hash: response.transaction_hash,
+            class_hash: response.class_hash.ok_or_else(|| {
+                internal_server_error("The sequencer didn't return the declared class hash.")
+            })?,
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    async fn add_deploy_account_transaction(
+        &self,
+        deploy_account_transaction: BroadcastedDeployAccountTransaction,
+    ) -> Result<AddDeployAccountTransactionResult, Error> {
+        let response = self
+            .sequencer_client
+            .add_transaction(&deploy_account_transaction.into())
+            .await
+            .map_err(add_transaction_error)?;
+        Ok(AddDeployAccountTransactionResult {
+            transaction_hash: response.transaction_hash,
+            contract_address: response.address.ok_or_else(|| {
+                internal_server_error("The sequencer didn't return the deployed account address.")
+            })?,
+        })
+    }
+}
+
+// The added transactions are sent once, so the retry configuration of the client isn't used.
+fn get_sequencer_client(sequencer_url: &str) -> anyhow::Result<StarknetClient> {
+    let retry_config =
+        RetryConfig { retry_base_millis: 0, retry_max_delay_millis: 0, max_retries: 0 };
+    Ok(StarknetClient::new(sequencer_url, None, retry_config)?)
 }
 
-#[instrument(skip(storage_reader), level = "debug", err)]
+/// Runs the HTTP server, serving each version of the specification on its own path and the latest
+/// version on the root path as well.
+#[instrument(skip(storage_reader, pending_data), level = "debug", err)]
 pub async fn run_server(
     config: &GatewayConfig,
     storage_reader: StorageReader,
-) -> anyhow::Result<(SocketAddr, HttpServerHandle)> {
+    pending_data: Arc<RwLock<Option<PendingData>>>,
+) -> anyhow::Result<(SocketAddr, ServerHandle)> {
     debug!("Starting gateway.");
-    let server = HttpServerBuilder::default().build(&config.server_address).await?;
+    let server_impl = JsonRpcServerImpl {
+        chain_id: config.chain_id.clone(),
+        storage_reader: storage_reader.clone(),
+        max_events_chunk_size: config.max_events_chunk_size,
+        max_events_keys: config.max_events_keys,
+        max_proof_keys: config.max_proof_keys,
+        execution_config: config.execution_config.clone(),
+        pending_data,
+        sequencer_client: Arc::new(get_sequencer_client(&config.sequencer_url)?),
+    };
+    let papyrus_impl = JsonRpcPapyrusImpl { storage_reader, chunk_size: CHUNK_SIZE };
+
+    let mut v0_2_module = JsonRpcServerV0_2Impl(server_impl.clone()).into_rpc();
+    v0_2_module.merge(papyrus_impl.clone().into_rpc())?;
+    let v0_2_methods = Methods::from(v0_2_module);
+    let mut v0_3_module = server_impl.into_rpc();
+    v0_3_module.merge(papyrus_impl.into_rpc())?;
+    let v0_3_methods = Methods::from(v0_3_module);
+
+    let routes = HashMap::from([
+        ("/", v0_3_methods.clone()),
+        (V0_2_PATH, v0_2_methods),
+        (V0_3_PATH, v0_3_methods),
+    ]);
+    let (addr, handle) = run_router(&config.server_address, routes).await?;
+    info!(local_address = %addr, "Gateway is running.");
+    Ok((addr, handle))
+}
+
+/// Runs a WebSocket server that serves the JSON-RPC methods of the gateway, as well as
+/// subscriptions to the changes published in the sync notifications.
+#[instrument(skip(storage_reader, pending_data, sync_notifications), level = "debug", err)]
+pub async fn run_ws_server(
+    config: &GatewayConfig,
+    storage_reader: StorageReader,
+    pending_data: Arc<RwLock<Option<PendingData>>>,
+    sync_notifications: broadcast::Sender<SyncNotification>,
+) -> anyhow::Result<(SocketAddr, WsServerHandle)> {
+    debug!("Starting WebSocket gateway.");
+    let server = WsServerBuilder::default()
+        .set_middleware(MetricsMiddleware)
+        .build(&config.ws_server_address)
+        .await?;
     let addr = server.local_addr()?;
-    let handle = server.start(
-        JsonRpcServerImpl {
-            chain_id: config.chain_id.clone(),
+    let mut module = JsonRpcServerImpl {
+        chain_id: config.chain_id.clone(),
+        storage_reader: storage_reader.clone(),
+        max_events_chunk_size: config.max_events_chunk_size,
+        max_events_keys: config.max_events_keys,
+        max_proof_keys: config.max_proof_keys,
+        execution_config: config.execution_config.clone(),
+        pending_data,
+        sequencer_client: Arc::new(get_sequencer_client(&config.sequencer_url)?),
+    }
+    .into_rpc();
+    module.merge(
+        JsonRpcPapyrusImpl { storage_reader: storage_reader.clone(), chunk_size: CHUNK_SIZE }
+            .into_rpc(),
+    )?;
+    module.merge(
+        JsonRpcSubscriptionsImpl {
             storage_reader,
-            max_events_chunk_size: config.max_events_chunk_size,
+            sync_notifications,
             max_events_keys: config.max_events_keys,
         }
         .into_rpc(),
     )?;
-    info!(local_address = %addr, "Gateway is running.");
+    let handle = server.start(module)?;
+    info!(local_address = %addr, "WebSocket gateway is running.");
     Ok((addr, handle))
 }
@@ -0,0 +1,48 @@
+//! Prometheus metrics of the JSON-RPC servers, registered in the default registry on first use.
+
+use std::time::Instant;
+
+use jsonrpsee::core::middleware::Middleware;
+use once_cell::sync::Lazy;
+use prometheus::{register_histogram_vec, register_int_counter_vec, HistogramVec, IntCounterVec};
+
+static RPC_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "papyrus_rpc_requests_total",
+        "The number of JSON-RPC method calls, by method and result.",
+        &["method", "result"]
+    )
+    .expect("Failed to register the RPC requests metric.")
+});
+
+static RPC_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "papyrus_rpc_request_duration_seconds",
+        "The latency of JSON-RPC method calls, by method.",
+        &["method"]
+    )
+    .expect("Failed to register the RPC request duration metric.")
+});
+
+/// Records the count and latency of every method call of the server it's attached to.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct MetricsMiddleware;
+
+impl Middleware for MetricsMiddleware {
+    type Instant = Instant;
+
+    fn on_request(&self) -> Self::Instant {
+        Instant::now()
+    }
+
+    fn on_result(&self, name: &str, success: bool, started_at: Self::Instant) {
+        let result = if success { "success" } else { "failure" };
+        RPC_REQUESTS.with_label_values(&[name, result]).inc();
+        RPC_REQUEST_DURATION.with_label_values(&[name]).observe(started_at.elapsed().as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn get_rpc_requests_count(method: &str, result: &str) -> u64 {
+    RPC_REQUESTS.with_label_values(&[method, result]).get()
+}
@@ -0,0 +1,385 @@
+use jsonrpsee::core::Error;
+use jsonrpsee::proc_macros::rpc;
+use papyrus_storage::body::BodyStorageReader;
+use papyrus_storage::db::TransactionKind;
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::pruning::PruningStorageReader;
+use papyrus_storage::state::StateStorageReader;
+use papyrus_storage::{StorageReader, StorageTxn, TransactionIndex};
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{BlockHash, BlockNumber, GasPrice};
+use starknet_api::core::{ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::TransactionOffsetInBlock;
+use tracing::instrument;
+
+use crate::api::{BlockId, ContinuationToken, JsonRpcError};
+use crate::state::StorageEntry;
+use crate::transaction::TransactionWithType;
+use crate::{
+    get_block_header_by_number, get_block_number, get_latest_block_number, get_state_number,
+    internal_server_error, set_compiled_class_hash, verify_state_not_pruned,
+};
+
+// The number of transactions or changes in each chunk of getTransactionsBySender,
+// getStorageHistory and getNonceHistory.
+pub(crate) const CHUNK_SIZE: usize = 100;
+// The maximal number of entries in a range of getStorageRange.
+const MAX_STORAGE_RANGE_LIMIT: usize = 1000;
+
+/// A transaction sent by an account, with the block that includes it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SenderTransaction {
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+    pub transaction: TransactionWithType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SenderTransactionsChunk {
+    pub transactions: Vec<SenderTransaction>,
+    pub continuation_token: Option<ContinuationToken>,
+}
+
+/// The storage entries of a contract from a start key, and the key that follows them.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StorageRange {
+    pub storage_entries: Vec<StorageEntry>,
+    pub next_key: Option<StorageKey>,
+}
+
+/// A change to the value of a storage key, by the block that made it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StorageValueChange {
+    pub block_number: BlockNumber,
+    pub value: StarkFelt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StorageHistoryChunk {
+    pub changes: Vec<StorageValueChange>,
+    pub continuation_token: Option<ContinuationToken>,
+}
+
+/// A change to the nonce of a contract, by the block that made it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NonceChange {
+    pub block_number: BlockNumber,
+    pub nonce: Nonce,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NonceHistoryChunk {
+    pub changes: Vec<NonceChange>,
+    pub continuation_token: Option<ContinuationToken>,
+}
+
+/// Methods that aren't part of the Starknet specification, served from the indices of the storage.
+#[rpc(server, client, namespace = "papyrus")]
+pub trait JsonRpcPapyrus {
+    /// Gets the invoke, declare and deploy account transactions sent by the account in the given
+    /// range of accepted blocks, in the order they were included. The pending block isn't
+    /// included.
+    #[method(name = "getTransactionsBySender")]
+    fn get_transactions_by_sender(
+        &self,
+        sender_address: ContractAddress,
+        from_block: Option<BlockId>,
+        to_block: Option<BlockId>,
+        continuation_token: Option<ContinuationToken>,
+    ) -> Result<SenderTransactionsChunk, Error>;
+
+    /// Gets the non-zero storage entries of the contract at the given accepted block, from the
+    /// start key on in the order of the keys, and the key of the next entry if there are more.
+    #[method(name = "getStorageRange")]
+    fn get_storage_range(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+        start_key: StorageKey,
+        limit: usize,
+    ) -> Result<StorageRange, Error>;
+
+    /// Gets the changes to the value of the storage key of the contract in the given range of
+    /// accepted blocks, in the order of the blocks.
+    #[method(name = "getStorageHistory")]
+    fn get_storage_history(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+        from_block: Option<BlockId>,
+        to_block: Option<BlockId>,
+        continuation_token: Option<ContinuationToken>,
+    ) -> Result<StorageHistoryChunk, Error>;
+
+    /// Gets the changes to the nonce of the contract in the given range of accepted blocks, in the
+    /// order of the blocks.
+    #[method(name = "getNonceHistory")]
+    fn get_nonce_history(
+        &self,
+        contract_address: ContractAddress,
+        from_block: Option<BlockId>,
+        to_block: Option<BlockId>,
+        continuation_token: Option<ContinuationToken>,
+    ) -> Result<NonceHistoryChunk, Error>;
+
+    /// Gets the gas price of the given accepted block, which the block header of the Starknet
+    /// specification doesn't include.
+    #[method(name = "getBlockGasPrice")]
+    fn get_block_gas_price(&self, block_id: BlockId) -> Result<GasPrice, Error>;
+}
+
+#[derive(Clone)]
+pub(crate) struct JsonRpcPapyrusImpl {
+    pub(crate) storage_reader: StorageReader,
+    pub(crate) chunk_size: usize,
+}
+
+impl JsonRpcPapyrusServer for JsonRpcPapyrusImpl {
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_transactions_by_sender(
+        &self,
+        sender_address: ContractAddress,
+        from_block: Option<BlockId>,
+        to_block: Option<BlockId>,
+        continuation_token: Option<ContinuationToken>,
+    ) -> Result<SenderTransactionsChunk, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let from_block_number = match from_block {
+            Some(block_id) => get_block_number(&txn, block_id)?,
+            None => BlockNumber(0),
+        };
+        let to_block_number = match to_block {
+            Some(block_id) => get_block_number(&txn, block_id)?,
+            None => match get_latest_block_number(&txn)? {
+                Some(block_number) => block_number,
+                // There are no blocks.
+                None => {
+                    return Ok(SenderTransactionsChunk {
+                        transactions: vec![],
+                        continuation_token: None,
+                    });
+                }
+            },
+        };
+
+        // The continuation token is the index of the first transaction of the next chunk.
+        let start_transaction_index = match continuation_token {
+            Some(token) => serde_json::from_str(&token.0)
+                .map_err(|_| Error::from(JsonRpcError::InvalidContinuationToken))?,
+            None => TransactionIndex(from_block_number, TransactionOffsetInBlock(0)),
+        };
+
+        // Take one more transaction than the chunk size, to know whether there's a next chunk.
+        let mut sender_transactions = txn
+            .get_transactions_by_sender(
+                &sender_address,
+                start_transaction_index,
+                to_block_number,
+                self.chunk_size + 1,
+            )
+            .map_err(internal_server_error)?;
+        let continuation_token = if sender_transactions.len() > self.chunk_size {
+            let (next_transaction_index, _) =
+                sender_transactions.pop().expect("The chunk shouldn't be empty.");
+            Some(ContinuationToken(
+                serde_json::to_string(&next_transaction_index).map_err(internal_server_error)?,
+            ))
+        } else {
+            None
+        };
+
+        let mut transactions = Vec::with_capacity(sender_transactions.len());
+        for (transaction_index, _) in sender_transactions {
+            let block_number = transaction_index.0;
+            let header = get_block_header_by_number(&txn, block_number)?;
+            let transaction = txn
+                .get_transaction(transaction_index)
+                .map_err(internal_server_error)?
+                .ok_or_else(|| internal_server_error("Unknown internal error."))?;
+            transactions.push(SenderTransaction {
+                block_hash: header.block_hash,
+                block_number,
+                transaction: TransactionWithType::from(set_compiled_class_hash(
+                    &txn,
+                    block_number,
+                    transaction.into(),
+                )?),
+            });
+        }
+
+        Ok(SenderTransactionsChunk { transactions, continuation_token })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_storage_range(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+        start_key: StorageKey,
+        limit: usize,
+    ) -> Result<StorageRange, Error> {
+        if limit > MAX_STORAGE_RANGE_LIMIT {
+            return Err(Error::from(JsonRpcError::PageSizeTooBig));
+        }
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let state = get_state_number(&txn, block_id, &None)?;
+        let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
+        state_reader
+            .get_class_hash_at(state, &contract_address)
+            .map_err(internal_server_error)?
+            .ok_or_else(|| Error::from(JsonRpcError::ContractNotFound))?;
+
+        // Take one more entry than the limit, to know the key of the next entry.
+        let mut entries = state_reader
+            .get_storage_range_at(state, &contract_address, &start_key, limit + 1)
+            .map_err(internal_server_error)?;
+        let next_key = if entries.len() > limit { entries.pop().map(|(key, _)| key) } else { None };
+
+        Ok(StorageRange {
+            storage_entries: entries
+                .into_iter()
+                .map(|(key, value)| StorageEntry { key, value })
+                .collect(),
+            next_key,
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_storage_history(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+        from_block: Option<BlockId>,
+        to_block: Option<BlockId>,
+        continuation_token: Option<ContinuationToken>,
+    ) -> Result<StorageHistoryChunk, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let (from_block_number, to_block_number) =
+            match get_history_range(&txn, from_block, to_block, continuation_token)? {
+                Some(range) => range,
+                None => {
+                    return Ok(StorageHistoryChunk { changes: vec![], continuation_token: None })
+                }
+            };
+
+        // Take one more change than the chunk size, to know whether there's a next chunk.
+        let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
+        let mut changes = state_reader
+            .get_storage_history(
+                &contract_address,
+                &key,
+                from_block_number,
+                to_block_number,
+                self.chunk_size + 1,
+            )
+            .map_err(internal_server_error)?;
+        let continuation_token = if changes.len() > self.chunk_size {
+            let (next_block_number, _) = changes.pop().expect("The chunk shouldn't be empty.");
+            Some(history_continuation_token(next_block_number)?)
+        } else {
+            None
+        };
+
+        Ok(StorageHistoryChunk {
+            changes: changes
+                .into_iter()
+                .map(|(block_number, value)| StorageValueChange { block_number, value })
+                .collect(),
+            continuation_token,
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_nonce_history(
+        &self,
+        contract_address: ContractAddress,
+        from_block: Option<BlockId>,
+        to_block: Option<BlockId>,
+        continuation_token: Option<ContinuationToken>,
+    ) -> Result<NonceHistoryChunk, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let (from_block_number, to_block_number) =
+            match get_history_range(&txn, from_block, to_block, continuation_token)? {
+                Some(range) => range,
+                None => return Ok(NonceHistoryChunk { changes: vec![], continuation_token: None }),
+            };
+
+        // Take one more change than the chunk size, to know whether there's a next chunk.
+        let state_reader = txn.get_state_reader().map_err(internal_server_error)?;
+        let mut changes = state_reader
+            .get_nonce_history(
+                &contract_address,
+                from_block_number,
+                to_block_number,
+                self.chunk_size + 1,
+            )
+            .map_err(internal_server_error)?;
+        let continuation_token = if changes.len() > self.chunk_size {
+            let (next_block_number, _) = changes.pop().expect("The chunk shouldn't be empty.");
+            Some(history_continuation_token(next_block_number)?)
+        } else {
+            None
+        };
+
+        Ok(NonceHistoryChunk {
+            changes: changes
+                .into_iter()
+                .map(|(block_number, nonce)| NonceChange { block_number, nonce })
+                .collect(),
+            continuation_token,
+        })
+    }
+
+    #[instrument(skip(self), level = "debug", err, ret)]
+    fn get_block_gas_price(&self, block_id: BlockId) -> Result<GasPrice, Error> {
+        let txn = self.storage_reader.begin_ro_txn().map_err(internal_server_error)?;
+        let block_number = get_block_number(&txn, block_id)?;
+        let header = txn
+            .get_block_header(block_number)
+            .map_err(internal_server_error)?
+            .ok_or_else(|| Error::from(JsonRpcError::BlockNotFound))?;
+        Ok(header.gas_price)
+    }
+}
+
+// Returns the block numbers of the given range of blocks, or None if there are no blocks. The range
+// defaults to all the blocks whose history is kept, as a pruned storage only keeps the full history
+// from the block before the pruned state marker. A continuation token moves the start of the range
+// to the block of the first change of the next chunk.
+fn get_history_range<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    from_block: Option<BlockId>,
+    to_block: Option<BlockId>,
+    continuation_token: Option<ContinuationToken>,
+) -> Result<Option<(BlockNumber, BlockNumber)>, Error> {
+    let from_block_number = match from_block {
+        Some(block_id) => get_block_number(txn, block_id)?,
+        None => {
+            txn.get_pruned_state_marker().map_err(internal_server_error)?.prev().unwrap_or_default()
+        }
+    };
+    let to_block_number = match to_block {
+        Some(block_id) => get_block_number(txn, block_id)?,
+        None => match get_latest_block_number(txn)? {
+            Some(block_number) => block_number,
+            None => return Ok(None),
+        },
+    };
+    verify_state_not_pruned(txn, from_block_number)?;
+    match continuation_token {
+        Some(token) => {
+            let next_block_number = serde_json::from_str(&token.0)
+                .map_err(|_| Error::from(JsonRpcError::InvalidContinuationToken))?;
+            Ok(Some((next_block_number, to_block_number)))
+        }
+        None => Ok(Some((from_block_number, to_block_number))),
+    }
+}
+
+// The continuation token of the history methods is the block of the first change of the next
+// chunk.
+fn history_continuation_token(next_block_number: BlockNumber) -> Result<ContinuationToken, Error> {
+    Ok(ContinuationToken(serde_json::to_string(&next_block_number).map_err(internal_server_error)?))
+}
@@ -0,0 +1,330 @@
+use assert_matches::assert_matches;
+use indexmap::IndexMap;
+use jsonrpsee::core::Error;
+use jsonrpsee::http_server::types::error::CallError;
+use jsonrpsee::types::error::ErrorObject;
+use papyrus_storage::body::BodyStorageWriter;
+use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_storage::pruning::{PruningConfig, PruningStorageWriter};
+use papyrus_storage::state::StateStorageWriter;
+use starknet_api::block::{BlockHash, BlockHeader, BlockNumber, GasPrice};
+use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{StateDiff, StorageKey};
+use starknet_api::transaction::Transaction;
+use starknet_api::{patricia_key, stark_felt};
+use test_utils::get_test_block;
+
+use crate::api::{BlockHashOrNumber, BlockId, ContinuationToken, JsonRpcError};
+use crate::papyrus::{
+    NonceChange, NonceHistoryChunk, SenderTransaction, SenderTransactionsChunk,
+    StorageHistoryChunk, StorageRange, StorageValueChange,
+};
+use crate::state::StorageEntry;
+use crate::test_utils::get_test_papyrus_server_and_storage_writer;
+use crate::transaction::TransactionWithType;
+
+#[tokio::test]
+async fn get_transactions_by_sender() {
+    let (module, mut storage_writer) = get_test_papyrus_server_and_storage_writer(2);
+    let sender = ContractAddress(patricia_key!("0x100"));
+    let mut block = get_test_block(10);
+    for tx in block.body.transactions.iter_mut() {
+        match tx {
+            Transaction::Declare(tx) => tx.sender_address = sender,
+            Transaction::DeployAccount(tx) => tx.contract_address = sender,
+            Transaction::Invoke(tx) => tx.sender_address = sender,
+            Transaction::Deploy(_) | Transaction::L1Handler(_) => {}
+        }
+    }
+    let block_number = block.header.block_number;
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block_number, &block.header)
+        .unwrap()
+        .append_body(block_number, block.body.clone())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let expected_transactions: Vec<_> = block
+        .body
+        .transactions
+        .into_iter()
+        .filter(|tx| {
+            matches!(
+                tx,
+                Transaction::Declare(_) | Transaction::DeployAccount(_) | Transaction::Invoke(_)
+            )
+        })
+        .map(|tx| SenderTransaction {
+            block_hash: block.header.block_hash,
+            block_number,
+            transaction: TransactionWithType::from(tx),
+        })
+        .collect();
+
+    // Collect the transactions chunk by chunk.
+    let block_id = BlockId::HashOrNumber(BlockHashOrNumber::Number(block_number));
+    let mut transactions = vec![];
+    let mut continuation_token = None;
+    loop {
+        let chunk = module
+            .call::<_, SenderTransactionsChunk>(
+                "papyrus_getTransactionsBySender",
+                (sender, Some(block_id), Some(block_id), continuation_token),
+            )
+            .await
+            .unwrap();
+        assert!(chunk.transactions.len() <= 2);
+        transactions.extend(chunk.transactions);
+        continuation_token = chunk.continuation_token;
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+    assert_eq!(transactions, expected_transactions);
+
+    // Another account sent no transactions.
+    let chunk = module
+        .call::<_, SenderTransactionsChunk>(
+            "papyrus_getTransactionsBySender",
+            (ContractAddress(patricia_key!("0x101")), None::<BlockId>, None::<BlockId>),
+        )
+        .await
+        .unwrap();
+    assert_eq!(chunk, SenderTransactionsChunk { transactions: vec![], continuation_token: None });
+}
+
+#[tokio::test]
+async fn get_transactions_by_sender_invalid_ct() {
+    let (module, mut storage_writer) = get_test_papyrus_server_and_storage_writer(2);
+    let block = starknet_api::block::Block::default();
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block.header.block_number, &block.header)
+        .unwrap()
+        .append_body(block.header.block_number, block.body)
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let err = module
+        .call::<_, SenderTransactionsChunk>(
+            "papyrus_getTransactionsBySender",
+            (
+                ContractAddress(patricia_key!("0x100")),
+                None::<BlockId>,
+                None::<BlockId>,
+                ContinuationToken("junk".to_owned()),
+            ),
+        )
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::InvalidContinuationToken as i32,
+        JsonRpcError::InvalidContinuationToken.to_string(),
+        None::<()>,
+    ));
+}
+
+#[tokio::test]
+async fn get_storage_range() {
+    let (module, mut storage_writer) = get_test_papyrus_server_and_storage_writer(2);
+    let address = ContractAddress(patricia_key!("0x11"));
+    let key0 = StorageKey(patricia_key!("0x1"));
+    let key1 = StorageKey(patricia_key!("0x2"));
+    let key2 = StorageKey(patricia_key!("0x3"));
+    let state_diff = StateDiff {
+        deployed_contracts: IndexMap::from([(address, ClassHash(stark_felt!("0x4")))]),
+        storage_diffs: IndexMap::from([(
+            address,
+            IndexMap::from([
+                (key0, stark_felt!("0x10")),
+                (key1, stark_felt!("0x0")),
+                (key2, stark_felt!("0x12")),
+            ]),
+        )]),
+        ..StateDiff::default()
+    };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &BlockHeader::default())
+        .unwrap()
+        .append_state_diff(BlockNumber(0), state_diff, IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    // The zero value of key1 is skipped.
+    let block_id = BlockId::HashOrNumber(BlockHashOrNumber::Number(BlockNumber(0)));
+    let range = module
+        .call::<_, StorageRange>("papyrus_getStorageRange", (block_id, address, key0, 1))
+        .await
+        .unwrap();
+    assert_eq!(
+        range,
+        StorageRange {
+            storage_entries: vec![StorageEntry { key: key0, value: stark_felt!("0x10") }],
+            next_key: Some(key2),
+        }
+    );
+    let range = module
+        .call::<_, StorageRange>("papyrus_getStorageRange", (block_id, address, key2, 1))
+        .await
+        .unwrap();
+    assert_eq!(
+        range,
+        StorageRange {
+            storage_entries: vec![StorageEntry { key: key2, value: stark_felt!("0x12") }],
+            next_key: None,
+        }
+    );
+
+    let err = module
+        .call::<_, StorageRange>(
+            "papyrus_getStorageRange",
+            (block_id, ContractAddress(patricia_key!("0x12")), key0, 1),
+        )
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::ContractNotFound as i32,
+        JsonRpcError::ContractNotFound.to_string(),
+        None::<()>,
+    ));
+
+    let err = module
+        .call::<_, StorageRange>("papyrus_getStorageRange", (block_id, address, key0, 1001))
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::PageSizeTooBig as i32,
+        JsonRpcError::PageSizeTooBig.to_string(),
+        None::<()>,
+    ));
+}
+
+#[tokio::test]
+async fn get_state_history() {
+    let (module, mut storage_writer) = get_test_papyrus_server_and_storage_writer(2);
+    let address = ContractAddress(patricia_key!("0x11"));
+    let key = StorageKey(patricia_key!("0x101"));
+    for block_number in 0..3 {
+        let header = BlockHeader {
+            block_hash: BlockHash(StarkHash::from(block_number + 1)),
+            block_number: BlockNumber(block_number),
+            ..BlockHeader::default()
+        };
+        let mut diff = StateDiff {
+            storage_diffs: IndexMap::from([(
+                address,
+                IndexMap::from([(key, StarkFelt::from(block_number + 1))]),
+            )]),
+            ..StateDiff::default()
+        };
+        if block_number == 0 {
+            diff.deployed_contracts = IndexMap::from([(address, ClassHash(stark_felt!("0x4")))]);
+        } else {
+            diff.nonces = IndexMap::from([(address, Nonce(StarkFelt::from(block_number)))]);
+        }
+        storage_writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_header(header.block_number, &header)
+            .unwrap()
+            .append_state_diff(header.block_number, diff, IndexMap::new())
+            .unwrap()
+            .commit()
+            .unwrap();
+    }
+
+    let from_block = BlockId::HashOrNumber(BlockHashOrNumber::Number(BlockNumber(1)));
+    let chunk = module
+        .call::<_, StorageHistoryChunk>(
+            "papyrus_getStorageHistory",
+            (address, key, Some(from_block), None::<BlockId>),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        chunk,
+        StorageHistoryChunk {
+            changes: vec![
+                StorageValueChange { block_number: BlockNumber(1), value: StarkFelt::from(2) },
+                StorageValueChange { block_number: BlockNumber(2), value: StarkFelt::from(3) },
+            ],
+            continuation_token: None,
+        }
+    );
+    // The deployment of the contract sets its nonce to zero. The changes are returned in chunks of
+    // 2.
+    let expected_changes: Vec<_> = (0..3)
+        .map(|block_number| NonceChange {
+            block_number: BlockNumber(block_number),
+            nonce: Nonce(StarkFelt::from(block_number)),
+        })
+        .collect();
+    let chunk =
+        module.call::<_, NonceHistoryChunk>("papyrus_getNonceHistory", [address]).await.unwrap();
+    assert_eq!(chunk.changes, expected_changes[..2]);
+    let continuation_token = chunk.continuation_token.unwrap();
+    let chunk = module
+        .call::<_, NonceHistoryChunk>(
+            "papyrus_getNonceHistory",
+            (address, None::<BlockId>, None::<BlockId>, Some(continuation_token)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        chunk,
+        NonceHistoryChunk { changes: expected_changes[2..].to_vec(), continuation_token: None }
+    );
+
+    // A pruned storage only has the history from the block before the pruned state marker.
+    let pruning_config = PruningConfig { history_length: 1, ..PruningConfig::default() };
+    let (txn, _) = storage_writer.begin_rw_txn().unwrap().prune(&pruning_config).unwrap();
+    txn.commit().unwrap();
+    let chunk = module
+        .call::<_, StorageHistoryChunk>("papyrus_getStorageHistory", (address, key))
+        .await
+        .unwrap();
+    assert_eq!(chunk.changes.first().map(|change| change.block_number), Some(BlockNumber(1)));
+    let from_block = BlockId::HashOrNumber(BlockHashOrNumber::Number(BlockNumber(0)));
+    let err = module
+        .call::<_, NonceHistoryChunk>("papyrus_getNonceHistory", (address, Some(from_block)))
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::BlockPruned as i32,
+        JsonRpcError::BlockPruned.to_string(),
+        None::<()>,
+    ));
+}
+
+#[tokio::test]
+async fn get_block_gas_price() {
+    let (module, mut storage_writer) = get_test_papyrus_server_and_storage_writer(2);
+    let header = BlockHeader { gas_price: GasPrice(100), ..BlockHeader::default() };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(header.block_number, &header)
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let block_id = BlockId::HashOrNumber(BlockHashOrNumber::Number(BlockNumber(0)));
+    let res = module.call::<_, GasPrice>("papyrus_getBlockGasPrice", [block_id]).await.unwrap();
+    assert_eq!(res, GasPrice(100));
+
+    let block_id = BlockId::HashOrNumber(BlockHashOrNumber::Number(BlockNumber(1)));
+    let err = module.call::<_, GasPrice>("papyrus_getBlockGasPrice", [block_id]).await.unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::BlockNotFound as i32,
+        JsonRpcError::BlockNotFound.to_string(),
+        None::<()>,
+    ));
+}
@@ -0,0 +1,136 @@
+//! The Merkle proofs returned by `starknet_getProof`, and their verification.
+
+use papyrus_storage::state::commitment::{
+    verify_state_proof, BinaryNode as StorageBinaryNode, ContractData as StorageContractData,
+    EdgeNode as StorageEdgeNode, ProofVerificationError, StateProof, TrieNode,
+};
+use serde::{Deserialize, Serialize};
+use starknet_api::core::{ClassHash, ContractAddress, GlobalRoot, Nonce};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StorageKey;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GetProofResult {
+    pub state_commitment: GlobalRoot,
+    /// The root of the classes trie, zero until a Sierra class is declared.
+    #[serde(default)]
+    pub class_commitment: StarkHash,
+    /// The nodes on the path from the root of the contracts trie to the contract, from the root
+    /// down.
+    pub contract_proof: Vec<ProofNode>,
+    /// None if the contract isn't deployed.
+    pub contract_data: Option<ContractData>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ContractData {
+    pub class_hash: ClassHash,
+    pub nonce: Nonce,
+    /// The root of the storage of the contract.
+    pub root: StarkHash,
+    /// The nodes on the path from the storage root to each of the requested keys.
+    pub storage_proofs: Vec<Vec<ProofNode>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofNode {
+    Binary(BinaryNode),
+    Edge(EdgeNode),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BinaryNode {
+    pub left: StarkHash,
+    pub right: StarkHash,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EdgeNode {
+    pub child: StarkHash,
+    pub path: EdgePath,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EdgePath {
+    pub value: StarkFelt,
+    pub len: u8,
+}
+
+/// Verifies a result of `starknet_getProof` against the state root of the block, and returns the
+/// values of the storage of the contract at the keys, which are zero for a contract that isn't
+/// deployed.
+pub fn verify_proof(
+    state_root: &GlobalRoot,
+    contract_address: &ContractAddress,
+    keys: &[StorageKey],
+    proof: &GetProofResult,
+) -> Result<Vec<StarkFelt>, ProofVerificationError> {
+    verify_state_proof(state_root, contract_address, keys, &StateProof::from(proof.clone()))
+}
+
+impl From<StateProof> for GetProofResult {
+    fn from(proof: StateProof) -> Self {
+        Self {
+            state_commitment: proof.state_root,
+            class_commitment: proof.classes_root,
+            contract_proof: proof.contract_proof.into_iter().map(ProofNode::from).collect(),
+            contract_data: proof.contract_data.map(|contract_data| ContractData {
+                class_hash: contract_data.class_hash,
+                nonce: contract_data.nonce,
+                root: contract_data.storage_root,
+                storage_proofs: contract_data
+                    .storage_proofs
+                    .into_iter()
+                    .map(|storage_proof| storage_proof.into_iter().map(ProofNode::from).collect())
+                    .collect(),
+            }),
+        }
+    }
+}
+
+impl From<GetProofResult> for StateProof {
+    fn from(proof: GetProofResult) -> Self {
+        Self {
+            state_root: proof.state_commitment,
+            classes_root: proof.class_commitment,
+            contract_proof: proof.contract_proof.into_iter().map(TrieNode::from).collect(),
+            contract_data: proof.contract_data.map(|contract_data| StorageContractData {
+                class_hash: contract_data.class_hash,
+                nonce: contract_data.nonce,
+                storage_root: contract_data.root,
+                storage_proofs: contract_data
+                    .storage_proofs
+                    .into_iter()
+                    .map(|storage_proof| storage_proof.into_iter().map(TrieNode::from).collect())
+                    .collect(),
+            }),
+        }
+    }
+}
+
+impl From<TrieNode> for ProofNode {
+    fn from(node: TrieNode) -> Self {
+        match node {
+            TrieNode::Binary(StorageBinaryNode { left, right }) => {
+                ProofNode::Binary(BinaryNode { left, right })
+            }
+            TrieNode::Edge(StorageEdgeNode { child, path, length }) => {
+                ProofNode::Edge(EdgeNode { child, path: EdgePath { value: path, len: length } })
+            }
+        }
+    }
+}
+
+impl From<ProofNode> for TrieNode {
+    fn from(node: ProofNode) -> Self {
+        match node {
+            ProofNode::Binary(BinaryNode { left, right }) => {
+                TrieNode::Binary(StorageBinaryNode { left, right })
+            }
+            ProofNode::Edge(EdgeNode { child, path }) => {
+                TrieNode::Edge(StorageEdgeNode { child, path: path.value, length: path.len })
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Routes the requests of the HTTP server by their path to the methods of the versions of the
+//! specification. A jsonrpsee server serves the same methods on all paths, so the server is a hyper
+//! service that calls the methods of the version of the path in-process.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_util::future::{join_all, pending};
+use hyper::body::HttpBody;
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use jsonrpsee::core::middleware::Middleware;
+use jsonrpsee::types::error::ErrorCode;
+use jsonrpsee::Methods;
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+use tokio::task::{JoinError, JoinHandle};
+
+use crate::metrics::MetricsMiddleware;
+
+// The maximal size of the body of a request, as in the jsonrpsee servers.
+const MAX_REQUEST_BODY_SIZE: u64 = 10 * 1024 * 1024;
+
+/// A handle of the running HTTP server, which completes when the server stops. Dropping it leaves
+/// the server running.
+pub struct ServerHandle {
+    stop_sender: oneshot::Sender<()>,
+    server: JoinHandle<hyper::Result<()>>,
+}
+
+impl ServerHandle {
+    /// Stops accepting connections, and returns the task of the server, which completes once the
+    /// requests in progress are answered.
+    pub fn stop(self) -> JoinHandle<hyper::Result<()>> {
+        // The server may have stopped already.
+        let _ = self.stop_sender.send(());
+        self.server
+    }
+}
+
+impl Future for ServerHandle {
+    type Output = Result<hyper::Result<()>, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.server).poll(cx)
+    }
+}
+
+/// Runs an HTTP server that serves the requests with the methods of the route of their path,
+/// ignoring a trailing slash.
+pub(crate) async fn run_router(
+    server_address: &str,
+    routes: HashMap<&'static str, Methods>,
+) -> anyhow::Result<(SocketAddr, ServerHandle)> {
+    let addr = tokio::net::lookup_host(server_address)
+        .await?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("The address {server_address} didn't resolve."))?;
+    let routes = Arc::new(routes);
+    let make_service = make_service_fn(move |_connection| {
+        let routes = routes.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let routes = routes.clone();
+                async move { Ok::<_, Infallible>(route(&routes, request).await) }
+            }))
+        }
+    });
+    let (stop_sender, stop_receiver) = oneshot::channel::<()>();
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    let local_addr = server.local_addr();
+    let server = server.with_graceful_shutdown(async move {
+        // A dropped handle doesn't stop the server.
+        if stop_receiver.await.is_err() {
+            pending::<()>().await;
+        }
+    });
+    Ok((local_addr, ServerHandle { stop_sender, server: tokio::spawn(server) }))
+}
+
+async fn route(routes: &HashMap<&'static str, Methods>, request: Request<Body>) -> Response<Body> {
+    let path = request.uri().path();
+    let methods = match routes.get(path).or_else(|| routes.get(path.trim_end_matches('/'))) {
+        Some(methods) => methods.clone(),
+        None => return response_with_status(StatusCode::NOT_FOUND, "Not found."),
+    };
+    if request.method() != Method::POST {
+        return response_with_status(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed.");
+    }
+    if request.body().size_hint().lower() > MAX_REQUEST_BODY_SIZE {
+        return response_with_status(StatusCode::PAYLOAD_TOO_LARGE, "Payload too large.");
+    }
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(_) => return response_with_status(StatusCode::BAD_REQUEST, "Bad request."),
+    };
+    let response = match serde_json::from_slice::<Value>(&body) {
+        Err(_) => error_response(ErrorCode::ParseError, Value::Null),
+        Ok(Value::Array(calls)) if calls.is_empty() => {
+            error_response(ErrorCode::InvalidRequest, Value::Null)
+        }
+        Ok(Value::Array(calls)) => {
+            Value::Array(join_all(calls.into_iter().map(|call| call_method(&methods, call))).await)
+        }
+        Ok(call) => call_method(&methods, call).await,
+    };
+    let mut response = Response::new(Body::from(response.to_string()));
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        "application/json; charset=utf-8".parse().expect("A valid header value."),
+    );
+    response
+}
+
+// Calls the method of a JSON-RPC request and returns its response, recording the metrics of the
+// call like the middleware of the jsonrpsee servers.
+async fn call_method(methods: &Methods, call: Value) -> Value {
+    let id = call.get("id").cloned().unwrap_or(Value::Null);
+    let method = call.get("method").and_then(Value::as_str).unwrap_or_default().to_owned();
+    let started_at = MetricsMiddleware.on_request();
+    let response = match methods.raw_json_request(&call.to_string()).await {
+        Ok((response, _subscription)) => serde_json::from_str(&response)
+            .unwrap_or_else(|_| error_response(ErrorCode::InternalError, id)),
+        Err(_) => error_response(ErrorCode::InvalidRequest, id),
+    };
+    MetricsMiddleware.on_result(&method, response.get("error").is_none(), started_at);
+    response
+}
+
+fn error_response(code: ErrorCode, id: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": {"code": code.code(), "message": code.message()},
+        "id": id,
+    })
+}
+
+fn response_with_status(status: StatusCode, body: &'static str) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
+}
@@ -2,7 +2,10 @@ use std::collections::HashMap;
 
 use papyrus_storage::compression_utils::{CompressionError, GzEncoded};
 use papyrus_storage::db::serialization::{StorageSerde, StorageSerdeError};
-use papyrus_storage::state::data::ThinStateDiff as papyrus_storage_ThinStateDiff;
+use papyrus_storage::state::data::{
+    CompiledClassHash, SierraContractClass as papyrus_storage_SierraContractClass,
+    SierraEntryPoint, ThinStateDiff as papyrus_storage_ThinStateDiff,
+};
 use serde::{Deserialize, Serialize};
 use starknet_api::block::BlockHash;
 use starknet_api::core::{ClassHash, ContractAddress, GlobalRoot, Nonce};
@@ -114,6 +117,36 @@ impl TryFrom<starknet_api::state::ContractClass> for ContractClass {
     }
 }
 
+/// A Cairo 1 contract class, as a Sierra program.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct SierraContractClass {
+    pub sierra_program: Vec<StarkFelt>,
+    pub contract_class_version: String,
+    /// The selector of each entry point is a unique identifier in the program.
+    pub entry_points_by_type: HashMap<EntryPointType, Vec<SierraEntryPoint>>,
+    /// The ABI of the class, as a JSON string.
+    pub abi: String,
+}
+
+impl From<papyrus_storage_SierraContractClass> for SierraContractClass {
+    fn from(class: papyrus_storage_SierraContractClass) -> Self {
+        Self {
+            sierra_program: class.sierra_program,
+            contract_class_version: class.contract_class_version,
+            entry_points_by_type: class.entry_points_by_type,
+            abi: class.abi,
+        }
+    }
+}
+
+/// A class as returned by the gateway, either a Cairo 0 class or a Sierra class.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum GatewayContractClass {
+    Cairo0(ContractClass),
+    Sierra(SierraContractClass),
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub struct StateUpdate {
     pub block_hash: BlockHash,
@@ -122,12 +155,28 @@ pub struct StateUpdate {
     pub state_diff: ThinStateDiff,
 }
 
+/// The state update of the pending block, relative to the latest block.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct PendingStateUpdate {
+    pub old_root: GlobalRoot,
+    pub state_diff: ThinStateDiff,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MaybePendingStateUpdate {
+    StateUpdate(StateUpdate),
+    Pending(PendingStateUpdate),
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub struct ThinStateDiff {
     pub deployed_contracts: Vec<DeployedContract>,
     pub storage_diffs: Vec<StorageDiff>,
     pub declared_contract_hashes: Vec<ClassHash>,
     pub nonces: Vec<ContractNonce>,
+    pub declared_classes: Vec<ClassHashes>,
+    pub replaced_classes: Vec<ReplacedClass>,
 }
 
 impl From<papyrus_storage_ThinStateDiff> for ThinStateDiff {
@@ -152,10 +201,30 @@ impl From<papyrus_storage_ThinStateDiff> for ThinStateDiff {
                     .into_iter()
                     .map(|(contract_address, nonce)| ContractNonce { contract_address, nonce }),
             ),
+            declared_classes: Vec::from_iter(diff.declared_classes.into_iter().map(
+                |(class_hash, compiled_class_hash)| ClassHashes { class_hash, compiled_class_hash },
+            )),
+            replaced_classes: Vec::from_iter(diff.replaced_classes.into_iter().map(
+                |(contract_address, class_hash)| ReplacedClass { contract_address, class_hash },
+            )),
         }
     }
 }
 
+/// A declared Sierra class with the hash of its compiled class.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct ClassHashes {
+    pub class_hash: ClassHash,
+    pub compiled_class_hash: CompiledClassHash,
+}
+
+/// A deployed contract whose class was replaced.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct ReplacedClass {
+    pub contract_address: ContractAddress,
+    pub class_hash: ClassHash,
+}
+
 /// The nonce of a StarkNet contract.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
 pub struct ContractNonce {
@@ -188,7 +257,7 @@ pub struct StorageEntry {
 // The StorageSerde implementation for serde_json::Value writes the length (in bytes)
 // of the value. Here we serialize the whole program as one value so no need to write
 // its length.
-pub struct Program(serde_json::Value);
+pub struct Program(pub(crate) serde_json::Value);
 impl StorageSerde for Program {
     /// Serializes the entire program as one json value.
     fn serialize_into(&self, res: &mut impl std::io::Write) -> Result<(), StorageSerdeError> {
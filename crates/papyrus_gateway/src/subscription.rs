@@ -0,0 +1,223 @@
+use std::collections::HashSet;
+
+use async_stream::stream;
+use futures_util::Stream;
+use jsonrpsee::core::Error;
+use jsonrpsee::proc_macros::rpc;
+use jsonrpsee::ws_server::SubscriptionSink;
+use papyrus_storage::body::events::EventsReader;
+use papyrus_storage::body::BodyStorageReader;
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::{EventIndex, StorageError, StorageReader, StorageResult, TransactionIndex};
+use papyrus_sync::SyncNotification;
+use serde::{Deserialize, Serialize};
+use starknet_api::core::ContractAddress;
+use starknet_api::transaction::{
+    EventIndexInTransactionOutput, EventKey, TransactionOffsetInBlock,
+};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error, instrument, warn};
+
+use crate::api::{BlockHashAndNumber, JsonRpcError};
+use crate::block::BlockHeader;
+use crate::is_matching_event;
+use crate::transaction::Event;
+
+/// The events filter of an events subscription. Has the same semantics as the address and keys of
+/// the filter of starknet_getEvents.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EventSubscriptionFilter {
+    pub address: Option<ContractAddress>,
+    #[serde(default)]
+    pub keys: Vec<HashSet<EventKey>>,
+}
+
+#[rpc(server, client, namespace = "starknet")]
+pub trait JsonRpcSubscriptions {
+    /// Subscribes to the headers of the blocks added by the sync.
+    #[subscription(
+        name = "subscribeNewHeads",
+        unsubscribe = "unsubscribeNewHeads",
+        item = BlockHeader
+    )]
+    fn subscribe_new_heads(&self);
+
+    /// Subscribes to the events of the blocks added by the sync that match the given filter.
+    #[subscription(name = "subscribeEvents", unsubscribe = "unsubscribeEvents", item = Event)]
+    fn subscribe_events(&self, filter: EventSubscriptionFilter);
+
+    /// Subscribes to the blocks reverted by the sync. A reorg of several blocks is published as
+    /// one item per block, starting from the last block.
+    #[subscription(
+        name = "subscribeReorgs",
+        unsubscribe = "unsubscribeReorgs",
+        item = BlockHashAndNumber
+    )]
+    fn subscribe_reorgs(&self);
+}
+
+/// Subscriptions server, fed by the notifications of the sync.
+pub(crate) struct JsonRpcSubscriptionsImpl {
+    pub(crate) storage_reader: StorageReader,
+    pub(crate) sync_notifications: broadcast::Sender<SyncNotification>,
+    pub(crate) max_events_keys: usize,
+}
+
+impl JsonRpcSubscriptionsServer for JsonRpcSubscriptionsImpl {
+    #[instrument(skip(self, sink), level = "debug", err)]
+    fn subscribe_new_heads(&self, sink: SubscriptionSink) -> Result<(), Error> {
+        let storage_reader = self.storage_reader.clone();
+        pipe_notifications(sink, self.sync_notifications.subscribe(), move |notification| {
+            get_new_head(&storage_reader, notification)
+        });
+        Ok(())
+    }
+
+    #[instrument(skip(self, sink), level = "debug", err)]
+    fn subscribe_events(
+        &self,
+        sink: SubscriptionSink,
+        filter: EventSubscriptionFilter,
+    ) -> Result<(), Error> {
+        if filter.keys.len() > self.max_events_keys {
+            return Err(Error::from(JsonRpcError::TooManyKeysInFilter));
+        }
+        let storage_reader = self.storage_reader.clone();
+        pipe_notifications(sink, self.sync_notifications.subscribe(), move |notification| {
+            get_new_events(&storage_reader, &filter, notification)
+        });
+        Ok(())
+    }
+
+    #[instrument(skip(self, sink), level = "debug", err)]
+    fn subscribe_reorgs(&self, sink: SubscriptionSink) -> Result<(), Error> {
+        pipe_notifications(sink, self.sync_notifications.subscribe(), |notification| {
+            Ok(match notification {
+                SyncNotification::BlockReverted { block_number, block_hash } => {
+                    vec![BlockHashAndNumber { block_hash, block_number }]
+                }
+                SyncNotification::BlockAdded { .. } => vec![],
+            })
+        });
+        Ok(())
+    }
+}
+
+// Sends the items created from the sync notifications to the subscriber until the subscription is
+// closed.
+fn pipe_notifications<T, F>(
+    mut sink: SubscriptionSink,
+    receiver: broadcast::Receiver<SyncNotification>,
+    to_items: F,
+) where
+    T: Serialize + Send + 'static,
+    F: Fn(SyncNotification) -> StorageResult<Vec<T>> + Send + 'static,
+{
+    let items = Box::pin(notifications_to_items(receiver, to_items));
+    tokio::spawn(async move {
+        let closed = sink.pipe_from_stream(items).await;
+        debug!("Subscription closed: {closed:?}.");
+    });
+}
+
+// The stream ends if the subscriber falls too far behind the sync, so that it doesn't miss items
+// silently.
+fn notifications_to_items<T, F>(
+    mut receiver: broadcast::Receiver<SyncNotification>,
+    to_items: F,
+) -> impl Stream<Item = T>
+where
+    F: Fn(SyncNotification) -> StorageResult<Vec<T>>,
+{
+    stream! {
+        loop {
+            let notification = match receiver.recv().await {
+                Ok(notification) => notification,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(
+                        "Subscriber missed {skipped} sync notifications, closing the subscription."
+                    );
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            match to_items(notification) {
+                Ok(items) => {
+                    for item in items {
+                        yield item;
+                    }
+                }
+                Err(err) => {
+                    error!("Failed reading the subscription items from the storage: {err}.");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// Returns the header of the added block. The block might have been reverted since the notification
+// was sent, in which case there's nothing to return.
+fn get_new_head(
+    storage_reader: &StorageReader,
+    notification: SyncNotification,
+) -> StorageResult<Vec<BlockHeader>> {
+    let (block_number, block_hash) = match notification {
+        SyncNotification::BlockAdded { block_number, block_hash } => (block_number, block_hash),
+        SyncNotification::BlockReverted { .. } => return Ok(vec![]),
+    };
+    let header = storage_reader.begin_ro_txn()?.get_block_header(block_number)?;
+    Ok(header
+        .filter(|header| header.block_hash == block_hash)
+        .map(BlockHeader::from)
+        .into_iter()
+        .collect())
+}
+
+fn get_new_events(
+    storage_reader: &StorageReader,
+    filter: &EventSubscriptionFilter,
+    notification: SyncNotification,
+) -> StorageResult<Vec<Event>> {
+    let (block_number, block_hash) = match notification {
+        SyncNotification::BlockAdded { block_number, block_hash } => (block_number, block_hash),
+        SyncNotification::BlockReverted { .. } => return Ok(vec![]),
+    };
+    let txn = storage_reader.begin_ro_txn()?;
+    // The block might have been reverted since the notification was sent.
+    let stored_block_hash = txn.get_block_header(block_number)?.map(|header| header.block_hash);
+    if stored_block_hash != Some(block_hash) {
+        return Ok(vec![]);
+    }
+
+    let first_event_index = EventIndex(
+        TransactionIndex(block_number, TransactionOffsetInBlock(0)),
+        EventIndexInTransactionOutput(0),
+    );
+    let mut events = vec![];
+    for ((from_address, event_index), content) in
+        txn.iter_events(filter.address, &filter.keys, first_event_index, block_number)?
+    {
+        if (event_index.0).0 != block_number {
+            break;
+        }
+        if filter.address.is_some() && from_address != filter.address.unwrap() {
+            break;
+        }
+        if !is_matching_event(filter.address, &filter.keys, from_address, &content) {
+            continue;
+        }
+        let transaction =
+            txn.get_transaction(event_index.0)?.ok_or(StorageError::DBInconsistency {
+                msg: format!("Missing the transaction of event {event_index:?}."),
+            })?;
+        events.push(Event {
+            block_hash: Some(block_hash),
+            block_number: Some(block_number),
+            transaction_hash: transaction.transaction_hash(),
+            event: starknet_api::transaction::Event { from_address, content },
+        });
+    }
+    Ok(events)
+}
@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+use std::ops::Index;
+
+use assert_matches::assert_matches;
+use jsonrpsee::core::Error;
+use jsonrpsee::http_server::types::error::CallError;
+use jsonrpsee::types::error::ErrorObject;
+use jsonrpsee::types::EmptyParams;
+use papyrus_storage::body::BodyStorageWriter;
+use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_sync::SyncNotification;
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::hash::StarkFelt;
+use starknet_api::stark_felt;
+use starknet_api::transaction::EventKey;
+use test_utils::{get_test_block, get_test_block_with_events};
+
+use crate::api::{BlockHashAndNumber, JsonRpcError};
+use crate::block::BlockHeader;
+use crate::subscription::EventSubscriptionFilter;
+use crate::test_utils::{
+    get_test_gateway_config, get_test_subscriptions_server_and_storage_writer,
+};
+use crate::transaction::Event;
+
+#[tokio::test]
+async fn subscribe_new_heads() {
+    let (module, mut storage_writer, sync_notifications) =
+        get_test_subscriptions_server_and_storage_writer();
+    let mut subscription =
+        module.subscribe("starknet_subscribeNewHeads", EmptyParams::new()).await.unwrap();
+
+    let block = get_test_block(1);
+    let block_number = block.header.block_number;
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block_number, &block.header)
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    // A notification of a block that is no longer in the storage is skipped.
+    sync_notifications
+        .send(SyncNotification::BlockAdded {
+            block_number,
+            block_hash: BlockHash(stark_felt!("0x1234")),
+        })
+        .unwrap();
+    sync_notifications
+        .send(SyncNotification::BlockAdded { block_number, block_hash: block.header.block_hash })
+        .unwrap();
+
+    let (header, _) = subscription.next::<BlockHeader>().await.unwrap().unwrap();
+    assert_eq!(header, BlockHeader::from(block.header));
+}
+
+#[tokio::test]
+async fn subscribe_events() {
+    let (module, mut storage_writer, sync_notifications) =
+        get_test_subscriptions_server_and_storage_writer();
+    let filter = EventSubscriptionFilter { address: None, keys: vec![] };
+    let mut subscription = module.subscribe("starknet_subscribeEvents", [filter]).await.unwrap();
+
+    let block = get_test_block_with_events(2, 2);
+    let block_number = block.header.block_number;
+    let block_hash = block.header.block_hash;
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block_number, &block.header)
+        .unwrap()
+        .append_body(block_number, block.body.clone())
+        .unwrap()
+        .commit()
+        .unwrap();
+    sync_notifications.send(SyncNotification::BlockAdded { block_number, block_hash }).unwrap();
+
+    for (tx_i, tx_output) in block.body.transaction_outputs.iter().enumerate() {
+        let transaction_hash = block.body.transactions.index(tx_i).transaction_hash();
+        for event in tx_output.events() {
+            let (emitted_event, _) = subscription.next::<Event>().await.unwrap().unwrap();
+            assert_eq!(
+                emitted_event,
+                Event {
+                    block_hash: Some(block_hash),
+                    block_number: Some(block_number),
+                    transaction_hash,
+                    event: event.clone(),
+                }
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn subscribe_events_too_many_keys() {
+    let (module, _, _) = get_test_subscriptions_server_and_storage_writer();
+    let filter = EventSubscriptionFilter {
+        address: None,
+        keys: (0..get_test_gateway_config().max_events_keys + 1)
+            .map(|i| HashSet::from([EventKey(StarkFelt::from(i as u64))]))
+            .collect(),
+    };
+    let err = module.subscribe("starknet_subscribeEvents", [filter]).await.unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::TooManyKeysInFilter as i32,
+        JsonRpcError::TooManyKeysInFilter.to_string(),
+        None::<()>,
+    ));
+}
+
+#[tokio::test]
+async fn subscribe_reorgs() {
+    let (module, _, sync_notifications) = get_test_subscriptions_server_and_storage_writer();
+    let mut subscription =
+        module.subscribe("starknet_subscribeReorgs", EmptyParams::new()).await.unwrap();
+
+    let block_hash = BlockHash(stark_felt!("0x1"));
+    sync_notifications
+        .send(SyncNotification::BlockAdded { block_number: BlockNumber(1), block_hash })
+        .unwrap();
+    sync_notifications
+        .send(SyncNotification::BlockReverted { block_number: BlockNumber(1), block_hash })
+        .unwrap();
+
+    let (reverted_block, _) = subscription.next::<BlockHashAndNumber>().await.unwrap().unwrap();
+    assert_eq!(reverted_block, BlockHashAndNumber { block_hash, block_number: BlockNumber(1) });
+}
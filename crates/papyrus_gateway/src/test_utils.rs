@@ -1,36 +1,94 @@
+use std::sync::{Arc, RwLock};
+
 use jsonrpsee::http_server::RpcModule;
 use jsonschema::JSONSchema;
+use papyrus_execution::ExecutionConfig;
 use papyrus_storage::test_utils::get_test_storage;
 use papyrus_storage::StorageWriter;
+use papyrus_sync::{PendingData, SyncNotification};
 use starknet_api::core::ChainId;
+use tokio::sync::broadcast;
 
-use crate::{GatewayConfig, JsonRpcServer, JsonRpcServerImpl};
+use crate::papyrus::{JsonRpcPapyrusImpl, JsonRpcPapyrusServer};
+use crate::subscription::{JsonRpcSubscriptionsImpl, JsonRpcSubscriptionsServer};
+use crate::v0_2::{JsonRpcServerV0_2Impl, JsonRpcV0_2Server};
+use crate::{get_sequencer_client, GatewayConfig, JsonRpcServer, JsonRpcServerImpl};
 
 pub fn get_test_gateway_config() -> GatewayConfig {
     GatewayConfig {
         chain_id: ChainId("SN_GOERLI".to_string()),
         server_address: String::from("127.0.0.1:0"),
+        ws_server_address: String::from("127.0.0.1:0"),
         max_events_chunk_size: 10,
         max_events_keys: 10,
+        max_proof_keys: 10,
+        execution_config: ExecutionConfig::default(),
+        sequencer_url: mockito::server_url(),
     }
 }
 
 pub(crate) fn get_test_rpc_server_and_storage_writer()
 -> (RpcModule<JsonRpcServerImpl>, StorageWriter) {
+    let (module, storage_writer, _) = get_test_rpc_server_with_pending_data();
+    (module, storage_writer)
+}
+
+pub(crate) fn get_test_rpc_server_with_pending_data()
+-> (RpcModule<JsonRpcServerImpl>, StorageWriter, Arc<RwLock<Option<PendingData>>>) {
+    let (server_impl, storage_writer, pending_data) = get_test_server_impl();
+    (server_impl.into_rpc(), storage_writer, pending_data)
+}
+
+pub(crate) fn get_test_rpc_server_v0_2_and_storage_writer()
+-> (RpcModule<JsonRpcServerV0_2Impl>, StorageWriter) {
+    let (server_impl, storage_writer, _) = get_test_server_impl();
+    (JsonRpcServerV0_2Impl(server_impl).into_rpc(), storage_writer)
+}
+
+fn get_test_server_impl() -> (JsonRpcServerImpl, StorageWriter, Arc<RwLock<Option<PendingData>>>) {
     let (storage_reader, storage_writer) = get_test_storage();
     let config = get_test_gateway_config();
+    let pending_data = Arc::new(RwLock::new(None));
     (
         JsonRpcServerImpl {
             chain_id: config.chain_id,
             storage_reader,
             max_events_chunk_size: config.max_events_chunk_size,
             max_events_keys: config.max_events_keys,
+            max_proof_keys: config.max_proof_keys,
+            execution_config: config.execution_config,
+            pending_data: pending_data.clone(),
+            sequencer_client: Arc::new(get_sequencer_client(&config.sequencer_url).unwrap()),
+        },
+        storage_writer,
+        pending_data,
+    )
+}
+
+pub(crate) fn get_test_subscriptions_server_and_storage_writer()
+-> (RpcModule<JsonRpcSubscriptionsImpl>, StorageWriter, broadcast::Sender<SyncNotification>) {
+    let (storage_reader, storage_writer) = get_test_storage();
+    let config = get_test_gateway_config();
+    let (sync_notifications, _) = broadcast::channel(10);
+    (
+        JsonRpcSubscriptionsImpl {
+            storage_reader,
+            sync_notifications: sync_notifications.clone(),
+            max_events_keys: config.max_events_keys,
         }
         .into_rpc(),
         storage_writer,
+        sync_notifications,
     )
 }
 
+pub(crate) fn get_test_papyrus_server_and_storage_writer(
+    chunk_size: usize,
+) -> (RpcModule<JsonRpcPapyrusImpl>, StorageWriter) {
+    let (storage_reader, storage_writer) = get_test_storage();
+    (JsonRpcPapyrusImpl { storage_reader, chunk_size }.into_rpc(), storage_writer)
+}
+
 pub async fn get_starknet_spec_api_schema(component_names: &[&str]) -> JSONSchema {
     let target = "./resources/starknet_api_openrpc.json";
     let text = std::fs::read_to_string(target).unwrap();
@@ -0,0 +1,40 @@
+use papyrus_execution::objects::{
+    FunctionInvocation, TransactionTrace as ExecutionTransactionTrace,
+};
+use serde::{Deserialize, Serialize};
+use starknet_api::transaction::TransactionHash;
+
+use crate::state::ThinStateDiff;
+
+/// The trace of the execution of a transaction, with the state diff it produced.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TransactionTrace {
+    pub validate_invocation: Option<FunctionInvocation>,
+    pub execute_invocation: Option<FunctionInvocation>,
+    pub fee_transfer_invocation: Option<FunctionInvocation>,
+    pub state_diff: ThinStateDiff,
+    /// Why the transaction couldn't be re-executed, for example a deploy transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TransactionTraceWithHash {
+    pub transaction_hash: TransactionHash,
+    pub trace_root: TransactionTrace,
+}
+
+impl From<ExecutionTransactionTrace> for TransactionTraceWithHash {
+    fn from(trace: ExecutionTransactionTrace) -> Self {
+        Self {
+            transaction_hash: trace.transaction_hash,
+            trace_root: TransactionTrace {
+                validate_invocation: trace.validate_invocation,
+                execute_invocation: trace.execute_invocation,
+                fee_transfer_invocation: trace.fee_transfer_invocation,
+                state_diff: ThinStateDiff::from(trace.state_diff),
+                failure_reason: trace.failure_reason,
+            },
+        }
+    }
+}
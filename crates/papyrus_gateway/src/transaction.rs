@@ -1,14 +1,18 @@
 use papyrus_storage::body::events::ThinTransactionOutput;
+use papyrus_storage::state::data::CompiledClassHash;
+use papyrus_sync::calculate_invoke_transaction_hash;
 use serde::{Deserialize, Serialize};
 use starknet_api::block::{BlockHash, BlockNumber, BlockStatus};
-use starknet_api::core::{ContractAddress, EntryPointSelector, Nonce};
+use starknet_api::core::{ChainId, ContractAddress, EntryPointSelector, Nonce};
+use starknet_api::hash::StarkFelt;
 use starknet_api::transaction::{
-    Calldata, DeclareTransaction, DeclareTransactionOutput, DeployAccountTransaction,
-    DeployAccountTransactionOutput, DeployTransaction, DeployTransactionOutput, Fee,
-    InvokeTransactionOutput, L1HandlerTransaction, L1HandlerTransactionOutput, TransactionHash,
-    TransactionSignature, TransactionVersion,
+    Calldata, DeclareTransactionOutput, DeployAccountTransaction, DeployAccountTransactionOutput,
+    DeployTransaction, DeployTransactionOutput, Fee, InvokeTransactionOutput, L1HandlerTransaction,
+    L1HandlerTransactionOutput, TransactionHash, TransactionSignature, TransactionVersion,
 };
 
+use crate::api::JsonRpcError;
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
 #[serde(untagged)]
 pub enum Transactions {
@@ -68,6 +72,52 @@ impl From<starknet_api::transaction::InvokeTransaction> for InvokeTransactionV1
     }
 }
 
+/// An invoke transaction of version 1 that wasn't included in a block, as given to estimate its
+/// fee.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct BroadcastedInvokeTransaction {
+    pub max_fee: Fee,
+    pub version: TransactionVersion,
+    pub signature: TransactionSignature,
+    pub nonce: Nonce,
+    pub sender_address: ContractAddress,
+    pub calldata: Calldata,
+}
+
+impl BroadcastedInvokeTransaction {
+    /// Converts the transaction to the one executed to estimate the fee. Only version 1 is
+    /// supported, since the hash of other versions is calculated differently.
+    pub fn into_invoke_transaction(
+        self,
+        chain_id: &ChainId,
+    ) -> Result<starknet_api::transaction::InvokeTransaction, JsonRpcError> {
+        if self.version != TransactionVersion(StarkFelt::from(1u64)) {
+            return Err(JsonRpcError::UnsupportedTxVersion);
+        }
+        let mut tx = starknet_api::transaction::InvokeTransaction {
+            transaction_hash: TransactionHash::default(),
+            max_fee: self.max_fee,
+            version: self.version,
+            signature: self.signature,
+            nonce: self.nonce,
+            sender_address: self.sender_address,
+            entry_point_selector: None,
+            calldata: self.calldata,
+        };
+        tx.transaction_hash = calculate_invoke_transaction_hash(&tx, chain_id);
+        Ok(tx)
+    }
+}
+
+/// A declare transaction, with the hash of the compiled class if it declares a Sierra class.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct DeclareTransaction {
+    #[serde(flatten)]
+    pub transaction: starknet_api::transaction::DeclareTransaction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiled_class_hash: Option<CompiledClassHash>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
 #[serde(untagged)]
 pub enum InvokeTransaction {
@@ -89,7 +139,7 @@ pub enum Transaction {
 impl Transaction {
     pub fn transaction_hash(&self) -> TransactionHash {
         match self {
-            Transaction::Declare(tx) => tx.transaction_hash,
+            Transaction::Declare(tx) => tx.transaction.transaction_hash,
             Transaction::Deploy(tx) => tx.transaction_hash,
             Transaction::DeployAccount(tx) => tx.transaction_hash,
             Transaction::Invoke(InvokeTransaction::Version0(tx)) => tx.transaction_hash,
@@ -103,7 +153,10 @@ impl From<starknet_api::transaction::Transaction> for Transaction {
     fn from(tx: starknet_api::transaction::Transaction) -> Self {
         match tx {
             starknet_api::transaction::Transaction::Declare(declare_tx) => {
-                Transaction::Declare(declare_tx)
+                Transaction::Declare(DeclareTransaction {
+                    transaction: declare_tx,
+                    compiled_class_hash: None,
+                })
             }
             starknet_api::transaction::Transaction::Deploy(deploy_tx) => {
                 Transaction::Deploy(deploy_tx)
@@ -361,8 +414,11 @@ impl From<starknet_api::transaction::TransactionOutput> for TransactionOutput {
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Event {
-    pub block_hash: BlockHash,
-    pub block_number: BlockNumber,
+    // Events of the pending block have no block hash and number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<BlockHash>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<BlockNumber>,
     pub transaction_hash: TransactionHash,
     #[serde(flatten)]
     pub event: starknet_api::transaction::Event,
@@ -0,0 +1,405 @@
+//! Version 0.2 of the Starknet JSON-RPC specification, served for the clients that didn't move to
+//! the latest version. The version has no Cairo 1 classes, so they are left out of its state
+//! updates, its classes are Cairo 0 classes and its declare transactions declare Cairo 0 classes.
+
+use jsonrpsee::core::{async_trait, Error};
+use jsonrpsee::proc_macros::rpc;
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::core::{ClassHash, ContractAddress, GlobalRoot, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::{
+    Fee, TransactionHash, TransactionOffsetInBlock, TransactionSignature, TransactionVersion,
+};
+
+use crate::add_transaction::{
+    AddDeclareTransactionResult, AddDeployAccountTransactionResult, AddInvokeTransactionResult,
+    BroadcastedDeclareTransaction as LatestBroadcastedDeclareTransaction,
+    BroadcastedDeployAccountTransaction,
+};
+use crate::api::{
+    BlockHashAndNumber, BlockId, EventFilter, EventsChunk, FeeEstimate, FunctionCall, JsonRpcError,
+    JsonRpcServer,
+};
+use crate::block::MaybePendingBlock;
+use crate::state::{
+    ContractClass, ContractNonce, DeployedContract, GatewayContractClass,
+    MaybePendingStateUpdate as LatestMaybePendingStateUpdate, StorageDiff,
+    ThinStateDiff as LatestThinStateDiff,
+};
+use crate::transaction::{
+    BroadcastedInvokeTransaction, TransactionReceiptWithStatus, TransactionWithType,
+};
+use crate::JsonRpcServerImpl;
+
+/// The version of the specification of the methods served by [`JsonRpcV0_2`].
+pub const SPEC_VERSION: &str = "0.2.1";
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct StateUpdate {
+    pub block_hash: BlockHash,
+    pub new_root: GlobalRoot,
+    pub old_root: GlobalRoot,
+    pub state_diff: ThinStateDiff,
+}
+
+/// The state update of the pending block, relative to the latest block.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct PendingStateUpdate {
+    pub old_root: GlobalRoot,
+    pub state_diff: ThinStateDiff,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MaybePendingStateUpdate {
+    StateUpdate(StateUpdate),
+    Pending(PendingStateUpdate),
+}
+
+impl From<LatestMaybePendingStateUpdate> for MaybePendingStateUpdate {
+    fn from(state_update: LatestMaybePendingStateUpdate) -> Self {
+        match state_update {
+            LatestMaybePendingStateUpdate::StateUpdate(state_update) => {
+                Self::StateUpdate(StateUpdate {
+                    block_hash: state_update.block_hash,
+                    new_root: state_update.new_root,
+                    old_root: state_update.old_root,
+                    state_diff: state_update.state_diff.into(),
+                })
+            }
+            LatestMaybePendingStateUpdate::Pending(state_update) => {
+                Self::Pending(PendingStateUpdate {
+                    old_root: state_update.old_root,
+                    state_diff: state_update.state_diff.into(),
+                })
+            }
+        }
+    }
+}
+
+/// A state diff without the declared and replaced Sierra classes.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ThinStateDiff {
+    pub deployed_contracts: Vec<DeployedContract>,
+    pub storage_diffs: Vec<StorageDiff>,
+    pub declared_contract_hashes: Vec<ClassHash>,
+    pub nonces: Vec<ContractNonce>,
+}
+
+impl From<LatestThinStateDiff> for ThinStateDiff {
+    fn from(diff: LatestThinStateDiff) -> Self {
+        Self {
+            deployed_contracts: diff.deployed_contracts,
+            storage_diffs: diff.storage_diffs,
+            declared_contract_hashes: diff.declared_contract_hashes,
+            nonces: diff.nonces,
+        }
+    }
+}
+
+/// A declare transaction of a Cairo 0 class that wasn't included in a block.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct BroadcastedDeclareTransaction {
+    pub contract_class: ContractClass,
+    pub sender_address: ContractAddress,
+    pub max_fee: Fee,
+    pub version: TransactionVersion,
+    pub signature: TransactionSignature,
+    pub nonce: Nonce,
+}
+
+impl From<BroadcastedDeclareTransaction> for LatestBroadcastedDeclareTransaction {
+    fn from(tx: BroadcastedDeclareTransaction) -> Self {
+        Self {
+            contract_class: GatewayContractClass::Cairo0(tx.contract_class),
+            sender_address: tx.sender_address,
+            max_fee: tx.max_fee,
+            version: tx.version,
+            signature: tx.signature,
+            nonce: tx.nonce,
+            compiled_class_hash: None,
+        }
+    }
+}
+
+#[rpc(server, client, namespace = "starknet")]
+pub trait JsonRpcV0_2 {
+    /// Gets the most recent accepted block number.
+    #[method(name = "blockNumber")]
+    fn block_number(&self) -> Result<BlockNumber, Error>;
+
+    /// Gets the most recent accepted block hash and number.
+    #[method(name = "blockHashAndNumber")]
+    fn block_hash_and_number(&self) -> Result<BlockHashAndNumber, Error>;
+
+    /// Gets block information with transaction hashes given a block identifier.
+    #[method(name = "getBlockWithTxHashes")]
+    fn get_block_w_transaction_hashes(&self, block_id: BlockId)
+    -> Result<MaybePendingBlock, Error>;
+
+    /// Gets block information with full transactions given a block identifier.
+    #[method(name = "getBlockWithTxs")]
+    fn get_block_w_full_transactions(&self, block_id: BlockId) -> Result<MaybePendingBlock, Error>;
+
+    /// Gets the value of the storage at the given address, key, and block.
+    #[method(name = "getStorageAt")]
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+        block_id: BlockId,
+    ) -> Result<StarkFelt, Error>;
+
+    /// Gets the details of a submitted transaction.
+    #[method(name = "getTransactionByHash")]
+    fn get_transaction_by_hash(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionWithType, Error>;
+
+    /// Gets the details of a transaction by a given block id and index.
+    #[method(name = "getTransactionByBlockIdAndIndex")]
+    fn get_transaction_by_block_id_and_index(
+        &self,
+        block_id: BlockId,
+        index: TransactionOffsetInBlock,
+    ) -> Result<TransactionWithType, Error>;
+
+    /// Gets the number of transactions in a block given a block id.
+    #[method(name = "getBlockTransactionCount")]
+    fn get_block_transaction_count(&self, block_id: BlockId) -> Result<usize, Error>;
+
+    /// Gets the information about the result of executing the requested block, without the
+    /// Sierra classes.
+    #[method(name = "getStateUpdate")]
+    fn get_state_update(&self, block_id: BlockId) -> Result<MaybePendingStateUpdate, Error>;
+
+    /// Gets the transaction receipt by the transaction hash.
+    #[method(name = "getTransactionReceipt")]
+    fn get_transaction_receipt(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionReceiptWithStatus, Error>;
+
+    /// Gets the Cairo 0 contract class definition associated with the given hash.
+    #[method(name = "getClass")]
+    fn get_class(&self, block_id: BlockId, class_hash: ClassHash) -> Result<ContractClass, Error>;
+
+    /// Gets the Cairo 0 contract class definition in the given block at the given address.
+    #[method(name = "getClassAt")]
+    fn get_class_at(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<ContractClass, Error>;
+
+    /// Gets the contract class hash in the given block for the contract deployed at the given
+    /// address.
+    #[method(name = "getClassHashAt")]
+    fn get_class_hash_at(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<ClassHash, Error>;
+
+    /// Gets the nonce associated with the given address in the given block.
+    #[method(name = "getNonce")]
+    fn get_nonce(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<Nonce, Error>;
+
+    /// Returns the currently configured StarkNet chain id.
+    #[method(name = "chainId")]
+    fn chain_id(&self) -> Result<String, Error>;
+
+    /// Returns the version of the Starknet JSON-RPC specification the methods follow.
+    #[method(name = "specVersion")]
+    fn spec_version(&self) -> Result<String, Error>;
+
+    /// Returns all events matching the given filter.
+    #[method(name = "getEvents")]
+    fn get_events(&self, filter: EventFilter) -> Result<EventsChunk, Error>;
+
+    /// Calls a function of a contract on top of the state of the given block and returns the data
+    /// it returned, without creating a transaction.
+    #[method(name = "call")]
+    fn call(&self, request: FunctionCall, block_id: BlockId) -> Result<Vec<StarkFelt>, Error>;
+
+    /// Estimates the fee of the invoke transaction, if it were included on top of the given block.
+    #[method(name = "estimateFee")]
+    fn estimate_fee(
+        &self,
+        request: BroadcastedInvokeTransaction,
+        block_id: BlockId,
+    ) -> Result<FeeEstimate, Error>;
+
+    /// Sends the invoke transaction to the sequencer, to be added to its mempool.
+    #[method(name = "addInvokeTransaction")]
+    async fn add_invoke_transaction(
+        &self,
+        invoke_transaction: BroadcastedInvokeTransaction,
+    ) -> Result<AddInvokeTransactionResult, Error>;
+
+    /// Sends the declare transaction of a Cairo 0 class to the sequencer, to be added to its
+    /// mempool.
+    #[method(name = "addDeclareTransaction")]
+    async fn add_declare_transaction(
+        &self,
+        declare_transaction: BroadcastedDeclareTransaction,
+    ) -> Result<AddDeclareTransactionResult, Error>;
+
+    /// Sends the deploy account transaction to the sequencer, to be added to its mempool.
+    #[method(name = "addDeployAccountTransaction")]
+    async fn add_deploy_account_transaction(
+        &self,
+        deploy_account_transaction: BroadcastedDeployAccountTransaction,
+    ) -> Result<AddDeployAccountTransactionResult, Error>;
+}
+
+/// Serves the methods of version 0.2 by the latest implementation, which traces them, converting
+/// its results to the types of this version.
+pub(crate) struct JsonRpcServerV0_2Impl(pub(crate) JsonRpcServerImpl);
+
+#[async_trait]
+impl JsonRpcV0_2Server for JsonRpcServerV0_2Impl {
+    fn block_number(&self) -> Result<BlockNumber, Error> {
+        self.0.block_number()
+    }
+
+    fn block_hash_and_number(&self) -> Result<BlockHashAndNumber, Error> {
+        self.0.block_hash_and_number()
+    }
+
+    fn get_block_w_transaction_hashes(
+        &self,
+        block_id: BlockId,
+    ) -> Result<MaybePendingBlock, Error> {
+        self.0.get_block_w_transaction_hashes(block_id)
+    }
+
+    fn get_block_w_full_transactions(&self, block_id: BlockId) -> Result<MaybePendingBlock, Error> {
+        self.0.get_block_w_full_transactions(block_id)
+    }
+
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+        block_id: BlockId,
+    ) -> Result<StarkFelt, Error> {
+        self.0.get_storage_at(contract_address, key, block_id)
+    }
+
+    fn get_transaction_by_hash(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionWithType, Error> {
+        self.0.get_transaction_by_hash(transaction_hash)
+    }
+
+    fn get_transaction_by_block_id_and_index(
+        &self,
+        block_id: BlockId,
+        index: TransactionOffsetInBlock,
+    ) -> Result<TransactionWithType, Error> {
+        self.0.get_transaction_by_block_id_and_index(block_id, index)
+    }
+
+    fn get_block_transaction_count(&self, block_id: BlockId) -> Result<usize, Error> {
+        self.0.get_block_transaction_count(block_id)
+    }
+
+    fn get_state_update(&self, block_id: BlockId) -> Result<MaybePendingStateUpdate, Error> {
+        Ok(self.0.get_state_update(block_id)?.into())
+    }
+
+    fn get_transaction_receipt(
+        &self,
+        transaction_hash: TransactionHash,
+    ) -> Result<TransactionReceiptWithStatus, Error> {
+        self.0.get_transaction_receipt(transaction_hash)
+    }
+
+    fn get_class(&self, block_id: BlockId, class_hash: ClassHash) -> Result<ContractClass, Error> {
+        match self.0.get_class(block_id, class_hash)? {
+            GatewayContractClass::Cairo0(class) => Ok(class),
+            GatewayContractClass::Sierra(_) => Err(JsonRpcError::ClassHashNotFound.into()),
+        }
+    }
+
+    fn get_class_at(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<ContractClass, Error> {
+        match self.0.get_class_at(block_id, contract_address)? {
+            GatewayContractClass::Cairo0(class) => Ok(class),
+            GatewayContractClass::Sierra(_) => Err(JsonRpcError::ContractNotFound.into()),
+        }
+    }
+
+    fn get_class_hash_at(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<ClassHash, Error> {
+        self.0.get_class_hash_at(block_id, contract_address)
+    }
+
+    fn get_nonce(
+        &self,
+        block_id: BlockId,
+        contract_address: ContractAddress,
+    ) -> Result<Nonce, Error> {
+        self.0.get_nonce(block_id, contract_address)
+    }
+
+    fn chain_id(&self) -> Result<String, Error> {
+        self.0.chain_id()
+    }
+
+    fn spec_version(&self) -> Result<String, Error> {
+        Ok(SPEC_VERSION.to_owned())
+    }
+
+    fn get_events(&self, filter: EventFilter) -> Result<EventsChunk, Error> {
+        self.0.get_events(filter)
+    }
+
+    fn call(&self, request: FunctionCall, block_id: BlockId) -> Result<Vec<StarkFelt>, Error> {
+        self.0.call(request, block_id)
+    }
+
+    fn estimate_fee(
+        &self,
+        request: BroadcastedInvokeTransaction,
+        block_id: BlockId,
+    ) -> Result<FeeEstimate, Error> {
+        self.0.estimate_fee(request, block_id)
+    }
+
+    async fn add_invoke_transaction(
+        &self,
+        invoke_transaction: BroadcastedInvokeTransaction,
+    ) -> Result<AddInvokeTransactionResult, Error> {
+        self.0.add_invoke_transaction(invoke_transaction).await
+    }
+
+    async fn add_declare_transaction(
+        &self,
+        declare_transaction: BroadcastedDeclareTransaction,
+    ) -> Result<AddDeclareTransactionResult, Error> {
+        self.0.add_declare_transaction(declare_transaction.into()).await
+    }
+
+    async fn add_deploy_account_transaction(
+        &self,
+        deploy_account_transaction: BroadcastedDeployAccountTransaction,
+    ) -> Result<AddDeployAccountTransactionResult, Error> {
+        self.0.add_deploy_account_transaction(deploy_account_transaction).await
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashSet;
+
+use assert_matches::assert_matches;
+use indexmap::IndexMap;
+use jsonrpsee::core::Error;
+use jsonrpsee::http_server::types::error::CallError;
+use jsonrpsee::types::error::ErrorObject;
+use jsonrpsee::types::EmptyParams;
+use papyrus_storage::header::HeaderStorageWriter;
+use papyrus_storage::state::data::{Cairo1StateDiff, CompiledClassHash, SierraContractClass};
+use papyrus_storage::state::StateStorageWriter;
+use starknet_api::block::{BlockHeader, BlockNumber};
+use starknet_api::core::{ClassHash, ContractAddress, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StateDiff;
+use starknet_api::{patricia_key, stark_felt};
+
+use crate::api::{BlockId, JsonRpcError, Tag};
+use crate::state::ContractClass;
+use crate::test_utils::get_test_rpc_server_v0_2_and_storage_writer;
+use crate::v0_2::SPEC_VERSION;
+
+#[tokio::test]
+async fn spec_version() {
+    let (module, _) = get_test_rpc_server_v0_2_and_storage_writer();
+    let res = module.call::<_, String>("starknet_specVersion", EmptyParams::new()).await.unwrap();
+    assert_eq!(res, SPEC_VERSION);
+}
+
+#[tokio::test]
+async fn sierra_classes_are_left_out() {
+    let (module, mut storage_writer) = get_test_rpc_server_v0_2_and_storage_writer();
+    let address = ContractAddress(patricia_key!("0x11"));
+    let class_hash = ClassHash(stark_felt!("0x4"));
+    let sierra_class_hash = ClassHash(stark_felt!("0x5"));
+    let state_diff = StateDiff {
+        deployed_contracts: IndexMap::from([(address, class_hash)]),
+        declared_classes: IndexMap::from([(
+            class_hash,
+            starknet_api::state::ContractClass::default(),
+        )]),
+        ..StateDiff::default()
+    };
+    let cairo1_state_diff = Cairo1StateDiff {
+        declared_classes: IndexMap::from([(
+            sierra_class_hash,
+            (CompiledClassHash(stark_felt!("0x6")), SierraContractClass::default()),
+        )]),
+        replaced_classes: IndexMap::new(),
+    };
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &BlockHeader::default())
+        .unwrap()
+        .append_state_diff_with_cairo1(
+            BlockNumber(0),
+            state_diff,
+            cairo1_state_diff,
+            IndexMap::new(),
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+    let latest = BlockId::Tag(Tag::Latest);
+
+    // The state diff has no declared and replaced Sierra classes.
+    let res =
+        module.call::<_, serde_json::Value>("starknet_getStateUpdate", [latest]).await.unwrap();
+    let keys: HashSet<_> =
+        res["state_diff"].as_object().unwrap().keys().map(String::as_str).collect();
+    assert_eq!(
+        keys,
+        HashSet::from([
+            "deployed_contracts",
+            "storage_diffs",
+            "declared_contract_hashes",
+            "nonces"
+        ])
+    );
+
+    module.call::<_, ContractClass>("starknet_getClass", (latest, class_hash)).await.unwrap();
+    let err = module
+        .call::<_, ContractClass>("starknet_getClass", (latest, sierra_class_hash))
+        .await
+        .unwrap_err();
+    assert_matches!(err, Error::Call(CallError::Custom(err)) if err == ErrorObject::owned(
+        JsonRpcError::ClassHashNotFound as i32,
+        JsonRpcError::ClassHashNotFound.to_string(),
+        None::<()>,
+    ));
+}
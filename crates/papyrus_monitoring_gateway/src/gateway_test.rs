@@ -1,8 +1,17 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use assert_matches::assert_matches;
+use hyper::{Client, StatusCode, Uri};
 use jsonrpsee::types::EmptyParams;
 use papyrus_storage::{table_names, test_utils, DbTablesStats};
+use papyrus_sync::SyncStatus;
+use starknet_api::block::BlockNumber;
 
 use super::api::JsonRpcServer;
-use super::JsonRpcServerImpl;
+use super::health::{check_liveness, Health};
+use super::{run_http_server, JsonRpcServerImpl, MonitoringGatewayConfig};
 
 const TEST_CONFIG_REPRESENTATION: &str = "general_config_representation";
 
@@ -34,3 +43,86 @@ async fn test_config() -> Result<(), anyhow::Error> {
     assert_eq!(rep, TEST_CONFIG_REPRESENTATION);
     Ok(())
 }
+
+fn get_test_config() -> MonitoringGatewayConfig {
+    MonitoringGatewayConfig {
+        server_address: String::from("127.0.0.1:0"),
+        http_server_address: String::from("127.0.0.1:0"),
+        max_blocks_behind: 10,
+        max_sync_inactivity_duration: Duration::from_secs(60),
+    }
+}
+
+async fn get(addr: SocketAddr, path: &str) -> Result<(StatusCode, String), anyhow::Error> {
+    let response = Client::new().get(format!("http://{addr}{path}").parse::<Uri>()?).await?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    Ok((status, String::from_utf8(body.to_vec())?))
+}
+
+#[tokio::test]
+async fn test_metrics() -> Result<(), anyhow::Error> {
+    let (storage_reader, mut _storage_writer) = test_utils::get_test_storage();
+    let (addr, server_future) = run_http_server(get_test_config(), storage_reader, None).await?;
+    tokio::spawn(server_future);
+
+    let (status, metrics) = get(addr, "/metrics").await?;
+    assert_eq!(status, StatusCode::OK);
+    for kind in ["header", "body", "state"] {
+        assert!(metrics.contains(&format!("papyrus_sync_marker{{kind=\"{kind}\"}} 0")));
+    }
+    for &name in table_names() {
+        assert!(metrics.contains(&format!("papyrus_storage_table_entries{{table=\"{name}\"}}")));
+    }
+
+    let (status, _) = get(addr, "/other").await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_health_without_sync() -> Result<(), anyhow::Error> {
+    let (storage_reader, mut _storage_writer) = test_utils::get_test_storage();
+    let (addr, server_future) = run_http_server(get_test_config(), storage_reader, None).await?;
+    tokio::spawn(server_future);
+
+    assert_eq!(get(addr, "/health/live").await?.0, StatusCode::OK);
+    assert_eq!(get(addr, "/health/ready").await?.0, StatusCode::OK);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_health_with_sync() -> Result<(), anyhow::Error> {
+    let (storage_reader, mut _storage_writer) = test_utils::get_test_storage();
+    let sync_status = Arc::new(RwLock::new(SyncStatus::default()));
+    let (addr, server_future) =
+        run_http_server(get_test_config(), storage_reader, Some(sync_status.clone())).await?;
+    tokio::spawn(server_future);
+
+    // The central source wasn't reached yet.
+    assert_eq!(get(addr, "/health/live").await?.0, StatusCode::OK);
+    assert_eq!(get(addr, "/health/ready").await?.0, StatusCode::SERVICE_UNAVAILABLE);
+
+    // The storage is empty, so it's as many blocks behind the central source as its marker.
+    sync_status.write().unwrap().central_block_marker = Some(BlockNumber(10));
+    assert_eq!(get(addr, "/health/ready").await?.0, StatusCode::OK);
+    sync_status.write().unwrap().central_block_marker = Some(BlockNumber(11));
+    assert_eq!(get(addr, "/health/ready").await?.0, StatusCode::SERVICE_UNAVAILABLE);
+
+    sync_status.write().unwrap().stopped = true;
+    assert_eq!(get(addr, "/health/live").await?.0, StatusCode::SERVICE_UNAVAILABLE);
+    Ok(())
+}
+
+#[test]
+fn test_liveness_sync_inactivity() {
+    let sync_status = RwLock::new(SyncStatus {
+        last_progress_time: Instant::now() - Duration::from_secs(2),
+        ..SyncStatus::default()
+    });
+    assert_eq!(check_liveness(Some(&sync_status), Duration::from_secs(3)), Health::Healthy);
+    assert_matches!(
+        check_liveness(Some(&sync_status), Duration::from_secs(1)),
+        Health::Unhealthy { .. }
+    );
+}
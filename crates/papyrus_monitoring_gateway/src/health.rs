@@ -0,0 +1,77 @@
+//! Liveness and readiness probes of the node, for orchestrators such as Kubernetes.
+
+use std::sync::RwLock;
+use std::time::Duration;
+
+use papyrus_storage::body::BodyStorageReader;
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::state::StateStorageReader;
+use papyrus_storage::{StorageReader, StorageResult};
+use papyrus_sync::SyncStatus;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Health {
+    Healthy,
+    Unhealthy { reason: String },
+}
+
+/// The node is live as long as the sync is running and made progress recently. A node that
+/// doesn't run the sync is always live.
+pub(crate) fn check_liveness(
+    sync_status: Option<&RwLock<SyncStatus>>,
+    max_sync_inactivity_duration: Duration,
+) -> Health {
+    let sync_status = match sync_status {
+        None => return Health::Healthy,
+        Some(sync_status) => *sync_status.read().expect("Sync status lock is poisoned."),
+    };
+    if sync_status.stopped {
+        return Health::Unhealthy { reason: String::from("The sync stopped.") };
+    }
+    let inactivity_duration = sync_status.last_progress_time.elapsed();
+    if inactivity_duration > max_sync_inactivity_duration {
+        return Health::Unhealthy {
+            reason: format!(
+                "The sync made no progress in the last {} seconds.",
+                inactivity_duration.as_secs()
+            ),
+        };
+    }
+    Health::Healthy
+}
+
+/// The node is ready once all of its storage markers are at most max_blocks_behind blocks behind
+/// the central source. A node that doesn't run the sync is always ready.
+pub(crate) fn check_readiness(
+    storage_reader: &StorageReader,
+    sync_status: Option<&RwLock<SyncStatus>>,
+    max_blocks_behind: u64,
+) -> StorageResult<Health> {
+    let central_block_marker = match sync_status {
+        None => return Ok(Health::Healthy),
+        Some(sync_status) => {
+            match sync_status.read().expect("Sync status lock is poisoned.").central_block_marker {
+                None => {
+                    return Ok(Health::Unhealthy {
+                        reason: String::from("The block marker of the central source is unknown."),
+                    });
+                }
+                Some(central_block_marker) => central_block_marker,
+            }
+        }
+    };
+
+    let txn = storage_reader.begin_ro_txn()?;
+    let storage_marker =
+        txn.get_header_marker()?.min(txn.get_body_marker()?).min(txn.get_state_marker()?);
+    let blocks_behind = central_block_marker.0.saturating_sub(storage_marker.0);
+    if blocks_behind > max_blocks_behind {
+        return Ok(Health::Unhealthy {
+            reason: format!(
+                "The storage is {blocks_behind} blocks behind the central source, more than the \
+                 allowed {max_blocks_behind}."
+            ),
+        });
+    }
+    Ok(Health::Healthy)
+}
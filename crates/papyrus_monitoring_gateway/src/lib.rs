@@ -1,25 +1,41 @@
 mod api;
 #[cfg(test)]
 mod gateway_test;
+mod health;
+mod metrics;
 
+use std::convert::Infallible;
 use std::fmt::Display;
+use std::future::Future;
 use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 // use api::JsonRpcError;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
 use jsonrpsee::core::{async_trait, Error};
 use jsonrpsee::http_server::types::error::CallError;
 use jsonrpsee::http_server::{HttpServerBuilder, HttpServerHandle};
 use jsonrpsee::types::error::ErrorCode::InternalError;
 use jsonrpsee::types::error::{ErrorObject, INTERNAL_ERROR_MSG};
 use papyrus_storage::{DbTablesStats, StorageReader};
+use papyrus_sync::SyncStatus;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, instrument};
 
 use self::api::JsonRpcServer;
+use self::health::{check_liveness, check_readiness, Health};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct MonitoringGatewayConfig {
     pub server_address: String,
+    pub http_server_address: String,
+    /// The maximal number of blocks the storage can be behind the central source for the node to
+    /// be ready.
+    pub max_blocks_behind: u64,
+    /// The maximal duration without sync progress for the node to be live.
+    pub max_sync_inactivity_duration: Duration,
 }
 
 /// Rpc server.
@@ -64,3 +80,78 @@ pub async fn run_server(
     info!(local_address = %addr, "Monitoring gateway is running.");
     Ok((addr, handle))
 }
+
+/// Runs an HTTP server that serves the metrics of the node in the Prometheus text format at
+/// `/metrics`, and its liveness and readiness probes at `/health/live` and `/health/ready`. The
+/// sync status is None if the node doesn't run the sync.
+#[instrument(skip(storage_reader, sync_status), level = "debug", err)]
+pub async fn run_http_server(
+    config: MonitoringGatewayConfig,
+    storage_reader: StorageReader,
+    sync_status: Option<Arc<RwLock<SyncStatus>>>,
+) -> anyhow::Result<(SocketAddr, impl Future<Output = hyper::Result<()>>)> {
+    debug!("Starting monitoring HTTP server.");
+    let addr = config.http_server_address.parse()?;
+    let make_service = make_service_fn(move |_connection| {
+        let config = config.clone();
+        let storage_reader = storage_reader.clone();
+        let sync_status = sync_status.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let response = route(&config, &storage_reader, sync_status.as_deref(), &request);
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    let addr = server.local_addr();
+    info!(local_address = %addr, "Monitoring HTTP server is running.");
+    Ok((addr, server))
+}
+
+fn route(
+    config: &MonitoringGatewayConfig,
+    storage_reader: &StorageReader,
+    sync_status: Option<&RwLock<SyncStatus>>,
+    request: &Request<Body>,
+) -> Response<Body> {
+    if request.method() != Method::GET {
+        return response_with_status(StatusCode::NOT_FOUND, "Not found.");
+    }
+    match request.uri().path() {
+        "/metrics" => match metrics::gather_metrics(storage_reader) {
+            Ok(metrics) => Response::new(Body::from(metrics)),
+            Err(err) => internal_server_error_response("Failed gathering the metrics", err),
+        },
+        "/health/live" => {
+            health_response(check_liveness(sync_status, config.max_sync_inactivity_duration))
+        }
+        "/health/ready" => {
+            match check_readiness(storage_reader, sync_status, config.max_blocks_behind) {
+                Ok(health) => health_response(health),
+                Err(err) => internal_server_error_response("Failed checking the readiness", err),
+            }
+        }
+        _ => response_with_status(StatusCode::NOT_FOUND, "Not found."),
+    }
+}
+
+fn health_response(health: Health) -> Response<Body> {
+    match health {
+        Health::Healthy => response_with_status(StatusCode::OK, "OK."),
+        Health::Unhealthy { reason } => {
+            response_with_status(StatusCode::SERVICE_UNAVAILABLE, reason)
+        }
+    }
+}
+
+fn internal_server_error_response(context: &str, err: impl Display) -> Response<Body> {
+    error!("{context}: {err}.");
+    response_with_status(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL_ERROR_MSG)
+}
+
+fn response_with_status(status: StatusCode, body: impl Into<Body>) -> Response<Body> {
+    let mut response = Response::new(body.into());
+    *response.status_mut() = status;
+    response
+}
@@ -0,0 +1,63 @@
+//! Prometheus metrics of the storage, updated from the storage on every scrape. The metrics of the
+//! other components of the node are registered by them in the same default registry.
+
+use once_cell::sync::Lazy;
+use papyrus_storage::base_layer::BaseLayerStorageReader;
+use papyrus_storage::body::BodyStorageReader;
+use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::state::StateStorageReader;
+use papyrus_storage::{StorageReader, StorageResult};
+use prometheus::{register_int_gauge_vec, Encoder, IntGaugeVec, TextEncoder};
+
+static SYNC_MARKERS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "papyrus_sync_marker",
+        "The number of the first block that is not in the storage yet, by kind of data.",
+        &["kind"]
+    )
+    .expect("Failed to register the sync markers metric.")
+});
+
+static TABLE_ENTRIES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "papyrus_storage_table_entries",
+        "The number of entries in a storage table.",
+        &["table"]
+    )
+    .expect("Failed to register the table entries metric.")
+});
+
+static TABLE_PAGES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "papyrus_storage_table_pages",
+        "The number of pages of a storage table, by kind of page.",
+        &["table", "kind"]
+    )
+    .expect("Failed to register the table pages metric.")
+});
+
+/// Returns all the metrics of the node in the Prometheus text format.
+pub(crate) fn gather_metrics(storage_reader: &StorageReader) -> anyhow::Result<String> {
+    update_storage_metrics(storage_reader)?;
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+fn update_storage_metrics(storage_reader: &StorageReader) -> StorageResult<()> {
+    let txn = storage_reader.begin_ro_txn()?;
+    SYNC_MARKERS.with_label_values(&["header"]).set(txn.get_header_marker()?.0 as i64);
+    SYNC_MARKERS.with_label_values(&["body"]).set(txn.get_body_marker()?.0 as i64);
+    SYNC_MARKERS.with_label_values(&["state"]).set(txn.get_state_marker()?.0 as i64);
+    SYNC_MARKERS
+        .with_label_values(&["base_layer"])
+        .set(txn.get_base_layer_block_marker()?.0 as i64);
+
+    for (table, stats) in storage_reader.db_tables_stats()?.stats {
+        TABLE_ENTRIES.with_label_values(&[&table]).set(stats.entries as i64);
+        TABLE_PAGES.with_label_values(&[&table, "leaf"]).set(stats.leaf_pages as i64);
+        TABLE_PAGES.with_label_values(&[&table, "branch"]).set(stats.branch_pages as i64);
+        TABLE_PAGES.with_label_values(&[&table, "overflow"]).set(stats.overflow_pages as i64);
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use papyrus_node::config::Config;
+use starknet_api::block::BlockNumber;
+use starknet_client::{record_responses, StarknetClient};
+
+/// Records the responses of the starknet feeder gateway from the central configuration into a
+/// directory, for syncing the node with the offline central source.
+#[derive(Parser)]
+struct Args {
+    /// The directory the responses are recorded into.
+    #[clap(long)]
+    output_dir: PathBuf,
+    /// The first block to record.
+    #[clap(long, default_value_t = 0)]
+    initial_block_number: u64,
+    /// The block to stop the recording at, not included.
+    #[clap(long)]
+    up_to_block_number: u64,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let config = Config::load(vec![]).expect("Load config");
+    let starknet_client = StarknetClient::new(
+        &config.central.url,
+        config.central.http_headers,
+        config.central.retry_config,
+    )
+    .expect("Create new client");
+    let block_marker = record_responses(
+        &starknet_client,
+        &args.output_dir,
+        BlockNumber(args.initial_block_number),
+        BlockNumber(args.up_to_block_number),
+    )
+    .await
+    .expect("Record responses");
+    println!(
+        "Recorded blocks {} to {block_marker} (not included) into {}.",
+        args.initial_block_number,
+        args.output_dir.display()
+    );
+}
@@ -3,11 +3,15 @@ use std::env::{self, args};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
+use papyrus_execution::ExecutionConfig;
+use papyrus_storage::body::bloom::EventBloomConfig;
+use papyrus_storage::pruning::PruningConfig;
+use papyrus_sync::CentralSourceKind;
 use starknet_api::core::ChainId;
 use tempfile::NamedTempFile;
 use test_utils::get_absolute_path;
 
-use crate::config::{Config, ConfigBuilder};
+use crate::config::{Config, ConfigBuilder, ConfigError, NodeCommand};
 
 #[test]
 fn load_default_config() {
@@ -74,6 +78,60 @@ fn prepare_command() {
     );
 }
 
+#[test]
+fn snapshot_commands() {
+    let builder = ConfigBuilder::default()
+        .prepare_command(vec![
+            "Papyrus".to_owned(),
+            "export_snapshot".to_owned(),
+            "--output_dir=snapshot".to_owned(),
+        ])
+        .unwrap()
+        .args()
+        .unwrap();
+    assert_eq!(
+        builder.config.command,
+        Some(NodeCommand::ExportSnapshot { output_dir: PathBuf::from("snapshot") })
+    );
+
+    let builder = ConfigBuilder::default()
+        .prepare_command(vec![
+            "Papyrus".to_owned(),
+            "--chain_id=CHAIN_ID".to_owned(),
+            "import_snapshot".to_owned(),
+            "--input_dir=snapshot".to_owned(),
+        ])
+        .unwrap()
+        .args()
+        .unwrap();
+    assert_eq!(
+        builder.config.command,
+        Some(NodeCommand::ImportSnapshot { input_dir: PathBuf::from("snapshot") })
+    );
+    assert_eq!(builder.chain_id, ChainId("CHAIN_ID".to_owned()));
+
+    let builder = ConfigBuilder::default()
+        .prepare_command(vec!["Papyrus".to_owned(), "migrate_storage".to_owned()])
+        .unwrap()
+        .args()
+        .unwrap();
+    assert_eq!(builder.config.command, Some(NodeCommand::MigrateStorage));
+
+    let builder = ConfigBuilder::default()
+        .prepare_command(vec!["Papyrus".to_owned(), "check_integrity".to_owned()])
+        .unwrap()
+        .args()
+        .unwrap();
+    assert_eq!(builder.config.command, Some(NodeCommand::CheckIntegrity));
+
+    let builder = ConfigBuilder::default()
+        .prepare_command(vec!["Papyrus".to_owned()])
+        .unwrap()
+        .args()
+        .unwrap();
+    assert!(builder.config.command.is_none());
+}
+
 #[test]
 fn load_yaml_config() {
     let mut f = NamedTempFile::new().unwrap();
@@ -81,6 +139,17 @@ fn load_yaml_config() {
 chain_id: TEST
 gateway:
     max_events_keys: 1234
+    execution:
+        invoke_tx_max_n_steps: 100
+    sequencer_url: https://alpha4.starknet.io/
+storage:
+    pruning:
+        history_length: 100
+    event_bloom:
+        size_bytes: 2048
+sync:
+    verify_hashes: true
+    verify_state_root: true
 ";
     f.write_all(yaml.as_bytes()).unwrap();
     let args = vec!["Papyrus".to_owned(), format!("--config_file={}", f.path().to_str().unwrap())];
@@ -88,6 +157,66 @@ gateway:
 
     assert_eq!(builder.chain_id, ChainId("TEST".to_owned()));
     assert_eq!(builder.config.gateway.max_events_keys, 1234);
+    assert_eq!(
+        builder.config.gateway.execution_config,
+        ExecutionConfig { invoke_tx_max_n_steps: 100, ..ExecutionConfig::default() }
+    );
+    assert_eq!(builder.sequencer_url.as_deref(), Some("https://alpha4.starknet.io/"));
+    assert!(builder.config.sync.as_ref().unwrap().verify_hashes);
+    assert!(builder.config.sync.as_ref().unwrap().verify_state_root);
+    assert_eq!(
+        builder.config.storage.pruning,
+        Some(PruningConfig { history_length: 100, ..PruningConfig::default() })
+    );
+    assert_eq!(
+        builder.config.storage.event_bloom,
+        EventBloomConfig { size_bytes: 2048, ..EventBloomConfig::default() }
+    );
+
+    let config = builder
+        .args()
+        .unwrap()
+        .propagate_chain_id()
+        .unwrap()
+        .propagate_sequencer_url()
+        .unwrap()
+        .config;
+    assert_eq!(config.sync.unwrap().chain_id, ChainId("TEST".to_owned()));
+    assert_eq!(config.gateway.sequencer_url, "https://alpha4.starknet.io/");
+}
+
+#[test]
+fn chain_id_is_short_string() {
+    for chain_id in ["A".repeat(31), "SN_MAIN".to_owned()] {
+        let builder = ConfigBuilder { chain_id: ChainId(chain_id), ..ConfigBuilder::default() };
+        assert!(builder.propagate_chain_id().is_ok());
+    }
+    for chain_id in ["A".repeat(32), "SN_MAÏN".to_owned()] {
+        let builder = ConfigBuilder { chain_id: ChainId(chain_id), ..ConfigBuilder::default() };
+        assert!(matches!(builder.propagate_chain_id(), Err(ConfigError::InvalidChainId { .. })));
+    }
+}
+
+#[test]
+fn sequencer_url_defaults_to_central_url() {
+    let args = vec!["Papyrus".to_owned(), "--central_url=https://alpha4.starknet.io/".to_owned()];
+    let config = ConfigBuilder::default()
+        .prepare_command(args)
+        .unwrap()
+        .args()
+        .unwrap()
+        .propagate_sequencer_url()
+        .unwrap()
+        .config;
+    assert_eq!(config.gateway.sequencer_url, "https://alpha4.starknet.io/");
+
+    // The URL of a peer isn't a Starknet gateway.
+    let mut builder = ConfigBuilder::default();
+    builder.config.central.source = CentralSourceKind::Peer;
+    assert!(matches!(
+        builder.propagate_sequencer_url(),
+        Err(ConfigError::MissingSequencerUrl { central_source: CentralSourceKind::Peer })
+    ));
 }
 
 #[test]
@@ -3,13 +3,18 @@ use std::path::Path;
 use std::time::Duration;
 use std::{env, fs};
 
+use papyrus_base_layer::ethereum_base_layer_contract::EthereumBaseLayerConfig;
+use papyrus_execution::ExecutionConfig;
+use papyrus_feeder_gateway::FeederGatewayConfig;
 use papyrus_gateway::GatewayConfig;
 use papyrus_monitoring_gateway::MonitoringGatewayConfig;
+use papyrus_storage::body::bloom::EventBloomConfig;
 use papyrus_storage::db::DbConfig;
+use papyrus_storage::pruning::PruningConfig;
 use papyrus_storage::StorageConfig;
-use papyrus_sync::{CentralSourceConfig, SyncConfig};
+use papyrus_sync::{CentralSourceConfig, CentralSourceKind, SyncConfig};
 use serde::{Deserialize, Serialize};
-use starknet_api::core::ChainId;
+use starknet_api::core::{ChainId, ContractAddress};
 use starknet_client::RetryConfig;
 
 use crate::config::{Config, ConfigBuilder};
@@ -26,6 +31,8 @@ pub struct FileConfigFormat {
     monitoring_gateway: Option<MonitoringGateway>,
     storage: Option<Storage>,
     sync: Option<Sync>,
+    base_layer: Option<BaseLayer>,
+    feeder_gateway: Option<FeederGateway>,
 }
 
 impl FileConfigFormat {
@@ -40,7 +47,7 @@ impl FileConfigFormat {
         }
 
         if let Some(gateway) = self.gateway {
-            gateway.update_gateway(&mut builder.config.gateway);
+            gateway.update_gateway(&mut builder.config.gateway, &mut builder.sequencer_url);
         }
 
         if let Some(monitoring_gateway) = self.monitoring_gateway {
@@ -56,6 +63,20 @@ impl FileConfigFormat {
         {
             file_config.update_sync(builder_config)
         }
+
+        // Tracking the base layer is optional, setting it in the file enables it.
+        if let Some(base_layer) = self.base_layer {
+            base_layer.update_base_layer(
+                builder.config.base_layer.get_or_insert_with(EthereumBaseLayerConfig::default),
+            );
+        }
+
+        // The feeder gateway is optional, setting it in the file enables it.
+        if let Some(feeder_gateway) = self.feeder_gateway {
+            feeder_gateway.update_feeder_gateway(
+                builder.config.feeder_gateway.get_or_insert_with(FeederGatewayConfig::default),
+            );
+        }
     }
 }
 
@@ -68,6 +89,8 @@ impl From<Config> for FileConfigFormat {
             monitoring_gateway: Some(MonitoringGateway::from(config.monitoring_gateway)),
             storage: Some(Storage::from(config.storage)),
             sync: config.sync.map(Sync::from),
+            base_layer: config.base_layer.map(BaseLayer::from),
+            feeder_gateway: config.feeder_gateway.map(FeederGateway::from),
         }
     }
 }
@@ -76,8 +99,22 @@ impl From<GatewayConfig> for Gateway {
     fn from(config: GatewayConfig) -> Self {
         Gateway {
             server_address: Some(config.server_address),
+            ws_server_address: Some(config.ws_server_address),
             max_events_chunk_size: Some(config.max_events_chunk_size),
             max_events_keys: Some(config.max_events_keys),
+            max_proof_keys: Some(config.max_proof_keys),
+            execution: Some(Execution::from(config.execution_config)),
+            sequencer_url: Some(config.sequencer_url),
+        }
+    }
+}
+
+impl From<ExecutionConfig> for Execution {
+    fn from(config: ExecutionConfig) -> Self {
+        Execution {
+            fee_contract_address: Some(config.fee_contract_address),
+            invoke_tx_max_n_steps: Some(config.invoke_tx_max_n_steps),
+            validate_max_n_steps: Some(config.validate_max_n_steps),
         }
     }
 }
@@ -85,6 +122,7 @@ impl From<GatewayConfig> for Gateway {
 impl From<CentralSourceConfig> for Central {
     fn from(config: CentralSourceConfig) -> Self {
         Central {
+            source: Some(config.source),
             concurrent_requests: Some(config.concurrent_requests),
             url: Some(config.url),
             http_headers: config.http_headers,
@@ -105,13 +143,22 @@ impl From<RetryConfig> for Retry {
 
 impl From<MonitoringGatewayConfig> for MonitoringGateway {
     fn from(config: MonitoringGatewayConfig) -> Self {
-        MonitoringGateway { server_address: Some(config.server_address) }
+        MonitoringGateway {
+            server_address: Some(config.server_address),
+            http_server_address: Some(config.http_server_address),
+            max_blocks_behind: Some(config.max_blocks_behind),
+            max_sync_inactivity_duration_secs: Some(config.max_sync_inactivity_duration.as_secs()),
+        }
     }
 }
 
 impl From<StorageConfig> for Storage {
     fn from(config: StorageConfig) -> Self {
-        Storage { db: Some(Db::from(config.db_config)) }
+        Storage {
+            db: Some(Db::from(config.db_config)),
+            pruning: config.pruning.map(Pruning::from),
+            event_bloom: Some(EventBloom::from(config.event_bloom)),
+        }
     }
 }
 
@@ -126,6 +173,22 @@ impl From<DbConfig> for Db {
     }
 }
 
+impl From<PruningConfig> for Pruning {
+    fn from(config: PruningConfig) -> Self {
+        Pruning {
+            history_length: Some(config.history_length),
+            max_entries_per_txn: Some(config.max_entries_per_txn),
+            sleep_duration_secs: Some(config.sleep_duration.as_secs()),
+        }
+    }
+}
+
+impl From<EventBloomConfig> for EventBloom {
+    fn from(config: EventBloomConfig) -> Self {
+        EventBloom { size_bytes: Some(config.size_bytes), hash_count: Some(config.hash_count) }
+    }
+}
+
 impl From<SyncConfig> for Sync {
     fn from(config: SyncConfig) -> Self {
         Sync {
@@ -135,6 +198,12 @@ impl From<SyncConfig> for Sync {
             recoverable_error_sleep_duration_secs: Some(
                 config.recoverable_error_sleep_duration.as_secs(),
             ),
+            pending_sleep_duration_secs: Some(config.pending_sleep_duration.as_secs()),
+            base_layer_propagation_sleep_duration_secs: Some(
+                config.base_layer_propagation_sleep_duration.as_secs(),
+            ),
+            verify_hashes: Some(config.verify_hashes),
+            verify_state_root: Some(config.verify_state_root),
         }
     }
 }
@@ -142,6 +211,7 @@ impl From<SyncConfig> for Sync {
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 #[serde(deny_unknown_fields)]
 struct Central {
+    source: Option<CentralSourceKind>,
     concurrent_requests: Option<usize>,
     url: Option<String>,
     http_headers: Option<HashMap<String, String>>,
@@ -150,6 +220,9 @@ struct Central {
 
 impl Central {
     fn update_central(self, config: &mut CentralSourceConfig) {
+        if let Some(source) = self.source {
+            config.source = source;
+        }
         if let Some(concurrent_requests) = self.concurrent_requests {
             config.concurrent_requests = concurrent_requests;
         }
@@ -198,21 +271,61 @@ impl Retry {
 #[serde(deny_unknown_fields)]
 struct Gateway {
     server_address: Option<String>,
+    ws_server_address: Option<String>,
     max_events_chunk_size: Option<usize>,
     max_events_keys: Option<usize>,
+    max_proof_keys: Option<usize>,
+    execution: Option<Execution>,
+    sequencer_url: Option<String>,
 }
 
 impl Gateway {
-    fn update_gateway(self, config: &mut GatewayConfig) {
+    // The sequencer URL defaults to the central URL, so it is applied once the configuration is
+    // complete.
+    fn update_gateway(self, config: &mut GatewayConfig, sequencer_url: &mut Option<String>) {
         if let Some(server_address) = self.server_address {
             config.server_address = server_address;
         }
+        if let Some(ws_server_address) = self.ws_server_address {
+            config.ws_server_address = ws_server_address;
+        }
         if let Some(max_events_chunk_size) = self.max_events_chunk_size {
             config.max_events_chunk_size = max_events_chunk_size;
         }
         if let Some(max_events_keys) = self.max_events_keys {
             config.max_events_keys = max_events_keys;
         }
+        if let Some(max_proof_keys) = self.max_proof_keys {
+            config.max_proof_keys = max_proof_keys;
+        }
+        if let Some(execution) = self.execution {
+            execution.update_execution(&mut config.execution_config);
+        }
+        if self.sequencer_url.is_some() {
+            *sequencer_url = self.sequencer_url;
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct Execution {
+    fee_contract_address: Option<ContractAddress>,
+    invoke_tx_max_n_steps: Option<u32>,
+    validate_max_n_steps: Option<u32>,
+}
+
+impl Execution {
+    fn update_execution(self, config: &mut ExecutionConfig) {
+        if let Some(fee_contract_address) = self.fee_contract_address {
+            config.fee_contract_address = fee_contract_address;
+        }
+        if let Some(invoke_tx_max_n_steps) = self.invoke_tx_max_n_steps {
+            config.invoke_tx_max_n_steps = invoke_tx_max_n_steps;
+        }
+        if let Some(validate_max_n_steps) = self.validate_max_n_steps {
+            config.validate_max_n_steps = validate_max_n_steps;
+        }
     }
 }
 
@@ -220,6 +333,9 @@ impl Gateway {
 #[serde(deny_unknown_fields)]
 struct MonitoringGateway {
     server_address: Option<String>,
+    http_server_address: Option<String>,
+    max_blocks_behind: Option<u64>,
+    max_sync_inactivity_duration_secs: Option<u64>,
 }
 
 impl MonitoringGateway {
@@ -227,6 +343,15 @@ impl MonitoringGateway {
         if let Some(server_address) = self.server_address {
             config.server_address = server_address;
         }
+        if let Some(http_server_address) = self.http_server_address {
+            config.http_server_address = http_server_address;
+        }
+        if let Some(max_blocks_behind) = self.max_blocks_behind {
+            config.max_blocks_behind = max_blocks_behind;
+        }
+        if let Some(max_sync_inactivity_duration) = self.max_sync_inactivity_duration_secs {
+            config.max_sync_inactivity_duration = Duration::from_secs(max_sync_inactivity_duration);
+        }
     }
 }
 
@@ -234,6 +359,8 @@ impl MonitoringGateway {
 #[serde(deny_unknown_fields)]
 struct Storage {
     db: Option<Db>,
+    pruning: Option<Pruning>,
+    event_bloom: Option<EventBloom>,
 }
 
 impl Storage {
@@ -241,6 +368,13 @@ impl Storage {
         if let Some(db) = self.db {
             db.update_db(&mut config.db_config);
         }
+        // Pruning is optional, setting it in the file enables it.
+        if let Some(pruning) = self.pruning {
+            pruning.update_pruning(config.pruning.get_or_insert_with(PruningConfig::default));
+        }
+        if let Some(event_bloom) = self.event_bloom {
+            event_bloom.update_event_bloom(&mut config.event_bloom);
+        }
     }
 }
 
@@ -262,11 +396,55 @@ impl Db {
     }
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct Pruning {
+    history_length: Option<u64>,
+    max_entries_per_txn: Option<usize>,
+    sleep_duration_secs: Option<u64>,
+}
+
+impl Pruning {
+    fn update_pruning(self, config: &mut PruningConfig) {
+        if let Some(history_length) = self.history_length {
+            config.history_length = history_length;
+        }
+        if let Some(max_entries_per_txn) = self.max_entries_per_txn {
+            config.max_entries_per_txn = max_entries_per_txn;
+        }
+        if let Some(sleep_duration) = self.sleep_duration_secs {
+            config.sleep_duration = Duration::from_secs(sleep_duration);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct EventBloom {
+    size_bytes: Option<usize>,
+    hash_count: Option<u8>,
+}
+
+impl EventBloom {
+    fn update_event_bloom(self, config: &mut EventBloomConfig) {
+        if let Some(size_bytes) = self.size_bytes {
+            config.size_bytes = size_bytes;
+        }
+        if let Some(hash_count) = self.hash_count {
+            config.hash_count = hash_count;
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 #[serde(deny_unknown_fields)]
 struct Sync {
     block_propagation_sleep_duration_secs: Option<u64>,
     recoverable_error_sleep_duration_secs: Option<u64>,
+    pending_sleep_duration_secs: Option<u64>,
+    base_layer_propagation_sleep_duration_secs: Option<u64>,
+    verify_hashes: Option<bool>,
+    verify_state_root: Option<bool>,
 }
 
 impl Sync {
@@ -279,5 +457,67 @@ impl Sync {
             config.recoverable_error_sleep_duration =
                 Duration::from_secs(recoverable_error_sleep_duration);
         }
+        if let Some(pending_sleep_duration) = self.pending_sleep_duration_secs {
+            config.pending_sleep_duration = Duration::from_secs(pending_sleep_duration);
+        }
+        if let Some(base_layer_propagation_sleep_duration) =
+            self.base_layer_propagation_sleep_duration_secs
+        {
+            config.base_layer_propagation_sleep_duration =
+                Duration::from_secs(base_layer_propagation_sleep_duration);
+        }
+        if let Some(verify_hashes) = self.verify_hashes {
+            config.verify_hashes = verify_hashes;
+        }
+        if let Some(verify_state_root) = self.verify_state_root {
+            config.verify_state_root = verify_state_root;
+        }
+    }
+}
+
+impl From<EthereumBaseLayerConfig> for BaseLayer {
+    fn from(config: EthereumBaseLayerConfig) -> Self {
+        BaseLayer {
+            node_url: Some(config.node_url),
+            starknet_contract_address: Some(config.starknet_contract_address),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct BaseLayer {
+    node_url: Option<String>,
+    starknet_contract_address: Option<String>,
+}
+
+impl BaseLayer {
+    fn update_base_layer(self, config: &mut EthereumBaseLayerConfig) {
+        if let Some(node_url) = self.node_url {
+            config.node_url = node_url;
+        }
+        if let Some(starknet_contract_address) = self.starknet_contract_address {
+            config.starknet_contract_address = starknet_contract_address;
+        }
+    }
+}
+
+impl From<FeederGatewayConfig> for FeederGateway {
+    fn from(config: FeederGatewayConfig) -> Self {
+        FeederGateway { server_address: Some(config.server_address) }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct FeederGateway {
+    server_address: Option<String>,
+}
+
+impl FeederGateway {
+    fn update_feeder_gateway(self, config: &mut FeederGatewayConfig) {
+        if let Some(server_address) = self.server_address {
+            config.server_address = server_address;
+        }
     }
 }
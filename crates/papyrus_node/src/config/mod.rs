@@ -11,17 +11,23 @@ use std::{env, fs, io};
 
 use clap::{arg, value_parser, Arg, ArgMatches, Command};
 use file_config::FileConfigFormat;
+use papyrus_base_layer::ethereum_base_layer_contract::EthereumBaseLayerConfig;
+use papyrus_execution::ExecutionConfig;
+use papyrus_feeder_gateway::FeederGatewayConfig;
 use papyrus_gateway::GatewayConfig;
 use papyrus_monitoring_gateway::MonitoringGatewayConfig;
+use papyrus_storage::body::bloom::EventBloomConfig;
 use papyrus_storage::db::DbConfig;
 use papyrus_storage::StorageConfig;
-use papyrus_sync::{CentralSourceConfig, SyncConfig};
+use papyrus_sync::{CentralSourceConfig, CentralSourceKind, SyncConfig};
 use serde::{Deserialize, Serialize};
 use starknet_api::core::ChainId;
 use starknet_client::RetryConfig;
 
 // The path of the default configuration file, provided as part of the crate.
 const CONFIG_FILE: &str = "config/config.yaml";
+// The chain id is hashed as a Cairo short string, of up to 31 ASCII characters.
+const MAX_CHAIN_ID_LENGTH: usize = 31;
 
 /// The configurations of the various components of the node.
 #[derive(Deserialize, Serialize, Clone)]
@@ -32,6 +38,29 @@ pub struct Config {
     pub storage: StorageConfig,
     /// None if the syncing should be disabled.
     pub sync: Option<SyncConfig>,
+    /// None if the blocks proved on the base layer shouldn't be tracked.
+    pub base_layer: Option<EthereumBaseLayerConfig>,
+    /// None if the feeder gateway shouldn't run.
+    pub feeder_gateway: Option<FeederGatewayConfig>,
+    /// The command given on the command line, None if the node should just run.
+    #[serde(skip)]
+    pub command: Option<NodeCommand>,
+}
+
+/// Commands of the node on its storage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NodeCommand {
+    /// Writes a snapshot of the storage into the directory and exits.
+    ExportSnapshot { output_dir: PathBuf },
+    /// Imports the snapshot in the directory into the empty storage, and then runs the node.
+    ImportSnapshot { input_dir: PathBuf },
+    /// Upgrades the storage to the storage version of the node and exits.
+    MigrateStorage,
+    /// Checks the integrity of the storage, prints the report and exits.
+    CheckIntegrity,
+    /// Rebuilds the event bloom filters of the stored blocks with the configured parameters and
+    /// exits.
+    RebuildEventBlooms,
 }
 
 impl Config {
@@ -60,6 +89,13 @@ pub enum ConfigError {
         "CLA http_header \"{illegal_header}\" is not valid. The Expected format is name:value"
     )]
     CLAHttpHeader { illegal_header: String },
+    #[error(
+        "gateway.sequencer_url isn't set, and the central URL isn't a Starknet gateway with the \
+         {central_source:?} central source."
+    )]
+    MissingSequencerUrl { central_source: CentralSourceKind },
+    #[error("Chain id {chain_id:?} isn't a short string of up to 31 ASCII characters.")]
+    InvalidChainId { chain_id: ChainId },
 }
 
 // Builds the configuration for the node based on default values, yaml configuration file and
@@ -68,6 +104,8 @@ pub enum ConfigError {
 pub(crate) struct ConfigBuilder {
     args: Option<ArgMatches>,
     chain_id: ChainId,
+    // The URL of the gateway of the sequencer, None to use the central URL.
+    sequencer_url: Option<String>,
     config: Config,
 }
 
@@ -80,8 +118,10 @@ impl Default for ConfigBuilder {
         ConfigBuilder {
             args: None,
             chain_id: chain_id.clone(),
+            sequencer_url: None,
             config: Config {
                 central: CentralSourceConfig {
+                    source: CentralSourceKind::FeederGateway,
                     concurrent_requests: 300,
                     url: String::from("https://alpha-mainnet.starknet.io/"),
                     http_headers: None,
@@ -92,21 +132,39 @@ impl Default for ConfigBuilder {
                     },
                 },
                 gateway: GatewayConfig {
-                    chain_id,
+                    chain_id: chain_id.clone(),
                     server_address: String::from("0.0.0.0:8080"),
+                    ws_server_address: String::from("0.0.0.0:8082"),
                     max_events_chunk_size: 1000,
                     max_events_keys: 100,
+                    max_proof_keys: 100,
+                    execution_config: ExecutionConfig::default(),
+                    // Set by propagate_sequencer_url.
+                    sequencer_url: String::new(),
                 },
                 monitoring_gateway: MonitoringGatewayConfig {
                     server_address: String::from("0.0.0.0:8081"),
+                    http_server_address: String::from("0.0.0.0:8083"),
+                    max_blocks_behind: 10,
+                    max_sync_inactivity_duration: Duration::from_secs(300),
                 },
                 storage: StorageConfig {
                     db_config: DbConfig { path: String::from("./data"), max_size: 1099511627776 },
+                    pruning: None,
+                    event_bloom: EventBloomConfig::default(),
                 },
                 sync: Some(SyncConfig {
                     block_propagation_sleep_duration: Duration::from_secs(10),
                     recoverable_error_sleep_duration: Duration::from_secs(10),
+                    pending_sleep_duration: Duration::from_secs(2),
+                    base_layer_propagation_sleep_duration: Duration::from_secs(10),
+                    verify_hashes: false,
+                    verify_state_root: false,
+                    chain_id,
                 }),
+                base_layer: None,
+                feeder_gateway: None,
+                command: None,
             },
         }
     }
@@ -115,7 +173,13 @@ impl Default for ConfigBuilder {
 impl ConfigBuilder {
     // Creates the configuration struct.
     fn build(args: Vec<String>) -> Result<Config, ConfigError> {
-        Ok(Self::default().prepare_command(args)?.yaml()?.args()?.propagate_chain_id().config)
+        Ok(Self::default()
+            .prepare_command(args)?
+            .yaml()?
+            .args()?
+            .propagate_chain_id()?
+            .propagate_sequencer_url()?
+            .config)
     }
 
     // Builds the applications command-line interface.
@@ -133,6 +197,28 @@ impl ConfigBuilder {
                 arg!(-n --no_sync [bool] "Optionally run without sync").value_parser(value_parser!(bool)).default_missing_value("true"),
                 arg!(--central_url ["URL"] "Central URL. It should match chain_id."),
             ])
+            .subcommand(
+                Command::new("export_snapshot")
+                    .about("Writes a snapshot of the storage into a directory and exits")
+                    .arg(arg!(--output_dir <path> "The directory of the snapshot").value_parser(value_parser!(PathBuf))),
+            )
+            .subcommand(
+                Command::new("import_snapshot")
+                    .about("Imports a snapshot into the empty storage and runs the node")
+                    .arg(arg!(--input_dir <path> "The directory of the snapshot").value_parser(value_parser!(PathBuf))),
+            )
+            .subcommand(
+                Command::new("migrate_storage")
+                    .about("Upgrades the storage to the storage version of the node and exits"),
+            )
+            .subcommand(
+                Command::new("check_integrity")
+                    .about("Checks the integrity of the storage, prints the report and exits"),
+            )
+            .subcommand(
+                Command::new("rebuild_event_blooms")
+                    .about("Rebuilds the event bloom filters of the stored blocks and exits"),
+            )
             .try_get_matches_from(args).unwrap_or_else(|e| e.exit()),
         );
         Ok(self)
@@ -202,16 +288,56 @@ impl ConfigBuilder {
                     self.config.central.url = central_url.to_string()
                 }
 
+                self.config.command = match args.subcommand() {
+                    Some(("export_snapshot", sub_args)) => Some(NodeCommand::ExportSnapshot {
+                        output_dir: sub_args
+                            .get_one::<PathBuf>("output_dir")
+                            .expect("Required argument.")
+                            .clone(),
+                    }),
+                    Some(("import_snapshot", sub_args)) => Some(NodeCommand::ImportSnapshot {
+                        input_dir: sub_args
+                            .get_one::<PathBuf>("input_dir")
+                            .expect("Required argument.")
+                            .clone(),
+                    }),
+                    Some(("migrate_storage", _)) => Some(NodeCommand::MigrateStorage),
+                    Some(("check_integrity", _)) => Some(NodeCommand::CheckIntegrity),
+                    Some(("rebuild_event_blooms", _)) => Some(NodeCommand::RebuildEventBlooms),
+                    _ => None,
+                };
+
                 Ok(self)
             }
         }
     }
 
     // Propagates the chain id into all the of configurations that use it.
-    fn propagate_chain_id(mut self) -> Self {
+    fn propagate_chain_id(mut self) -> Result<Self, ConfigError> {
+        if self.chain_id.0.len() > MAX_CHAIN_ID_LENGTH || !self.chain_id.0.is_ascii() {
+            return Err(ConfigError::InvalidChainId { chain_id: self.chain_id });
+        }
         self.config.gateway.chain_id = self.chain_id.clone();
+        if let Some(sync) = self.config.sync.as_mut() {
+            sync.chain_id = self.chain_id.clone();
+        }
         // Assuming a valid path.
         self.config.storage.db_config.path.push_str(format!("/{}", self.chain_id.0).as_str());
-        self
+        Ok(self)
+    }
+
+    // Sets the URL transactions are added to. Unless it is configured, the transactions are added
+    // to the feeder gateway the node syncs from, which belongs to the same chain.
+    fn propagate_sequencer_url(mut self) -> Result<Self, ConfigError> {
+        self.config.gateway.sequencer_url = match self.sequencer_url.take() {
+            Some(sequencer_url) => sequencer_url,
+            None => match self.config.central.source {
+                CentralSourceKind::FeederGateway => self.config.central.url.clone(),
+                central_source => {
+                    return Err(ConfigError::MissingSequencerUrl { central_source });
+                }
+            },
+        };
+        Ok(self)
     }
 }
@@ -1,10 +1,22 @@
 use std::env::args;
+use std::sync::{Arc, RwLock};
 
-use papyrus_gateway::run_server;
-use papyrus_monitoring_gateway::run_server as monitoring_run_server;
-use papyrus_node::config::Config;
+use papyrus_feeder_gateway::run_server as feeder_gateway_run_server;
+use papyrus_gateway::{run_server, run_ws_server};
+use papyrus_monitoring_gateway::{run_http_server, run_server as monitoring_run_server};
+use papyrus_node::config::{Config, NodeCommand};
+use papyrus_storage::body::bloom::rebuild_event_blooms;
+use papyrus_storage::integrity::check_storage_integrity;
+use papyrus_storage::pruning::PruningConfig;
+use papyrus_storage::snapshot::{export_snapshot, import_snapshot};
+use papyrus_storage::version::migrate_storage;
 use papyrus_storage::{open_storage, StorageReader, StorageWriter};
-use papyrus_sync::{CentralError, CentralSource, StateSync, StateSyncError};
+use papyrus_sync::{
+    CentralError, CentralSource, CentralSourceKind, CentralSourceTrait, EthereumBaseLayerSource,
+    GenericStateSync, OfflineCentralSource, PeerCentralSource, PendingData, StateSyncError,
+    SyncConfig, SyncNotification, SyncStatus,
+};
+use tokio::sync::broadcast;
 use tracing::info;
 use tracing::metadata::LevelFilter;
 use tracing_subscriber::prelude::*;
@@ -12,41 +24,167 @@ use tracing_subscriber::{fmt, EnvFilter};
 
 // TODO(yair): Add to config.
 const DEFAULT_LEVEL: LevelFilter = LevelFilter::INFO;
+// The number of sync notifications a subscriber can lag behind before its subscription is closed.
+const SYNC_NOTIFICATIONS_CAPACITY: usize = 1000;
 
 async fn run_threads(config: Config) -> anyhow::Result<()> {
-    let (storage_reader, storage_writer) = open_storage(config.storage.db_config.clone())?;
+    let (storage_reader, mut storage_writer) = open_storage(config.storage.db_config.clone())?;
+    storage_writer.set_event_bloom_config(config.storage.event_bloom);
 
-    let (_, server_future) = run_server(&config.gateway, storage_reader.clone()).await?;
+    // The pending block is written by the sync and read by the gateway.
+    let pending_data = Arc::new(RwLock::new(None));
+    // The gateway server runs in a task of its own.
+    let (_, server_handle) =
+        run_server(&config.gateway, storage_reader.clone(), pending_data.clone()).await?;
+    let (sync_notifications, _) = broadcast::channel(SYNC_NOTIFICATIONS_CAPACITY);
+    let (_, ws_server_future) = run_ws_server(
+        &config.gateway,
+        storage_reader.clone(),
+        pending_data.clone(),
+        sync_notifications.clone(),
+    )
+    .await?;
     let (_, monitoring_server_future) = monitoring_run_server(
         config.get_config_representation()?,
         config.monitoring_gateway.clone(),
         storage_reader.clone(),
     )
     .await?;
-    let sync_future = run_sync(config, storage_reader.clone(), storage_writer);
+    // The sync status is written by the sync and read by the health probes.
+    let sync_status = Arc::new(RwLock::new(SyncStatus::default()));
+    let (_, monitoring_http_server_future) = run_http_server(
+        config.monitoring_gateway.clone(),
+        storage_reader.clone(),
+        config.sync.is_some().then(|| sync_status.clone()),
+    )
+    .await?;
+    let feeder_gateway_future = match config.feeder_gateway.clone() {
+        Some(feeder_gateway_config) => {
+            Some(feeder_gateway_run_server(feeder_gateway_config, storage_reader.clone()).await?.1)
+        }
+        None => None,
+    };
+    let base_layer_source =
+        config.base_layer.clone().map(EthereumBaseLayerSource::new).transpose()?;
+    let sync_future = run_sync(
+        config,
+        base_layer_source,
+        storage_reader.clone(),
+        storage_writer,
+        pending_data,
+        sync_notifications,
+        sync_status,
+    );
 
-    let server_handle = tokio::spawn(server_future);
+    let ws_server_handle = tokio::spawn(ws_server_future);
     let monitoring_server_handle = tokio::spawn(monitoring_server_future);
+    let monitoring_http_server_handle = tokio::spawn(monitoring_http_server_future);
+    let feeder_gateway_handle = tokio::spawn(async move {
+        match feeder_gateway_future {
+            Some(feeder_gateway_future) => feeder_gateway_future.await,
+            None => Ok(()),
+        }
+    });
     let sync_handle = tokio::spawn(sync_future);
-    let (_, _, sync_result) =
-        tokio::try_join!(server_handle, monitoring_server_handle, sync_handle)?;
+    let (_, _, _, _, _, sync_result) = tokio::try_join!(
+        server_handle,
+        ws_server_handle,
+        monitoring_server_handle,
+        monitoring_http_server_handle,
+        feeder_gateway_handle,
+        sync_handle
+    )?;
     sync_result?;
     return Ok(());
 
     async fn run_sync(
         config: Config,
+        base_layer_source: Option<EthereumBaseLayerSource>,
         storage_reader: StorageReader,
         storage_writer: StorageWriter,
+        pending_data: Arc<RwLock<Option<PendingData>>>,
+        sync_notifications: broadcast::Sender<SyncNotification>,
+        sync_status: Arc<RwLock<SyncStatus>>,
     ) -> Result<(), StateSyncError> {
-        if let Some(sync_config) = config.sync {
-            let central_source =
-                CentralSource::new(config.central.clone()).map_err(CentralError::ClientCreation)?;
-            let mut sync =
-                StateSync::new(sync_config, central_source, storage_reader.clone(), storage_writer);
-            return sync.run().await;
+        let sync_config = match config.sync {
+            Some(sync_config) => sync_config,
+            None => return Ok(()),
+        };
+        let pruning_config = config.storage.pruning.clone();
+        match config.central.source {
+            CentralSourceKind::FeederGateway => {
+                let central_source = CentralSource::new(config.central.clone())
+                    .map_err(CentralError::ClientCreation)?;
+                run_sync_from_source(
+                    sync_config,
+                    central_source,
+                    base_layer_source,
+                    storage_reader,
+                    storage_writer,
+                    pruning_config,
+                    pending_data,
+                    sync_notifications,
+                    sync_status,
+                )
+                .await
+            }
+            CentralSourceKind::Peer => {
+                let central_source = PeerCentralSource::new(config.central.clone())?;
+                run_sync_from_source(
+                    sync_config,
+                    central_source,
+                    base_layer_source,
+                    storage_reader,
+                    storage_writer,
+                    pruning_config,
+                    pending_data,
+                    sync_notifications,
+                    sync_status,
+                )
+                .await
+            }
+            CentralSourceKind::Offline => {
+                let central_source = OfflineCentralSource::new(config.central.clone());
+                run_sync_from_source(
+                    sync_config,
+                    central_source,
+                    base_layer_source,
+                    storage_reader,
+                    storage_writer,
+                    pruning_config,
+                    pending_data,
+                    sync_notifications,
+                    sync_status,
+                )
+                .await
+            }
         }
+    }
 
-        Ok(())
+    #[allow(clippy::too_many_arguments)]
+    async fn run_sync_from_source<TCentralSource: CentralSourceTrait + Sync + Send + 'static>(
+        sync_config: SyncConfig,
+        central_source: TCentralSource,
+        base_layer_source: Option<EthereumBaseLayerSource>,
+        storage_reader: StorageReader,
+        storage_writer: StorageWriter,
+        pruning_config: Option<PruningConfig>,
+        pending_data: Arc<RwLock<Option<PendingData>>>,
+        sync_notifications: broadcast::Sender<SyncNotification>,
+        sync_status: Arc<RwLock<SyncStatus>>,
+    ) -> Result<(), StateSyncError> {
+        let mut sync = GenericStateSync::new(
+            sync_config,
+            central_source,
+            base_layer_source,
+            storage_reader,
+            storage_writer,
+            pruning_config,
+            pending_data,
+            sync_notifications,
+            sync_status,
+        );
+        sync.run().await
     }
 }
 
@@ -67,6 +205,38 @@ fn configure_tracing() {
 async fn main() -> anyhow::Result<()> {
     let config = Config::load(args().collect())?;
     configure_tracing();
+    match &config.command {
+        Some(NodeCommand::ExportSnapshot { output_dir }) => {
+            let (storage_reader, _) = open_storage(config.storage.db_config.clone())?;
+            export_snapshot(&storage_reader, config.gateway.chain_id.clone(), output_dir)?;
+            return Ok(());
+        }
+        Some(NodeCommand::ImportSnapshot { input_dir }) => {
+            import_snapshot(input_dir, &config.gateway.chain_id, config.storage.db_config.clone())?;
+        }
+        Some(NodeCommand::MigrateStorage) => {
+            migrate_storage(config.storage.db_config.clone())?;
+            return Ok(());
+        }
+        Some(NodeCommand::CheckIntegrity) => {
+            let (storage_reader, _) = open_storage(config.storage.db_config.clone())?;
+            let report = check_storage_integrity(&storage_reader)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if !report.is_consistent() {
+                anyhow::bail!("Found {} integrity issues in the storage.", report.issues.len());
+            }
+            return Ok(());
+        }
+        Some(NodeCommand::RebuildEventBlooms) => {
+            let (storage_reader, mut storage_writer) =
+                open_storage(config.storage.db_config.clone())?;
+            storage_writer.set_event_bloom_config(config.storage.event_bloom);
+            let body_marker = rebuild_event_blooms(&storage_reader, &mut storage_writer)?;
+            info!("Rebuilt the event bloom filters of {body_marker} blocks.");
+            return Ok(());
+        }
+        None => {}
+    }
     info!("Booting up.");
     run_threads(config).await
 }
@@ -0,0 +1,50 @@
+use starknet_api::block::BlockNumber;
+
+use crate::base_layer::{BaseLayerStorageReader, BaseLayerStorageWriter};
+use crate::test_utils::get_test_storage;
+
+#[test]
+fn update_and_revert_base_layer_marker() {
+    let (reader, mut writer) = get_test_storage();
+    assert_eq!(
+        reader.begin_ro_txn().unwrap().get_base_layer_block_marker().unwrap(),
+        BlockNumber(0)
+    );
+
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .update_base_layer_block_marker(&BlockNumber(7))
+        .unwrap()
+        .commit()
+        .unwrap();
+    assert_eq!(
+        reader.begin_ro_txn().unwrap().get_base_layer_block_marker().unwrap(),
+        BlockNumber(7)
+    );
+
+    // Reverting a block that wasn't proved doesn't change the marker.
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .try_revert_base_layer_marker(BlockNumber(7))
+        .unwrap()
+        .commit()
+        .unwrap();
+    assert_eq!(
+        reader.begin_ro_txn().unwrap().get_base_layer_block_marker().unwrap(),
+        BlockNumber(7)
+    );
+
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .try_revert_base_layer_marker(BlockNumber(5))
+        .unwrap()
+        .commit()
+        .unwrap();
+    assert_eq!(
+        reader.begin_ro_txn().unwrap().get_base_layer_block_marker().unwrap(),
+        BlockNumber(5)
+    );
+}
@@ -0,0 +1,142 @@
+//! Bloom filters over the emitting addresses and the event keys of each block, for skipping the
+//! blocks that can't have events matching the address and the keys of a filter when iterating the
+//! events of a contract address or all the events by their index.
+//!
+//! The filter of a block holds the addresses that emitted its events and the keys of its events, in
+//! any position. A filter may report an address or a key that the block doesn't have, but never
+//! misses one that it has. A block without a filter, e.g. a block stored before the filters were
+//! added, may always match until [`rebuild_event_blooms`] builds its filter.
+
+#[cfg(test)]
+#[path = "bloom_test.rs"]
+mod bloom_test;
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use starknet_api::block::BlockNumber;
+use starknet_api::core::ContractAddress;
+use starknet_api::hash::StarkFelt;
+use starknet_api::transaction::{Event, EventKey, TransactionOffsetInBlock};
+use tracing::info;
+
+use crate::body::BodyStorageReader;
+use crate::db::RW;
+use crate::{StorageReader, StorageResult, StorageTxn, StorageWriter, TransactionIndex};
+
+// The number of blocks whose filters are rebuilt in each write transaction.
+const REBUILD_BATCH_SIZE: u64 = 1000;
+// The seeds of the two hashes of a key, see [`EventBloom::bit_indices`].
+const FIRST_HASH_SEED: u64 = 0xcbf29ce484222325;
+const SECOND_HASH_SEED: u64 = 0x84222325cbf29ce4;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventBloomConfig {
+    /// The size of the filter of a block with events, in bytes.
+    pub size_bytes: usize,
+    /// The number of bits set in the filter for each key.
+    pub hash_count: u8,
+}
+
+impl Default for EventBloomConfig {
+    fn default() -> Self {
+        Self { size_bytes: 1024, hash_count: 3 }
+    }
+}
+
+/// A bloom filter over the emitting addresses and the keys of the events of a block. The filter of a
+/// block without events has no bits.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct EventBloom {
+    pub hash_count: u8,
+    pub bits: Vec<u8>,
+}
+
+impl EventBloom {
+    pub fn new<'a>(config: &EventBloomConfig, events: impl IntoIterator<Item = &'a Event>) -> Self {
+        let mut bloom = EventBloom { hash_count: config.hash_count, bits: Vec::new() };
+        for event in events {
+            if bloom.bits.is_empty() {
+                bloom.bits = vec![0; config.size_bytes.max(1)];
+            }
+            bloom.insert(event.from_address.0.key());
+            for key in &event.content.keys {
+                bloom.insert(&key.0);
+            }
+        }
+        bloom
+    }
+
+    /// Returns false if none of the events of the block can be emitted from the address, if given,
+    /// and have a key from each of the non-empty sets of keys, in any position.
+    pub fn may_match(&self, address: Option<&ContractAddress>, keys: &[HashSet<EventKey>]) -> bool {
+        if self.bits.is_empty() {
+            return false;
+        }
+        if address.map_or(false, |address| !self.may_contain(address.0.key())) {
+            return false;
+        }
+        keys.iter().all(|keys| keys.is_empty() || keys.iter().any(|key| self.may_contain(&key.0)))
+    }
+
+    fn insert(&mut self, felt: &StarkFelt) {
+        for bit in self.bit_indices(felt) {
+            self.bits[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    fn may_contain(&self, felt: &StarkFelt) -> bool {
+        self.bit_indices(felt).all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    // The i-th bit of the felt is derived from two hashes of it, as h1 + i * h2.
+    fn bit_indices(&self, felt: &StarkFelt) -> impl Iterator<Item = usize> {
+        let bits_count = (self.bits.len() * 8) as u64;
+        let first_hash = fnv_hash(felt.bytes(), FIRST_HASH_SEED);
+        // An odd step visits different bits for different i.
+        let second_hash = fnv_hash(felt.bytes(), SECOND_HASH_SEED) | 1;
+        (0..u64::from(self.hash_count)).map(move |i| {
+            (first_hash.wrapping_add(i.wrapping_mul(second_hash)) % bits_count) as usize
+        })
+    }
+}
+
+// FNV-1a, a hash that doesn't change between versions of the code, as the filters are stored.
+fn fnv_hash(bytes: &[u8], seed: u64) -> u64 {
+    bytes.iter().fold(seed, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3))
+}
+
+/// Builds the filters of all the stored blocks with the config of the writer, replacing their
+/// existing filters. Returns the body marker, up to which the filters were built.
+pub fn rebuild_event_blooms(
+    reader: &StorageReader,
+    writer: &mut StorageWriter,
+) -> StorageResult<BlockNumber> {
+    let body_marker = reader.begin_ro_txn()?.get_body_marker()?;
+    let mut block_number = BlockNumber(0);
+    while block_number < body_marker {
+        let batch_end = BlockNumber((block_number.0 + REBUILD_BATCH_SIZE).min(body_marker.0));
+        let txn = writer.begin_rw_txn()?;
+        while block_number < batch_end {
+            rebuild_event_bloom(&txn, block_number)?;
+            block_number = block_number.next();
+        }
+        txn.commit()?;
+        info!("Rebuilt the event bloom filters up to block {block_number}.");
+    }
+    Ok(body_marker)
+}
+
+fn rebuild_event_bloom(txn: &StorageTxn<'_, RW>, block_number: BlockNumber) -> StorageResult<()> {
+    let transactions_count =
+        txn.get_block_transaction_outputs(block_number)?.unwrap_or_default().len();
+    let mut events = Vec::new();
+    for offset in 0..transactions_count {
+        let transaction_index = TransactionIndex(block_number, TransactionOffsetInBlock(offset));
+        events.extend(txn.get_transaction_events(transaction_index)?.unwrap_or_default());
+    }
+    let event_blooms_table = txn.txn.open_table(&txn.tables.event_blooms)?;
+    let event_bloom = EventBloom::new(&txn.event_bloom_config, &events);
+    event_blooms_table.upsert(&txn.txn, &block_number, &event_bloom)?;
+    Ok(())
+}
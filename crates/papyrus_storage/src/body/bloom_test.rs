@@ -0,0 +1,206 @@
+use std::collections::HashSet;
+
+use starknet_api::block::BlockNumber;
+use starknet_api::core::{ContractAddress, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::transaction::{
+    EventIndexInTransactionOutput, EventKey, TransactionOffsetInBlock,
+};
+use starknet_api::{patricia_key, stark_felt};
+use test_utils::{get_rand_test_body_with_events, get_rng};
+
+use crate::body::bloom::{rebuild_event_blooms, EventBloom, EventBloomConfig};
+use crate::body::events::EventsReader;
+use crate::body::BodyStorageWriter;
+use crate::test_utils::get_test_storage;
+use crate::{EventIndex, StorageWriter, TransactionIndex};
+
+// Appends blocks whose events are emitted from the address and have the key of the block.
+fn append_blocks(writer: &mut StorageWriter, address: ContractAddress, keys: &[EventKey]) {
+    let mut rng = get_rng();
+    for (block_number, key) in keys.iter().enumerate() {
+        let body = get_rand_test_body_with_events(
+            &mut rng,
+            2,
+            2,
+            Some(vec![address]),
+            Some(vec![vec![key.clone()]]),
+        );
+        writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_body(BlockNumber(block_number as u64), body)
+            .unwrap()
+            .commit()
+            .unwrap();
+    }
+}
+
+#[test]
+fn bloom_may_match_its_events() {
+    let address = ContractAddress(patricia_key!("0x22"));
+    let key = EventKey(stark_felt!("0x6"));
+    let mut rng = get_rng();
+    let body = get_rand_test_body_with_events(
+        &mut rng,
+        2,
+        2,
+        Some(vec![address]),
+        Some(vec![vec![key.clone()]]),
+    );
+    let events = body.transaction_outputs.iter().flat_map(|tx_output| tx_output.events());
+    let bloom = EventBloom::new(&EventBloomConfig::default(), events);
+
+    assert!(bloom.may_match(None, &[]));
+    assert!(bloom.may_match(None, &[HashSet::new(), HashSet::from([key.clone()])]));
+    assert!(!bloom.may_match(None, &[HashSet::from([EventKey(stark_felt!("0x7"))])]));
+    assert!(bloom.may_match(Some(&address), &[HashSet::from([key.clone()])]));
+
+    // A block without events doesn't match any filter.
+    let empty_bloom = EventBloom::new(&EventBloomConfig::default(), std::iter::empty());
+    assert!(empty_bloom.bits.is_empty());
+    assert!(!empty_bloom.may_match(None, &[]));
+}
+
+#[test]
+fn bloom_skips_block_without_address() {
+    let address = ContractAddress(patricia_key!("0x22"));
+    let other_address = ContractAddress(patricia_key!("0x23"));
+    let key = EventKey(stark_felt!("0x6"));
+    let mut rng = get_rng();
+    let body = get_rand_test_body_with_events(
+        &mut rng,
+        2,
+        2,
+        Some(vec![address]),
+        Some(vec![vec![key.clone()]]),
+    );
+    let events = body.transaction_outputs.iter().flat_map(|tx_output| tx_output.events());
+    let bloom = EventBloom::new(&EventBloomConfig::default(), events);
+
+    // The block has events with the key, but none of them is emitted from the other address.
+    assert!(bloom.may_match(Some(&address), &[]));
+    assert!(!bloom.may_match(Some(&other_address), &[]));
+    assert!(!bloom.may_match(Some(&other_address), &[HashSet::from([key])]));
+}
+
+#[test]
+fn skip_blocks_by_bloom() {
+    let (reader, mut writer) = get_test_storage();
+    let address = ContractAddress(patricia_key!("0x22"));
+    let key0 = EventKey(stark_felt!("0x6"));
+    let key1 = EventKey(stark_felt!("0x7"));
+    append_blocks(&mut writer, address, &[key0, key1.clone()]);
+
+    // Only the events of block 1 can have key1.
+    let event_index = EventIndex(
+        TransactionIndex(BlockNumber(0), TransactionOffsetInBlock(0)),
+        EventIndexInTransactionOutput(0),
+    );
+    let txn = reader.begin_ro_txn().unwrap();
+    let events: Vec<_> = txn
+        .iter_events(None, &[HashSet::from([key1])], event_index, BlockNumber(1))
+        .unwrap()
+        .collect();
+    assert_eq!(events.len(), 4);
+    assert!(events.iter().all(|((_, event_index), _)| (event_index.0).0 == BlockNumber(1)));
+}
+
+#[test]
+fn skip_blocks_of_address_by_bloom() {
+    let (reader, mut writer) = get_test_storage();
+    let address = ContractAddress(patricia_key!("0x22"));
+    let key0 = EventKey(stark_felt!("0x6"));
+    let key1 = EventKey(stark_felt!("0x7"));
+    append_blocks(&mut writer, address, &[key1.clone(), key0, key1.clone()]);
+
+    // Only the events of blocks 0 and 2 can have key1.
+    let event_index = EventIndex(
+        TransactionIndex(BlockNumber(0), TransactionOffsetInBlock(0)),
+        EventIndexInTransactionOutput(0),
+    );
+    let txn = reader.begin_ro_txn().unwrap();
+    let blocks: Vec<_> = txn
+        .iter_events(Some(address), &[HashSet::from([key1])], event_index, BlockNumber(2))
+        .unwrap()
+        .map(|((_, event_index), _)| (event_index.0).0)
+        .collect();
+    assert_eq!(blocks, [[BlockNumber(0); 4], [BlockNumber(2); 4]].concat());
+}
+
+#[test]
+fn skip_blocks_of_address_up_to_next_address_and_block() {
+    let (reader, mut writer) = get_test_storage();
+    let address = ContractAddress(patricia_key!("0x22"));
+    let next_address = ContractAddress(patricia_key!("0x23"));
+    let key0 = EventKey(stark_felt!("0x6"));
+    let key1 = EventKey(stark_felt!("0x7"));
+    // Block 0 has the events of the address with key1, and the next address has events with key0
+    // in the following blocks, and events with key1 in the last block.
+    let mut blocks = vec![(address, key1.clone())];
+    blocks.extend(std::iter::repeat((next_address, key0)).take(10));
+    blocks.push((next_address, key1.clone()));
+    let mut rng = get_rng();
+    for (block_number, (address, key)) in blocks.into_iter().enumerate() {
+        let body = get_rand_test_body_with_events(
+            &mut rng,
+            2,
+            2,
+            Some(vec![address]),
+            Some(vec![vec![key]]),
+        );
+        writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_body(BlockNumber(block_number as u64), body)
+            .unwrap()
+            .commit()
+            .unwrap();
+    }
+
+    let event_index = EventIndex(
+        TransactionIndex(BlockNumber(0), TransactionOffsetInBlock(0)),
+        EventIndexInTransactionOutput(0),
+    );
+    let keys = [HashSet::from([key1])];
+    let txn = reader.begin_ro_txn().unwrap();
+    // The skipping ends at the events of the next address.
+    let events: Vec<_> =
+        txn.iter_events(Some(address), &keys, event_index, BlockNumber(11)).unwrap().collect();
+    assert_eq!(events.len(), 4);
+    assert!(events.iter().all(|((from_address, _), _)| *from_address == address));
+    // The skipping ends after the last block.
+    assert_eq!(
+        txn.iter_events(Some(next_address), &keys, event_index, BlockNumber(10)).unwrap().count(),
+        0
+    );
+}
+
+#[test]
+fn rebuild_blooms() {
+    let (reader, mut writer) = get_test_storage();
+    let address = ContractAddress(patricia_key!("0x22"));
+    append_blocks(&mut writer, address, &[EventKey(stark_felt!("0x6"))]);
+    let txn = reader.begin_ro_txn().unwrap();
+    let event_blooms_table = txn.txn.open_table(&txn.tables.event_blooms).unwrap();
+    let bloom = event_blooms_table.get(&txn.txn, &BlockNumber(0)).unwrap().unwrap();
+    drop(txn);
+
+    // A storage without the filters gets the same filters the blocks got when they were appended.
+    let txn = writer.begin_rw_txn().unwrap();
+    let event_blooms_table = txn.txn.open_table(&txn.tables.event_blooms).unwrap();
+    event_blooms_table.delete(&txn.txn, &BlockNumber(0)).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(rebuild_event_blooms(&reader, &mut writer).unwrap(), BlockNumber(1));
+    let txn = reader.begin_ro_txn().unwrap();
+    let event_blooms_table = txn.txn.open_table(&txn.tables.event_blooms).unwrap();
+    assert_eq!(event_blooms_table.get(&txn.txn, &BlockNumber(0)).unwrap(), Some(bloom));
+
+    // The filters are rebuilt with the config of the writer.
+    writer.set_event_bloom_config(EventBloomConfig { size_bytes: 16, hash_count: 2 });
+    rebuild_event_blooms(&reader, &mut writer).unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+    let event_blooms_table = txn.txn.open_table(&txn.tables.event_blooms).unwrap();
+    let bloom = event_blooms_table.get(&txn.txn, &BlockNumber(0)).unwrap().unwrap();
+    assert_eq!((bloom.bits.len(), bloom.hash_count), (16, 2));
+}
@@ -1,10 +1,13 @@
 use assert_matches::assert_matches;
 use starknet_api::block::{BlockBody, BlockNumber};
-use starknet_api::transaction::TransactionOffsetInBlock;
+use starknet_api::core::{ContractAddress, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::patricia_key;
+use starknet_api::transaction::{Transaction, TransactionOffsetInBlock};
 use test_utils::{get_test_block, get_test_body};
 
 use crate::body::events::ThinTransactionOutput;
-use crate::body::{BodyStorageReader, BodyStorageWriter};
+use crate::body::{transaction_sender, BodyStorageReader, BodyStorageWriter};
 use crate::test_utils::get_test_storage;
 use crate::{StorageError, StorageWriter, TransactionIndex};
 
@@ -270,6 +273,64 @@ async fn revert_transactions() {
     );
 }
 
+#[tokio::test]
+async fn get_transactions_by_sender() {
+    let (reader, mut writer) = get_test_storage();
+    let sender = ContractAddress(patricia_key!("0x100"));
+    let mut body = get_test_body(10);
+    for tx in body.transactions.iter_mut() {
+        match tx {
+            Transaction::Declare(tx) => tx.sender_address = sender,
+            Transaction::DeployAccount(tx) => tx.contract_address = sender,
+            Transaction::Invoke(tx) => tx.sender_address = sender,
+            Transaction::Deploy(_) | Transaction::L1Handler(_) => {}
+        }
+    }
+    let expected: Vec<_> = body
+        .transactions
+        .iter()
+        .enumerate()
+        .filter(|(_, tx)| transaction_sender(tx).is_some())
+        .map(|(offset, tx)| {
+            let tx_index = TransactionIndex(BlockNumber(0), TransactionOffsetInBlock(offset));
+            (tx_index, tx.transaction_hash())
+        })
+        .collect();
+    assert!(expected.len() > 1);
+    writer.begin_rw_txn().unwrap().append_body(BlockNumber(0), body).unwrap().commit().unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    let start = TransactionIndex(BlockNumber(0), TransactionOffsetInBlock(0));
+    assert_eq!(
+        txn.get_transactions_by_sender(&sender, start, BlockNumber(0), 100).unwrap(),
+        expected
+    );
+    assert_eq!(
+        txn.get_transactions_by_sender(&sender, start, BlockNumber(0), 1).unwrap(),
+        expected[..1]
+    );
+    // Continue from the transaction after the first one.
+    let next = TransactionIndex(BlockNumber(0), TransactionOffsetInBlock((expected[0].0).1.0 + 1));
+    assert_eq!(
+        txn.get_transactions_by_sender(&sender, next, BlockNumber(0), 100).unwrap(),
+        expected[1..]
+    );
+    let other_sender = ContractAddress(patricia_key!("0x101"));
+    assert!(
+        txn.get_transactions_by_sender(&other_sender, start, BlockNumber(0), 100)
+            .unwrap()
+            .is_empty()
+    );
+    drop(txn);
+
+    // Reverting the block removes its transactions from the index.
+    writer.begin_rw_txn().unwrap().revert_body(BlockNumber(0)).unwrap().0.commit().unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+    assert!(
+        txn.get_transactions_by_sender(&sender, start, BlockNumber(0), 100).unwrap().is_empty()
+    );
+}
+
 fn append_2_bodies(writer: &mut StorageWriter) {
     writer
         .begin_rw_txn()
@@ -2,24 +2,41 @@
 #[path = "events_test.rs"]
 mod events_test;
 
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
 use starknet_api::block::BlockNumber;
 use starknet_api::core::ContractAddress;
 use starknet_api::transaction::{
-    EventContent, EventIndexInTransactionOutput, Fee, MessageToL1, TransactionOutput,
+    EventContent, EventIndexInTransactionOutput, EventKey, Fee, MessageToL1,
+    TransactionOffsetInBlock, TransactionOutput,
 };
 
-use crate::body::{EventsTable, EventsTableKey};
+use crate::body::{EventBloomsTable, EventKeysTableKey, EventsTable, EventsTableKey};
 use crate::db::{DbCursor, DbTransaction, RO};
-use crate::{EventIndex, StorageResult, StorageTxn, TransactionIndex};
+use crate::{EventIndex, StorageError, StorageResult, StorageTxn, TransactionIndex};
 
 pub trait EventsReader<'txn, 'env> {
     /// Returns an itrator over events, which is a wrapper of two iterators.
     /// If the address is none it iterates the events by the order of the event index,
     /// else, it iterated the events by the order of the contract addresses.
+    /// In both orders, the blocks whose event bloom filter can't match the address and the keys are
+    /// skipped. The keys don't filter the returned events otherwise.
+    /// With keys, the iteration by the contract addresses ends at the events of another address
+    /// or after the given block number.
     fn iter_events(
         &'env self,
         address: Option<ContractAddress>,
+        keys: &[HashSet<EventKey>],
+        event_index: EventIndex,
+        to_block_number: BlockNumber,
+    ) -> StorageResult<EventIter<'txn, 'env>>;
+
+    /// Returns an iterator over the events that have one of the given keys, in any position, by
+    /// the order of the event index, up to the given block number.
+    fn iter_events_by_keys(
+        &'env self,
+        keys: &[EventKey],
         event_index: EventIndex,
         to_block_number: BlockNumber,
     ) -> StorageResult<EventIter<'txn, 'env>>;
@@ -30,26 +47,58 @@ impl<'txn, 'env> EventsReader<'txn, 'env> for StorageTxn<'env, RO> {
     fn iter_events(
         &'env self,
         address: Option<ContractAddress>,
+        keys: &[HashSet<EventKey>],
         event_index: EventIndex,
         to_block_number: BlockNumber,
     ) -> StorageResult<EventIter<'txn, 'env>> {
         if address.is_some() {
-            return Ok(EventIter::ByContractAddress(
-                self.iter_events_by_contract_address((address.unwrap(), event_index))?,
-            ));
+            return Ok(EventIter::ByContractAddress(self.iter_events_by_contract_address(
+                keys,
+                (address.unwrap(), event_index),
+                to_block_number,
+            )?));
         }
 
-        Ok(EventIter::ByEventIndex(self.iter_events_by_event_index(event_index, to_block_number)?))
+        Ok(EventIter::ByEventIndex(self.iter_events_by_event_index(
+            keys,
+            event_index,
+            to_block_number,
+        )?))
+    }
+
+    fn iter_events_by_keys(
+        &'env self,
+        keys: &[EventKey],
+        event_index: EventIndex,
+        to_block_number: BlockNumber,
+    ) -> StorageResult<EventIter<'txn, 'env>> {
+        let event_keys_table = self.txn.open_table(&self.tables.event_keys)?;
+        let mut key_cursors = Vec::new();
+        for key in keys {
+            let mut cursor = event_keys_table.cursor(&self.txn)?;
+            let current = cursor
+                .lower_bound(&(key.clone(), event_index))?
+                .filter(|((got_key, _), _)| got_key == key);
+            key_cursors.push((key.clone(), current, cursor));
+        }
+        let events_table = self.txn.open_table(&self.tables.events)?;
+        Ok(EventIter::ByEventKeys(EventIterByEventKeys {
+            txn: &self.txn,
+            events_table,
+            key_cursors,
+            to_block_number,
+        }))
     }
 }
 
 pub enum EventIter<'txn, 'env> {
-    ByContractAddress(EventIterByContractAddress<'txn>),
+    ByContractAddress(EventIterByContractAddress<'txn, 'env>),
     ByEventIndex(EventIterByEventIndex<'txn, 'env>),
+    ByEventKeys(EventIterByEventKeys<'txn, 'env>),
 }
 
-/// This iterator is a wrapper of two iterators [`EventIterByContractAddress`]
-/// and [`EventIterByEventIndex`].
+/// This iterator is a wrapper of the iterators [`EventIterByContractAddress`],
+/// [`EventIterByEventIndex`] and [`EventIterByEventKeys`].
 /// With this wrapper we can execute the same code, regardless the
 /// type of iteration used.
 impl Iterator for EventIter<'_, '_> {
@@ -59,6 +108,7 @@ impl Iterator for EventIter<'_, '_> {
         let res = match self {
             EventIter::ByContractAddress(it) => it.next(),
             EventIter::ByEventIndex(it) => it.next(),
+            EventIter::ByEventKeys(it) => it.next(),
         };
         if res.is_err() {
             return None;
@@ -69,17 +119,60 @@ impl Iterator for EventIter<'_, '_> {
 
 /// This iterator goes over the events in the order of the events table key.
 /// That is, the events iterated first by the contract address and then by the event index.
-pub struct EventIterByContractAddress<'txn> {
+pub struct EventIterByContractAddress<'txn, 'env> {
+    txn: &'txn DbTransaction<'env, RO>,
     current: Option<EventsTableKeyValue>,
     cursor: EventsTableCursor<'txn>,
+    event_blooms_table: EventBloomsTable<'env>,
+    address: ContractAddress,
+    keys: Vec<HashSet<EventKey>>,
+    to_block_number: BlockNumber,
+    // The block of the current event, once its bloom filter matched the keys.
+    matching_block: Option<BlockNumber>,
 }
 
-impl EventIterByContractAddress<'_> {
+impl EventIterByContractAddress<'_, '_> {
     fn next(&mut self) -> StorageResult<Option<EventsTableKeyValue>> {
         let res = self.current.take();
         self.current = self.cursor.next()?;
+        self.skip_unmatching_blocks()?;
         Ok(res)
     }
+
+    // Skips the events of the contract address in the blocks whose bloom filter can't match the
+    // address and the keys. The skipping ends at the events of another address or after the last
+    // block, so their filters aren't read.
+    fn skip_unmatching_blocks(&mut self) -> StorageResult<()> {
+        if self.keys.iter().all(|keys| keys.is_empty()) {
+            return Ok(());
+        }
+        while let Some(((address, event_index), _)) = &self.current {
+            let block_number = (event_index.0).0;
+            if *address != self.address || block_number > self.to_block_number {
+                self.current = None;
+                break;
+            }
+            if self.matching_block == Some(block_number) {
+                break;
+            }
+            if may_match_block(
+                self.txn,
+                &self.event_blooms_table,
+                Some(&self.address),
+                &self.keys,
+                block_number,
+            )? {
+                self.matching_block = Some(block_number);
+                break;
+            }
+            let first_event_of_next_block = EventIndex(
+                TransactionIndex(block_number.next(), TransactionOffsetInBlock(0)),
+                EventIndexInTransactionOutput(0),
+            );
+            self.current = self.cursor.lower_bound(&(self.address, first_event_of_next_block))?;
+        }
+        Ok(())
+    }
 }
 
 /// This iterator goes over the events in the order of the event index.
@@ -93,6 +186,10 @@ pub struct EventIterByEventIndex<'txn, 'env> {
     events_table: EventsTable<'env>,
     event_index_in_tx_current: EventIndexInTransactionOutput,
     to_block_number: BlockNumber,
+    event_blooms_table: EventBloomsTable<'env>,
+    keys: Vec<HashSet<EventKey>>,
+    // The block of the current transaction, once its bloom filter matched the keys.
+    matching_block: Option<BlockNumber>,
 }
 
 impl EventIterByEventIndex<'_, '_> {
@@ -119,10 +216,29 @@ impl EventIterByEventIndex<'_, '_> {
     // (event_index_in_tx_current).
     fn find_next_event_by_event_index(&mut self) -> StorageResult<()> {
         while let Some((tx_index, tx_output)) = &self.tx_current {
-            if tx_index.0 > self.to_block_number {
+            let block_number = tx_index.0;
+            if block_number > self.to_block_number {
                 self.tx_current = None;
                 break;
             }
+            // Skips to the next block if none of the events of the block can match the keys.
+            if self.matching_block != Some(block_number) {
+                if !may_match_block(
+                    self.txn,
+                    &self.event_blooms_table,
+                    None,
+                    &self.keys,
+                    block_number,
+                )? {
+                    self.tx_current = self.tx_cursor.lower_bound(&TransactionIndex(
+                        block_number.next(),
+                        TransactionOffsetInBlock(0),
+                    ))?;
+                    self.event_index_in_tx_current = EventIndexInTransactionOutput(0);
+                    continue;
+                }
+                self.matching_block = Some(block_number);
+            }
             // Checks if there's an event in the current event index.
             if tx_output.events_contract_addresses_as_ref().len() > self.event_index_in_tx_current.0
             {
@@ -139,17 +255,98 @@ impl EventIterByEventIndex<'_, '_> {
     }
 }
 
+fn may_match_block<'env>(
+    txn: &'env DbTransaction<'env, RO>,
+    event_blooms_table: &'env EventBloomsTable<'env>,
+    address: Option<&ContractAddress>,
+    keys: &[HashSet<EventKey>],
+    block_number: BlockNumber,
+) -> StorageResult<bool> {
+    if address.is_none() && keys.iter().all(|keys| keys.is_empty()) {
+        return Ok(true);
+    }
+    // A block without a filter may match, see [`crate::body::bloom`].
+    Ok(event_blooms_table
+        .get(txn, &block_number)?
+        .map_or(true, |event_bloom| event_bloom.may_match(address, keys)))
+}
+
+/// This iterator goes over the events that have one of the given keys, in the order of the event
+/// index. It merges the entries of the keys in the event keys table, where the entries of each key
+/// are ordered by the event index.
+pub struct EventIterByEventKeys<'txn, 'env> {
+    txn: &'txn DbTransaction<'env, RO>,
+    events_table: EventsTable<'env>,
+    // For each key, its current entry in the event keys table and a cursor over the next entries.
+    key_cursors: Vec<(EventKey, Option<EventKeysTableKeyValue>, EventKeysTableCursor<'txn>)>,
+    to_block_number: BlockNumber,
+}
+
+impl EventIterByEventKeys<'_, '_> {
+    fn next(&mut self) -> StorageResult<Option<EventsTableKeyValue>> {
+        let next_event = self
+            .key_cursors
+            .iter()
+            .filter_map(|(_, current, _)| current.as_ref())
+            .map(|((_, event_index), from_address)| (*event_index, *from_address))
+            .min_by_key(|(event_index, _)| event_index_position(event_index));
+        let (event_index, from_address) = match next_event {
+            Some((event_index, from_address)) if (event_index.0).0 <= self.to_block_number => {
+                (event_index, from_address)
+            }
+            _ => return Ok(None),
+        };
+
+        // An event with several of the keys is returned once, so all the keys that point to it
+        // advance.
+        for (key, current, cursor) in &mut self.key_cursors {
+            if matches!(current, Some(((_, current_index), _)) if *current_index == event_index) {
+                *current = cursor.next()?.filter(|((got_key, _), _)| got_key == key);
+            }
+        }
+
+        let events_table_key = (from_address, event_index);
+        let content = self.events_table.get(self.txn, &events_table_key)?.ok_or(
+            StorageError::DBInconsistency {
+                msg: format!("Missing the event {event_index:?} of the event keys table."),
+            },
+        )?;
+        Ok(Some((events_table_key, content)))
+    }
+}
+
+// The order of the events by their index.
+fn event_index_position(event_index: &EventIndex) -> (BlockNumber, usize, usize) {
+    let EventIndex(TransactionIndex(block_number, tx_offset), event_offset) = event_index;
+    (*block_number, tx_offset.0, event_offset.0)
+}
+
 impl<'txn, 'env> StorageTxn<'env, RO> {
     // Returns an events iterator that iterates events by the events table key,
     // starting from the first event with a key greater or equals to the given key.
     fn iter_events_by_contract_address(
         &'env self,
+        keys: &[HashSet<EventKey>],
         key: EventsTableKey,
-    ) -> StorageResult<EventIterByContractAddress<'txn>> {
+        to_block_number: BlockNumber,
+    ) -> StorageResult<EventIterByContractAddress<'txn, 'env>> {
         let events_table = self.txn.open_table(&self.tables.events)?;
         let mut cursor = events_table.cursor(&self.txn)?;
         let current = cursor.lower_bound(&key)?;
-        Ok(EventIterByContractAddress { current, cursor })
+        let event_blooms_table = self.txn.open_table(&self.tables.event_blooms)?;
+
+        let mut it = EventIterByContractAddress {
+            txn: &self.txn,
+            current,
+            cursor,
+            event_blooms_table,
+            address: key.0,
+            keys: keys.to_vec(),
+            to_block_number,
+            matching_block: None,
+        };
+        it.skip_unmatching_blocks()?;
+        Ok(it)
     }
 
     // Returns an events iterator that iterates events by event index,
@@ -157,6 +354,7 @@ impl<'txn, 'env> StorageTxn<'env, RO> {
     // upto the given to_block_number.
     fn iter_events_by_event_index(
         &'env self,
+        keys: &[HashSet<EventKey>],
         event_index: EventIndex,
         to_block_number: BlockNumber,
     ) -> StorageResult<EventIterByEventIndex<'txn, 'env>> {
@@ -164,6 +362,7 @@ impl<'txn, 'env> StorageTxn<'env, RO> {
         let mut tx_cursor = transaction_outputs_table.cursor(&self.txn)?;
         let tx_current = tx_cursor.lower_bound(&event_index.0)?;
         let events_table = self.txn.open_table(&self.tables.events)?;
+        let event_blooms_table = self.txn.open_table(&self.tables.event_blooms)?;
 
         let mut it = EventIterByEventIndex {
             txn: &self.txn,
@@ -172,6 +371,9 @@ impl<'txn, 'env> StorageTxn<'env, RO> {
             events_table,
             event_index_in_tx_current: event_index.1,
             to_block_number,
+            event_blooms_table,
+            keys: keys.to_vec(),
+            matching_block: None,
         };
         it.find_next_event_by_event_index()?;
         Ok(it)
@@ -294,6 +496,8 @@ impl From<TransactionOutput> for ThinTransactionOutput {
 
 type EventsTableKeyValue = (EventsTableKey, EventContent);
 type EventsTableCursor<'txn> = DbCursor<'txn, RO, EventsTableKey, EventContent>;
+type EventKeysTableKeyValue = (EventKeysTableKey, ContractAddress);
+type EventKeysTableCursor<'txn> = DbCursor<'txn, RO, EventKeysTableKey, ContractAddress>;
 type TransactionOutputsKeyValue = (TransactionIndex, ThinTransactionOutput);
 type TransactionOutputsTableCursor<'txn> =
     DbCursor<'txn, RO, TransactionIndex, ThinTransactionOutput>;
@@ -1,8 +1,10 @@
 use assert_matches::assert_matches;
 use starknet_api::core::{ContractAddress, PatriciaKey};
-use starknet_api::hash::StarkHash;
-use starknet_api::patricia_key;
-use starknet_api::transaction::{EventIndexInTransactionOutput, TransactionOffsetInBlock};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::transaction::{
+    EventIndexInTransactionOutput, EventKey, TransactionOffsetInBlock,
+};
+use starknet_api::{patricia_key, stark_felt};
 use test_utils::{get_rand_test_block_with_events, get_rng, get_test_block_with_events};
 
 use crate::body::events::EventsReader;
@@ -56,7 +58,9 @@ async fn iter_events_by_key() {
         EventIndexInTransactionOutput(0),
     );
     let txn = storage_reader.begin_ro_txn().unwrap();
-    for (i, e) in txn.iter_events(Some(address), event_index, block_number).unwrap().enumerate() {
+    for (i, e) in
+        txn.iter_events(Some(address), &[], event_index, block_number).unwrap().enumerate()
+    {
         assert_eq!(emitted_events[i], e);
     }
 }
@@ -96,11 +100,60 @@ async fn iter_events_by_index() {
         EventIndexInTransactionOutput(2),
     );
     let txn = storage_reader.begin_ro_txn().unwrap();
-    for (i, e) in txn.iter_events(None, event_index, block_number).unwrap().enumerate() {
+    for (i, e) in txn.iter_events(None, &[], event_index, block_number).unwrap().enumerate() {
         assert_eq!(emitted_events[i], e);
     }
 }
 
+#[tokio::test]
+async fn iter_events_by_event_keys() {
+    let (storage_reader, mut storage_writer) = get_test_storage();
+    let key0 = EventKey(stark_felt!("0x6"));
+    let key1 = EventKey(stark_felt!("0x7"));
+    let key2 = EventKey(stark_felt!("0x8"));
+    let keys = vec![vec![key0.clone(), key1.clone()], vec![key0.clone(), key2.clone()]];
+    let mut rng = get_rng();
+    let block = get_rand_test_block_with_events(&mut rng, 2, 5, None, Some(keys));
+    let block_number = block.header.block_number;
+    storage_writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block_number, &block.header)
+        .unwrap()
+        .append_body(block_number, block.body.clone())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    // The events with key0 or key2 in any position, starting from event index ((0,0),1), each
+    // once even if it has both keys.
+    let mut emitted_events = vec![];
+    for (tx_i, tx_output) in block.body.transaction_outputs.iter().enumerate() {
+        for (event_i, event) in tx_output.events().iter().enumerate() {
+            if tx_i == 0 && event_i < 1 {
+                continue;
+            }
+            if !event.content.keys.contains(&key0) && !event.content.keys.contains(&key2) {
+                continue;
+            }
+            let event_index = EventIndex(
+                TransactionIndex(block_number, TransactionOffsetInBlock(tx_i)),
+                EventIndexInTransactionOutput(event_i),
+            );
+            emitted_events.push(((event.from_address, event_index), event.content.clone()))
+        }
+    }
+
+    let event_index = EventIndex(
+        TransactionIndex(block_number, TransactionOffsetInBlock(0)),
+        EventIndexInTransactionOutput(1),
+    );
+    let txn = storage_reader.begin_ro_txn().unwrap();
+    let events: Vec<_> =
+        txn.iter_events_by_keys(&[key0, key2], event_index, block_number).unwrap().collect();
+    assert_eq!(events, emitted_events);
+}
+
 #[tokio::test]
 async fn revert_events() {
     let (storage_reader, mut storage_writer) = get_test_storage();
@@ -122,15 +175,13 @@ async fn revert_events() {
     );
 
     // Test iter events using the storage reader.
-    assert!(
-        storage_reader
-            .begin_ro_txn()
-            .unwrap()
-            .iter_events(None, event_index, block_number)
-            .unwrap()
-            .last()
-            .is_some()
-    );
+    assert!(storage_reader
+        .begin_ro_txn()
+        .unwrap()
+        .iter_events(None, &[], event_index, block_number)
+        .unwrap()
+        .last()
+        .is_some());
 
     // Test events raw table.
     let txn = storage_reader.begin_ro_txn().unwrap();
@@ -157,15 +208,13 @@ async fn revert_events() {
         .0
         .commit()
         .unwrap();
-    assert!(
-        storage_reader
-            .begin_ro_txn()
-            .unwrap()
-            .iter_events(None, event_index, block_number)
-            .unwrap()
-            .last()
-            .is_none()
-    );
+    assert!(storage_reader
+        .begin_ro_txn()
+        .unwrap()
+        .iter_events(None, &[], event_index, block_number)
+        .unwrap()
+        .last()
+        .is_none());
 
     let txn = storage_reader.begin_ro_txn().unwrap();
     let events_table = txn.txn.open_table(&txn.tables.events).unwrap();
@@ -176,4 +225,5 @@ async fn revert_events() {
             assert_matches!(events_table.get(&txn.txn, &(event.from_address, event_key)), Ok(None));
         }
     }
+    assert_eq!(storage_reader.db_tables_stats().unwrap().stats["event_keys"].entries, 0);
 }
@@ -1,3 +1,4 @@
+pub mod bloom;
 #[cfg(test)]
 #[path = "body_test.rs"]
 mod body_test;
@@ -6,22 +7,35 @@ pub mod events;
 use starknet_api::block::{BlockBody, BlockNumber};
 use starknet_api::core::ContractAddress;
 use starknet_api::transaction::{
-    Event, EventContent, EventIndexInTransactionOutput, Transaction, TransactionHash,
+    Event, EventContent, EventIndexInTransactionOutput, EventKey, Transaction, TransactionHash,
     TransactionOffsetInBlock, TransactionOutput,
 };
-use tracing::debug;
+use tracing::{debug, info};
 
+use crate::body::bloom::EventBloom;
 use crate::body::events::ThinTransactionOutput;
 use crate::db::{DbError, DbTransaction, TableHandle, TransactionKind, RW};
 use crate::{
-    EventIndex, MarkerKind, MarkersTable, StorageError, StorageResult, StorageTxn, TransactionIndex,
+    EventIndex, MarkerKind, MarkersTable, StorageError, StorageReader, StorageResult, StorageTxn,
+    StorageWriter, TransactionIndex,
 };
 
 type TransactionsTable<'env> = TableHandle<'env, TransactionIndex, Transaction>;
 type TransactionOutputsTable<'env> = TableHandle<'env, TransactionIndex, ThinTransactionOutput>;
 type TransactionHashToIdxTable<'env> = TableHandle<'env, TransactionHash, TransactionIndex>;
+// Indexes the transactions by the account that sent them.
+type SenderTransactionsTable<'env> =
+    TableHandle<'env, (ContractAddress, TransactionIndex), TransactionHash>;
 type EventsTableKey = (ContractAddress, EventIndex);
 type EventsTable<'env> = TableHandle<'env, EventsTableKey, EventContent>;
+type EventBloomsTable<'env> = TableHandle<'env, BlockNumber, EventBloom>;
+type EventKeysTableKey = (EventKey, EventIndex);
+// Indexes the events by their keys. The value is the contract address that emitted the event, to
+// find the event in the events table.
+type EventKeysTable<'env> = TableHandle<'env, EventKeysTableKey, ContractAddress>;
+
+// The number of blocks added to an index in each write transaction of its backfill.
+const BACKFILL_BATCH_SIZE: u64 = 1000;
 
 pub trait BodyStorageReader {
     // The block number marker is the first block number that doesn't exist yet.
@@ -48,6 +62,16 @@ pub trait BodyStorageReader {
         tx_hash: &TransactionHash,
     ) -> StorageResult<Option<TransactionIndex>>;
 
+    // Returns the indices and hashes of the transactions sent by the account, from the given
+    // transaction index up to the given block, inclusive, and at most limit of them.
+    fn get_transactions_by_sender(
+        &self,
+        sender: &ContractAddress,
+        from_transaction_index: TransactionIndex,
+        to_block_number: BlockNumber,
+        limit: usize,
+    ) -> StorageResult<Vec<(TransactionIndex, TransactionHash)>>;
+
     fn get_block_transactions(
         &self,
         block_number: BlockNumber,
@@ -133,6 +157,27 @@ impl<'env, Mode: TransactionKind> BodyStorageReader for StorageTxn<'env, Mode> {
         Ok(idx)
     }
 
+    fn get_transactions_by_sender(
+        &self,
+        sender: &ContractAddress,
+        from_transaction_index: TransactionIndex,
+        to_block_number: BlockNumber,
+        limit: usize,
+    ) -> StorageResult<Vec<(TransactionIndex, TransactionHash)>> {
+        let sender_transactions_table = self.txn.open_table(&self.tables.sender_transactions)?;
+        let mut cursor = sender_transactions_table.cursor(&self.txn)?;
+        let mut current = cursor.lower_bound(&(*sender, from_transaction_index))?;
+        let mut res = Vec::new();
+        while let Some(((current_sender, tx_index), tx_hash)) = current {
+            if current_sender != *sender || tx_index.0 > to_block_number || res.len() == limit {
+                break;
+            }
+            res.push((tx_index, tx_hash));
+            current = cursor.next()?;
+        }
+        Ok(res)
+    }
+
     fn get_block_transactions(
         &self,
         block_number: BlockNumber,
@@ -184,15 +229,24 @@ impl<'env> BodyStorageWriter for StorageTxn<'env, RW> {
         let transactions_table = self.txn.open_table(&self.tables.transactions)?;
         let transaction_outputs_table = self.txn.open_table(&self.tables.transaction_outputs)?;
         let events_table = self.txn.open_table(&self.tables.events)?;
+        let event_keys_table = self.txn.open_table(&self.tables.event_keys)?;
+        let event_blooms_table = self.txn.open_table(&self.tables.event_blooms)?;
         let transaction_hash_to_idx_table =
             self.txn.open_table(&self.tables.transaction_hash_to_idx)?;
+        let sender_transactions_table = self.txn.open_table(&self.tables.sender_transactions)?;
 
         update_marker(&self.txn, &markers_table, block_number)?;
+        let event_bloom = EventBloom::new(
+            &self.event_bloom_config,
+            block_body.transaction_outputs.iter().flat_map(|tx_output| tx_output.events()),
+        );
+        event_blooms_table.insert(&self.txn, &block_number, &event_bloom)?;
         write_transactions(
             &block_body,
             &self.txn,
             &transactions_table,
             &transaction_hash_to_idx_table,
+            &sender_transactions_table,
             block_number,
         )?;
         write_transaction_outputs(
@@ -200,6 +254,7 @@ impl<'env> BodyStorageWriter for StorageTxn<'env, RW> {
             &self.txn,
             &transaction_outputs_table,
             &events_table,
+            &event_keys_table,
             block_number,
         )?;
 
@@ -216,6 +271,9 @@ impl<'env> BodyStorageWriter for StorageTxn<'env, RW> {
         let transaction_hash_to_idx_table =
             self.txn.open_table(&self.tables.transaction_hash_to_idx)?;
         let events_table = self.txn.open_table(&self.tables.events)?;
+        let event_keys_table = self.txn.open_table(&self.tables.event_keys)?;
+        let event_blooms_table = self.txn.open_table(&self.tables.event_blooms)?;
+        let sender_transactions_table = self.txn.open_table(&self.tables.sender_transactions)?;
 
         // Assert that body marker equals the reverted block number + 1
         let current_header_marker = self.get_body_marker()?;
@@ -236,31 +294,35 @@ impl<'env> BodyStorageWriter for StorageTxn<'env, RW> {
 
         // Delete the transactions data.
         let mut events = vec![];
-        for (offset, (tx_output, tx_hash)) in transaction_outputs
-            .iter()
-            .zip(transactions.iter().map(|t| t.transaction_hash()))
-            .enumerate()
+        for (offset, (tx_output, tx)) in
+            transaction_outputs.iter().zip(transactions.iter()).enumerate()
         {
             let tx_index = TransactionIndex(block_number, TransactionOffsetInBlock(offset));
             let mut tx_events = vec![];
             for (index, from_address) in
                 tx_output.events_contract_addresses_as_ref().iter().enumerate()
             {
-                let key =
-                    (*from_address, EventIndex(tx_index, EventIndexInTransactionOutput(index)));
-                tx_events.push(
-                    events_table
-                        .get(&self.txn, &key)?
-                        .expect("Missing events for transaction output {tx_index}."),
-                );
+                let event_index = EventIndex(tx_index, EventIndexInTransactionOutput(index));
+                let key = (*from_address, event_index);
+                let content = events_table
+                    .get(&self.txn, &key)?
+                    .expect("Missing events for transaction output {tx_index}.");
+                for event_key in &content.keys {
+                    event_keys_table.delete(&self.txn, &(event_key.clone(), event_index))?;
+                }
+                tx_events.push(content);
                 events_table.delete(&self.txn, &key)?;
             }
             events.push(tx_events);
             transactions_table.delete(&self.txn, &tx_index)?;
             transaction_outputs_table.delete(&self.txn, &tx_index)?;
-            transaction_hash_to_idx_table.delete(&self.txn, &tx_hash)?;
+            transaction_hash_to_idx_table.delete(&self.txn, &tx.transaction_hash())?;
+            if let Some(sender) = transaction_sender(tx) {
+                sender_transactions_table.delete(&self.txn, &(sender, tx_index))?;
+            }
         }
 
+        event_blooms_table.delete(&self.txn, &block_number)?;
         markers_table.upsert(&self.txn, &MarkerKind::Body, &block_number)?;
         Ok((self, Some((transactions, transaction_outputs, events))))
     }
@@ -271,6 +333,7 @@ fn write_transactions<'env>(
     txn: &DbTransaction<'env, RW>,
     transactions_table: &'env TransactionsTable<'env>,
     transaction_hash_to_idx_table: &'env TransactionHashToIdxTable<'env>,
+    sender_transactions_table: &'env SenderTransactionsTable<'env>,
     block_number: BlockNumber,
 ) -> StorageResult<()> {
     for (index, tx) in block_body.transactions.iter().enumerate() {
@@ -278,6 +341,13 @@ fn write_transactions<'env>(
         let transaction_index = TransactionIndex(block_number, tx_offset_in_block);
         transactions_table.insert(txn, &transaction_index, tx)?;
         update_tx_hash_mapping(txn, transaction_hash_to_idx_table, tx, transaction_index)?;
+        if let Some(sender) = transaction_sender(tx) {
+            sender_transactions_table.insert(
+                txn,
+                &(sender, transaction_index),
+                &tx.transaction_hash(),
+            )?;
+        }
     }
     Ok(())
 }
@@ -287,12 +357,13 @@ fn write_transaction_outputs<'env>(
     txn: &DbTransaction<'env, RW>,
     transaction_outputs_table: &'env TransactionOutputsTable<'env>,
     events_table: &'env EventsTable<'env>,
+    event_keys_table: &'env EventKeysTable<'env>,
     block_number: BlockNumber,
 ) -> StorageResult<()> {
     for (index, tx_output) in block_body.transaction_outputs.into_iter().enumerate() {
         let transaction_index = TransactionIndex(block_number, TransactionOffsetInBlock(index));
 
-        write_events(&tx_output, txn, events_table, transaction_index)?;
+        write_events(&tx_output, txn, events_table, event_keys_table, transaction_index)?;
         transaction_outputs_table.insert(
             txn,
             &transaction_index,
@@ -306,15 +377,114 @@ fn write_events<'env>(
     tx_output: &TransactionOutput,
     txn: &DbTransaction<'env, RW>,
     events_table: &'env EventsTable<'env>,
+    event_keys_table: &'env EventKeysTable<'env>,
     transaction_index: TransactionIndex,
 ) -> StorageResult<()> {
     for (index, event) in tx_output.events().iter().enumerate() {
         let event_index = EventIndex(transaction_index, EventIndexInTransactionOutput(index));
         events_table.insert(txn, &(event.from_address, event_index), &event.content)?;
+        // An event may have the same key in several positions, so its entry may already exist.
+        for key in &event.content.keys {
+            event_keys_table.upsert(txn, &(key.clone(), event_index), &event.from_address)?;
+        }
     }
     Ok(())
 }
 
+/// Adds the events of all the stored blocks to the index of the events by their keys, for a storage
+/// written before the index was added.
+pub(crate) fn backfill_event_keys(
+    reader: &StorageReader,
+    writer: &mut StorageWriter,
+) -> StorageResult<()> {
+    let body_marker = reader.begin_ro_txn()?.get_body_marker()?;
+    let mut block_number = BlockNumber(0);
+    while block_number < body_marker {
+        let batch_end = BlockNumber((block_number.0 + BACKFILL_BATCH_SIZE).min(body_marker.0));
+        let txn = writer.begin_rw_txn()?;
+        while block_number < batch_end {
+            index_block_event_keys(&txn, block_number)?;
+            block_number = block_number.next();
+        }
+        txn.commit()?;
+        info!("Indexed the event keys up to block {block_number}.");
+    }
+    Ok(())
+}
+
+fn index_block_event_keys(
+    txn: &StorageTxn<'_, RW>,
+    block_number: BlockNumber,
+) -> StorageResult<()> {
+    let event_keys_table = txn.txn.open_table(&txn.tables.event_keys)?;
+    let transactions_count =
+        txn.get_block_transaction_outputs(block_number)?.unwrap_or_default().len();
+    for offset in 0..transactions_count {
+        let transaction_index = TransactionIndex(block_number, TransactionOffsetInBlock(offset));
+        let events = txn.get_transaction_events(transaction_index)?.unwrap_or_default();
+        for (index, event) in events.iter().enumerate() {
+            let event_index = EventIndex(transaction_index, EventIndexInTransactionOutput(index));
+            for key in &event.content.keys {
+                event_keys_table.upsert(
+                    &txn.txn,
+                    &(key.clone(), event_index),
+                    &event.from_address,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Adds the transactions of all the stored blocks to the index of the transactions by their sender,
+/// for a storage written before the index was added.
+pub(crate) fn backfill_sender_transactions(
+    reader: &StorageReader,
+    writer: &mut StorageWriter,
+) -> StorageResult<()> {
+    let body_marker = reader.begin_ro_txn()?.get_body_marker()?;
+    let mut block_number = BlockNumber(0);
+    while block_number < body_marker {
+        let batch_end = BlockNumber((block_number.0 + BACKFILL_BATCH_SIZE).min(body_marker.0));
+        let txn = writer.begin_rw_txn()?;
+        while block_number < batch_end {
+            index_block_senders(&txn, block_number)?;
+            block_number = block_number.next();
+        }
+        txn.commit()?;
+        info!("Indexed the transaction senders up to block {block_number}.");
+    }
+    Ok(())
+}
+
+fn index_block_senders(txn: &StorageTxn<'_, RW>, block_number: BlockNumber) -> StorageResult<()> {
+    let sender_transactions_table = txn.txn.open_table(&txn.tables.sender_transactions)?;
+    let transactions = txn.get_block_transactions(block_number)?.unwrap_or_default();
+    for (offset, tx) in transactions.iter().enumerate() {
+        if let Some(sender) = transaction_sender(tx) {
+            let transaction_index =
+                TransactionIndex(block_number, TransactionOffsetInBlock(offset));
+            sender_transactions_table.upsert(
+                &txn.txn,
+                &(sender, transaction_index),
+                &tx.transaction_hash(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Returns the account that sent the transaction. Deploy and L1 handler transactions aren't sent by
+// an account.
+pub(crate) fn transaction_sender(tx: &Transaction) -> Option<ContractAddress> {
+    match tx {
+        Transaction::Declare(tx) => Some(tx.sender_address),
+        Transaction::DeployAccount(tx) => Some(tx.contract_address),
+        Transaction::Invoke(tx) => Some(tx.sender_address),
+        Transaction::Deploy(_) | Transaction::L1Handler(_) => None,
+    }
+}
+
 fn update_tx_hash_mapping<'env>(
     txn: &DbTransaction<'env, RW>,
     transaction_hash_to_idx_table: &'env TransactionHashToIdxTable<'env>,
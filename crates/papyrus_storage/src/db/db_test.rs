@@ -94,3 +94,24 @@ fn table_stats() {
     assert_eq!(empty_stat.overflow_pages, 0);
     assert_eq!(empty_stat.leaf_pages, 0);
 }
+
+#[test]
+fn rewrite_raw_values_in_batches() {
+    let (reader, mut writer) = get_test_env();
+    let table_id = writer.create_table::<[u8; 1], [u8; 1]>("table").unwrap();
+    let wtxn = writer.begin_rw_txn().unwrap();
+    let table = wtxn.open_table(&table_id).unwrap();
+    for i in 0..5 {
+        table.insert(&wtxn, &[i], &[i]).unwrap();
+    }
+    wtxn.commit().unwrap();
+
+    // A batch size that doesn't divide the number of entries.
+    writer.rewrite_raw_values("table", 2, |value| Some(vec![value[0] + 10])).unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    let table = txn.open_table(&table_id).unwrap();
+    for i in 0..5 {
+        assert_eq!(table.get(&txn, &[i]).unwrap(), Some([i + 10]));
+    }
+}
@@ -21,7 +21,7 @@ use crate::db::serialization::{StorageSerde, StorageSerdeEx};
 
 // Maximum number of Sub-Databases.
 // TODO(spapini): Get these from configuration, and have a separate test configuration.
-const MAX_DBS: usize = 21;
+const MAX_DBS: usize = 32;
 const MIN_SIZE: usize = 1 << 20; // Minimum db size 1MB;
 const GROWTH_STEP: isize = 1 << 26; // Growth step 64MB;
 
@@ -125,6 +125,76 @@ impl DbWriter {
         txn.commit()?;
         Ok(TableIdentifier { name, _key_type: PhantomData {}, _value_type: PhantomData {} })
     }
+
+    /// Inserts serialized entries into a table in a single transaction, overwriting existing
+    /// entries with the same keys.
+    pub(crate) fn insert_raw_entries(
+        &mut self,
+        name: &str,
+        entries: &[(Vec<u8>, Vec<u8>)],
+    ) -> Result<()> {
+        let txn = self.env.begin_rw_txn()?;
+        let database = txn.open_db(Some(name))?;
+        for (key, value) in entries {
+            txn.put(&database, key, value, WriteFlags::UPSERT)?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Removes all the entries of the tables in a single transaction.
+    pub(crate) fn clear_tables(&mut self, names: &[&str]) -> Result<()> {
+        let txn = self.env.begin_rw_txn()?;
+        for name in names {
+            let database = txn.open_db(Some(name))?;
+            txn.clear_db(&database)?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Rewrites the serialized values of a table, in a write transaction per `batch_size` entries.
+    /// Fails if `rewrite` returns None for a value.
+    pub(crate) fn rewrite_raw_values(
+        &mut self,
+        name: &str,
+        batch_size: usize,
+        rewrite: impl Fn(&[u8]) -> Option<Vec<u8>>,
+    ) -> Result<()> {
+        let mut last_key: Option<Vec<u8>> = None;
+        loop {
+            let txn = self.env.begin_rw_txn()?;
+            let database = txn.open_db(Some(name))?;
+            let mut batch = Vec::with_capacity(batch_size);
+            {
+                let mut cursor = txn.cursor(&database)?;
+                let mut entry = match &last_key {
+                    None => cursor.first::<DbKeyType<'_>, DbValueType<'_>>()?,
+                    // Skip the last entry of the previous batch.
+                    Some(key) => match cursor.set_range::<DbKeyType<'_>, DbValueType<'_>>(key)? {
+                        Some(_) => cursor.next::<DbKeyType<'_>, DbValueType<'_>>()?,
+                        None => None,
+                    },
+                };
+                while let Some((key, value)) = entry {
+                    let new_value = rewrite(&value).ok_or(DbError::InnerDeserialization)?;
+                    batch.push((key.into_owned(), new_value));
+                    if batch.len() == batch_size {
+                        break;
+                    }
+                    entry = cursor.next::<DbKeyType<'_>, DbValueType<'_>>()?;
+                }
+            }
+            for (key, value) in &batch {
+                txn.put(&database, key, value, WriteFlags::UPSERT)?;
+            }
+            txn.commit()?;
+            if batch.len() < batch_size {
+                return Ok(());
+            }
+            last_key = batch.pop().map(|(key, _)| key);
+        }
+    }
 }
 
 type DbWriteTransaction<'env> = DbTransaction<'env, RW>;
@@ -153,6 +223,23 @@ impl<'a, Mode: TransactionKind> DbTransaction<'a, Mode> {
         let database = self.txn.open_db(Some(table_id.name))?;
         Ok(TableHandle { database, _key_type: PhantomData {}, _value_type: PhantomData {} })
     }
+
+    /// Calls `f` with the serialized key and value of every entry in a table, in the order of the
+    /// keys.
+    pub(crate) fn for_each_raw_entry<E: From<DbError>>(
+        &self,
+        name: &str,
+        mut f: impl FnMut(&[u8], &[u8]) -> result::Result<(), E>,
+    ) -> result::Result<(), E> {
+        let database = self.txn.open_db(Some(name)).map_err(DbError::from)?;
+        let mut cursor = self.txn.cursor(&database).map_err(DbError::from)?;
+        while let Some((key, value)) =
+            cursor.next::<DbKeyType<'_>, DbValueType<'_>>().map_err(DbError::from)?
+        {
+            f(&key, &value)?;
+        }
+        Ok(())
+    }
 }
 
 pub struct TableIdentifier<K: StorageSerde, V: StorageSerde> {
@@ -0,0 +1,491 @@
+//! An offline check of the invariants between the tables of the storage, for finding corruption
+//! after a crash in the middle of a write or a faulty disk.
+//!
+//! The check reads the whole storage in a single read transaction, so it can run while the node is
+//! running, and reports every violated invariant it finds instead of stopping at the first one.
+
+#[cfg(test)]
+#[path = "integrity_test.rs"]
+mod integrity_test;
+
+use serde::Serialize;
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::{
+    EventIndexInTransactionOutput, EventKey, TransactionHash, TransactionOffsetInBlock,
+};
+use tracing::info;
+
+use crate::body::{transaction_sender, BodyStorageReader};
+use crate::db::{TableHandle, RO};
+use crate::header::HeaderStorageReader;
+use crate::pruning::PruningStorageReader;
+use crate::state::data::CompiledClassHash;
+use crate::state::StateStorageReader;
+use crate::{EventIndex, StorageReader, StorageResult, StorageTxn, TransactionIndex};
+
+/// The result of [`check_storage_integrity`].
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct IntegrityReport {
+    pub header_marker: BlockNumber,
+    pub body_marker: BlockNumber,
+    pub state_marker: BlockNumber,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    pub fn is_consistent(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// A violated invariant between the tables of the storage.
+#[derive(thiserror::Error, Clone, Debug, Serialize, PartialEq, Eq)]
+pub enum IntegrityIssue {
+    #[error(
+        "The body marker {body_marker} and state marker {state_marker} can't be after the header \
+         marker {header_marker}."
+    )]
+    MarkersOutOfOrder {
+        header_marker: BlockNumber,
+        body_marker: BlockNumber,
+        state_marker: BlockNumber,
+    },
+    #[error("Missing header of block {block_number}.")]
+    MissingHeader { block_number: BlockNumber },
+    #[error(
+        "Block hash {block_hash} of block {block_number} is mapped to {mapped_block_number:?}."
+    )]
+    WrongBlockHashMapping {
+        block_hash: BlockHash,
+        block_number: BlockNumber,
+        mapped_block_number: Option<BlockNumber>,
+    },
+    #[error("Block hash {block_hash} is mapped to block {block_number}, which has another hash.")]
+    DanglingBlockHashMapping { block_hash: BlockHash, block_number: BlockNumber },
+    #[error(
+        "Block {block_number} has {transactions} transactions and {transaction_outputs} \
+         transaction outputs."
+    )]
+    TransactionOutputsCountMismatch {
+        block_number: BlockNumber,
+        transactions: usize,
+        transaction_outputs: usize,
+    },
+    #[error(
+        "Transaction hash {tx_hash:?} of transaction {transaction_index:?} is mapped to \
+         {mapped_index:?}."
+    )]
+    WrongTransactionHashMapping {
+        tx_hash: TransactionHash,
+        transaction_index: TransactionIndex,
+        mapped_index: Option<TransactionIndex>,
+    },
+    #[error(
+        "Transaction hash {tx_hash:?} is mapped to transaction {transaction_index:?}, which \
+         doesn't exist or has another hash."
+    )]
+    DanglingTransactionHashMapping {
+        tx_hash: TransactionHash,
+        transaction_index: TransactionIndex,
+    },
+    #[error(
+        "Transaction {transaction_index:?} sent by {sender:?} is missing from the sender \
+         transactions table."
+    )]
+    MissingSenderTransaction { sender: ContractAddress, transaction_index: TransactionIndex },
+    #[error(
+        "Sender {sender:?} is mapped to transaction {transaction_index:?}, which doesn't exist or \
+         has another sender or hash."
+    )]
+    DanglingSenderTransaction {
+        sender: ContractAddress,
+        transaction_index: TransactionIndex,
+        tx_hash: TransactionHash,
+    },
+    #[error("Missing event {event_index:?} emitted from contract address {from_address:?}.")]
+    MissingEvent { event_index: EventIndex, from_address: ContractAddress },
+    #[error(
+        "Event {event_index:?} emitted from contract address {from_address:?} doesn't match its \
+         transaction output."
+    )]
+    DanglingEvent { event_index: EventIndex, from_address: ContractAddress },
+    #[error("Key {key:?} of event {event_index:?} is missing from the event keys table.")]
+    MissingEventKey { key: EventKey, event_index: EventIndex },
+    #[error(
+        "Key {key:?} in the event keys table points to event {event_index:?} emitted from contract \
+         address {from_address:?}, which doesn't exist or doesn't have the key."
+    )]
+    DanglingEventKey { key: EventKey, event_index: EventIndex, from_address: ContractAddress },
+    #[error("Missing state diff of block {block_number}.")]
+    MissingStateDiff { block_number: BlockNumber },
+    #[error(
+        "Contract {address:?} deployed with class {class_hash:?} at block {block_number} doesn't \
+         match the deployed contracts table."
+    )]
+    WrongDeployedContract {
+        address: ContractAddress,
+        class_hash: ClassHash,
+        block_number: BlockNumber,
+    },
+    #[error(
+        "Contract {address:?} in the deployed contracts table isn't deployed by the state diff of \
+         block {block_number}."
+    )]
+    DanglingDeployedContract { address: ContractAddress, block_number: BlockNumber },
+    #[error("Class {class_hash:?} declared at block {block_number} is missing.")]
+    MissingDeclaredClass { class_hash: ClassHash, block_number: BlockNumber },
+    #[error(
+        "Compiled class hash of class {class_hash:?} declared at block {block_number} is \
+         {stored_compiled_class_hash:?}, expected {compiled_class_hash:?}."
+    )]
+    WrongCompiledClassHash {
+        class_hash: ClassHash,
+        block_number: BlockNumber,
+        compiled_class_hash: CompiledClassHash,
+        stored_compiled_class_hash: Option<CompiledClassHash>,
+    },
+    #[error(
+        "Class of contract {address:?} replaced at block {block_number} is \
+         {stored_class_hash:?}, expected {class_hash:?}."
+    )]
+    WrongReplacedClass {
+        address: ContractAddress,
+        block_number: BlockNumber,
+        class_hash: ClassHash,
+        stored_class_hash: Option<ClassHash>,
+    },
+    #[error(
+        "Nonce of contract {address:?} at block {block_number} is {stored_nonce:?}, expected \
+         {nonce:?}."
+    )]
+    WrongNonce {
+        address: ContractAddress,
+        block_number: BlockNumber,
+        nonce: Nonce,
+        stored_nonce: Option<Nonce>,
+    },
+    #[error(
+        "Storage key {key:?} of contract {address:?} at block {block_number} is \
+         {stored_value:?}, expected {value:?}."
+    )]
+    WrongStorageValue {
+        address: ContractAddress,
+        key: StorageKey,
+        block_number: BlockNumber,
+        value: StarkFelt,
+        stored_value: Option<StarkFelt>,
+    },
+}
+
+/// Checks the invariants between the tables of the storage:
+/// * The body and state markers aren't after the header marker.
+/// * The block hash mapping matches the headers.
+/// * The transaction hash mapping matches the transactions.
+/// * The events match the transaction outputs, and the event keys table indexes their keys.
+/// * The state diffs match the deployed contracts, declared classes, Sierra classes, compiled class
+///   hashes, replaced classes, nonces and storage tables. The nonces, storage values and replaced
+///   classes are only checked from the pruned state marker on.
+pub fn check_storage_integrity(reader: &StorageReader) -> StorageResult<IntegrityReport> {
+    let txn = reader.begin_ro_txn()?;
+    let mut report = IntegrityReport {
+        header_marker: txn.get_header_marker()?,
+        body_marker: txn.get_body_marker()?,
+        state_marker: txn.get_state_marker()?,
+        issues: Vec::new(),
+    };
+    if report.body_marker > report.header_marker || report.state_marker > report.header_marker {
+        report.issues.push(IntegrityIssue::MarkersOutOfOrder {
+            header_marker: report.header_marker,
+            body_marker: report.body_marker,
+            state_marker: report.state_marker,
+        });
+    }
+    check_headers(&txn, report.header_marker, &mut report.issues)?;
+    check_bodies(&txn, report.body_marker, &mut report.issues)?;
+    check_state_diffs(&txn, report.state_marker, &mut report.issues)?;
+    info!(
+        "Checked the integrity of the storage up to block {} and found {} issues.",
+        report.header_marker,
+        report.issues.len()
+    );
+    Ok(report)
+}
+
+fn check_headers<'env>(
+    txn: &'env StorageTxn<'env, RO>,
+    header_marker: BlockNumber,
+    issues: &mut Vec<IntegrityIssue>,
+) -> StorageResult<()> {
+    for block_number in (0..header_marker.0).map(BlockNumber) {
+        let header = match txn.get_block_header(block_number)? {
+            Some(header) => header,
+            None => {
+                issues.push(IntegrityIssue::MissingHeader { block_number });
+                continue;
+            }
+        };
+        let mapped_block_number = txn.get_block_number_by_hash(&header.block_hash)?;
+        if mapped_block_number != Some(block_number) {
+            issues.push(IntegrityIssue::WrongBlockHashMapping {
+                block_hash: header.block_hash,
+                block_number,
+                mapped_block_number,
+            });
+        }
+    }
+
+    let block_hash_to_number_table = txn.txn.open_table(&txn.tables.block_hash_to_number)?;
+    let mut cursor = block_hash_to_number_table.cursor(&txn.txn)?;
+    while let Some((block_hash, block_number)) = cursor.next()? {
+        let header = txn.get_block_header(block_number)?;
+        if header.map(|header| header.block_hash) != Some(block_hash) {
+            issues.push(IntegrityIssue::DanglingBlockHashMapping { block_hash, block_number });
+        }
+    }
+    Ok(())
+}
+
+fn check_bodies<'env>(
+    txn: &'env StorageTxn<'env, RO>,
+    body_marker: BlockNumber,
+    issues: &mut Vec<IntegrityIssue>,
+) -> StorageResult<()> {
+    let events_table = txn.txn.open_table(&txn.tables.events)?;
+    let sender_transactions_table = txn.txn.open_table(&txn.tables.sender_transactions)?;
+    for block_number in (0..body_marker.0).map(BlockNumber) {
+        let transactions = txn.get_block_transactions(block_number)?.unwrap_or_default();
+        let transaction_outputs =
+            txn.get_block_transaction_outputs(block_number)?.unwrap_or_default();
+        if transactions.len() != transaction_outputs.len() {
+            issues.push(IntegrityIssue::TransactionOutputsCountMismatch {
+                block_number,
+                transactions: transactions.len(),
+                transaction_outputs: transaction_outputs.len(),
+            });
+        }
+        for (offset, tx) in transactions.iter().enumerate() {
+            let transaction_index =
+                TransactionIndex(block_number, TransactionOffsetInBlock(offset));
+            let tx_hash = tx.transaction_hash();
+            let mapped_index = txn.get_transaction_idx_by_hash(&tx_hash)?;
+            if mapped_index != Some(transaction_index) {
+                issues.push(IntegrityIssue::WrongTransactionHashMapping {
+                    tx_hash,
+                    transaction_index,
+                    mapped_index,
+                });
+            }
+            if let Some(sender) = transaction_sender(tx) {
+                if sender_transactions_table.get(&txn.txn, &(sender, transaction_index))?
+                    != Some(tx_hash)
+                {
+                    issues.push(IntegrityIssue::MissingSenderTransaction {
+                        sender,
+                        transaction_index,
+                    });
+                }
+            }
+        }
+        for (offset, tx_output) in transaction_outputs.iter().enumerate() {
+            let transaction_index =
+                TransactionIndex(block_number, TransactionOffsetInBlock(offset));
+            for (index, from_address) in
+                tx_output.events_contract_addresses_as_ref().iter().enumerate()
+            {
+                let event_index =
+                    EventIndex(transaction_index, EventIndexInTransactionOutput(index));
+                if events_table.get(&txn.txn, &(*from_address, event_index))?.is_none() {
+                    issues.push(IntegrityIssue::MissingEvent {
+                        event_index,
+                        from_address: *from_address,
+                    });
+                }
+            }
+        }
+    }
+
+    let transaction_hash_to_idx_table = txn.txn.open_table(&txn.tables.transaction_hash_to_idx)?;
+    let mut cursor = transaction_hash_to_idx_table.cursor(&txn.txn)?;
+    while let Some((tx_hash, transaction_index)) = cursor.next()? {
+        let tx = txn.get_transaction(transaction_index)?;
+        if tx.map(|tx| tx.transaction_hash()) != Some(tx_hash) {
+            issues.push(IntegrityIssue::DanglingTransactionHashMapping {
+                tx_hash,
+                transaction_index,
+            });
+        }
+    }
+
+    let mut cursor = sender_transactions_table.cursor(&txn.txn)?;
+    while let Some(((sender, transaction_index), tx_hash)) = cursor.next()? {
+        let tx = txn.get_transaction(transaction_index)?;
+        if tx.map(|tx| (transaction_sender(&tx), tx.transaction_hash()))
+            != Some((Some(sender), tx_hash))
+        {
+            issues.push(IntegrityIssue::DanglingSenderTransaction {
+                sender,
+                transaction_index,
+                tx_hash,
+            });
+        }
+    }
+
+    let event_keys_table = txn.txn.open_table(&txn.tables.event_keys)?;
+    let mut cursor = events_table.cursor(&txn.txn)?;
+    while let Some(((from_address, event_index), content)) = cursor.next()? {
+        let EventIndex(transaction_index, EventIndexInTransactionOutput(index)) = event_index;
+        let tx_output = txn.get_transaction_output(transaction_index)?;
+        let emitted_from = tx_output
+            .as_ref()
+            .and_then(|tx_output| tx_output.events_contract_addresses_as_ref().get(index).copied());
+        if emitted_from != Some(from_address) {
+            issues.push(IntegrityIssue::DanglingEvent { event_index, from_address });
+        }
+        for key in content.keys {
+            if event_keys_table.get(&txn.txn, &(key.clone(), event_index))? != Some(from_address) {
+                issues.push(IntegrityIssue::MissingEventKey { key, event_index });
+            }
+        }
+    }
+
+    let mut cursor = event_keys_table.cursor(&txn.txn)?;
+    while let Some(((key, event_index), from_address)) = cursor.next()? {
+        let content = events_table.get(&txn.txn, &(from_address, event_index))?;
+        if !content.map_or(false, |content| content.keys.contains(&key)) {
+            issues.push(IntegrityIssue::DanglingEventKey { key, event_index, from_address });
+        }
+    }
+    Ok(())
+}
+
+fn check_state_diffs<'env>(
+    txn: &'env StorageTxn<'env, RO>,
+    state_marker: BlockNumber,
+    issues: &mut Vec<IntegrityIssue>,
+) -> StorageResult<()> {
+    let deployed_contracts_table = txn.txn.open_table(&txn.tables.deployed_contracts)?;
+    let declared_classes_table = txn.txn.open_table(&txn.tables.declared_classes)?;
+    let sierra_classes_table = txn.txn.open_table(&txn.tables.sierra_classes)?;
+    let compiled_class_hashes_table = txn.txn.open_table(&txn.tables.compiled_class_hashes)?;
+    let replaced_classes_table = txn.txn.open_table(&txn.tables.replaced_classes)?;
+    let nonces_table = txn.txn.open_table(&txn.tables.nonces)?;
+    let storage_table = txn.txn.open_table(&txn.tables.contract_storage)?;
+    // The pruning deletes the nonces, storage values and replaced classes that were superseded
+    // before its marker.
+    let pruned_state_marker = txn.get_pruned_state_marker()?;
+    for block_number in (0..state_marker.0).map(BlockNumber) {
+        let state_diff = match txn.get_state_diff(block_number)? {
+            Some(state_diff) => state_diff,
+            None => {
+                issues.push(IntegrityIssue::MissingStateDiff { block_number });
+                continue;
+            }
+        };
+        for (address, class_hash) in &state_diff.deployed_contracts {
+            let deployed_contract = deployed_contracts_table.get(&txn.txn, address)?;
+            if deployed_contract.map(|contract| (contract.block_number, contract.class_hash))
+                != Some((block_number, *class_hash))
+            {
+                issues.push(IntegrityIssue::WrongDeployedContract {
+                    address: *address,
+                    class_hash: *class_hash,
+                    block_number,
+                });
+            }
+            // A deployed contract starts with the default nonce, unless the diff sets it.
+            if !state_diff.nonces.contains_key(address) && block_number >= pruned_state_marker {
+                check_nonce(txn, &nonces_table, *address, block_number, Nonce::default(), issues)?;
+            }
+        }
+        for class_hash in &state_diff.declared_contract_hashes {
+            // A class may be declared again, so it's kept with the block of its first declaration.
+            let declared_class = declared_classes_table.get(&txn.txn, class_hash)?;
+            if !declared_class.map_or(false, |class| class.block_number <= block_number) {
+                issues.push(IntegrityIssue::MissingDeclaredClass {
+                    class_hash: *class_hash,
+                    block_number,
+                });
+            }
+        }
+        for (class_hash, compiled_class_hash) in &state_diff.declared_classes {
+            // A Sierra class can only be declared once.
+            let sierra_class = sierra_classes_table.get(&txn.txn, class_hash)?;
+            if sierra_class.map(|class| class.block_number) != Some(block_number) {
+                issues.push(IntegrityIssue::MissingDeclaredClass {
+                    class_hash: *class_hash,
+                    block_number,
+                });
+            }
+            let stored_compiled_class_hash =
+                compiled_class_hashes_table.get(&txn.txn, &(*class_hash, block_number))?;
+            if stored_compiled_class_hash != Some(*compiled_class_hash) {
+                issues.push(IntegrityIssue::WrongCompiledClassHash {
+                    class_hash: *class_hash,
+                    block_number,
+                    compiled_class_hash: *compiled_class_hash,
+                    stored_compiled_class_hash,
+                });
+            }
+        }
+        if block_number < pruned_state_marker {
+            continue;
+        }
+        for (address, class_hash) in &state_diff.replaced_classes {
+            let stored_class_hash =
+                replaced_classes_table.get(&txn.txn, &(*address, block_number))?;
+            if stored_class_hash != Some(*class_hash) {
+                issues.push(IntegrityIssue::WrongReplacedClass {
+                    address: *address,
+                    block_number,
+                    class_hash: *class_hash,
+                    stored_class_hash,
+                });
+            }
+        }
+        for (address, nonce) in &state_diff.nonces {
+            check_nonce(txn, &nonces_table, *address, block_number, *nonce, issues)?;
+        }
+        for (address, storage_entries) in &state_diff.storage_diffs {
+            for (key, value) in storage_entries {
+                let stored_value = storage_table.get(&txn.txn, &(*address, *key, block_number))?;
+                if stored_value != Some(*value) {
+                    issues.push(IntegrityIssue::WrongStorageValue {
+                        address: *address,
+                        key: *key,
+                        block_number,
+                        value: *value,
+                        stored_value,
+                    });
+                }
+            }
+        }
+    }
+
+    let mut cursor = deployed_contracts_table.cursor(&txn.txn)?;
+    while let Some((address, deployed_contract)) = cursor.next()? {
+        let block_number = deployed_contract.block_number;
+        let state_diff = txn.get_state_diff(block_number)?;
+        if !state_diff.map_or(false, |diff| diff.deployed_contracts.contains_key(&address)) {
+            issues.push(IntegrityIssue::DanglingDeployedContract { address, block_number });
+        }
+    }
+    Ok(())
+}
+
+fn check_nonce<'env>(
+    txn: &'env StorageTxn<'env, RO>,
+    nonces_table: &'env TableHandle<'env, (ContractAddress, BlockNumber), Nonce>,
+    address: ContractAddress,
+    block_number: BlockNumber,
+    nonce: Nonce,
+    issues: &mut Vec<IntegrityIssue>,
+) -> StorageResult<()> {
+    let stored_nonce = nonces_table.get(&txn.txn, &(address, block_number))?;
+    if stored_nonce != Some(nonce) {
+        issues.push(IntegrityIssue::WrongNonce { address, block_number, nonce, stored_nonce });
+    }
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use indexmap::IndexMap;
+use starknet_api::block::BlockNumber;
+use starknet_api::core::{ClassHash, ContractAddress, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{ContractClass, StateDiff};
+use starknet_api::transaction::{EventIndexInTransactionOutput, TransactionOffsetInBlock};
+use starknet_api::{patricia_key, stark_felt};
+use test_utils::{get_test_block_with_events, get_test_state_diff};
+
+use crate::body::BodyStorageWriter;
+use crate::header::HeaderStorageWriter;
+use crate::integrity::{check_storage_integrity, IntegrityIssue};
+use crate::state::data::{Cairo1StateDiff, CompiledClassHash, SierraContractClass};
+use crate::state::StateStorageWriter;
+use crate::test_utils::get_test_storage;
+use crate::{EventIndex, TransactionIndex};
+
+#[test]
+fn check_integrity() {
+    let (reader, mut writer) = get_test_storage();
+    let block = get_test_block_with_events(2, 1);
+    let state_diff = get_test_state_diff();
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &block.header)
+        .unwrap()
+        .append_body(BlockNumber(0), block.body.clone())
+        .unwrap()
+        .append_state_diff(BlockNumber(0), state_diff.clone(), IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let report = check_storage_integrity(&reader).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.issues);
+    assert_eq!(report.header_marker, BlockNumber(1));
+    assert_eq!(report.body_marker, BlockNumber(1));
+    assert_eq!(report.state_marker, BlockNumber(1));
+
+    // Corrupt the storage.
+    let block_hash = block.header.block_hash;
+    let event = &block.body.transaction_outputs[0].events()[0];
+    let event_index = EventIndex(
+        TransactionIndex(BlockNumber(0), TransactionOffsetInBlock(0)),
+        EventIndexInTransactionOutput(0),
+    );
+    let (address, storage_entries) = state_diff.storage_diffs.first().unwrap();
+    let (key, value) = storage_entries.first().unwrap();
+    let txn = writer.begin_rw_txn().unwrap();
+    let block_hash_to_number_table = txn.txn.open_table(&txn.tables.block_hash_to_number).unwrap();
+    block_hash_to_number_table.delete(&txn.txn, &block_hash).unwrap();
+    let events_table = txn.txn.open_table(&txn.tables.events).unwrap();
+    events_table.delete(&txn.txn, &(event.from_address, event_index)).unwrap();
+    let event_keys_table = txn.txn.open_table(&txn.tables.event_keys).unwrap();
+    for key in &event.content.keys {
+        event_keys_table.delete(&txn.txn, &(key.clone(), event_index)).unwrap();
+    }
+    let storage_table = txn.txn.open_table(&txn.tables.contract_storage).unwrap();
+    storage_table.delete(&txn.txn, &(*address, *key, BlockNumber(0))).unwrap();
+    txn.commit().unwrap();
+
+    let report = check_storage_integrity(&reader).unwrap();
+    assert_eq!(
+        report.issues,
+        vec![
+            IntegrityIssue::WrongBlockHashMapping {
+                block_hash,
+                block_number: BlockNumber(0),
+                mapped_block_number: None,
+            },
+            IntegrityIssue::MissingEvent { event_index, from_address: event.from_address },
+            IntegrityIssue::WrongStorageValue {
+                address: *address,
+                key: *key,
+                block_number: BlockNumber(0),
+                value: *value,
+                stored_value: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn check_integrity_of_cairo1_state() {
+    let address = ContractAddress(patricia_key!("0x11"));
+    let class_hash = ClassHash(stark_felt!("0x4"));
+    let sierra_class_hash = ClassHash(stark_felt!("0x5"));
+    let compiled_class_hash = CompiledClassHash(stark_felt!("0x6"));
+    let diff0 = StateDiff {
+        deployed_contracts: IndexMap::from([(address, class_hash)]),
+        declared_classes: IndexMap::from([(class_hash, ContractClass::default())]),
+        ..StateDiff::default()
+    };
+    let cairo1_diff1 = Cairo1StateDiff {
+        declared_classes: IndexMap::from([(
+            sierra_class_hash,
+            (compiled_class_hash, SierraContractClass::default()),
+        )]),
+        replaced_classes: IndexMap::from([(address, sierra_class_hash)]),
+    };
+    let (reader, mut writer) = get_test_storage();
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_state_diff(BlockNumber(0), diff0, IndexMap::new())
+        .unwrap()
+        .append_state_diff_with_cairo1(
+            BlockNumber(1),
+            StateDiff::default(),
+            cairo1_diff1,
+            IndexMap::new(),
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let report = check_storage_integrity(&reader).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.issues);
+
+    // Corrupt the storage.
+    let txn = writer.begin_rw_txn().unwrap();
+    let compiled_class_hashes_table =
+        txn.txn.open_table(&txn.tables.compiled_class_hashes).unwrap();
+    compiled_class_hashes_table.delete(&txn.txn, &(sierra_class_hash, BlockNumber(1))).unwrap();
+    let replaced_classes_table = txn.txn.open_table(&txn.tables.replaced_classes).unwrap();
+    replaced_classes_table.delete(&txn.txn, &(address, BlockNumber(1))).unwrap();
+    txn.commit().unwrap();
+
+    let report = check_storage_integrity(&reader).unwrap();
+    assert_eq!(
+        report.issues,
+        vec![
+            IntegrityIssue::WrongCompiledClassHash {
+                class_hash: sierra_class_hash,
+                block_number: BlockNumber(1),
+                compiled_class_hash,
+                stored_compiled_class_hash: None,
+            },
+            IntegrityIssue::WrongReplacedClass {
+                address,
+                block_number: BlockNumber(1),
+                class_hash: sierra_class_hash,
+                stored_class_hash: None,
+            },
+        ]
+    );
+}
@@ -1,10 +1,15 @@
+pub mod base_layer;
 pub mod body;
 pub mod compression_utils;
 pub mod db;
 pub mod header;
+pub mod integrity;
 pub mod ommer;
+pub mod pruning;
 mod serializers;
+pub mod snapshot;
 pub mod state;
+pub mod version;
 
 #[cfg(any(feature = "testing", test))]
 #[path = "test_utils.rs"]
@@ -16,28 +21,48 @@ use std::sync::Arc;
 use db::DbTableStats;
 use serde::{Deserialize, Serialize};
 use starknet_api::block::{BlockHash, BlockHeader, BlockNumber};
-use starknet_api::core::{ClassHash, ContractAddress, Nonce};
-use starknet_api::hash::StarkFelt;
+use starknet_api::core::{ClassHash, ContractAddress, GlobalRoot, Nonce};
+use starknet_api::hash::{StarkFelt, StarkHash};
 use starknet_api::state::{ContractClass, StorageKey};
 use starknet_api::transaction::{
-    EventContent, EventIndexInTransactionOutput, Transaction, TransactionHash,
+    EventContent, EventIndexInTransactionOutput, EventKey, Transaction, TransactionHash,
     TransactionOffsetInBlock,
 };
 
+use crate::body::bloom::{EventBloom, EventBloomConfig};
 use crate::body::events::ThinTransactionOutput;
 use crate::db::{
     open_env, DbConfig, DbError, DbReader, DbTransaction, DbWriter, TableHandle, TableIdentifier,
     TransactionKind, RO, RW,
 };
-use crate::state::data::{IndexedDeclaredContract, IndexedDeployedContract, ThinStateDiff};
+use crate::pruning::PruningConfig;
+use crate::snapshot::verify_no_snapshot_import_in_progress;
+use crate::state::commitment::{StateTrieRoots, TrieNode};
+use crate::state::data::{
+    CompiledClassHash, IndexedDeclaredContract, IndexedDeployedContract, IndexedSierraClass,
+    ThinStateDiff,
+};
+use crate::version::verify_storage_version;
 
+/// Opens the storage, after checking that its version matches this code, see [`version`].
 pub fn open_storage(db_config: DbConfig) -> StorageResult<(StorageReader, StorageWriter)> {
+    let (reader, mut writer) = open_storage_unchecked(db_config)?;
+    verify_storage_version(&reader, &mut writer)?;
+    verify_no_snapshot_import_in_progress(&reader.begin_ro_txn()?)?;
+    Ok((reader, writer))
+}
+
+fn open_storage_unchecked(db_config: DbConfig) -> StorageResult<(StorageReader, StorageWriter)> {
     let (db_reader, mut db_writer) = open_env(db_config)?;
     let tables = Arc::new(Tables {
         block_hash_to_number: db_writer.create_table("block_hash_to_number")?,
+        compiled_class_hashes: db_writer.create_table("compiled_class_hashes")?,
         contract_storage: db_writer.create_table("contract_storage")?,
+        contract_storage_roots: db_writer.create_table("contract_storage_roots")?,
         declared_classes: db_writer.create_table("declared_classes")?,
         deployed_contracts: db_writer.create_table("deployed_contracts")?,
+        event_blooms: db_writer.create_table("event_blooms")?,
+        event_keys: db_writer.create_table("event_keys")?,
         events: db_writer.create_table("events")?,
         headers: db_writer.create_table("headers")?,
         markers: db_writer.create_table("markers")?,
@@ -51,13 +76,21 @@ pub fn open_storage(db_config: DbConfig) -> StorageResult<(StorageReader, Storag
         ommer_state_diffs: db_writer.create_table("ommer_state_diffs")?,
         ommer_transaction_outputs: db_writer.create_table("ommer_transaction_outputs")?,
         ommer_transactions: db_writer.create_table("ommer_transactions")?,
+        replaced_classes: db_writer.create_table("replaced_classes")?,
+        sender_transactions: db_writer.create_table("sender_transactions")?,
+        sierra_classes: db_writer.create_table("sierra_classes")?,
         state_diffs: db_writer.create_table("state_diffs")?,
+        state_roots: db_writer.create_table("state_roots")?,
+        state_trie_nodes: db_writer.create_table("state_trie_nodes")?,
+        state_trie_nodes_created: db_writer.create_table("state_trie_nodes_created")?,
+        state_trie_roots: db_writer.create_table("state_trie_roots")?,
         transaction_hash_to_idx: db_writer.create_table("transaction_hash_to_idx")?,
         transaction_outputs: db_writer.create_table("transaction_outputs")?,
         transactions: db_writer.create_table("transactions")?,
     });
     let reader = StorageReader { db_reader, tables: tables.clone() };
-    let writer = StorageWriter { db_writer, tables };
+    let writer =
+        StorageWriter { db_writer, tables, event_bloom_config: EventBloomConfig::default() };
     Ok((reader, writer))
 }
 
@@ -69,7 +102,11 @@ pub struct StorageReader {
 
 impl StorageReader {
     pub fn begin_ro_txn(&self) -> StorageResult<StorageTxn<'_, RO>> {
-        Ok(StorageTxn { txn: self.db_reader.begin_ro_txn()?, tables: self.tables.clone() })
+        Ok(StorageTxn {
+            txn: self.db_reader.begin_ro_txn()?,
+            tables: self.tables.clone(),
+            event_bloom_config: EventBloomConfig::default(),
+        })
     }
 
     pub fn db_tables_stats(&self) -> StorageResult<DbTablesStats> {
@@ -84,17 +121,29 @@ impl StorageReader {
 pub struct StorageWriter {
     db_writer: DbWriter,
     tables: Arc<Tables>,
+    event_bloom_config: EventBloomConfig,
 }
 
 impl StorageWriter {
     pub fn begin_rw_txn(&mut self) -> StorageResult<StorageTxn<'_, RW>> {
-        Ok(StorageTxn { txn: self.db_writer.begin_rw_txn()?, tables: self.tables.clone() })
+        Ok(StorageTxn {
+            txn: self.db_writer.begin_rw_txn()?,
+            tables: self.tables.clone(),
+            event_bloom_config: self.event_bloom_config,
+        })
+    }
+
+    /// Sets the config of the event bloom filters of the blocks appended from now on.
+    pub fn set_event_bloom_config(&mut self, event_bloom_config: EventBloomConfig) {
+        self.event_bloom_config = event_bloom_config;
     }
 }
 
 pub struct StorageTxn<'env, Mode: TransactionKind> {
     txn: DbTransaction<'env, Mode>,
     tables: Arc<Tables>,
+    // Only used by write transactions, to build the event bloom filters of the appended blocks.
+    event_bloom_config: EventBloomConfig,
 }
 
 impl<'env> StorageTxn<'env, RW> {
@@ -110,9 +159,13 @@ pub fn table_names() -> &'static [&'static str] {
 struct_field_names! {
     struct Tables {
         block_hash_to_number: TableIdentifier<BlockHash, BlockNumber>,
+        compiled_class_hashes: TableIdentifier<(ClassHash, BlockNumber), CompiledClassHash>,
         contract_storage: TableIdentifier<(ContractAddress, StorageKey, BlockNumber), StarkFelt>,
+        contract_storage_roots: TableIdentifier<(ContractAddress, BlockNumber), StarkHash>,
         declared_classes: TableIdentifier<ClassHash, IndexedDeclaredContract>,
         deployed_contracts: TableIdentifier<ContractAddress, IndexedDeployedContract>,
+        event_blooms: TableIdentifier<BlockNumber, EventBloom>,
+        event_keys: TableIdentifier<(EventKey, EventIndex), ContractAddress>,
         events: TableIdentifier<(ContractAddress, EventIndex), EventContent>,
         headers: TableIdentifier<BlockNumber, BlockHeader>,
         markers: TableIdentifier<MarkerKind, BlockNumber>,
@@ -126,7 +179,14 @@ struct_field_names! {
         ommer_state_diffs: TableIdentifier<BlockHash, ThinStateDiff>,
         ommer_transaction_outputs: TableIdentifier<OmmerTransactionKey, ThinTransactionOutput>,
         ommer_transactions: TableIdentifier<OmmerTransactionKey, Transaction>,
+        replaced_classes: TableIdentifier<(ContractAddress, BlockNumber), ClassHash>,
+        sender_transactions: TableIdentifier<(ContractAddress, TransactionIndex), TransactionHash>,
+        sierra_classes: TableIdentifier<ClassHash, IndexedSierraClass>,
         state_diffs: TableIdentifier<BlockNumber, ThinStateDiff>,
+        state_roots: TableIdentifier<BlockNumber, GlobalRoot>,
+        state_trie_nodes: TableIdentifier<StarkHash, TrieNode>,
+        state_trie_nodes_created: TableIdentifier<BlockNumber, Vec<StarkHash>>,
+        state_trie_roots: TableIdentifier<BlockNumber, StateTrieRoots>,
         transaction_hash_to_idx: TableIdentifier<TransactionHash, TransactionIndex>,
         transaction_outputs: TableIdentifier<TransactionIndex, ThinTransactionOutput>,
         transactions: TableIdentifier<TransactionIndex, Transaction>
@@ -208,6 +268,28 @@ pub enum StorageError {
     },
     #[error("Ommer nonce of contract {contract_address:?} of block {block_hash} already exists.")]
     OmmerNonceAlreadyExists { block_hash: BlockHash, contract_address: ContractAddress },
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+    #[error("Invalid snapshot: {msg}")]
+    InvalidSnapshot { msg: String },
+    #[error("A snapshot can only be imported into an empty storage.")]
+    StorageNotEmpty,
+    #[error(
+        "The import of a snapshot into the storage didn't finish. Rerun the import_snapshot \
+         command to complete it."
+    )]
+    SnapshotImportInProgress,
+    #[error(
+        "The storage is of version {stored_version}, expected {expected_version}. Run the \
+         migrate_storage command to upgrade it."
+    )]
+    StorageVersionMismatch { stored_version: u64, expected_version: u64 },
+    #[error("No migration of the storage from version {from_version}.")]
+    MissingMigration { from_version: u64 },
+    #[error("The state of block {block_number} was pruned, so the block can't be reverted.")]
+    StatePruned { block_number: BlockNumber },
 }
 
 pub type StorageResult<V> = std::result::Result<V, StorageError>;
@@ -215,6 +297,9 @@ pub type StorageResult<V> = std::result::Result<V, StorageError>;
 #[derive(Serialize, Deserialize, Clone)]
 pub struct StorageConfig {
     pub db_config: DbConfig,
+    /// None if the storage should keep the full history of the state.
+    pub pruning: Option<PruningConfig>,
+    pub event_bloom: EventBloomConfig,
 }
 
 /// A mapping from a table name in the database to its statistics.
@@ -228,6 +313,13 @@ pub enum MarkerKind {
     Header,
     Body,
     State,
+    BaseLayerBlock,
+    // The value of this marker is the storage version, see [`version`].
+    StorageVersion,
+    // The first state number whose state wasn't pruned, see [`pruning`].
+    PrunedState,
+    // Set while a snapshot is imported, see [`snapshot`]. Its value is meaningless.
+    SnapshotImport,
 }
 
 pub type MarkersTable<'env> = TableHandle<'env, MarkerKind, BlockNumber>;
@@ -0,0 +1,295 @@
+//! Pruning of the history of the state, for nodes that don't serve queries on the state of old
+//! blocks.
+//!
+//! The storage keeps a version of a storage value, a nonce, a replaced class and a contract storage
+//! root for every block that changed it. A pruned storage keeps, for every key, only the latest
+//! version before the pruned state marker and the versions after it, so the state at any state
+//! number from the marker on can still be read. The ommer blocks before the marker are deleted
+//! entirely.
+//!
+//! Only the last blocks are kept in full, so a pruned storage can't revert a block before the
+//! marker. The history length should be longer than any expected reorg.
+//!
+//! The state diffs and the classes are kept in full. So are the nodes of the state tries: a node is
+//! addressed by its hash and shared by every trie and block that has the same subtree, so a node
+//! that a block replaced may still be reachable from another root, and deleting it would need a
+//! reference count per node. Only the records of the nodes each pruned block created, which are
+//! needed just to revert it, are deleted.
+
+#[cfg(test)]
+#[path = "pruning_test.rs"]
+mod pruning_test;
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::core::{ClassHash, ContractAddress};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::{EventIndexInTransactionOutput, TransactionOffsetInBlock};
+use tracing::debug;
+
+use crate::db::serialization::StorageSerde;
+use crate::db::{DbTransaction, TableHandle, TransactionKind, RW};
+use crate::state::StateStorageReader;
+use crate::{
+    MarkerKind, OmmerEventKey, OmmerTransactionKey, StorageError, StorageResult, StorageTxn,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PruningConfig {
+    /// The number of latest blocks whose state history is kept.
+    pub history_length: u64,
+    /// The number of deleted entries after which a write transaction of the pruning is committed.
+    pub max_entries_per_txn: usize,
+    /// The time between pruning passes once there is nothing to prune.
+    pub sleep_duration: Duration,
+}
+
+impl Default for PruningConfig {
+    fn default() -> Self {
+        Self {
+            history_length: 1000,
+            max_entries_per_txn: 10000,
+            sleep_duration: Duration::from_secs(60),
+        }
+    }
+}
+
+pub trait PruningStorageReader {
+    // The pruned state marker is the first state number whose state is kept in full. The state
+    // before it can't be read.
+    fn get_pruned_state_marker(&self) -> StorageResult<BlockNumber>;
+}
+
+pub trait PruningStorageWriter
+where
+    Self: Sized,
+{
+    /// Prunes the state of the blocks that are older than the history length, until about
+    /// `max_entries_per_txn` entries are deleted. Returns whether there is more to prune.
+    // To enforce that no commit happen after a failure, we consume and return Self on success.
+    fn prune(self, config: &PruningConfig) -> StorageResult<(Self, bool)>;
+}
+
+impl<'env, Mode: TransactionKind> PruningStorageReader for StorageTxn<'env, Mode> {
+    fn get_pruned_state_marker(&self) -> StorageResult<BlockNumber> {
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        Ok(markers_table.get(&self.txn, &MarkerKind::PrunedState)?.unwrap_or_default())
+    }
+}
+
+impl<'env> PruningStorageWriter for StorageTxn<'env, RW> {
+    fn prune(self, config: &PruningConfig) -> StorageResult<(Self, bool)> {
+        let markers_table = self.txn.open_table(&self.tables.markers)?;
+        let target = BlockNumber(self.get_state_marker()?.0.saturating_sub(config.history_length));
+        let mut pruned_state_marker = self.get_pruned_state_marker()?;
+
+        // Once the marker passes a block, the versions the block changed supersede the versions
+        // before them.
+        let mut deleted_entries = 0;
+        while pruned_state_marker < target && deleted_entries < config.max_entries_per_txn {
+            deleted_entries += prune_block_state(&self, pruned_state_marker)?;
+            pruned_state_marker = pruned_state_marker.next();
+        }
+        markers_table.upsert(&self.txn, &MarkerKind::PrunedState, &pruned_state_marker)?;
+
+        let mut ommer_blocks = get_ommer_blocks_before(&self, pruned_state_marker)?.into_iter();
+        while deleted_entries < config.max_entries_per_txn {
+            match ommer_blocks.next() {
+                Some(block_hash) => deleted_entries += delete_ommer_block(&self, block_hash)?,
+                None => break,
+            }
+        }
+
+        debug!(
+            "Pruned the storage up to block {pruned_state_marker}, deleting {deleted_entries} \
+             entries."
+        );
+        let more_to_prune = pruned_state_marker < target || ommer_blocks.next().is_some();
+        Ok((self, more_to_prune))
+    }
+}
+
+// Deletes the versions superseded by the versions of the block, and returns their number.
+fn prune_block_state(txn: &StorageTxn<'_, RW>, block_number: BlockNumber) -> StorageResult<usize> {
+    let storage_table = txn.txn.open_table(&txn.tables.contract_storage)?;
+    let nonces_table = txn.txn.open_table(&txn.tables.nonces)?;
+    let replaced_classes_table = txn.txn.open_table(&txn.tables.replaced_classes)?;
+    let storage_roots_table = txn.txn.open_table(&txn.tables.contract_storage_roots)?;
+    let created_nodes_table = txn.txn.open_table(&txn.tables.state_trie_nodes_created)?;
+
+    let state_diff = match txn.get_state_diff(block_number)? {
+        Some(state_diff) => state_diff,
+        None => {
+            return Err(StorageError::DBInconsistency {
+                msg: format!("Missing state diff of block {block_number} before the state marker."),
+            });
+        }
+    };
+    let mut deleted_entries = 0;
+    for (address, storage_entries) in &state_diff.storage_diffs {
+        for key in storage_entries.keys() {
+            deleted_entries += delete_superseded_storage_value(
+                &txn.txn,
+                &storage_table,
+                address,
+                key,
+                block_number,
+            )?;
+        }
+        deleted_entries +=
+            delete_superseded_version(&txn.txn, &storage_roots_table, address, block_number)?;
+    }
+    // A deployed contract has no earlier nonce to supersede.
+    for address in state_diff.nonces.keys() {
+        deleted_entries +=
+            delete_superseded_version(&txn.txn, &nonces_table, address, block_number)?;
+    }
+    // The class a contract was deployed with is kept, like the contract.
+    for address in state_diff.replaced_classes.keys() {
+        deleted_entries +=
+            delete_superseded_version(&txn.txn, &replaced_classes_table, address, block_number)?;
+    }
+    // The trie nodes created by the block are only needed to revert it.
+    created_nodes_table.delete(&txn.txn, &block_number)?;
+    Ok(deleted_entries)
+}
+
+// Deletes the value of the storage key before its value at the block, if it has one at the block.
+fn delete_superseded_storage_value<'env>(
+    txn: &'env DbTransaction<'env, RW>,
+    storage_table: &'env TableHandle<'env, (ContractAddress, StorageKey, BlockNumber), StarkFelt>,
+    address: &ContractAddress,
+    key: &StorageKey,
+    block_number: BlockNumber,
+) -> StorageResult<usize> {
+    let mut cursor = storage_table.cursor(txn)?;
+    let db_key = (*address, *key, block_number);
+    if cursor.lower_bound(&db_key)?.map(|(got_key, _)| got_key) != Some(db_key) {
+        return Ok(0);
+    }
+    match cursor.prev()? {
+        Some(((got_address, got_key, got_block_number), _))
+            if got_address == *address && got_key == *key =>
+        {
+            storage_table.delete(txn, &(got_address, got_key, got_block_number))?;
+            Ok(1)
+        }
+        _ => Ok(0),
+    }
+}
+
+// Deletes the version of the contract before its version at the block, if it has one at the block.
+fn delete_superseded_version<'env, V: StorageSerde>(
+    txn: &'env DbTransaction<'env, RW>,
+    table: &'env TableHandle<'env, (ContractAddress, BlockNumber), V>,
+    address: &ContractAddress,
+    block_number: BlockNumber,
+) -> StorageResult<usize> {
+    let mut cursor = table.cursor(txn)?;
+    let db_key = (*address, block_number);
+    if cursor.lower_bound(&db_key)?.map(|(got_key, _)| got_key) != Some(db_key) {
+        return Ok(0);
+    }
+    match cursor.prev()? {
+        Some(((got_address, got_block_number), _)) if got_address == *address => {
+            table.delete(txn, &(got_address, got_block_number))?;
+            Ok(1)
+        }
+        _ => Ok(0),
+    }
+}
+
+fn get_ommer_blocks_before(
+    txn: &StorageTxn<'_, RW>,
+    block_number: BlockNumber,
+) -> StorageResult<Vec<BlockHash>> {
+    let ommer_headers_table = txn.txn.open_table(&txn.tables.ommer_headers)?;
+    let mut cursor = ommer_headers_table.cursor(&txn.txn)?;
+    let mut block_hashes = Vec::new();
+    while let Some((block_hash, header)) = cursor.next()? {
+        if header.block_number < block_number {
+            block_hashes.push(block_hash);
+        }
+    }
+    Ok(block_hashes)
+}
+
+// Deletes the header, body and state diff of the ommer block, and returns the number of deleted
+// entries.
+fn delete_ommer_block(txn: &StorageTxn<'_, RW>, block_hash: BlockHash) -> StorageResult<usize> {
+    let ommer_headers_table = txn.txn.open_table(&txn.tables.ommer_headers)?;
+    let ommer_transactions_table = txn.txn.open_table(&txn.tables.ommer_transactions)?;
+    let ommer_transaction_outputs_table =
+        txn.txn.open_table(&txn.tables.ommer_transaction_outputs)?;
+    let ommer_events_table = txn.txn.open_table(&txn.tables.ommer_events)?;
+    let ommer_state_diffs_table = txn.txn.open_table(&txn.tables.ommer_state_diffs)?;
+    let ommer_declared_classes_table = txn.txn.open_table(&txn.tables.ommer_declared_classes)?;
+    let ommer_deployed_contracts_table =
+        txn.txn.open_table(&txn.tables.ommer_deployed_contracts)?;
+    let ommer_storage_table = txn.txn.open_table(&txn.tables.ommer_contract_storage)?;
+    let ommer_nonces_table = txn.txn.open_table(&txn.tables.ommer_nonces)?;
+
+    // The keys of the body and of the declared classes are prefixed by the block hash.
+    let mut transaction_outputs = Vec::new();
+    let mut cursor = ommer_transaction_outputs_table.cursor(&txn.txn)?;
+    let mut current =
+        cursor.lower_bound(&OmmerTransactionKey(block_hash, TransactionOffsetInBlock(0)))?;
+    while let Some((tx_key, thin_tx_output)) = current {
+        if tx_key.0 != block_hash {
+            break;
+        }
+        transaction_outputs.push((tx_key, thin_tx_output));
+        current = cursor.next()?;
+    }
+    let mut class_hashes = Vec::new();
+    let mut cursor = ommer_declared_classes_table.cursor(&txn.txn)?;
+    let mut current = cursor.lower_bound(&(block_hash, ClassHash::default()))?;
+    while let Some(((got_block_hash, class_hash), _)) = current {
+        if got_block_hash != block_hash {
+            break;
+        }
+        class_hashes.push(class_hash);
+        current = cursor.next()?;
+    }
+
+    let mut deleted_entries = 1;
+    ommer_headers_table.delete(&txn.txn, &block_hash)?;
+    for (tx_key, thin_tx_output) in transaction_outputs {
+        for (event_offset, address) in
+            thin_tx_output.events_contract_addresses_as_ref().iter().enumerate()
+        {
+            let event_key = OmmerEventKey(tx_key, EventIndexInTransactionOutput(event_offset));
+            ommer_events_table.delete(&txn.txn, &(*address, event_key))?;
+            deleted_entries += 1;
+        }
+        ommer_transactions_table.delete(&txn.txn, &tx_key)?;
+        ommer_transaction_outputs_table.delete(&txn.txn, &tx_key)?;
+        deleted_entries += 2;
+    }
+    for class_hash in class_hashes {
+        ommer_declared_classes_table.delete(&txn.txn, &(block_hash, class_hash))?;
+        deleted_entries += 1;
+    }
+    if let Some(state_diff) = ommer_state_diffs_table.get(&txn.txn, &block_hash)? {
+        for address in state_diff.deployed_contracts.keys() {
+            ommer_deployed_contracts_table.delete(&txn.txn, &(*address, block_hash))?;
+            deleted_entries += 1;
+        }
+        for (address, storage_entries) in &state_diff.storage_diffs {
+            for key in storage_entries.keys() {
+                ommer_storage_table.delete(&txn.txn, &(*address, *key, block_hash))?;
+                deleted_entries += 1;
+            }
+        }
+        for address in state_diff.nonces.keys() {
+            ommer_nonces_table.delete(&txn.txn, &(*address, block_hash))?;
+            deleted_entries += 1;
+        }
+        ommer_state_diffs_table.delete(&txn.txn, &block_hash)?;
+        deleted_entries += 1;
+    }
+    Ok(deleted_entries)
+}
@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use indexmap::IndexMap;
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{ContractClass, StateDiff, StateNumber, StorageKey};
+use starknet_api::transaction::{EventContent, TransactionOutput};
+use starknet_api::{patricia_key, stark_felt};
+use test_utils::{get_test_block_with_events, get_test_state_diff};
+
+use crate::body::events::ThinTransactionOutput;
+use crate::ommer::{OmmerStorageReader, OmmerStorageWriter};
+use crate::pruning::{PruningConfig, PruningStorageReader, PruningStorageWriter};
+use crate::state::data::ThinStateDiff;
+use crate::state::{StateStorageReader, StateStorageWriter};
+use crate::test_utils::get_test_storage;
+use crate::{StorageError, StorageReader, StorageWriter};
+
+fn pruning_config(history_length: u64, max_entries_per_txn: usize) -> PruningConfig {
+    PruningConfig { history_length, max_entries_per_txn, sleep_duration: Duration::from_secs(1) }
+}
+
+// Prunes in a single write transaction, and returns whether there's more to prune.
+fn prune(writer: &mut StorageWriter, config: &PruningConfig) -> bool {
+    let (txn, more_to_prune) = writer.begin_rw_txn().unwrap().prune(config).unwrap();
+    txn.commit().unwrap();
+    more_to_prune
+}
+
+fn entries_count(reader: &StorageReader, table_name: &str) -> usize {
+    reader.db_tables_stats().unwrap().stats[table_name].entries
+}
+
+// Deploys a contract at block 0, and sets its storage value and nonce to the block number at each
+// of the blocks.
+fn append_state_diffs(
+    writer: &mut StorageWriter,
+    blocks_count: u64,
+    address: ContractAddress,
+    key: StorageKey,
+) {
+    let class_hash = ClassHash(stark_felt!("0x4"));
+    for block_number in 0..blocks_count {
+        let mut diff = StateDiff {
+            storage_diffs: IndexMap::from([(
+                address,
+                IndexMap::from([(key, StarkFelt::from(block_number + 1))]),
+            )]),
+            nonces: IndexMap::from([(address, Nonce(StarkHash::from(block_number + 1)))]),
+            ..StateDiff::default()
+        };
+        if block_number == 0 {
+            diff.deployed_contracts = IndexMap::from([(address, class_hash)]);
+            diff.declared_classes = IndexMap::from([(class_hash, ContractClass::default())]);
+        }
+        writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_state_diff(BlockNumber(block_number), diff, IndexMap::new())
+            .unwrap()
+            .commit()
+            .unwrap();
+    }
+}
+
+#[test]
+fn prune_state_history() {
+    let address = ContractAddress(patricia_key!("0x11"));
+    let key = StorageKey(patricia_key!("0x101"));
+    let (reader, mut writer) = get_test_storage();
+    append_state_diffs(&mut writer, 5, address, key);
+    assert_eq!(reader.begin_ro_txn().unwrap().get_pruned_state_marker().unwrap(), BlockNumber(0));
+
+    assert!(!prune(&mut writer, &pruning_config(2, 1000)));
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_pruned_state_marker().unwrap(), BlockNumber(3));
+    // The versions of blocks 2, 3 and 4 are kept.
+    assert_eq!(entries_count(&reader, "contract_storage"), 3);
+    assert_eq!(entries_count(&reader, "nonces"), 3);
+    assert_eq!(entries_count(&reader, "contract_storage_roots"), 3);
+
+    // The state from the pruned state marker on is intact.
+    let state_reader = txn.get_state_reader().unwrap();
+    for block_number in 2..5 {
+        let state_number = StateNumber::right_after_block(BlockNumber(block_number));
+        assert_eq!(
+            state_reader.get_storage_at(state_number, &address, &key).unwrap(),
+            StarkFelt::from(block_number + 1)
+        );
+        assert_eq!(
+            state_reader.get_nonce_at(state_number, &address).unwrap(),
+            Some(Nonce(StarkHash::from(block_number + 1)))
+        );
+    }
+
+    // Nothing to prune until the chain advances.
+    assert!(!prune(&mut writer, &pruning_config(2, 1000)));
+    assert_eq!(reader.begin_ro_txn().unwrap().get_pruned_state_marker().unwrap(), BlockNumber(3));
+}
+
+#[test]
+fn prune_in_bounded_transactions() {
+    let address = ContractAddress(patricia_key!("0x11"));
+    let key = StorageKey(patricia_key!("0x101"));
+    let (reader, mut writer) = get_test_storage();
+    append_state_diffs(&mut writer, 5, address, key);
+
+    // Each block after the first supersedes a storage value, a storage root and a nonce.
+    let config = pruning_config(0, 3);
+    assert!(prune(&mut writer, &config));
+    assert_eq!(reader.begin_ro_txn().unwrap().get_pruned_state_marker().unwrap(), BlockNumber(2));
+    let mut txns_count = 1;
+    while prune(&mut writer, &config) {
+        txns_count += 1;
+    }
+    assert_eq!(txns_count, 4);
+    assert_eq!(reader.begin_ro_txn().unwrap().get_pruned_state_marker().unwrap(), BlockNumber(5));
+    assert_eq!(entries_count(&reader, "contract_storage"), 1);
+    assert_eq!(entries_count(&reader, "nonces"), 1);
+
+    // A pruned block can't be reverted.
+    assert_matches!(
+        writer.begin_rw_txn().unwrap().revert_state_diff(BlockNumber(4)).err(),
+        Some(StorageError::StatePruned { block_number: BlockNumber(4) })
+    );
+}
+
+#[test]
+fn prune_ommer_blocks() {
+    let (reader, mut writer) = get_test_storage();
+    for block_number in 0..3 {
+        writer
+            .begin_rw_txn()
+            .unwrap()
+            .append_state_diff(BlockNumber(block_number), StateDiff::default(), IndexMap::new())
+            .unwrap()
+            .commit()
+            .unwrap();
+    }
+
+    // An ommer block of block 0, with its body and state diff.
+    let mut block = get_test_block_with_events(2, 2);
+    block.header.block_number = BlockNumber(0);
+    let block_hash = block.header.block_hash;
+    let (thin_tx_outputs, transaction_outputs_events): (Vec<_>, Vec<_>) = block
+        .body
+        .transaction_outputs
+        .into_iter()
+        .map(|tx_output: TransactionOutput| {
+            let events: Vec<EventContent> =
+                tx_output.events().iter().map(|event| event.content.clone()).collect();
+            (ThinTransactionOutput::from(tx_output), events)
+        })
+        .unzip();
+    let (thin_state_diff, declared_classes) = ThinStateDiff::from_state_diff(get_test_state_diff());
+    // An ommer block of block 2, which is kept.
+    let kept_block_hash = BlockHash(stark_felt!("0x2"));
+    let mut kept_header = block.header.clone();
+    kept_header.block_number = BlockNumber(2);
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .insert_ommer_header(block_hash, &block.header)
+        .unwrap()
+        .insert_ommer_body(
+            block_hash,
+            &block.body.transactions,
+            &thin_tx_outputs,
+            &transaction_outputs_events,
+        )
+        .unwrap()
+        .insert_ommer_state_diff(block_hash, &thin_state_diff, &declared_classes)
+        .unwrap()
+        .insert_ommer_header(kept_block_hash, &kept_header)
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    assert!(!prune(&mut writer, &pruning_config(1, 1000)));
+    let txn = reader.begin_ro_txn().unwrap();
+    assert!(txn.get_ommer_header(block_hash).unwrap().is_none());
+    assert_eq!(txn.get_ommer_header(kept_block_hash).unwrap(), Some(kept_header));
+    for table_name in [
+        "ommer_contract_storage",
+        "ommer_declared_classes",
+        "ommer_deployed_contracts",
+        "ommer_events",
+        "ommer_nonces",
+        "ommer_state_diffs",
+        "ommer_transaction_outputs",
+        "ommer_transactions",
+    ] {
+        assert_eq!(entries_count(&reader, table_name), 0, "{table_name}");
+    }
+}
@@ -30,6 +30,7 @@ use starknet_api::transaction::{
     TransactionVersion,
 };
 
+use crate::body::bloom::EventBloom;
 use crate::body::events::{
     ThinDeclareTransactionOutput, ThinDeployAccountTransactionOutput, ThinDeployTransactionOutput,
     ThinInvokeTransactionOutput, ThinL1HandlerTransactionOutput, ThinTransactionOutput,
@@ -37,7 +38,11 @@ use crate::body::events::{
 use crate::db::serialization::{StorageSerde, StorageSerdeError};
 #[cfg(test)]
 use crate::serializers::serializers_test::{create_storage_serde_test, StorageSerdeTest};
-use crate::state::data::{IndexedDeclaredContract, IndexedDeployedContract, ThinStateDiff};
+use crate::state::commitment::{BinaryNode, EdgeNode, StateTrieRoots, TrieNode};
+use crate::state::data::{
+    CompiledClassHash, IndexedDeclaredContract, IndexedDeployedContract, IndexedSierraClass,
+    SierraContractClass, SierraEntryPoint, ThinStateDiff, ThinStateDiffV0,
+};
 use crate::{EventIndex, MarkerKind, OmmerEventKey, OmmerTransactionKey, TransactionIndex};
 
 auto_storage_serde! {
@@ -58,9 +63,14 @@ auto_storage_serde! {
         AcceptedOnL1 = 2,
         Rejected = 3,
     }
+    pub struct BinaryNode {
+        pub left: StarkHash,
+        pub right: StarkHash,
+    }
     pub struct BlockTimestamp(pub u64);
     pub struct Calldata(pub Arc<Vec<StarkFelt>>);
     pub struct ClassHash(pub StarkHash);
+    pub struct CompiledClassHash(pub StarkHash);
     pub struct ContractAddressSalt(pub StarkHash);
     // TODO(anatg): Consider using the compression utils.
     pub struct ContractClass {
@@ -101,6 +111,11 @@ auto_storage_serde! {
         pub contract_address_salt: ContractAddressSalt,
         pub constructor_calldata: Calldata,
     }
+    pub struct EdgeNode {
+        pub child: StarkHash,
+        pub path: StarkFelt,
+        pub length: u8,
+    }
     pub struct EntryPoint {
         pub selector: EntryPointSelector,
         pub offset: EntryPointOffset,
@@ -117,6 +132,10 @@ auto_storage_serde! {
         pub keys: Vec<TypedParameter>,
         pub data: Vec<TypedParameter>,
     }
+    pub struct EventBloom {
+        pub hash_count: u8,
+        pub bits: Vec<u8>,
+    }
     pub struct EventContent {
         pub keys: Vec<EventKey>,
         pub data: EventData,
@@ -150,6 +169,10 @@ auto_storage_serde! {
         pub block_number: BlockNumber,
         pub class_hash: ClassHash,
     }
+    pub struct IndexedSierraClass {
+        pub block_number: BlockNumber,
+        pub contract_class: SierraContractClass,
+    }
     pub struct InvokeTransaction {
         pub transaction_hash: TransactionHash,
         pub max_fee: Fee,
@@ -166,6 +189,10 @@ auto_storage_serde! {
         Header = 0,
         Body = 1,
         State = 2,
+        BaseLayerBlock = 3,
+        StorageVersion = 4,
+        PrunedState = 5,
+        SnapshotImport = 6,
     }
     pub struct MessageToL1 {
         pub to_address: EthAddress,
@@ -190,6 +217,20 @@ auto_storage_serde! {
         pub prime: serde_json::Value,
         pub reference_manager: serde_json::Value,
     }
+    pub struct SierraContractClass {
+        pub sierra_program: Vec<StarkFelt>,
+        pub contract_class_version: String,
+        pub entry_points_by_type: HashMap<EntryPointType, Vec<SierraEntryPoint>>,
+        pub abi: String,
+    }
+    pub struct SierraEntryPoint {
+        pub function_idx: usize,
+        pub selector: EntryPointSelector,
+    }
+    pub struct StateTrieRoots {
+        pub contracts_root: StarkHash,
+        pub classes_root: StarkHash,
+    }
     pub struct StructAbiEntry {
         pub name: String,
         pub size: usize,
@@ -241,6 +282,14 @@ auto_storage_serde! {
         pub storage_diffs: IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>>,
         pub declared_contract_hashes: Vec<ClassHash>,
         pub nonces: IndexMap<ContractAddress, Nonce>,
+        pub declared_classes: IndexMap<ClassHash, CompiledClassHash>,
+        pub replaced_classes: IndexMap<ContractAddress, ClassHash>,
+    }
+    pub struct ThinStateDiffV0 {
+        pub deployed_contracts: IndexMap<ContractAddress, ClassHash>,
+        pub storage_diffs: IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>>,
+        pub declared_contract_hashes: Vec<ClassHash>,
+        pub nonces: IndexMap<ContractAddress, Nonce>,
     }
     pub enum ThinTransactionOutput {
         Declare(ThinDeclareTransactionOutput) = 0,
@@ -257,6 +306,10 @@ auto_storage_serde! {
         L1Handler(L1HandlerTransaction) = 4,
     }
     pub struct TransactionHash(pub StarkHash);
+    pub enum TrieNode {
+        Binary(BinaryNode) = 0,
+        Edge(EdgeNode) = 1,
+    }
     struct TransactionIndex(pub BlockNumber, pub TransactionOffsetInBlock);
     pub struct TransactionOffsetInBlock(pub usize);
     pub struct TransactionSignature(pub Vec<StarkFelt>);
@@ -272,6 +325,7 @@ auto_storage_serde! {
 
     (BlockNumber, TransactionOffsetInBlock);
     (BlockHash, ClassHash);
+    (ClassHash, BlockNumber);
     (ContractAddress, BlockHash);
     (ContractAddress, BlockNumber);
     (ContractAddress, Nonce);
@@ -279,6 +333,8 @@ auto_storage_serde! {
     (ContractAddress, OmmerEventKey);
     (ContractAddress, StorageKey, BlockHash);
     (ContractAddress, StorageKey, BlockNumber);
+    (ContractAddress, TransactionIndex);
+    (EventKey, EventIndex);
 }
 
 ////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,267 @@
+//! Export and import of consistent snapshots of the storage, so that new nodes can start syncing
+//! from the markers of a snapshot instead of from genesis.
+//!
+//! A snapshot is a directory with two files:
+//! * `data.gz` - the gzip-compressed serialized entries of all the tables, read in a single read
+//!   transaction, so the snapshot can be taken while the node is running.
+//! * `manifest.json` - the [`SnapshotManifest`] describing the data. It's written after the data,
+//!   so a directory without a manifest holds a partial snapshot.
+//!
+//! The data is imported in several write transactions, under a marker that is removed when the
+//! import finishes. A storage with the marker can't be opened, and rerunning the import clears it
+//! and starts over.
+
+#[cfg(test)]
+#[path = "snapshot_test.rs"]
+mod snapshot_test;
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use starknet_api::block::BlockNumber;
+use starknet_api::core::ChainId;
+use tracing::info;
+
+use crate::base_layer::BaseLayerStorageReader;
+use crate::body::BodyStorageReader;
+use crate::db::{DbConfig, TransactionKind, RW};
+use crate::header::HeaderStorageReader;
+use crate::state::StateStorageReader;
+use crate::version::{verify_storage_version, STORAGE_VERSION};
+use crate::{
+    open_storage_unchecked, table_names, MarkerKind, StorageError, StorageReader, StorageResult,
+    StorageTxn,
+};
+
+/// The version of the snapshot format.
+pub const SNAPSHOT_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+const DATA_FILE: &str = "data.gz";
+// The number of entries inserted in each write transaction while importing a snapshot.
+const IMPORT_BATCH_SIZE: u64 = 10000;
+
+/// The description of the data of a snapshot.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SnapshotManifest {
+    pub version: u32,
+    /// The version of the storage the snapshot was taken from.
+    pub storage_version: u64,
+    pub chain_id: ChainId,
+    pub markers: SnapshotMarkers,
+    /// The tables, in the order of their entries in the data.
+    pub tables: Vec<SnapshotTableStats>,
+}
+
+/// The markers of the storage the snapshot was taken from.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SnapshotMarkers {
+    pub header: BlockNumber,
+    pub body: BlockNumber,
+    pub state: BlockNumber,
+    pub base_layer_block: BlockNumber,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SnapshotTableStats {
+    pub name: String,
+    pub entries: u64,
+    /// The total size of the serialized keys and values, in bytes.
+    pub bytes: u64,
+}
+
+/// Writes a snapshot of the storage into `dir`, and returns its manifest.
+pub fn export_snapshot(
+    reader: &StorageReader,
+    chain_id: ChainId,
+    dir: &Path,
+) -> StorageResult<SnapshotManifest> {
+    std::fs::create_dir_all(dir)?;
+    let txn = reader.begin_ro_txn()?;
+    let markers = get_markers(&txn)?;
+
+    let data_file = BufWriter::new(File::create(dir.join(DATA_FILE))?);
+    let mut encoder = GzEncoder::new(data_file, Compression::default());
+    let mut tables = Vec::new();
+    for name in table_names() {
+        let mut stats = SnapshotTableStats { name: name.to_string(), entries: 0, bytes: 0 };
+        txn.txn.for_each_raw_entry(name, |key, value| -> StorageResult<()> {
+            write_bytes(&mut encoder, key)?;
+            write_bytes(&mut encoder, value)?;
+            stats.entries += 1;
+            stats.bytes += (key.len() + value.len()) as u64;
+            Ok(())
+        })?;
+        tables.push(stats);
+    }
+    encoder.finish()?.flush()?;
+
+    let manifest = SnapshotManifest {
+        version: SNAPSHOT_VERSION,
+        storage_version: STORAGE_VERSION,
+        chain_id,
+        markers,
+        tables,
+    };
+    serde_json::to_writer_pretty(File::create(dir.join(MANIFEST_FILE))?, &manifest)?;
+    info!("Exported a snapshot of the storage with markers {markers:?} into {}.", dir.display());
+    Ok(manifest)
+}
+
+/// Imports the snapshot in `dir` into the empty storage of `db_config`, after validating that its
+/// manifest matches the chain and this version of the storage. A storage with an import that didn't
+/// finish is cleared first. Returns the manifest.
+pub fn import_snapshot(
+    dir: &Path,
+    chain_id: &ChainId,
+    db_config: DbConfig,
+) -> StorageResult<SnapshotManifest> {
+    let manifest: SnapshotManifest =
+        serde_json::from_reader(BufReader::new(File::open(dir.join(MANIFEST_FILE))?))?;
+    validate_manifest(&manifest, chain_id)?;
+
+    let (reader, mut writer) = open_storage_unchecked(db_config)?;
+    verify_storage_version(&reader, &mut writer)?;
+    if is_snapshot_import_in_progress(&reader.begin_ro_txn()?)? {
+        info!("Clearing the storage of a snapshot import that didn't finish.");
+        writer.db_writer.clear_tables(table_names())?;
+        // Clearing the markers table removed the version of the storage.
+        verify_storage_version(&reader, &mut writer)?;
+    } else {
+        // A new storage only has its version in the markers table.
+        let has_entries = reader
+            .db_tables_stats()?
+            .stats
+            .iter()
+            .any(|(name, stats)| name != "markers" && stats.entries > 0);
+        if has_entries || get_markers(&reader.begin_ro_txn()?)? != SnapshotMarkers::default() {
+            return Err(StorageError::StorageNotEmpty);
+        }
+    }
+    let txn = writer.begin_rw_txn()?;
+    set_snapshot_import_in_progress(&txn, true)?;
+    txn.commit()?;
+
+    let mut decoder = GzDecoder::new(BufReader::new(File::open(dir.join(DATA_FILE))?));
+    for table in &manifest.tables {
+        let mut remaining_entries = table.entries;
+        while remaining_entries > 0 {
+            let batch_size = remaining_entries.min(IMPORT_BATCH_SIZE);
+            let mut batch = Vec::with_capacity(batch_size as usize);
+            for _ in 0..batch_size {
+                batch.push((read_bytes(&mut decoder)?, read_bytes(&mut decoder)?));
+            }
+            writer.db_writer.insert_raw_entries(&table.name, &batch)?;
+            remaining_entries -= batch_size;
+        }
+    }
+    if decoder.read(&mut [0u8])? != 0 {
+        return Err(invalid_snapshot("The data has entries beyond the manifest's tables."));
+    }
+
+    let markers = get_markers(&reader.begin_ro_txn()?)?;
+    if markers != manifest.markers {
+        return Err(invalid_snapshot(&format!(
+            "The imported markers {markers:?} don't match the manifest's {:?}.",
+            manifest.markers
+        )));
+    }
+    let txn = writer.begin_rw_txn()?;
+    set_snapshot_import_in_progress(&txn, false)?;
+    txn.commit()?;
+    info!("Imported a snapshot of the storage with markers {markers:?} from {}.", dir.display());
+    Ok(manifest)
+}
+
+// Fails if a snapshot import into the storage didn't finish, as the storage is partial.
+pub(crate) fn verify_no_snapshot_import_in_progress<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+) -> StorageResult<()> {
+    if is_snapshot_import_in_progress(txn)? {
+        return Err(StorageError::SnapshotImportInProgress);
+    }
+    Ok(())
+}
+
+fn is_snapshot_import_in_progress<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+) -> StorageResult<bool> {
+    let markers_table = txn.txn.open_table(&txn.tables.markers)?;
+    Ok(markers_table.get(&txn.txn, &MarkerKind::SnapshotImport)?.is_some())
+}
+
+fn set_snapshot_import_in_progress(
+    txn: &StorageTxn<'_, RW>,
+    in_progress: bool,
+) -> StorageResult<()> {
+    let markers_table = txn.txn.open_table(&txn.tables.markers)?;
+    if in_progress {
+        markers_table.upsert(&txn.txn, &MarkerKind::SnapshotImport, &BlockNumber(0))?;
+    } else {
+        markers_table.delete(&txn.txn, &MarkerKind::SnapshotImport)?;
+    }
+    Ok(())
+}
+
+fn validate_manifest(manifest: &SnapshotManifest, chain_id: &ChainId) -> StorageResult<()> {
+    if manifest.version != SNAPSHOT_VERSION {
+        return Err(invalid_snapshot(&format!(
+            "Unsupported version {}, expected {SNAPSHOT_VERSION}.",
+            manifest.version
+        )));
+    }
+    if manifest.storage_version != STORAGE_VERSION {
+        return Err(invalid_snapshot(&format!(
+            "The snapshot is of storage version {}, expected {STORAGE_VERSION}.",
+            manifest.storage_version
+        )));
+    }
+    if manifest.chain_id != *chain_id {
+        return Err(invalid_snapshot(&format!(
+            "The snapshot is of chain {}, expected {}.",
+            manifest.chain_id.0, chain_id.0
+        )));
+    }
+    let manifest_table_names: Vec<&str> =
+        manifest.tables.iter().map(|table| table.name.as_str()).collect();
+    if manifest_table_names != table_names() {
+        return Err(invalid_snapshot(&format!(
+            "The tables {manifest_table_names:?} don't match the storage tables {:?}.",
+            table_names()
+        )));
+    }
+    Ok(())
+}
+
+fn get_markers<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+) -> StorageResult<SnapshotMarkers> {
+    Ok(SnapshotMarkers {
+        header: txn.get_header_marker()?,
+        body: txn.get_body_marker()?,
+        state: txn.get_state_marker()?,
+        base_layer_block: txn.get_base_layer_block_marker()?,
+    })
+}
+
+// The entries are written as their length in little endian followed by their bytes.
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_bytes(reader: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let mut len_bytes = [0u8; 4];
+    reader.read_exact(&mut len_bytes)?;
+    let mut bytes = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn invalid_snapshot(msg: &str) -> StorageError {
+    StorageError::InvalidSnapshot { msg: msg.to_owned() }
+}
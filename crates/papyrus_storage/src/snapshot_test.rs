@@ -0,0 +1,127 @@
+use assert_matches::assert_matches;
+use indexmap::IndexMap;
+use starknet_api::block::{BlockHash, BlockHeader, BlockNumber};
+use starknet_api::core::ChainId;
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::stark_felt;
+use tempfile::tempdir;
+use test_utils::{get_test_block, get_test_state_diff};
+
+use crate::body::{BodyStorageReader, BodyStorageWriter};
+use crate::header::{HeaderStorageReader, HeaderStorageWriter};
+use crate::snapshot::{
+    export_snapshot, import_snapshot, set_snapshot_import_in_progress, SnapshotMarkers,
+};
+use crate::state::{StateStorageReader, StateStorageWriter};
+use crate::test_utils::{get_test_config, get_test_storage};
+use crate::StorageError;
+
+#[test]
+fn export_and_import_snapshot() {
+    let (reader, mut writer) = get_test_storage();
+    let block = get_test_block(2);
+    let state_diff = get_test_state_diff();
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &block.header)
+        .unwrap()
+        .append_body(BlockNumber(0), block.body.clone())
+        .unwrap()
+        .append_state_diff(BlockNumber(0), state_diff, IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+    let chain_id = ChainId("SN_TEST".to_owned());
+    let dir = tempdir().unwrap();
+
+    let manifest = export_snapshot(&reader, chain_id.clone(), dir.path()).unwrap();
+    let expected_markers = SnapshotMarkers {
+        header: BlockNumber(1),
+        body: BlockNumber(1),
+        state: BlockNumber(1),
+        base_layer_block: BlockNumber(0),
+    };
+    assert_eq!(manifest.markers, expected_markers);
+    let headers_stats = manifest.tables.iter().find(|table| table.name == "headers").unwrap();
+    assert_eq!(headers_stats.entries, 1);
+
+    let db_config = get_test_config();
+    let imported_manifest = import_snapshot(dir.path(), &chain_id, db_config.clone()).unwrap();
+    assert_eq!(imported_manifest, manifest);
+
+    let (imported_reader, _) = crate::open_storage(db_config.clone()).unwrap();
+    let txn = imported_reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_block_header(BlockNumber(0)).unwrap(), Some(block.header));
+    assert_eq!(txn.get_block_transactions(BlockNumber(0)).unwrap(), Some(block.body.transactions));
+    let original_state_diff =
+        reader.begin_ro_txn().unwrap().get_state_diff(BlockNumber(0)).unwrap();
+    assert_eq!(txn.get_state_diff(BlockNumber(0)).unwrap(), original_state_diff);
+    drop(txn);
+    drop(imported_reader);
+
+    // Importing into a non-empty storage fails.
+    assert_matches!(
+        import_snapshot(dir.path(), &chain_id, db_config),
+        Err(StorageError::StorageNotEmpty)
+    );
+}
+
+#[test]
+fn import_snapshot_of_another_chain() {
+    let (reader, _) = get_test_storage();
+    let dir = tempdir().unwrap();
+    export_snapshot(&reader, ChainId("SN_MAIN".to_owned()), dir.path()).unwrap();
+
+    assert_matches!(
+        import_snapshot(dir.path(), &ChainId("SN_GOERLI".to_owned()), get_test_config()),
+        Err(StorageError::InvalidSnapshot { .. })
+    );
+}
+
+#[test]
+fn rerun_interrupted_import() {
+    let (reader, mut writer) = get_test_storage();
+    let header = get_test_block(0).header;
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(BlockNumber(0), &header)
+        .unwrap()
+        .commit()
+        .unwrap();
+    let chain_id = ChainId("SN_TEST".to_owned());
+    let dir = tempdir().unwrap();
+    export_snapshot(&reader, chain_id.clone(), dir.path()).unwrap();
+
+    // An import that stopped after writing more headers than the snapshot has.
+    let db_config = get_test_config();
+    let next_header = BlockHeader {
+        block_hash: BlockHash(stark_felt!("0x1234")),
+        block_number: BlockNumber(1),
+        parent_hash: header.block_hash,
+        ..header
+    };
+    let (_, mut imported_writer) = crate::open_storage(db_config.clone()).unwrap();
+    let txn = imported_writer.begin_rw_txn().unwrap();
+    set_snapshot_import_in_progress(&txn, true).unwrap();
+    txn.append_header(BlockNumber(0), &header)
+        .unwrap()
+        .append_header(BlockNumber(1), &next_header)
+        .unwrap()
+        .commit()
+        .unwrap();
+    drop(imported_writer);
+    assert_matches!(
+        crate::open_storage(db_config.clone()),
+        Err(StorageError::SnapshotImportInProgress)
+    );
+
+    // Rerunning the import clears the partial data.
+    import_snapshot(dir.path(), &chain_id, db_config.clone()).unwrap();
+    let (imported_reader, _) = crate::open_storage(db_config).unwrap();
+    let txn = imported_reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_header_marker().unwrap(), BlockNumber(1));
+    assert_eq!(txn.get_block_header(BlockNumber(0)).unwrap(), Some(header));
+    assert!(txn.get_block_header(BlockNumber(1)).unwrap().is_none());
+}
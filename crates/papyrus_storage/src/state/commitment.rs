@@ -0,0 +1,674 @@
+//! The global state commitment, computed incrementally with the state diffs.
+//!
+//! The storage of each contract is committed to by a Patricia-Merkle trie of height 251 from the
+//! storage keys to their values, and the contracts are committed to by a trie of the same height
+//! from the contract addresses to the hashes of their states. The Sierra classes are committed to
+//! by a trie of the same height from the class hashes to their compiled class hashes, whose nodes
+//! are hashed with Poseidon instead of Pedersen. Until a Sierra class is declared, the state root
+//! of the block is the root of the contracts trie, and from then on it is a Poseidon hash of the
+//! roots of the contracts and the classes tries.
+//!
+//! The nodes of all the tries are kept in a single table, addressed by their hashes, so that
+//! unchanged subtrees are shared between blocks. The nodes added by each block are recorded, so
+//! that reverting the block deletes them.
+//!
+//! A storage that was synced before the commitment was computed doesn't have the tries of its
+//! blocks, so they are only updated when the tries of the previous block exist.
+//!
+//! The nodes on the path from the root of a trie to a key prove the value at the key, or its
+//! absence, see [`verify_state_proof`].
+
+#[cfg(test)]
+#[path = "commitment_test.rs"]
+mod commitment_test;
+
+use std::collections::HashSet;
+
+use starknet_api::block::BlockNumber;
+use starknet_api::core::{ClassHash, ContractAddress, GlobalRoot, Nonce};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{StateNumber, StorageKey};
+use starknet_crypto::{pedersen_hash, poseidon_hash, poseidon_hash_many, FieldElement};
+
+use crate::db::{DbTransaction, TableHandle, TransactionKind, RW};
+use crate::state::data::ThinStateDiff;
+use crate::state::StateStorageReader;
+use crate::{StorageError, StorageResult, StorageTxn};
+
+type TrieNodesTable<'env> = TableHandle<'env, StarkHash, TrieNode>;
+type ContractStorageRootsTable<'env> = TableHandle<'env, (ContractAddress, BlockNumber), StarkHash>;
+type StateRootsTable<'env> = TableHandle<'env, BlockNumber, GlobalRoot>;
+type StateTrieRootsTable<'env> = TableHandle<'env, BlockNumber, StateTrieRoots>;
+// The hash of the nodes of a trie.
+type HashFunction = fn(&StarkFelt, &StarkFelt) -> StarkHash;
+
+// The number of bits of the keys of the tries.
+const TRIE_HEIGHT: u8 = 251;
+// The version of the hash of the contract states.
+const CONTRACT_STATE_HASH_VERSION: u64 = 0;
+// The prefixes of the hashes of the leaves of the classes trie and of the state root.
+const CONTRACT_CLASS_LEAF_VERSION: &[u8] = b"CONTRACT_CLASS_LEAF_V0";
+const GLOBAL_STATE_VERSION: &[u8] = b"STARKNET_STATE_V0";
+
+/// The roots of the contracts and the classes tries after a block, which the state root is the
+/// hash of.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct StateTrieRoots {
+    pub contracts_root: StarkHash,
+    pub classes_root: StarkHash,
+}
+
+/// A node of a trie with two non-empty children, hashed as `h(left, right)`.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct BinaryNode {
+    pub left: StarkHash,
+    pub right: StarkHash,
+}
+
+/// A node of a trie with a single non-empty descendant, `length` levels below it in the direction
+/// of the bits of `path`, hashed as `h(child, path) + length`.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct EdgeNode {
+    pub child: StarkHash,
+    pub path: StarkFelt,
+    pub length: u8,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TrieNode {
+    Binary(BinaryNode),
+    Edge(EdgeNode),
+}
+
+/// Merkle proofs of the state of a contract and of its storage against a state root.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StateProof {
+    pub state_root: GlobalRoot,
+    /// The root of the classes trie, zero until a Sierra class is declared.
+    pub classes_root: StarkHash,
+    /// The nodes on the path from the root of the contracts trie to the contract, from the root
+    /// down.
+    pub contract_proof: Vec<TrieNode>,
+    /// None if the contract isn't deployed.
+    pub contract_data: Option<ContractData>,
+}
+
+/// The state of a contract, and proofs of its storage against the root of its storage.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ContractData {
+    pub class_hash: ClassHash,
+    pub nonce: Nonce,
+    pub storage_root: StarkHash,
+    /// The nodes on the path from the storage root to each of the requested keys.
+    pub storage_proofs: Vec<Vec<TrieNode>>,
+}
+
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum ProofVerificationError {
+    #[error("The hash of a node of the proof doesn't match its parent.")]
+    HashMismatch,
+    #[error("The proof is not a path from the root of a trie to a leaf.")]
+    MalformedProof,
+    #[error("The contract data doesn't match the contract proof.")]
+    ContractDataMismatch,
+    #[error("Expected proofs of {expected} storage keys, found {found}.")]
+    StorageProofsCountMismatch { expected: usize, found: usize },
+}
+
+// A non-empty subtree as an edge of `length` bits (possibly none) whose bits are the lowest bits of
+// `path`, down to `bottom`, which is the hash of a binary node or a leaf.
+#[derive(Clone, Copy)]
+struct Subtree {
+    bottom: StarkHash,
+    path: [u8; 32],
+    length: u8,
+}
+
+impl Subtree {
+    fn node(hash: StarkHash) -> Self {
+        Self { bottom: hash, path: [0; 32], length: 0 }
+    }
+
+    // The subtree as a child of a node whose other child is empty.
+    fn under_edge(self, right: bool) -> Self {
+        let mut path = self.path;
+        if right {
+            set_bit(&mut path, self.length);
+        }
+        Self { path, length: self.length + 1, ..self }
+    }
+}
+
+/// Updates the tries with the state diff of the block and writes its state root. The state diff
+/// must already be written.
+pub(crate) fn update_state_commitment(
+    txn: &StorageTxn<'_, RW>,
+    block_number: BlockNumber,
+    state_diff: &ThinStateDiff,
+) -> StorageResult<()> {
+    let nodes_table = txn.txn.open_table(&txn.tables.state_trie_nodes)?;
+    let created_nodes_table = txn.txn.open_table(&txn.tables.state_trie_nodes_created)?;
+    let storage_roots_table = txn.txn.open_table(&txn.tables.contract_storage_roots)?;
+    let state_roots_table = txn.txn.open_table(&txn.tables.state_roots)?;
+    let trie_roots_table = txn.txn.open_table(&txn.tables.state_trie_roots)?;
+
+    let previous_roots = match block_number.prev() {
+        None => StateTrieRoots::default(),
+        Some(previous_block_number) => match get_trie_roots(
+            &txn.txn,
+            &state_roots_table,
+            &trie_roots_table,
+            previous_block_number,
+        )? {
+            Some(roots) => roots,
+            // The storage doesn't have the tries of the previous blocks.
+            None => return Ok(()),
+        },
+    };
+
+    let mut trie_writer =
+        TrieWriter { txn: &txn.txn, nodes_table: &nodes_table, created_nodes: Vec::new() };
+    let state_reader = txn.get_state_reader()?;
+    let state_number = StateNumber::right_after_block(block_number);
+    let contract_addresses: HashSet<&ContractAddress> = state_diff
+        .deployed_contracts
+        .keys()
+        .chain(state_diff.storage_diffs.keys())
+        .chain(state_diff.nonces.keys())
+        .chain(state_diff.replaced_classes.keys())
+        .collect();
+    let mut contract_leaves = Vec::with_capacity(contract_addresses.len());
+    for address in contract_addresses {
+        let mut storage_root =
+            get_previous_storage_root(&txn.txn, &storage_roots_table, address, block_number)?;
+        if let Some(storage_entries) = state_diff.storage_diffs.get(address) {
+            let updates =
+                storage_entries.iter().map(|(key, value)| (as_bits(key.0.key()), *value)).collect();
+            storage_root = trie_writer.update(storage_root, updates, pedersen)?;
+            storage_roots_table.insert(&txn.txn, &(*address, block_number), &storage_root)?;
+        }
+
+        let class_hash = state_reader.get_class_hash_at(state_number, address)?.unwrap_or_default();
+        let nonce = state_reader.get_nonce_at(state_number, address)?.unwrap_or_default();
+        contract_leaves.push((
+            as_bits(address.0.key()),
+            contract_state_hash(&class_hash, &storage_root, &nonce),
+        ));
+    }
+    let contracts_root =
+        trie_writer.update(previous_roots.contracts_root, contract_leaves, pedersen)?;
+    let class_leaves = state_diff
+        .declared_classes
+        .iter()
+        .map(|(class_hash, compiled_class_hash)| {
+            (as_bits(&class_hash.0), class_leaf_hash(&compiled_class_hash.0))
+        })
+        .collect();
+    let classes_root = trie_writer.update(previous_roots.classes_root, class_leaves, poseidon)?;
+    let roots = StateTrieRoots { contracts_root, classes_root };
+
+    state_roots_table.insert(&txn.txn, &block_number, &global_root(&roots))?;
+    trie_roots_table.insert(&txn.txn, &block_number, &roots)?;
+    created_nodes_table.insert(&txn.txn, &block_number, &trie_writer.created_nodes)?;
+    Ok(())
+}
+
+/// Returns proofs of the contract and its storage at the keys after the block, None if the state
+/// root of the block wasn't computed.
+pub(crate) fn get_state_proof<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+    block_number: BlockNumber,
+    contract_address: &ContractAddress,
+    keys: &[StorageKey],
+) -> StorageResult<Option<StateProof>> {
+    let nodes_table = txn.txn.open_table(&txn.tables.state_trie_nodes)?;
+    let storage_roots_table = txn.txn.open_table(&txn.tables.contract_storage_roots)?;
+    let state_roots_table = txn.txn.open_table(&txn.tables.state_roots)?;
+    let trie_roots_table = txn.txn.open_table(&txn.tables.state_trie_roots)?;
+
+    let roots = match get_trie_roots(&txn.txn, &state_roots_table, &trie_roots_table, block_number)?
+    {
+        Some(roots) => roots,
+        None => return Ok(None),
+    };
+    let contract_proof =
+        get_trie_proof(&txn.txn, &nodes_table, roots.contracts_root, contract_address.0.key())?;
+
+    let state_reader = txn.get_state_reader()?;
+    let state_number = StateNumber::right_after_block(block_number);
+    let contract_data = match state_reader.get_class_hash_at(state_number, contract_address)? {
+        None => None,
+        Some(class_hash) => {
+            let nonce =
+                state_reader.get_nonce_at(state_number, contract_address)?.unwrap_or_default();
+            let storage_root = get_previous_storage_root(
+                &txn.txn,
+                &storage_roots_table,
+                contract_address,
+                block_number.next(),
+            )?;
+            let storage_proofs = keys
+                .iter()
+                .map(|key| get_trie_proof(&txn.txn, &nodes_table, storage_root, key.0.key()))
+                .collect::<StorageResult<_>>()?;
+            Some(ContractData { class_hash, nonce, storage_root, storage_proofs })
+        }
+    };
+    Ok(Some(StateProof {
+        state_root: global_root(&roots),
+        classes_root: roots.classes_root,
+        contract_proof,
+        contract_data,
+    }))
+}
+
+/// Verifies the proofs against the state root, and returns the values of the storage of the
+/// contract at the keys, which are zero for a contract that isn't deployed.
+pub fn verify_state_proof(
+    state_root: &GlobalRoot,
+    contract_address: &ContractAddress,
+    keys: &[StorageKey],
+    proof: &StateProof,
+) -> Result<Vec<StarkFelt>, ProofVerificationError> {
+    // The root of the contracts trie is the hash of the first node of the proof.
+    let contracts_root = match proof.contract_proof.first() {
+        None => StarkHash::default(),
+        Some(TrieNode::Binary(node)) => pedersen(&node.left, &node.right),
+        Some(TrieNode::Edge(node)) => edge_hash(node, pedersen),
+    };
+    let roots = StateTrieRoots { contracts_root, classes_root: proof.classes_root };
+    if global_root(&roots) != *state_root {
+        return Err(ProofVerificationError::HashMismatch);
+    }
+    let contract_leaf =
+        verify_trie_proof(&contracts_root, contract_address.0.key(), &proof.contract_proof)?;
+    let contract_data = match &proof.contract_data {
+        None if contract_leaf == StarkFelt::default() => {
+            return Ok(vec![StarkFelt::default(); keys.len()]);
+        }
+        None => return Err(ProofVerificationError::ContractDataMismatch),
+        Some(contract_data) => contract_data,
+    };
+    let contract_hash = contract_state_hash(
+        &contract_data.class_hash,
+        &contract_data.storage_root,
+        &contract_data.nonce,
+    );
+    if contract_leaf != contract_hash {
+        return Err(ProofVerificationError::ContractDataMismatch);
+    }
+    if contract_data.storage_proofs.len() != keys.len() {
+        return Err(ProofVerificationError::StorageProofsCountMismatch {
+            expected: keys.len(),
+            found: contract_data.storage_proofs.len(),
+        });
+    }
+    keys.iter()
+        .zip(&contract_data.storage_proofs)
+        .map(|(key, storage_proof)| {
+            verify_trie_proof(&contract_data.storage_root, key.0.key(), storage_proof)
+        })
+        .collect()
+}
+
+/// Deletes what [`update_state_commitment`] wrote for the block.
+pub(crate) fn revert_state_commitment(
+    txn: &StorageTxn<'_, RW>,
+    block_number: BlockNumber,
+    state_diff: &ThinStateDiff,
+) -> StorageResult<()> {
+    let nodes_table = txn.txn.open_table(&txn.tables.state_trie_nodes)?;
+    let created_nodes_table = txn.txn.open_table(&txn.tables.state_trie_nodes_created)?;
+    let storage_roots_table = txn.txn.open_table(&txn.tables.contract_storage_roots)?;
+    let state_roots_table = txn.txn.open_table(&txn.tables.state_roots)?;
+    let trie_roots_table = txn.txn.open_table(&txn.tables.state_trie_roots)?;
+
+    let created_nodes = match created_nodes_table.get(&txn.txn, &block_number)? {
+        Some(created_nodes) => created_nodes,
+        // The tries weren't updated with this block.
+        None => return Ok(()),
+    };
+    for hash in created_nodes {
+        nodes_table.delete(&txn.txn, &hash)?;
+    }
+    created_nodes_table.delete(&txn.txn, &block_number)?;
+    state_roots_table.delete(&txn.txn, &block_number)?;
+    trie_roots_table.delete(&txn.txn, &block_number)?;
+    for address in state_diff.storage_diffs.keys() {
+        storage_roots_table.delete(&txn.txn, &(*address, block_number))?;
+    }
+    Ok(())
+}
+
+// Returns the roots of the tries after the block, None if they weren't computed. A storage written
+// before the classes trie was computed has only the state roots of blocks without Sierra classes,
+// which are the roots of the contracts trie.
+fn get_trie_roots<'env, Mode: TransactionKind>(
+    txn: &'env DbTransaction<'env, Mode>,
+    state_roots_table: &'env StateRootsTable<'env>,
+    trie_roots_table: &'env StateTrieRootsTable<'env>,
+    block_number: BlockNumber,
+) -> StorageResult<Option<StateTrieRoots>> {
+    if let Some(roots) = trie_roots_table.get(txn, &block_number)? {
+        return Ok(Some(roots));
+    }
+    Ok(state_roots_table.get(txn, &block_number)?.map(|state_root| StateTrieRoots {
+        contracts_root: state_root.0,
+        classes_root: StarkHash::default(),
+    }))
+}
+
+// Returns the latest root of the storage of the contract before the block.
+fn get_previous_storage_root<'env, Mode: TransactionKind>(
+    txn: &'env DbTransaction<'env, Mode>,
+    storage_roots_table: &'env ContractStorageRootsTable<'env>,
+    address: &ContractAddress,
+    block_number: BlockNumber,
+) -> StorageResult<StarkHash> {
+    let mut cursor = storage_roots_table.cursor(txn)?;
+    cursor.lower_bound(&(*address, block_number))?;
+    match cursor.prev()? {
+        Some(((got_address, _got_block_number), root)) if got_address == *address => Ok(root),
+        _ => Ok(StarkHash::default()),
+    }
+}
+
+// Returns the nodes on the path from the root of the trie towards the key, until the leaf or until
+// the path leaves the key.
+fn get_trie_proof<'env, Mode: TransactionKind>(
+    txn: &'env DbTransaction<'env, Mode>,
+    nodes_table: &'env TrieNodesTable<'env>,
+    root: StarkHash,
+    key: &StarkFelt,
+) -> StorageResult<Vec<TrieNode>> {
+    let key = as_bits(key);
+    let mut proof = Vec::new();
+    let mut hash = root;
+    let mut height = TRIE_HEIGHT;
+    // The root of an empty trie is zero.
+    while height > 0 && hash != StarkHash::default() {
+        let node = nodes_table.get(txn, &hash)?.ok_or_else(|| missing_node(hash))?;
+        proof.push(node);
+        match node {
+            TrieNode::Binary(BinaryNode { left, right }) => {
+                hash = if bit(&key, height - 1) { right } else { left };
+                height -= 1;
+            }
+            TrieNode::Edge(EdgeNode { child, path, length }) => {
+                if key_bits(&key, height - length, length) != as_bits(&path) {
+                    break;
+                }
+                hash = child;
+                height -= length;
+            }
+        }
+    }
+    Ok(proof)
+}
+
+// Returns the value at the key in the trie with the given root, which is zero if the proof shows
+// there is no leaf at the key. Only the tries hashed with Pedersen are proved.
+fn verify_trie_proof(
+    root: &StarkHash,
+    key: &StarkFelt,
+    proof: &[TrieNode],
+) -> Result<StarkFelt, ProofVerificationError> {
+    let key = as_bits(key);
+    let mut hash = *root;
+    let mut height = TRIE_HEIGHT;
+    for (index, node) in proof.iter().enumerate() {
+        if height == 0 {
+            return Err(ProofVerificationError::MalformedProof);
+        }
+        match node {
+            TrieNode::Binary(node) => {
+                if pedersen(&node.left, &node.right) != hash {
+                    return Err(ProofVerificationError::HashMismatch);
+                }
+                hash = if bit(&key, height - 1) { node.right } else { node.left };
+                height -= 1;
+            }
+            TrieNode::Edge(node) => {
+                if node.length == 0 || node.length > height {
+                    return Err(ProofVerificationError::MalformedProof);
+                }
+                if edge_hash(node, pedersen) != hash {
+                    return Err(ProofVerificationError::HashMismatch);
+                }
+                if key_bits(&key, height - node.length, node.length) != as_bits(&node.path) {
+                    // The edge leads away from the key, so there is no leaf at the key.
+                    if index + 1 != proof.len() {
+                        return Err(ProofVerificationError::MalformedProof);
+                    }
+                    return Ok(StarkFelt::default());
+                }
+                hash = node.child;
+                height -= node.length;
+            }
+        }
+    }
+    if hash == StarkHash::default() && proof.is_empty() {
+        // An empty trie.
+        return Ok(StarkFelt::default());
+    }
+    if height != 0 {
+        return Err(ProofVerificationError::MalformedProof);
+    }
+    Ok(hash)
+}
+
+// Writes the nodes of updated tries.
+struct TrieWriter<'env> {
+    txn: &'env DbTransaction<'env, RW>,
+    nodes_table: &'env TrieNodesTable<'env>,
+    // The nodes that didn't exist before, in the order of their insertion.
+    created_nodes: Vec<StarkHash>,
+}
+
+impl<'env> TrieWriter<'env> {
+    // Sets the leaves of the trie with the given root and returns the new root. Zero values delete
+    // the leaves, and the root of an empty trie is zero.
+    fn update(
+        &mut self,
+        root: StarkHash,
+        mut updates: Vec<([u8; 32], StarkFelt)>,
+        hash: HashFunction,
+    ) -> StorageResult<StarkHash> {
+        updates.sort_unstable_by_key(|(key, _)| *key);
+        let root =
+            if root == StarkHash::default() { None } else { Some(self.load(root, TRIE_HEIGHT)?) };
+        match self.update_subtree(root, TRIE_HEIGHT, &updates, hash)? {
+            None => Ok(StarkHash::default()),
+            Some(subtree) => self.persist(subtree, hash),
+        }
+    }
+
+    // Updates a subtree at the given height (the number of levels above the leaves) with updates
+    // sorted by their keys.
+    fn update_subtree(
+        &mut self,
+        subtree: Option<Subtree>,
+        height: u8,
+        updates: &[([u8; 32], StarkFelt)],
+        hash: HashFunction,
+    ) -> StorageResult<Option<Subtree>> {
+        if updates.is_empty() {
+            return Ok(subtree);
+        }
+        if height == 0 {
+            // The keys in a state diff are unique, so this is the single update of the leaf.
+            let value = updates[0].1;
+            return Ok((value != StarkFelt::default()).then(|| Subtree::node(value)));
+        }
+
+        let (left, right) = self.split(subtree, height)?;
+        let split = updates.partition_point(|(key, _)| !bit(key, height - 1));
+        let (left_updates, right_updates) = updates.split_at(split);
+        let left = self.update_subtree(left, height - 1, left_updates, hash)?;
+        let right = self.update_subtree(right, height - 1, right_updates, hash)?;
+        match (left, right) {
+            (None, None) => Ok(None),
+            (Some(child), None) => Ok(Some(child.under_edge(false))),
+            (None, Some(child)) => Ok(Some(child.under_edge(true))),
+            (Some(left), Some(right)) => {
+                let node = BinaryNode {
+                    left: self.persist(left, hash)?,
+                    right: self.persist(right, hash)?,
+                };
+                let node_hash = hash(&node.left, &node.right);
+                self.insert(node_hash, TrieNode::Binary(node))?;
+                Ok(Some(Subtree::node(node_hash)))
+            }
+        }
+    }
+
+    // Returns the children of a subtree at the given height.
+    fn split(
+        &self,
+        subtree: Option<Subtree>,
+        height: u8,
+    ) -> StorageResult<(Option<Subtree>, Option<Subtree>)> {
+        let subtree = match subtree {
+            None => return Ok((None, None)),
+            Some(subtree) => subtree,
+        };
+        if subtree.length == 0 {
+            return match self.nodes_table.get(self.txn, &subtree.bottom)? {
+                Some(TrieNode::Binary(node)) => Ok((
+                    Some(self.load(node.left, height - 1)?),
+                    Some(self.load(node.right, height - 1)?),
+                )),
+                _ => Err(missing_node(subtree.bottom)),
+            };
+        }
+        let top = subtree.length - 1;
+        let mut path = subtree.path;
+        let right = bit(&path, top);
+        clear_bit(&mut path, top);
+        let child = Subtree { path, length: top, ..subtree };
+        Ok(if right { (None, Some(child)) } else { (Some(child), None) })
+    }
+
+    // Returns the subtree of the node with the given hash at the given height.
+    fn load(&self, hash: StarkHash, height: u8) -> StorageResult<Subtree> {
+        if height == 0 {
+            return Ok(Subtree::node(hash));
+        }
+        match self.nodes_table.get(self.txn, &hash)? {
+            Some(TrieNode::Binary(_)) => Ok(Subtree::node(hash)),
+            Some(TrieNode::Edge(EdgeNode { child, path, length })) => {
+                Ok(Subtree { bottom: child, path: as_bits(&path), length })
+            }
+            None => Err(missing_node(hash)),
+        }
+    }
+
+    // Writes the edge of the subtree, if it has one, and returns the hash of the subtree.
+    fn persist(&mut self, subtree: Subtree, hash: HashFunction) -> StorageResult<StarkHash> {
+        if subtree.length == 0 {
+            return Ok(subtree.bottom);
+        }
+        let path = StarkFelt::new(subtree.path).expect("An edge path is shorter than a felt.");
+        let node = EdgeNode { child: subtree.bottom, path, length: subtree.length };
+        let node_hash = edge_hash(&node, hash);
+        self.insert(node_hash, TrieNode::Edge(node))?;
+        Ok(node_hash)
+    }
+
+    fn insert(&mut self, hash: StarkHash, node: TrieNode) -> StorageResult<()> {
+        // Identical subtrees, in the same trie or in others, share their nodes.
+        if self.nodes_table.get(self.txn, &hash)?.is_none() {
+            self.nodes_table.insert(self.txn, &hash, &node)?;
+            self.created_nodes.push(hash);
+        }
+        Ok(())
+    }
+}
+
+fn missing_node(hash: StarkHash) -> StorageError {
+    StorageError::DBInconsistency { msg: format!("Missing state trie node {hash:?}.") }
+}
+
+fn contract_state_hash(
+    class_hash: &ClassHash,
+    storage_root: &StarkHash,
+    nonce: &Nonce,
+) -> StarkHash {
+    let hash = pedersen(&pedersen(&class_hash.0, storage_root), &nonce.0);
+    pedersen(&hash, &StarkFelt::from(CONTRACT_STATE_HASH_VERSION))
+}
+
+fn class_leaf_hash(compiled_class_hash: &StarkHash) -> StarkHash {
+    as_stark_felt(poseidon_hash(
+        short_string(CONTRACT_CLASS_LEAF_VERSION),
+        felt(compiled_class_hash),
+    ))
+}
+
+// The state root commits to the classes trie once it isn't empty.
+fn global_root(roots: &StateTrieRoots) -> GlobalRoot {
+    if roots.classes_root == StarkHash::default() {
+        return GlobalRoot(roots.contracts_root);
+    }
+    GlobalRoot(as_stark_felt(poseidon_hash_many(&[
+        short_string(GLOBAL_STATE_VERSION),
+        felt(&roots.contracts_root),
+        felt(&roots.classes_root),
+    ])))
+}
+
+fn edge_hash(node: &EdgeNode, hash: HashFunction) -> StarkHash {
+    let mut length = [0u8; 32];
+    length[31] = node.length;
+    let length = FieldElement::from_bytes_be(&length).expect("A byte is a field element.");
+    as_stark_felt(felt(&hash(&node.child, &node.path)) + length)
+}
+
+fn pedersen(a: &StarkFelt, b: &StarkFelt) -> StarkHash {
+    as_stark_felt(pedersen_hash(&felt(a), &felt(b)))
+}
+
+fn poseidon(a: &StarkFelt, b: &StarkFelt) -> StarkHash {
+    as_stark_felt(poseidon_hash(felt(a), felt(b)))
+}
+
+// An ASCII string of up to 31 characters, as a field element.
+fn short_string(string: &[u8]) -> FieldElement {
+    FieldElement::from_byte_slice_be(string).expect("A short string is a field element.")
+}
+
+fn felt(felt: &StarkFelt) -> FieldElement {
+    FieldElement::from_bytes_be(&as_bits(felt)).expect("A felt is a field element.")
+}
+
+fn as_stark_felt(felt: FieldElement) -> StarkFelt {
+    StarkFelt::new(felt.to_bytes_be()).expect("A field element is a felt.")
+}
+
+// The big-endian bytes of a felt, whose bit 0 is the least significant.
+fn as_bits(felt: &StarkFelt) -> [u8; 32] {
+    felt.bytes().try_into().expect("A felt has 32 bytes.")
+}
+
+// The `length` bits of the key starting at bit `from`, as a number.
+fn key_bits(key: &[u8; 32], from: u8, length: u8) -> [u8; 32] {
+    let mut bits = [0; 32];
+    for index in 0..length {
+        if bit(key, from + index) {
+            set_bit(&mut bits, index);
+        }
+    }
+    bits
+}
+
+fn bit(bits: &[u8; 32], index: u8) -> bool {
+    bits[31 - usize::from(index / 8)] & (1 << (index % 8)) != 0
+}
+
+fn set_bit(bits: &mut [u8; 32], index: u8) {
+    bits[31 - usize::from(index / 8)] |= 1 << (index % 8);
+}
+
+fn clear_bit(bits: &mut [u8; 32], index: u8) {
+    bits[31 - usize::from(index / 8)] &= !(1 << (index % 8));
+}
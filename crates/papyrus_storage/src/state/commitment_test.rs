@@ -0,0 +1,306 @@
+use indexmap::IndexMap;
+use starknet_api::block::BlockNumber;
+use starknet_api::core::{ClassHash, ContractAddress, GlobalRoot, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{ContractClass, StateDiff, StorageKey};
+use starknet_api::{patricia_key, stark_felt};
+
+use super::{
+    class_leaf_hash, contract_state_hash, edge_hash, global_root, pedersen, poseidon,
+    verify_state_proof, EdgeNode, ProofVerificationError, StateTrieRoots, TrieNode, TRIE_HEIGHT,
+};
+use crate::state::data::{Cairo1StateDiff, CompiledClassHash, SierraContractClass};
+use crate::state::{StateStorageReader, StateStorageWriter};
+use crate::test_utils::get_test_storage;
+use crate::{StorageReader, StorageWriter};
+
+// The root of a trie with a single leaf.
+fn single_leaf_root(key: &StarkFelt, value: StarkFelt) -> StarkHash {
+    edge_hash(&EdgeNode { child: value, path: *key, length: TRIE_HEIGHT }, pedersen)
+}
+
+fn append_state_diff(writer: &mut StorageWriter, block_number: BlockNumber, diff: StateDiff) {
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_state_diff(block_number, diff, IndexMap::new())
+        .unwrap()
+        .commit()
+        .unwrap();
+}
+
+fn get_state_root(reader: &StorageReader, block_number: BlockNumber) -> Option<GlobalRoot> {
+    reader.begin_ro_txn().unwrap().get_state_root(block_number).unwrap()
+}
+
+fn trie_nodes_count(reader: &StorageReader) -> usize {
+    reader.db_tables_stats().unwrap().stats["state_trie_nodes"].entries
+}
+
+#[test]
+fn single_leaf_tries() {
+    let address = ContractAddress(patricia_key!("0x11"));
+    let class_hash = ClassHash(stark_felt!("0x4"));
+    let key = StorageKey(patricia_key!("0x101"));
+    let value = stark_felt!("0x201");
+    let nonce = Nonce(StarkHash::from(1));
+    let diff = StateDiff {
+        deployed_contracts: IndexMap::from([(address, class_hash)]),
+        storage_diffs: IndexMap::from([(address, IndexMap::from([(key, value)]))]),
+        declared_classes: IndexMap::from([(class_hash, ContractClass::default())]),
+        nonces: IndexMap::from([(address, nonce)]),
+    };
+    let (reader, mut writer) = get_test_storage();
+    append_state_diff(&mut writer, BlockNumber(0), diff);
+
+    let storage_root = single_leaf_root(key.0.key(), value);
+    let state_root =
+        single_leaf_root(address.0.key(), contract_state_hash(&class_hash, &storage_root, &nonce));
+    assert_eq!(get_state_root(&reader, BlockNumber(0)), Some(GlobalRoot(state_root)));
+    // The edges of the two tries.
+    assert_eq!(trie_nodes_count(&reader), 2);
+}
+
+#[test]
+fn update_and_delete_leaves() {
+    let address = ContractAddress(patricia_key!("0x11"));
+    let class_hash = ClassHash(stark_felt!("0x4"));
+    let key0 = StorageKey(patricia_key!("0x0"));
+    let key1 = StorageKey(patricia_key!("0x1"));
+    let diff0 = StateDiff {
+        deployed_contracts: IndexMap::from([(address, class_hash)]),
+        storage_diffs: IndexMap::from([(
+            address,
+            IndexMap::from([(key1, stark_felt!("0x201")), (key0, stark_felt!("0x200"))]),
+        )]),
+        declared_classes: IndexMap::from([(class_hash, ContractClass::default())]),
+        nonces: IndexMap::new(),
+    };
+    let diff1 = StateDiff {
+        storage_diffs: IndexMap::from([(address, IndexMap::from([(key0, stark_felt!("0x0"))]))]),
+        ..StateDiff::default()
+    };
+    let diff2 = StateDiff {
+        storage_diffs: IndexMap::from([(address, IndexMap::from([(key1, stark_felt!("0x0"))]))]),
+        ..StateDiff::default()
+    };
+    let (reader, mut writer) = get_test_storage();
+
+    // Adjacent leaves are the children of a binary node at the bottom of an edge.
+    append_state_diff(&mut writer, BlockNumber(0), diff0);
+    let bottom = pedersen(&stark_felt!("0x200"), &stark_felt!("0x201"));
+    let storage_root = edge_hash(
+        &EdgeNode { child: bottom, path: StarkFelt::default(), length: TRIE_HEIGHT - 1 },
+        pedersen,
+    );
+    let state_root = single_leaf_root(
+        address.0.key(),
+        contract_state_hash(&class_hash, &storage_root, &Nonce::default()),
+    );
+    assert_eq!(get_state_root(&reader, BlockNumber(0)), Some(GlobalRoot(state_root)));
+
+    // Deleting a leaf leaves its sibling at the bottom of an edge from the root.
+    append_state_diff(&mut writer, BlockNumber(1), diff1);
+    let storage_root = single_leaf_root(key1.0.key(), stark_felt!("0x201"));
+    let state_root = single_leaf_root(
+        address.0.key(),
+        contract_state_hash(&class_hash, &storage_root, &Nonce::default()),
+    );
+    assert_eq!(get_state_root(&reader, BlockNumber(1)), Some(GlobalRoot(state_root)));
+
+    // The root of an empty storage is zero.
+    append_state_diff(&mut writer, BlockNumber(2), diff2);
+    let state_root = single_leaf_root(
+        address.0.key(),
+        contract_state_hash(&class_hash, &StarkHash::default(), &Nonce::default()),
+    );
+    assert_eq!(get_state_root(&reader, BlockNumber(2)), Some(GlobalRoot(state_root)));
+}
+
+#[test]
+fn revert_state_diff() {
+    let c0 = ContractAddress(patricia_key!("0x11"));
+    let c1 = ContractAddress(patricia_key!("0x12"));
+    let class_hash = ClassHash(stark_felt!("0x4"));
+    let key0 = StorageKey(patricia_key!("0x1001"));
+    let key1 = StorageKey(patricia_key!("0x101"));
+    let diff0 = StateDiff {
+        deployed_contracts: IndexMap::from([(c0, class_hash)]),
+        storage_diffs: IndexMap::from([(
+            c0,
+            IndexMap::from([(key0, stark_felt!("0x200")), (key1, stark_felt!("0x201"))]),
+        )]),
+        declared_classes: IndexMap::from([(class_hash, ContractClass::default())]),
+        nonces: IndexMap::new(),
+    };
+    let diff1 = StateDiff {
+        deployed_contracts: IndexMap::from([(c1, class_hash)]),
+        storage_diffs: IndexMap::from([
+            (c0, IndexMap::from([(key0, stark_felt!("0x300")), (key1, stark_felt!("0x0"))])),
+            (c1, IndexMap::from([(key0, stark_felt!("0x200"))])),
+        ]),
+        declared_classes: IndexMap::new(),
+        nonces: IndexMap::from([(c0, Nonce(StarkHash::from(1)))]),
+    };
+    let (reader, mut writer) = get_test_storage();
+    append_state_diff(&mut writer, BlockNumber(0), diff0);
+    let state_root_0 = get_state_root(&reader, BlockNumber(0)).unwrap();
+    let nodes_count_0 = trie_nodes_count(&reader);
+
+    append_state_diff(&mut writer, BlockNumber(1), diff1.clone());
+    let state_root_1 = get_state_root(&reader, BlockNumber(1)).unwrap();
+    assert_ne!(state_root_1, state_root_0);
+
+    let (txn, _) = writer.begin_rw_txn().unwrap().revert_state_diff(BlockNumber(1)).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(get_state_root(&reader, BlockNumber(0)), Some(state_root_0));
+    assert_eq!(get_state_root(&reader, BlockNumber(1)), None);
+    assert_eq!(trie_nodes_count(&reader), nodes_count_0);
+
+    // The tries are back at their state before the block.
+    append_state_diff(&mut writer, BlockNumber(1), diff1);
+    assert_eq!(get_state_root(&reader, BlockNumber(1)), Some(state_root_1));
+}
+
+#[test]
+fn state_proofs() {
+    let c0 = ContractAddress(patricia_key!("0x11"));
+    let c1 = ContractAddress(patricia_key!("0x12"));
+    let undeployed = ContractAddress(patricia_key!("0x13"));
+    let class_hash = ClassHash(stark_felt!("0x4"));
+    let key0 = StorageKey(patricia_key!("0x1001"));
+    let key1 = StorageKey(patricia_key!("0x101"));
+    let missing_key = StorageKey(patricia_key!("0x100"));
+    let diff = StateDiff {
+        deployed_contracts: IndexMap::from([(c0, class_hash), (c1, class_hash)]),
+        storage_diffs: IndexMap::from([(
+            c0,
+            IndexMap::from([(key0, stark_felt!("0x200")), (key1, stark_felt!("0x201"))]),
+        )]),
+        declared_classes: IndexMap::from([(class_hash, ContractClass::default())]),
+        nonces: IndexMap::from([(c0, Nonce(StarkHash::from(1)))]),
+    };
+    let (reader, mut writer) = get_test_storage();
+    append_state_diff(&mut writer, BlockNumber(0), diff);
+    let state_root = get_state_root(&reader, BlockNumber(0)).unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+
+    let keys = [key0, key1, missing_key];
+    let proof = txn.get_state_proof(BlockNumber(0), &c0, &keys).unwrap().unwrap();
+    assert_eq!(proof.state_root, state_root);
+    assert_eq!(
+        verify_state_proof(&state_root, &c0, &keys, &proof).unwrap(),
+        vec![stark_felt!("0x200"), stark_felt!("0x201"), StarkFelt::default()]
+    );
+
+    // An empty storage.
+    let proof = txn.get_state_proof(BlockNumber(0), &c1, &[key0]).unwrap().unwrap();
+    assert_eq!(proof.contract_data.as_ref().unwrap().storage_proofs, vec![vec![]]);
+    assert_eq!(
+        verify_state_proof(&state_root, &c1, &[key0], &proof).unwrap(),
+        vec![StarkFelt::default()]
+    );
+
+    // A contract that isn't deployed.
+    let proof = txn.get_state_proof(BlockNumber(0), &undeployed, &[key0]).unwrap().unwrap();
+    assert!(proof.contract_data.is_none());
+    assert_eq!(
+        verify_state_proof(&state_root, &undeployed, &[key0], &proof).unwrap(),
+        vec![StarkFelt::default()]
+    );
+
+    // Tampered proofs.
+    let mut proof = txn.get_state_proof(BlockNumber(0), &c0, &[key0]).unwrap().unwrap();
+    assert_eq!(
+        verify_state_proof(&GlobalRoot(stark_felt!("0x1")), &c0, &[key0], &proof),
+        Err(ProofVerificationError::HashMismatch)
+    );
+    assert_eq!(
+        verify_state_proof(&state_root, &c0, &[key0, key1], &proof),
+        Err(ProofVerificationError::StorageProofsCountMismatch { expected: 2, found: 1 })
+    );
+    let contract_data = proof.contract_data.as_mut().unwrap();
+    contract_data.nonce = Nonce(StarkHash::from(2));
+    assert_eq!(
+        verify_state_proof(&state_root, &c0, &[key0], &proof),
+        Err(ProofVerificationError::ContractDataMismatch)
+    );
+    let mut proof = txn.get_state_proof(BlockNumber(0), &c0, &[key0]).unwrap().unwrap();
+    let storage_proof = &mut proof.contract_data.as_mut().unwrap().storage_proofs[0];
+    if let Some(TrieNode::Edge(edge)) = storage_proof.last_mut() {
+        edge.child = stark_felt!("0x300");
+    }
+    assert_eq!(
+        verify_state_proof(&state_root, &c0, &[key0], &proof),
+        Err(ProofVerificationError::HashMismatch)
+    );
+
+    // No state root.
+    assert_eq!(txn.get_state_proof(BlockNumber(1), &c0, &[key0]).unwrap(), None);
+}
+
+#[test]
+fn sierra_classes_trie() {
+    let address = ContractAddress(patricia_key!("0x11"));
+    let cairo0_class_hash = ClassHash(stark_felt!("0x4"));
+    let sierra_class_hash = ClassHash(stark_felt!("0x5"));
+    let compiled_class_hash = CompiledClassHash(stark_felt!("0x6"));
+    let diff0 = StateDiff {
+        deployed_contracts: IndexMap::from([(address, cairo0_class_hash)]),
+        declared_classes: IndexMap::from([(cairo0_class_hash, ContractClass::default())]),
+        ..StateDiff::default()
+    };
+    let cairo1_diff1 = Cairo1StateDiff {
+        declared_classes: IndexMap::from([(
+            sierra_class_hash,
+            (compiled_class_hash, SierraContractClass::default()),
+        )]),
+        replaced_classes: IndexMap::new(),
+    };
+    let (reader, mut writer) = get_test_storage();
+
+    // Before a Sierra class is declared, the state root is the root of the contracts trie.
+    append_state_diff(&mut writer, BlockNumber(0), diff0);
+    let contracts_root = single_leaf_root(
+        address.0.key(),
+        contract_state_hash(&cairo0_class_hash, &StarkHash::default(), &Nonce::default()),
+    );
+    assert_eq!(get_state_root(&reader, BlockNumber(0)), Some(GlobalRoot(contracts_root)));
+
+    // The classes trie is hashed with Poseidon, and the state root commits to both tries.
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_state_diff_with_cairo1(
+            BlockNumber(1),
+            StateDiff::default(),
+            cairo1_diff1,
+            IndexMap::new(),
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+    let classes_root = edge_hash(
+        &EdgeNode {
+            child: class_leaf_hash(&compiled_class_hash.0),
+            path: sierra_class_hash.0,
+            length: TRIE_HEIGHT,
+        },
+        poseidon,
+    );
+    let state_root = global_root(&StateTrieRoots { contracts_root, classes_root });
+    assert_ne!(state_root, GlobalRoot(contracts_root));
+    assert_eq!(get_state_root(&reader, BlockNumber(1)), Some(state_root));
+
+    // The proofs are verified against the roots of both tries.
+    let txn = reader.begin_ro_txn().unwrap();
+    let mut proof = txn.get_state_proof(BlockNumber(1), &address, &[]).unwrap().unwrap();
+    assert_eq!(proof.state_root, state_root);
+    assert_eq!(proof.classes_root, classes_root);
+    assert_eq!(verify_state_proof(&state_root, &address, &[], &proof), Ok(vec![]));
+    proof.classes_root = StarkHash::default();
+    assert_eq!(
+        verify_state_proof(&state_root, &address, &[], &proof),
+        Err(ProofVerificationError::HashMismatch)
+    );
+}
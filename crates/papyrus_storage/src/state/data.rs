@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use starknet_api::block::BlockNumber;
-use starknet_api::core::{ClassHash, ContractAddress, Nonce};
-use starknet_api::hash::StarkFelt;
-use starknet_api::state::{ContractClass, StateDiff, StorageKey};
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector, Nonce};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{ContractClass, EntryPointType, StateDiff, StorageKey};
+
+use crate::db::serialization::StorageSerdeEx;
 
 /// Data structs that are serialized into the database.
 
@@ -16,6 +20,10 @@ pub struct ThinStateDiff {
     pub storage_diffs: IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>>,
     pub declared_contract_hashes: Vec<ClassHash>,
     pub nonces: IndexMap<ContractAddress, Nonce>,
+    /// The declared Sierra classes, with the hashes of their compiled classes.
+    pub declared_classes: IndexMap<ClassHash, CompiledClassHash>,
+    /// The deployed contracts whose class was replaced, with their new class.
+    pub replaced_classes: IndexMap<ContractAddress, ClassHash>,
 }
 
 impl ThinStateDiff {
@@ -27,10 +35,26 @@ impl ThinStateDiff {
                 storage_diffs: diff.storage_diffs,
                 declared_contract_hashes: diff.declared_classes.keys().copied().collect(),
                 nonces: diff.nonces,
+                declared_classes: IndexMap::new(),
+                replaced_classes: IndexMap::new(),
             },
             diff.declared_classes,
         )
     }
+
+    /// Adds the Cairo 1 part of the state diff, and returns the declared Sierra classes.
+    pub fn add_cairo1_state_diff(
+        &mut self,
+        diff: Cairo1StateDiff,
+    ) -> IndexMap<ClassHash, SierraContractClass> {
+        let mut sierra_classes = IndexMap::with_capacity(diff.declared_classes.len());
+        for (class_hash, (compiled_class_hash, contract_class)) in diff.declared_classes {
+            self.declared_classes.insert(class_hash, compiled_class_hash);
+            sierra_classes.insert(class_hash, contract_class);
+        }
+        self.replaced_classes = diff.replaced_classes;
+        sierra_classes
+    }
 }
 
 impl From<StateDiff> for ThinStateDiff {
@@ -39,6 +63,40 @@ impl From<StateDiff> for ThinStateDiff {
     }
 }
 
+/// The parts of the state diff of a block that came with Cairo 1, which [`StateDiff`] doesn't
+/// have.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Cairo1StateDiff {
+    /// The declared Sierra classes, with the hashes of their compiled classes.
+    pub declared_classes: IndexMap<ClassHash, (CompiledClassHash, SierraContractClass)>,
+    /// The deployed contracts whose class was replaced, with their new class.
+    pub replaced_classes: IndexMap<ContractAddress, ClassHash>,
+}
+
+/// The hash of the compiled (CASM) class of a Sierra class, which the sequencer executes.
+#[derive(
+    Debug, Copy, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord,
+)]
+pub struct CompiledClassHash(pub StarkHash);
+
+/// A Cairo 1 contract class, as a Sierra program.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct SierraContractClass {
+    pub sierra_program: Vec<StarkFelt>,
+    pub contract_class_version: String,
+    /// The selector of each entry point is a unique identifier in the program.
+    pub entry_points_by_type: HashMap<EntryPointType, Vec<SierraEntryPoint>>,
+    /// The ABI of the class, as a JSON string.
+    pub abi: String,
+}
+
+/// An entry point of a Sierra class, as the index of its function in the Sierra program.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct SierraEntryPoint {
+    pub function_idx: usize,
+    pub selector: EntryPointSelector,
+}
+
 #[derive(Debug, Default, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
 pub(crate) struct IndexedDeployedContract {
     pub block_number: BlockNumber,
@@ -50,3 +108,37 @@ pub(crate) struct IndexedDeclaredContract {
     pub block_number: BlockNumber,
     pub contract_class: ContractClass,
 }
+
+#[derive(Debug, Default, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub(crate) struct IndexedSierraClass {
+    pub block_number: BlockNumber,
+    pub contract_class: SierraContractClass,
+}
+
+// The encoding of [`ThinStateDiff`] up to storage version 0, before Cairo 1.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub(crate) struct ThinStateDiffV0 {
+    pub deployed_contracts: IndexMap<ContractAddress, ClassHash>,
+    pub storage_diffs: IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>>,
+    pub declared_contract_hashes: Vec<ClassHash>,
+    pub nonces: IndexMap<ContractAddress, Nonce>,
+}
+
+/// Rewrites a [`ThinStateDiff`] of storage version 0 as a state diff without Cairo 1 classes.
+pub(crate) fn migrate_thin_state_diff_from_v0(bytes: &[u8]) -> Option<Vec<u8>> {
+    // The value was already rewritten by an interrupted run of the migration.
+    if ThinStateDiff::deserialize(&mut &bytes[..]).is_some() {
+        return Some(bytes.to_vec());
+    }
+    let diff = ThinStateDiffV0::deserialize(&mut &bytes[..])?;
+    ThinStateDiff {
+        deployed_contracts: diff.deployed_contracts,
+        storage_diffs: diff.storage_diffs,
+        declared_contract_hashes: diff.declared_contract_hashes,
+        nonces: diff.nonces,
+        declared_classes: IndexMap::new(),
+        replaced_classes: IndexMap::new(),
+    }
+    .serialize()
+    .ok()
+}
@@ -1,3 +1,4 @@
+pub mod commitment;
 pub mod data;
 #[cfg(test)]
 #[path = "state_test.rs"]
@@ -7,38 +8,71 @@ use std::collections::HashSet;
 
 use indexmap::IndexMap;
 use starknet_api::block::BlockNumber;
-use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::core::{ClassHash, ContractAddress, GlobalRoot, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::{ContractClass, StateDiff, StateNumber, StorageKey};
 use tracing::debug;
 
 use crate::db::{DbError, DbTransaction, TableHandle, TransactionKind, RW};
-use crate::state::data::{IndexedDeclaredContract, IndexedDeployedContract, ThinStateDiff};
+use crate::pruning::PruningStorageReader;
+use crate::state::commitment::{
+    get_state_proof, revert_state_commitment, update_state_commitment, StateProof,
+};
+use crate::state::data::{
+    Cairo1StateDiff, CompiledClassHash, IndexedDeclaredContract, IndexedDeployedContract,
+    IndexedSierraClass, SierraContractClass, ThinStateDiff,
+};
 use crate::{MarkerKind, MarkersTable, StorageError, StorageResult, StorageTxn};
 
+type CompiledClassHashesTable<'env> =
+    TableHandle<'env, (ClassHash, BlockNumber), CompiledClassHash>;
 type DeclaredClassesTable<'env> = TableHandle<'env, ClassHash, IndexedDeclaredContract>;
 type DeployedContractsTable<'env> = TableHandle<'env, ContractAddress, IndexedDeployedContract>;
 type ContractStorageTable<'env> =
     TableHandle<'env, (ContractAddress, StorageKey, BlockNumber), StarkFelt>;
 type NoncesTable<'env> = TableHandle<'env, (ContractAddress, BlockNumber), Nonce>;
+type ReplacedClassesTable<'env> = TableHandle<'env, (ContractAddress, BlockNumber), ClassHash>;
+type SierraClassesTable<'env> = TableHandle<'env, ClassHash, IndexedSierraClass>;
 
 // Structure of state data:
 // * declared_classes: (class_hash) -> (block_num, contract_class). Each entry specifies at which
 //   block was this class declared and with what class definition.
+// * sierra_classes: (class_hash) -> (block_num, sierra_class). The same for the Cairo 1 classes.
+// * compiled_class_hashes: (class_hash, block_num) -> (compiled_class_hash). Specifies that at
+//   `block_num`, the Sierra class was declared with the hash of its compiled class.
 // * deployed_contracts_table: (contract_address) -> (block_num, class_hash). Each entry specifies
 //   at which block was this contract deployed and with what class hash. Note that each contract may
 //   only be deployed once, so we don't need to support multiple entries per contract address.
+// * replaced_classes: (contract_address, block_num) -> (class_hash). Specifies that at
+//   `block_num`, the class of the deployed contract was replaced. The class of a contract at a
+//   block number is the latest replacement before it, or the class it was deployed with.
 // * storage_table: (contract_address, key, block_num) -> (value). Specifies that at `block_num`,
 //   the `key` at `contract_address` was changed to `value`. This structure let's us do quick
 //   lookup, since the database supports "Get the closet element from  the left". Thus, to lookup
 //   the value at a specific block_number, we can search (contract_address, key, block_num), and
 //   retrieve the closest from left, which should be the latest update to the value before that
 //   block_num.
+//   In a pruned storage, only the latest update before the pruned state marker is kept, see
+//   [`crate::pruning`].
+// * state_roots: (block_num) -> (global_root). The state commitment after each block, kept with
+//   the tries it is computed from, see [`commitment`].
+// * state_trie_roots: (block_num) -> (contracts_root, classes_root). The roots of the tries the
+//   state commitment after each block is the hash of.
 
 pub trait StateStorageReader<Mode: TransactionKind> {
     fn get_state_marker(&self) -> StorageResult<BlockNumber>;
     fn get_state_diff(&self, block_number: BlockNumber) -> StorageResult<Option<ThinStateDiff>>;
     fn get_state_reader(&self) -> StorageResult<StateReader<'_, Mode>>;
+    /// The state root computed by the storage after the block, None if it wasn't computed.
+    fn get_state_root(&self, block_number: BlockNumber) -> StorageResult<Option<GlobalRoot>>;
+    /// Merkle proofs of the contract and of its storage at the keys against the state root after
+    /// the block, None if the state root wasn't computed.
+    fn get_state_proof(
+        &self,
+        block_number: BlockNumber,
+        contract_address: &ContractAddress,
+        keys: &[StorageKey],
+    ) -> StorageResult<Option<StateProof>>;
 }
 
 type RevertedStateDiff = (ThinStateDiff, IndexMap<ClassHash, ContractClass>);
@@ -56,6 +90,23 @@ where
         // Class definitions of deployed contracts with classes that were not declared in this
         // state diff.
         deployed_contract_class_definitions: IndexMap<ClassHash, ContractClass>,
+    ) -> StorageResult<Self> {
+        self.append_state_diff_with_cairo1(
+            block_number,
+            state_diff,
+            Cairo1StateDiff::default(),
+            deployed_contract_class_definitions,
+        )
+    }
+
+    /// Appends the state diff of a block that may declare Sierra classes and replace the classes
+    /// of contracts.
+    fn append_state_diff_with_cairo1(
+        self,
+        block_number: BlockNumber,
+        state_diff: StateDiff,
+        cairo1_state_diff: Cairo1StateDiff,
+        deployed_contract_class_definitions: IndexMap<ClassHash, ContractClass>,
     ) -> StorageResult<Self>;
 
     fn revert_state_diff(
@@ -78,29 +129,51 @@ impl<'env, Mode: TransactionKind> StateStorageReader<Mode> for StorageTxn<'env,
     fn get_state_reader(&self) -> StorageResult<StateReader<'_, Mode>> {
         StateReader::new(self)
     }
+    fn get_state_root(&self, block_number: BlockNumber) -> StorageResult<Option<GlobalRoot>> {
+        let state_roots_table = self.txn.open_table(&self.tables.state_roots)?;
+        let state_root = state_roots_table.get(&self.txn, &block_number)?;
+        Ok(state_root)
+    }
+    fn get_state_proof(
+        &self,
+        block_number: BlockNumber,
+        contract_address: &ContractAddress,
+        keys: &[StorageKey],
+    ) -> StorageResult<Option<StateProof>> {
+        get_state_proof(self, block_number, contract_address, keys)
+    }
 }
 
 /// A single coherent state at a single point in time,
 pub struct StateReader<'env, Mode: TransactionKind> {
     txn: &'env DbTransaction<'env, Mode>,
+    compiled_class_hashes_table: CompiledClassHashesTable<'env>,
     declared_classes_table: DeclaredClassesTable<'env>,
     deployed_contracts_table: DeployedContractsTable<'env>,
     nonces_table: NoncesTable<'env>,
+    replaced_classes_table: ReplacedClassesTable<'env>,
+    sierra_classes_table: SierraClassesTable<'env>,
     storage_table: ContractStorageTable<'env>,
 }
 
 #[allow(dead_code)]
 impl<'env, Mode: TransactionKind> StateReader<'env, Mode> {
     fn new(txn: &'env StorageTxn<'env, Mode>) -> StorageResult<Self> {
+        let compiled_class_hashes_table = txn.txn.open_table(&txn.tables.compiled_class_hashes)?;
         let declared_classes_table = txn.txn.open_table(&txn.tables.declared_classes)?;
         let deployed_contracts_table = txn.txn.open_table(&txn.tables.deployed_contracts)?;
         let nonces_table = txn.txn.open_table(&txn.tables.nonces)?;
+        let replaced_classes_table = txn.txn.open_table(&txn.tables.replaced_classes)?;
+        let sierra_classes_table = txn.txn.open_table(&txn.tables.sierra_classes)?;
         let storage_table = txn.txn.open_table(&txn.tables.contract_storage)?;
         Ok(StateReader {
             txn: &txn.txn,
+            compiled_class_hashes_table,
             declared_classes_table,
             deployed_contracts_table,
             nonces_table,
+            replaced_classes_table,
+            sierra_classes_table,
             storage_table,
         })
     }
@@ -110,13 +183,21 @@ impl<'env, Mode: TransactionKind> StateReader<'env, Mode> {
         state_number: StateNumber,
         address: &ContractAddress,
     ) -> StorageResult<Option<ClassHash>> {
-        let value = self.deployed_contracts_table.get(self.txn, address)?;
-        if let Some(value) = value {
-            if state_number.is_after(value.block_number) {
-                return Ok(Some(value.class_hash));
+        let deployed_class_hash = match self.deployed_contracts_table.get(self.txn, address)? {
+            Some(value) if state_number.is_after(value.block_number) => value.class_hash,
+            _ => return Ok(None),
+        };
+        // The relevant replacement is the last replacement strictly before the block after the
+        // state number.
+        let db_key = (*address, state_number.block_after());
+        let mut cursor = self.replaced_classes_table.cursor(self.txn)?;
+        cursor.lower_bound(&db_key)?;
+        match cursor.prev()? {
+            Some(((got_address, _got_block_number), class_hash)) if got_address == *address => {
+                Ok(Some(class_hash))
             }
+            _ => Ok(Some(deployed_class_hash)),
         }
-        Ok(None)
     }
 
     pub fn get_nonce_at(
@@ -174,6 +255,100 @@ impl<'env, Mode: TransactionKind> StateReader<'env, Mode> {
         }
     }
 
+    /// Returns the storage entries of the contract at the state number, from the start key on in
+    /// the order of the keys, up to `limit` of them. Keys whose value is zero are skipped, as zero
+    /// is the value of any key that wasn't written.
+    pub fn get_storage_range_at(
+        &self,
+        state_number: StateNumber,
+        address: &ContractAddress,
+        start_key: &StorageKey,
+        limit: usize,
+    ) -> StorageResult<Vec<(StorageKey, StarkFelt)>> {
+        // The updates to the storage keys are indexed by the block_number at which they occurred.
+        let first_irrelevant_block: BlockNumber = state_number.block_after();
+        let mut cursor = self.storage_table.cursor(self.txn)?;
+        let mut current = cursor.lower_bound(&(*address, *start_key, BlockNumber(0)))?;
+        let mut entries = Vec::new();
+        // The value at the state number of the last key seen, if it was written by then.
+        let mut last_entry: Option<(StorageKey, StarkFelt)> = None;
+        while let Some(((got_address, got_key, got_block_number), value)) = current {
+            if got_address != *address || entries.len() == limit {
+                break;
+            }
+            if let Some((last_key, last_value)) = last_entry {
+                if last_key != got_key {
+                    if last_value != StarkFelt::default() {
+                        entries.push((last_key, last_value));
+                    }
+                    last_entry = None;
+                }
+            }
+            if got_block_number < first_irrelevant_block {
+                last_entry = Some((got_key, value));
+            }
+            current = cursor.next()?;
+        }
+        if let Some((last_key, last_value)) = last_entry {
+            if last_value != StarkFelt::default() && entries.len() < limit {
+                entries.push((last_key, last_value));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Returns the changes to the nonce of the contract in the given range of blocks, inclusive,
+    /// as the block of each change and the nonce it set, and at most limit of them.
+    pub fn get_nonce_history(
+        &self,
+        address: &ContractAddress,
+        from_block_number: BlockNumber,
+        to_block_number: BlockNumber,
+        limit: usize,
+    ) -> StorageResult<Vec<(BlockNumber, Nonce)>> {
+        let mut cursor = self.nonces_table.cursor(self.txn)?;
+        let mut current = cursor.lower_bound(&(*address, from_block_number))?;
+        let mut changes = Vec::new();
+        while let Some(((got_address, got_block_number), nonce)) = current {
+            if got_address != *address
+                || got_block_number > to_block_number
+                || changes.len() == limit
+            {
+                break;
+            }
+            changes.push((got_block_number, nonce));
+            current = cursor.next()?;
+        }
+        Ok(changes)
+    }
+
+    /// Returns the changes to the value of the storage key in the given range of blocks,
+    /// inclusive, as the block of each change and the value it set, and at most limit of them.
+    pub fn get_storage_history(
+        &self,
+        address: &ContractAddress,
+        key: &StorageKey,
+        from_block_number: BlockNumber,
+        to_block_number: BlockNumber,
+        limit: usize,
+    ) -> StorageResult<Vec<(BlockNumber, StarkFelt)>> {
+        let mut cursor = self.storage_table.cursor(self.txn)?;
+        let mut current = cursor.lower_bound(&(*address, *key, from_block_number))?;
+        let mut changes = Vec::new();
+        while let Some(((got_address, got_key, got_block_number), value)) = current {
+            if got_address != *address
+                || got_key != *key
+                || got_block_number > to_block_number
+                || changes.len() == limit
+            {
+                break;
+            }
+            changes.push((got_block_number, value));
+            current = cursor.next()?;
+        }
+        Ok(changes)
+    }
+
     pub fn get_class_definition_at(
         &self,
         state_number: StateNumber,
@@ -187,13 +362,49 @@ impl<'env, Mode: TransactionKind> StateReader<'env, Mode> {
         }
         Ok(None)
     }
+
+    /// Returns the Sierra class with the class hash, if it was declared before the state number.
+    pub fn get_sierra_class_definition_at(
+        &self,
+        state_number: StateNumber,
+        class_hash: &ClassHash,
+    ) -> StorageResult<Option<SierraContractClass>> {
+        let value = self.sierra_classes_table.get(self.txn, class_hash)?;
+        if let Some(value) = value {
+            if state_number.is_after(value.block_number) {
+                return Ok(Some(value.contract_class));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the hash of the compiled class of the Sierra class, if it was declared before the
+    /// state number.
+    pub fn get_compiled_class_hash_at(
+        &self,
+        state_number: StateNumber,
+        class_hash: &ClassHash,
+    ) -> StorageResult<Option<CompiledClassHash>> {
+        let db_key = (*class_hash, state_number.block_after());
+        let mut cursor = self.compiled_class_hashes_table.cursor(self.txn)?;
+        cursor.lower_bound(&db_key)?;
+        match cursor.prev()? {
+            Some(((got_class_hash, _got_block_number), compiled_class_hash))
+                if got_class_hash == *class_hash =>
+            {
+                Ok(Some(compiled_class_hash))
+            }
+            _ => Ok(None),
+        }
+    }
 }
 
 impl<'env> StateStorageWriter for StorageTxn<'env, RW> {
-    fn append_state_diff(
+    fn append_state_diff_with_cairo1(
         self,
         block_number: BlockNumber,
         state_diff: StateDiff,
+        cairo1_state_diff: Cairo1StateDiff,
         mut deployed_contract_class_definitions: IndexMap<ClassHash, ContractClass>,
     ) -> StorageResult<Self> {
         let markers_table = self.txn.open_table(&self.tables.markers)?;
@@ -202,6 +413,10 @@ impl<'env> StateStorageWriter for StorageTxn<'env, RW> {
         let declared_classes_table = self.txn.open_table(&self.tables.declared_classes)?;
         let storage_table = self.txn.open_table(&self.tables.contract_storage)?;
         let state_diffs_table = self.txn.open_table(&self.tables.state_diffs)?;
+        let sierra_classes_table = self.txn.open_table(&self.tables.sierra_classes)?;
+        let compiled_class_hashes_table =
+            self.txn.open_table(&self.tables.compiled_class_hashes)?;
+        let replaced_classes_table = self.txn.open_table(&self.tables.replaced_classes)?;
 
         update_marker(&self.txn, &markers_table, block_number)?;
 
@@ -215,10 +430,18 @@ impl<'env> StateStorageWriter for StorageTxn<'env, RW> {
         )?;
         write_storage_diffs(&state_diff.storage_diffs, &self.txn, block_number, &storage_table)?;
         write_nonces(&state_diff.nonces, &self.txn, block_number, &nonces_table)?;
+        write_replaced_classes(
+            &cairo1_state_diff.replaced_classes,
+            &self.txn,
+            block_number,
+            &replaced_classes_table,
+        )?;
 
         // Write state diff.
-        let (thin_state_diff, declared_classes) = ThinStateDiff::from_state_diff(state_diff);
+        let (mut thin_state_diff, declared_classes) = ThinStateDiff::from_state_diff(state_diff);
+        let sierra_classes = thin_state_diff.add_cairo1_state_diff(cairo1_state_diff);
         state_diffs_table.insert(&self.txn, &block_number, &thin_state_diff)?;
+        update_state_commitment(&self, block_number, &thin_state_diff)?;
 
         // Write declared classes.
         if !deployed_contract_class_definitions.is_empty() {
@@ -242,6 +465,14 @@ impl<'env> StateStorageWriter for StorageTxn<'env, RW> {
                 &declared_classes_table,
             )?;
         }
+        write_sierra_classes(
+            sierra_classes,
+            &thin_state_diff.declared_classes,
+            &self.txn,
+            block_number,
+            &sierra_classes_table,
+            &compiled_class_hashes_table,
+        )?;
 
         Ok(self)
     }
@@ -256,6 +487,10 @@ impl<'env> StateStorageWriter for StorageTxn<'env, RW> {
         let nonces_table = self.txn.open_table(&self.tables.nonces)?;
         let storage_table = self.txn.open_table(&self.tables.contract_storage)?;
         let state_diffs_table = self.txn.open_table(&self.tables.state_diffs)?;
+        let sierra_classes_table = self.txn.open_table(&self.tables.sierra_classes)?;
+        let compiled_class_hashes_table =
+            self.txn.open_table(&self.tables.compiled_class_hashes)?;
+        let replaced_classes_table = self.txn.open_table(&self.tables.replaced_classes)?;
 
         let current_state_marker = self.get_state_marker()?;
 
@@ -268,6 +503,10 @@ impl<'env> StateStorageWriter for StorageTxn<'env, RW> {
             );
             return Ok((self, None));
         }
+        // The versions the block superseded were deleted by the pruning.
+        if block_number < self.get_pruned_state_marker()? {
+            return Err(StorageError::StatePruned { block_number });
+        }
 
         let thin_state_diff = self
             .get_state_diff(block_number)?
@@ -288,7 +527,23 @@ impl<'env> StateStorageWriter for StorageTxn<'env, RW> {
         )?;
         delete_storage_diffs(&self.txn, block_number, &thin_state_diff, &storage_table)?;
         delete_nonces(&self.txn, block_number, &thin_state_diff, &nonces_table)?;
+        // The definitions of the reverted Sierra classes aren't kept, only their hashes in the
+        // reverted state diff.
+        delete_sierra_classes(
+            &self.txn,
+            block_number,
+            &thin_state_diff,
+            &sierra_classes_table,
+            &compiled_class_hashes_table,
+        )?;
+        delete_replaced_classes(
+            &self.txn,
+            block_number,
+            &thin_state_diff,
+            &replaced_classes_table,
+        )?;
         state_diffs_table.delete(&self.txn, &block_number)?;
+        revert_state_commitment(&self, block_number, &thin_state_diff)?;
 
         Ok((self, Some((thin_state_diff, deleted_classes))))
     }
@@ -334,6 +589,34 @@ fn write_declared_classes<'env>(
     Ok(())
 }
 
+fn write_sierra_classes<'env>(
+    sierra_classes: IndexMap<ClassHash, SierraContractClass>,
+    compiled_class_hashes: &IndexMap<ClassHash, CompiledClassHash>,
+    txn: &DbTransaction<'env, RW>,
+    block_number: BlockNumber,
+    sierra_classes_table: &'env SierraClassesTable<'env>,
+    compiled_class_hashes_table: &'env CompiledClassHashesTable<'env>,
+) -> StorageResult<()> {
+    for (class_hash, contract_class) in sierra_classes {
+        let value = IndexedSierraClass { block_number, contract_class };
+        sierra_classes_table.insert(txn, &class_hash, &value).map_err(|err| {
+            if matches!(err, DbError::Inner(libmdbx::Error::KeyExist)) {
+                StorageError::ClassAlreadyExists { class_hash }
+            } else {
+                StorageError::from(err)
+            }
+        })?;
+    }
+    for (class_hash, compiled_class_hash) in compiled_class_hashes {
+        compiled_class_hashes_table.upsert(
+            txn,
+            &(*class_hash, block_number),
+            compiled_class_hash,
+        )?;
+    }
+    Ok(())
+}
+
 fn write_deployed_contracts<'env>(
     deployed_contracts: &IndexMap<ContractAddress, ClassHash>,
     txn: &DbTransaction<'env, RW>,
@@ -378,6 +661,18 @@ fn write_nonces<'env>(
     Ok(())
 }
 
+fn write_replaced_classes<'env>(
+    replaced_classes: &IndexMap<ContractAddress, ClassHash>,
+    txn: &DbTransaction<'env, RW>,
+    block_number: BlockNumber,
+    replaced_classes_table: &'env ReplacedClassesTable<'env>,
+) -> StorageResult<()> {
+    for (contract_address, class_hash) in replaced_classes {
+        replaced_classes_table.upsert(txn, &(*contract_address, block_number), class_hash)?;
+    }
+    Ok(())
+}
+
 fn write_storage_diffs<'env>(
     storage_diffs: &IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>>,
     txn: &DbTransaction<'env, RW>,
@@ -464,3 +759,29 @@ fn delete_nonces<'env>(
     }
     Ok(())
 }
+
+fn delete_sierra_classes<'env>(
+    txn: &'env DbTransaction<'env, RW>,
+    block_number: BlockNumber,
+    thin_state_diff: &ThinStateDiff,
+    sierra_classes_table: &'env SierraClassesTable<'env>,
+    compiled_class_hashes_table: &'env CompiledClassHashesTable<'env>,
+) -> StorageResult<()> {
+    for class_hash in thin_state_diff.declared_classes.keys() {
+        sierra_classes_table.delete(txn, class_hash)?;
+        compiled_class_hashes_table.delete(txn, &(*class_hash, block_number))?;
+    }
+    Ok(())
+}
+
+fn delete_replaced_classes<'env>(
+    txn: &'env DbTransaction<'env, RW>,
+    block_number: BlockNumber,
+    thin_state_diff: &ThinStateDiff,
+    replaced_classes_table: &'env ReplacedClassesTable<'env>,
+) -> StorageResult<()> {
+    for contract_address in thin_state_diff.replaced_classes.keys() {
+        replaced_classes_table.delete(txn, &(*contract_address, block_number))?;
+    }
+    Ok(())
+}
@@ -10,6 +10,7 @@ use starknet_api::state::{
 use starknet_api::{patricia_key, stark_felt};
 use test_utils::get_test_state_diff;
 
+use crate::state::data::{Cairo1StateDiff, CompiledClassHash, SierraContractClass};
 use crate::state::{StateStorageReader, StateStorageWriter, StorageError};
 use crate::test_utils::get_test_storage;
 use crate::{StorageWriter, ThinStateDiff};
@@ -157,6 +158,60 @@ fn append_state_diff() {
     assert_eq!(statetxn.get_storage_at(state0, &c1, &key0).unwrap(), stark_felt!("0x0"));
     assert_eq!(statetxn.get_storage_at(state1, &c1, &key0).unwrap(), stark_felt!("0x0"));
     assert_eq!(statetxn.get_storage_at(state2, &c1, &key0).unwrap(), stark_felt!("0x0"));
+
+    // Storage ranges. The keys are ordered by their value, so key1 precedes key0.
+    let start_key = StorageKey(patricia_key!("0x0"));
+    assert_eq!(statetxn.get_storage_range_at(state0, &c0, &start_key, 10).unwrap(), vec![]);
+    assert_eq!(
+        statetxn.get_storage_range_at(state1, &c0, &start_key, 10).unwrap(),
+        vec![(key1, stark_felt!("0x201")), (key0, stark_felt!("0x200"))]
+    );
+    assert_eq!(
+        statetxn.get_storage_range_at(state1, &c0, &start_key, 1).unwrap(),
+        vec![(key1, stark_felt!("0x201"))]
+    );
+    assert_eq!(
+        statetxn.get_storage_range_at(state1, &c0, &key0, 10).unwrap(),
+        vec![(key0, stark_felt!("0x200"))]
+    );
+    assert_eq!(
+        statetxn.get_storage_range_at(state2, &c0, &start_key, 10).unwrap(),
+        vec![(key0, stark_felt!("0x300"))]
+    );
+    assert_eq!(statetxn.get_storage_range_at(state2, &c1, &start_key, 10).unwrap(), vec![]);
+
+    // History.
+    assert_eq!(
+        statetxn.get_storage_history(&c0, &key1, BlockNumber(0), BlockNumber(1), 10).unwrap(),
+        vec![(BlockNumber(0), stark_felt!("0x201")), (BlockNumber(1), stark_felt!("0x0"))]
+    );
+    assert_eq!(
+        statetxn.get_storage_history(&c0, &key1, BlockNumber(1), BlockNumber(2), 10).unwrap(),
+        vec![(BlockNumber(1), stark_felt!("0x0"))]
+    );
+    assert_eq!(
+        statetxn.get_storage_history(&c0, &key0, BlockNumber(0), BlockNumber(0), 10).unwrap(),
+        vec![(BlockNumber(0), stark_felt!("0x200"))]
+    );
+    assert_eq!(
+        statetxn.get_storage_history(&c2, &key0, BlockNumber(0), BlockNumber(1), 10).unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        statetxn.get_nonce_history(&c0, BlockNumber(0), BlockNumber(1), 10).unwrap(),
+        vec![
+            (BlockNumber(0), Nonce(StarkHash::from(1))),
+            (BlockNumber(1), Nonce(StarkHash::from(2)))
+        ]
+    );
+    assert_eq!(
+        statetxn.get_nonce_history(&c2, BlockNumber(0), BlockNumber(0), 10).unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        statetxn.get_nonce_history(&c0, BlockNumber(0), BlockNumber(1), 1).unwrap(),
+        vec![(BlockNumber(0), Nonce(StarkHash::from(1)))]
+    );
 }
 
 #[test]
@@ -363,3 +418,137 @@ fn revert_state() {
         StarkFelt::from(0)
     );
 }
+
+#[test]
+fn append_cairo1_state_diff() {
+    let c0 = ContractAddress(patricia_key!("0x11"));
+    let cl0 = ClassHash(stark_felt!("0x4"));
+    let sierra_cl0 = ClassHash(stark_felt!("0x5"));
+    let sierra_cl1 = ClassHash(stark_felt!("0x6"));
+    let compiled_cl0 = CompiledClassHash(stark_felt!("0x7"));
+    let compiled_cl1 = CompiledClassHash(stark_felt!("0x8"));
+    let sierra_class0 =
+        SierraContractClass { contract_class_version: "0.1.0".to_owned(), ..Default::default() };
+    let sierra_class1 =
+        SierraContractClass { sierra_program: vec![stark_felt!("0x1")], ..Default::default() };
+    let diff0 = StateDiff {
+        deployed_contracts: IndexMap::from([(c0, cl0)]),
+        storage_diffs: IndexMap::new(),
+        declared_classes: IndexMap::from([(cl0, ContractClass::default())]),
+        nonces: IndexMap::new(),
+    };
+    let cairo1_diff0 = Cairo1StateDiff {
+        declared_classes: IndexMap::from([(sierra_cl0, (compiled_cl0, sierra_class0.clone()))]),
+        replaced_classes: IndexMap::new(),
+    };
+    let cairo1_diff1 = Cairo1StateDiff {
+        declared_classes: IndexMap::from([(sierra_cl1, (compiled_cl1, sierra_class1.clone()))]),
+        replaced_classes: IndexMap::from([(c0, sierra_cl0)]),
+    };
+
+    let (reader, mut writer) = get_test_storage();
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_state_diff_with_cairo1(BlockNumber(0), diff0, cairo1_diff0, IndexMap::new())
+        .unwrap()
+        .append_state_diff_with_cairo1(
+            BlockNumber(1),
+            StateDiff::default(),
+            cairo1_diff1,
+            IndexMap::new(),
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let txn = reader.begin_ro_txn().unwrap();
+    let thin_diff1 = txn.get_state_diff(BlockNumber(1)).unwrap().unwrap();
+    assert_eq!(thin_diff1.declared_classes, IndexMap::from([(sierra_cl1, compiled_cl1)]));
+    assert_eq!(thin_diff1.replaced_classes, IndexMap::from([(c0, sierra_cl0)]));
+
+    let state0 = StateNumber::right_before_block(BlockNumber(0));
+    let state1 = StateNumber::right_before_block(BlockNumber(1));
+    let state2 = StateNumber::right_before_block(BlockNumber(2));
+    let statetxn = txn.get_state_reader().unwrap();
+
+    assert_eq!(statetxn.get_sierra_class_definition_at(state0, &sierra_cl0).unwrap(), None);
+    assert_eq!(
+        statetxn.get_sierra_class_definition_at(state1, &sierra_cl0).unwrap(),
+        Some(sierra_class0)
+    );
+    assert_eq!(statetxn.get_sierra_class_definition_at(state1, &sierra_cl1).unwrap(), None);
+    assert_eq!(
+        statetxn.get_sierra_class_definition_at(state2, &sierra_cl1).unwrap(),
+        Some(sierra_class1)
+    );
+
+    assert_eq!(statetxn.get_compiled_class_hash_at(state0, &sierra_cl0).unwrap(), None);
+    assert_eq!(
+        statetxn.get_compiled_class_hash_at(state1, &sierra_cl0).unwrap(),
+        Some(compiled_cl0)
+    );
+    assert_eq!(
+        statetxn.get_compiled_class_hash_at(state2, &sierra_cl0).unwrap(),
+        Some(compiled_cl0)
+    );
+    assert_eq!(statetxn.get_compiled_class_hash_at(state1, &sierra_cl1).unwrap(), None);
+    assert_eq!(
+        statetxn.get_compiled_class_hash_at(state2, &sierra_cl1).unwrap(),
+        Some(compiled_cl1)
+    );
+
+    // The class of the contract is replaced by block 1.
+    assert_eq!(statetxn.get_class_hash_at(state0, &c0).unwrap(), None);
+    assert_eq!(statetxn.get_class_hash_at(state1, &c0).unwrap(), Some(cl0));
+    assert_eq!(statetxn.get_class_hash_at(state2, &c0).unwrap(), Some(sierra_cl0));
+}
+
+#[test]
+fn revert_cairo1_state_diff() {
+    let c0 = ContractAddress(patricia_key!("0x11"));
+    let cl0 = ClassHash(stark_felt!("0x4"));
+    let sierra_cl0 = ClassHash(stark_felt!("0x5"));
+    let diff0 = StateDiff {
+        deployed_contracts: IndexMap::from([(c0, cl0)]),
+        storage_diffs: IndexMap::new(),
+        declared_classes: IndexMap::from([(cl0, ContractClass::default())]),
+        nonces: IndexMap::new(),
+    };
+    let cairo1_diff1 = Cairo1StateDiff {
+        declared_classes: IndexMap::from([(
+            sierra_cl0,
+            (CompiledClassHash(stark_felt!("0x7")), SierraContractClass::default()),
+        )]),
+        replaced_classes: IndexMap::from([(c0, sierra_cl0)]),
+    };
+
+    let (reader, mut writer) = get_test_storage();
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_state_diff(BlockNumber(0), diff0, IndexMap::new())
+        .unwrap()
+        .append_state_diff_with_cairo1(
+            BlockNumber(1),
+            StateDiff::default(),
+            cairo1_diff1,
+            IndexMap::new(),
+        )
+        .unwrap()
+        .commit()
+        .unwrap();
+
+    let (txn, reverted) = writer.begin_rw_txn().unwrap().revert_state_diff(BlockNumber(1)).unwrap();
+    txn.commit().unwrap();
+    let (reverted_diff, _) = reverted.unwrap();
+    assert!(reverted_diff.declared_classes.contains_key(&sierra_cl0));
+    assert!(reverted_diff.replaced_classes.contains_key(&c0));
+
+    let txn = reader.begin_ro_txn().unwrap();
+    let statetxn = txn.get_state_reader().unwrap();
+    let state = StateNumber::right_after_block(BlockNumber(1));
+    assert_eq!(statetxn.get_sierra_class_definition_at(state, &sierra_cl0).unwrap(), None);
+    assert_eq!(statetxn.get_compiled_class_hash_at(state, &sierra_cl0).unwrap(), None);
+    assert_eq!(statetxn.get_class_hash_at(state, &c0).unwrap(), Some(cl0));
+}
@@ -1,22 +1,29 @@
+use std::collections::HashMap;
+
 use indexmap::IndexMap;
 use rand::Rng;
 use rand_chacha::ChaCha8Rng;
 use starknet_api::block::{BlockHash, BlockNumber};
-use starknet_api::core::{ClassHash, ContractAddress, Nonce};
-use starknet_api::hash::StarkFelt;
-use starknet_api::state::{ContractClass, StorageKey};
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector, Nonce};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{ContractClass, EntryPointType, StorageKey};
 use starknet_api::transaction::{
     EventIndexInTransactionOutput, Fee, MessageToL1, TransactionOffsetInBlock,
 };
 use tempfile::tempdir;
 use test_utils::{auto_impl_get_test_instance, get_number_of_variants, GetTestInstance};
 
+use crate::body::bloom::EventBloom;
 use crate::body::events::{
     ThinDeclareTransactionOutput, ThinDeployAccountTransactionOutput, ThinDeployTransactionOutput,
     ThinInvokeTransactionOutput, ThinL1HandlerTransactionOutput, ThinTransactionOutput,
 };
 use crate::db::DbConfig;
-use crate::state::data::{IndexedDeclaredContract, IndexedDeployedContract, ThinStateDiff};
+use crate::state::commitment::{BinaryNode, EdgeNode, StateTrieRoots, TrieNode};
+use crate::state::data::{
+    CompiledClassHash, IndexedDeclaredContract, IndexedDeployedContract, IndexedSierraClass,
+    SierraContractClass, SierraEntryPoint, ThinStateDiff, ThinStateDiffV0,
+};
 use crate::{
     open_storage, EventIndex, MarkerKind, OmmerEventKey, OmmerTransactionKey, StorageReader,
     StorageWriter, TransactionIndex,
@@ -34,7 +41,30 @@ pub fn get_test_storage() -> (StorageReader, StorageWriter) {
     open_storage(config).unwrap()
 }
 
+/// Replaces the event bloom filter of the block, for checking that the blocks whose filter can't
+/// match a filter of events are skipped.
+pub fn set_event_bloom(writer: &mut StorageWriter, block_number: BlockNumber, bloom: &EventBloom) {
+    let txn = writer.begin_rw_txn().unwrap();
+    let event_blooms_table = txn.txn.open_table(&txn.tables.event_blooms).unwrap();
+    event_blooms_table.upsert(&txn.txn, &block_number, bloom).unwrap();
+    txn.commit().unwrap();
+}
+
 auto_impl_get_test_instance! {
+    pub struct BinaryNode {
+        pub left: StarkHash,
+        pub right: StarkHash,
+    }
+    pub struct CompiledClassHash(pub StarkHash);
+    pub struct EdgeNode {
+        pub child: StarkHash,
+        pub path: StarkFelt,
+        pub length: u8,
+    }
+    pub struct EventBloom {
+        pub hash_count: u8,
+        pub bits: Vec<u8>,
+    }
     struct EventIndex(pub TransactionIndex, pub EventIndexInTransactionOutput);
     pub struct IndexedDeclaredContract {
         pub block_number: BlockNumber,
@@ -44,13 +74,34 @@ auto_impl_get_test_instance! {
         pub block_number: BlockNumber,
         pub class_hash: ClassHash,
     }
+    pub struct IndexedSierraClass {
+        pub block_number: BlockNumber,
+        pub contract_class: SierraContractClass,
+    }
     enum MarkerKind {
         Header = 0,
         Body = 1,
         State = 2,
+        BaseLayerBlock = 3,
+        StorageVersion = 4,
+        PrunedState = 5,
     }
     struct OmmerTransactionKey(pub BlockHash, pub TransactionOffsetInBlock);
     struct OmmerEventKey(pub OmmerTransactionKey, pub EventIndexInTransactionOutput);
+    pub struct SierraContractClass {
+        pub sierra_program: Vec<StarkFelt>,
+        pub contract_class_version: String,
+        pub entry_points_by_type: HashMap<EntryPointType, Vec<SierraEntryPoint>>,
+        pub abi: String,
+    }
+    pub struct SierraEntryPoint {
+        pub function_idx: usize,
+        pub selector: EntryPointSelector,
+    }
+    pub struct StateTrieRoots {
+        pub contracts_root: StarkHash,
+        pub classes_root: StarkHash,
+    }
     pub struct ThinDeclareTransactionOutput {
         pub actual_fee: Fee,
         pub messages_sent: Vec<MessageToL1>,
@@ -81,6 +132,14 @@ auto_impl_get_test_instance! {
         pub storage_diffs: IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>>,
         pub declared_contract_hashes: Vec<ClassHash>,
         pub nonces: IndexMap<ContractAddress, Nonce>,
+        pub declared_classes: IndexMap<ClassHash, CompiledClassHash>,
+        pub replaced_classes: IndexMap<ContractAddress, ClassHash>,
+    }
+    pub struct ThinStateDiffV0 {
+        pub deployed_contracts: IndexMap<ContractAddress, ClassHash>,
+        pub storage_diffs: IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>>,
+        pub declared_contract_hashes: Vec<ClassHash>,
+        pub nonces: IndexMap<ContractAddress, Nonce>,
     }
     pub enum ThinTransactionOutput {
         Declare(ThinDeclareTransactionOutput) = 0,
@@ -90,4 +149,8 @@ auto_impl_get_test_instance! {
         L1Handler(ThinL1HandlerTransactionOutput) = 4,
     }
     struct TransactionIndex(pub BlockNumber, pub TransactionOffsetInBlock);
+    pub enum TrieNode {
+        Binary(BinaryNode) = 0,
+        Edge(EdgeNode) = 1,
+    }
 }
@@ -0,0 +1,153 @@
+//! The version of the on-disk encoding of the storage, and the migrations between versions.
+//!
+//! The version is kept in the markers table. [`open_storage`] refuses to open a storage of a
+//! different version, and [`migrate_storage`] upgrades an older storage by running the registered
+//! migrations one version at a time.
+//!
+//! [`open_storage`]: crate::open_storage
+
+#[cfg(test)]
+#[path = "version_test.rs"]
+mod version_test;
+
+use starknet_api::block::BlockNumber;
+use tracing::info;
+
+use crate::body::{backfill_event_keys, backfill_sender_transactions};
+use crate::db::{DbConfig, TransactionKind, RW};
+use crate::state::data::migrate_thin_state_diff_from_v0;
+use crate::{
+    open_storage_unchecked, MarkerKind, StorageError, StorageReader, StorageResult, StorageTxn,
+    StorageWriter,
+};
+
+/// The version of the encoding of the tables written by this code. It must be increased, with a
+/// matching migration in [`MIGRATIONS`], whenever the encoding of a table changes or a table that
+/// must be filled from the stored blocks is added.
+pub const STORAGE_VERSION: u64 = 2;
+// The number of entries rewritten in each write transaction of a migration.
+const MIGRATION_BATCH_SIZE: usize = 10000;
+
+/// Rewrites a serialized value of a table from the encoding of one version to the next. Returns
+/// None if the value can't be decoded. A migration may be interrupted between its write
+/// transactions and rerun, so rewriting an already rewritten value must leave it unchanged.
+pub(crate) type ValueRewrite = fn(&[u8]) -> Option<Vec<u8>>;
+
+/// Fills a table that a version added from the existing tables. A backfill may be interrupted
+/// between its write transactions and rerun, so adding an entry that already exists must leave it
+/// unchanged.
+pub(crate) type Backfill = fn(&StorageReader, &mut StorageWriter) -> StorageResult<()>;
+
+/// A migration of the storage from `from_version` to the next version.
+pub(crate) struct Migration {
+    pub from_version: u64,
+    /// The tables to rewrite, with the rewrite of their values.
+    pub table_rewrites: &'static [(&'static str, ValueRewrite)],
+    /// The tables to fill, with their backfill, run after the rewrites.
+    pub backfills: &'static [(&'static str, Backfill)],
+}
+
+/// The registered migrations, ordered by the version they upgrade from.
+pub(crate) static MIGRATIONS: &[Migration] = &[
+    // Version 1 added the declared Sierra classes and the replaced classes to the state diffs.
+    Migration {
+        from_version: 0,
+        table_rewrites: &[
+            ("ommer_state_diffs", migrate_thin_state_diff_from_v0),
+            ("state_diffs", migrate_thin_state_diff_from_v0),
+        ],
+        backfills: &[],
+    },
+    // Version 2 added the indexes of the events by their keys and of the transactions by their
+    // sender.
+    Migration {
+        from_version: 1,
+        table_rewrites: &[],
+        backfills: &[
+            ("event_keys", backfill_event_keys),
+            ("sender_transactions", backfill_sender_transactions),
+        ],
+    },
+];
+
+/// Upgrades the storage to [`STORAGE_VERSION`]. Returns the version the storage was in.
+pub fn migrate_storage(db_config: DbConfig) -> StorageResult<u64> {
+    let (reader, mut writer) = open_storage_unchecked(db_config)?;
+    run_migrations(&reader, &mut writer, MIGRATIONS, STORAGE_VERSION)
+}
+
+// Sets the version of a new storage, and checks the version of an existing one.
+pub(crate) fn verify_storage_version(
+    reader: &StorageReader,
+    writer: &mut StorageWriter,
+) -> StorageResult<()> {
+    let stored_version = init_storage_version(reader, writer)?;
+    if stored_version != STORAGE_VERSION {
+        return Err(StorageError::StorageVersionMismatch {
+            stored_version,
+            expected_version: STORAGE_VERSION,
+        });
+    }
+    Ok(())
+}
+
+fn run_migrations(
+    reader: &StorageReader,
+    writer: &mut StorageWriter,
+    migrations: &[Migration],
+    target_version: u64,
+) -> StorageResult<u64> {
+    let initial_version = init_storage_version(reader, writer)?;
+    if initial_version > target_version {
+        return Err(StorageError::StorageVersionMismatch {
+            stored_version: initial_version,
+            expected_version: target_version,
+        });
+    }
+    for version in initial_version..target_version {
+        let migration = migrations
+            .iter()
+            .find(|migration| migration.from_version == version)
+            .ok_or(StorageError::MissingMigration { from_version: version })?;
+        for (table_name, rewrite) in migration.table_rewrites {
+            info!("Migrating table {table_name} from storage version {version}.");
+            writer.db_writer.rewrite_raw_values(table_name, MIGRATION_BATCH_SIZE, rewrite)?;
+        }
+        for (table_name, backfill) in migration.backfills {
+            info!("Filling table {table_name} from storage version {version}.");
+            backfill(reader, writer)?;
+        }
+        let txn = writer.begin_rw_txn()?;
+        set_storage_version(&txn, version + 1)?;
+        txn.commit()?;
+        info!("Migrated the storage to version {}.", version + 1);
+    }
+    Ok(initial_version)
+}
+
+// Returns the stored version, after setting it if the storage has none. A new storage gets the
+// current version, and a storage from before the version was kept gets the first version.
+fn init_storage_version(reader: &StorageReader, writer: &mut StorageWriter) -> StorageResult<u64> {
+    if let Some(version) = get_storage_version(&reader.begin_ro_txn()?)? {
+        return Ok(version);
+    }
+    let is_new = reader.db_tables_stats()?.stats.values().all(|stats| stats.entries == 0);
+    let version = if is_new { STORAGE_VERSION } else { 0 };
+    let txn = writer.begin_rw_txn()?;
+    set_storage_version(&txn, version)?;
+    txn.commit()?;
+    Ok(version)
+}
+
+// The version is kept in the markers table as a block number.
+fn get_storage_version<Mode: TransactionKind>(
+    txn: &StorageTxn<'_, Mode>,
+) -> StorageResult<Option<u64>> {
+    let markers_table = txn.txn.open_table(&txn.tables.markers)?;
+    Ok(markers_table.get(&txn.txn, &MarkerKind::StorageVersion)?.map(|version| version.0))
+}
+
+fn set_storage_version(txn: &StorageTxn<'_, RW>, version: u64) -> StorageResult<()> {
+    let markers_table = txn.txn.open_table(&txn.tables.markers)?;
+    Ok(markers_table.upsert(&txn.txn, &MarkerKind::StorageVersion, &BlockNumber(version))?)
+}
@@ -0,0 +1,188 @@
+use assert_matches::assert_matches;
+use indexmap::IndexMap;
+use starknet_api::block::{BlockHash, BlockHeader, BlockNumber};
+use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::transaction::{EventKey, InvokeTransaction, Transaction, TransactionHash};
+use starknet_api::{patricia_key, stark_felt};
+use test_utils::{get_rng, get_test_block_with_events, GetTestInstance};
+
+use crate::body::BodyStorageWriter;
+use crate::db::serialization::StorageSerdeEx;
+use crate::header::{HeaderStorageReader, HeaderStorageWriter};
+use crate::state::data::{ThinStateDiff, ThinStateDiffV0};
+use crate::state::StateStorageReader;
+use crate::test_utils::{get_test_config, get_test_storage};
+use crate::version::{
+    get_storage_version, run_migrations, set_storage_version, Migration, MIGRATIONS,
+    STORAGE_VERSION,
+};
+use crate::{open_storage, EventIndex, StorageError, StorageReader, TransactionIndex};
+
+const MIGRATED_BLOCK_NUMBER: BlockNumber = BlockNumber(100);
+
+fn migrate_block_number(bytes: &[u8]) -> Option<Vec<u8>> {
+    BlockNumber::deserialize(&mut &bytes[..])?;
+    MIGRATED_BLOCK_NUMBER.serialize().ok()
+}
+
+static TEST_MIGRATIONS: &[Migration] = &[Migration {
+    from_version: 0,
+    table_rewrites: &[("block_hash_to_number", migrate_block_number)],
+    backfills: &[],
+}];
+
+#[test]
+fn new_storage_version() {
+    let db_config = get_test_config();
+    let (reader, _) = open_storage(db_config.clone()).unwrap();
+    assert_eq!(
+        get_storage_version(&reader.begin_ro_txn().unwrap()).unwrap(),
+        Some(STORAGE_VERSION)
+    );
+    drop(reader);
+
+    // Reopening a storage of the current version succeeds.
+    open_storage(db_config).unwrap();
+}
+
+#[test]
+fn open_storage_of_another_version() {
+    let db_config = get_test_config();
+    let (_, mut writer) = open_storage(db_config.clone()).unwrap();
+    let txn = writer.begin_rw_txn().unwrap();
+    set_storage_version(&txn, STORAGE_VERSION + 1).unwrap();
+    txn.commit().unwrap();
+    drop(writer);
+
+    assert_matches!(
+        open_storage(db_config).err(),
+        Some(StorageError::StorageVersionMismatch { stored_version, expected_version })
+        if stored_version == STORAGE_VERSION + 1 && expected_version == STORAGE_VERSION
+    );
+}
+
+#[test]
+fn migrate_tables() {
+    let (reader, mut writer) = get_test_storage();
+    let block_hash = BlockHash(stark_felt!("0x1"));
+    let header = BlockHeader { block_hash, ..BlockHeader::default() };
+    let txn = writer.begin_rw_txn().unwrap();
+    set_storage_version(&txn, 0).unwrap();
+    txn.append_header(BlockNumber(0), &header).unwrap().commit().unwrap();
+
+    let initial_version = run_migrations(&reader, &mut writer, TEST_MIGRATIONS, 1).unwrap();
+    assert_eq!(initial_version, 0);
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(get_storage_version(&txn).unwrap(), Some(1));
+    assert_eq!(txn.get_block_number_by_hash(&block_hash).unwrap(), Some(MIGRATED_BLOCK_NUMBER));
+    drop(txn);
+
+    // There is no migration from version 1.
+    assert_matches!(
+        run_migrations(&reader, &mut writer, TEST_MIGRATIONS, 2),
+        Err(StorageError::MissingMigration { from_version: 1 })
+    );
+}
+
+#[test]
+fn migrate_state_diffs_from_v0() {
+    let (reader, mut writer) = get_test_storage();
+    let address = ContractAddress(patricia_key!("0x1"));
+    let diff_v0 = ThinStateDiffV0 {
+        deployed_contracts: IndexMap::from([(address, ClassHash(stark_felt!("0x2")))]),
+        storage_diffs: IndexMap::new(),
+        declared_contract_hashes: vec![ClassHash(stark_felt!("0x2"))],
+        nonces: IndexMap::from([(address, Nonce(stark_felt!("0x3")))]),
+    };
+    let txn = writer.begin_rw_txn().unwrap();
+    set_storage_version(&txn, 0).unwrap();
+    txn.commit().unwrap();
+    writer
+        .db_writer
+        .insert_raw_entries(
+            "state_diffs",
+            &[(BlockNumber(0).serialize().unwrap(), diff_v0.serialize().unwrap())],
+        )
+        .unwrap();
+
+    run_migrations(&reader, &mut writer, MIGRATIONS, STORAGE_VERSION).unwrap();
+    let expected_diff = ThinStateDiff {
+        deployed_contracts: diff_v0.deployed_contracts,
+        storage_diffs: diff_v0.storage_diffs,
+        declared_contract_hashes: diff_v0.declared_contract_hashes,
+        nonces: diff_v0.nonces,
+        declared_classes: IndexMap::new(),
+        replaced_classes: IndexMap::new(),
+    };
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_state_diff(BlockNumber(0)).unwrap(), Some(expected_diff.clone()));
+    drop(txn);
+
+    // Rerunning an interrupted migration leaves the rewritten values unchanged.
+    let txn = writer.begin_rw_txn().unwrap();
+    set_storage_version(&txn, 0).unwrap();
+    txn.commit().unwrap();
+    run_migrations(&reader, &mut writer, MIGRATIONS, STORAGE_VERSION).unwrap();
+    let txn = reader.begin_ro_txn().unwrap();
+    assert_eq!(txn.get_state_diff(BlockNumber(0)).unwrap(), Some(expected_diff));
+}
+
+#[test]
+fn backfill_indexes_from_v1() {
+    let (reader, mut writer) = get_test_storage();
+    let mut block = get_test_block_with_events(2, 5);
+    block.body.transactions[0] =
+        Transaction::Invoke(InvokeTransaction::get_test_instance(&mut get_rng()));
+    let block_number = block.header.block_number;
+    writer
+        .begin_rw_txn()
+        .unwrap()
+        .append_header(block_number, &block.header)
+        .unwrap()
+        .append_body(block_number, block.body)
+        .unwrap()
+        .commit()
+        .unwrap();
+    let event_keys = get_event_keys_entries(&reader);
+    assert!(!event_keys.is_empty());
+    let sender_transactions = get_sender_transactions_entries(&reader);
+    assert!(!sender_transactions.is_empty());
+
+    // A storage of version 1 has no indexes of the events by their keys and of the transactions by
+    // their sender.
+    writer.db_writer.clear_tables(&["event_keys", "sender_transactions"]).unwrap();
+    let txn = writer.begin_rw_txn().unwrap();
+    set_storage_version(&txn, 1).unwrap();
+    txn.commit().unwrap();
+
+    run_migrations(&reader, &mut writer, MIGRATIONS, STORAGE_VERSION).unwrap();
+    assert_eq!(get_event_keys_entries(&reader), event_keys);
+    assert_eq!(get_sender_transactions_entries(&reader), sender_transactions);
+}
+
+fn get_event_keys_entries(
+    reader: &StorageReader,
+) -> Vec<((EventKey, EventIndex), ContractAddress)> {
+    let txn = reader.begin_ro_txn().unwrap();
+    let event_keys_table = txn.txn.open_table(&txn.tables.event_keys).unwrap();
+    let mut cursor = event_keys_table.cursor(&txn.txn).unwrap();
+    let mut entries = Vec::new();
+    while let Some(entry) = cursor.next().unwrap() {
+        entries.push(entry);
+    }
+    entries
+}
+
+fn get_sender_transactions_entries(
+    reader: &StorageReader,
+) -> Vec<((ContractAddress, TransactionIndex), TransactionHash)> {
+    let txn = reader.begin_ro_txn().unwrap();
+    let sender_transactions_table = txn.txn.open_table(&txn.tables.sender_transactions).unwrap();
+    let mut cursor = sender_transactions_table.cursor(&txn.txn).unwrap();
+    let mut entries = Vec::new();
+    while let Some(entry) = cursor.next().unwrap() {
+        entries.push(entry);
+    }
+    entries
+}
@@ -0,0 +1,434 @@
+//! Calculation of block hashes and transaction hashes, for verifying the blocks received from the
+//! central source instead of trusting it.
+//!
+//! The hashes are calculated with the Pedersen hash over the Stark field, as in Starknet:
+//! * A transaction hash is the hash chain of a prefix of its type, its version and its fields.
+//! * A block hash is the hash chain of the header fields and the transaction and event
+//!   commitments, which are the roots of height-64 Patricia-Merkle trees of the transactions (with
+//!   their signatures) and of the events, indexed by their order in the block.
+//!
+//! Starknet changed the formulas over its versions, so the hashes of old blocks and transactions
+//! are checked against the legacy formulas as well.
+//!
+//! The hash of a declare transaction of version 2 includes the compiled class hash of the declared
+//! class, which is only part of the state diff of the block, so it's verified when the state diff
+//! arrives, see [`verify_declare_v2_hashes`].
+
+#[cfg(test)]
+#[path = "block_hash_test.rs"]
+mod block_hash_test;
+
+use papyrus_storage::state::data::Cairo1StateDiff;
+use starknet_api::block::{Block, BlockNumber};
+use starknet_api::core::ChainId;
+use starknet_api::hash::StarkFelt;
+use starknet_api::transaction::{
+    DeclareTransaction, DeployAccountTransaction, DeployTransaction, Event, InvokeTransaction,
+    L1HandlerTransaction, Transaction, TransactionHash,
+};
+use starknet_crypto::{pedersen_hash, FieldElement};
+
+// The version of the declare transactions whose hash includes the compiled class hash.
+const DECLARE_V2_VERSION: u64 = 2;
+// The height of the transaction and event commitment trees.
+const COMMITMENT_TREE_HEIGHT: u8 = 64;
+// The selector of the constructor entry point, starknet_keccak("constructor").
+const CONSTRUCTOR_ENTRY_POINT_SELECTOR: [u8; 32] = [
+    0x02, 0x8f, 0xfe, 0x4f, 0xf0, 0xf2, 0x26, 0xa9, 0x10, 0x72, 0x53, 0xe1, 0x7a, 0x90, 0x40, 0x99,
+    0xaa, 0x4f, 0x63, 0xa0, 0x2a, 0x56, 0x21, 0xde, 0x05, 0x76, 0xe5, 0xaa, 0x71, 0xbc, 0x51, 0x94,
+];
+
+/// A block or transaction hash that doesn't match its content.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum HashVerificationError {
+    #[error("Block hash of block {block_number} doesn't match its content.")]
+    BlockHash { block_number: BlockNumber },
+    #[error("Transaction hash {tx_hash:?} doesn't match the transaction.")]
+    TransactionHash { tx_hash: TransactionHash },
+}
+
+/// Verifies the hashes of the transactions of the block, and then the block hash.
+pub fn verify_block_hashes(block: &Block, chain_id: &ChainId) -> Result<(), HashVerificationError> {
+    let chain_id = ascii_as_felt(&chain_id.0);
+    for tx in &block.body.transactions {
+        if !is_valid_transaction_hash(tx, chain_id) {
+            return Err(HashVerificationError::TransactionHash { tx_hash: tx.transaction_hash() });
+        }
+    }
+    let block_hash = felt(&block.header.block_hash.0);
+    if !block_hash_candidates(block, chain_id).contains(&block_hash) {
+        return Err(HashVerificationError::BlockHash { block_number: block.header.block_number });
+    }
+    Ok(())
+}
+
+/// Verifies the hashes of the declare transactions of version 2 of a block, with the compiled class
+/// hashes of the classes they declared in the state diff of the block.
+pub fn verify_declare_v2_hashes(
+    transactions: &[Transaction],
+    cairo1_state_diff: &Cairo1StateDiff,
+    chain_id: &ChainId,
+) -> Result<(), HashVerificationError> {
+    let chain_id = ascii_as_felt(&chain_id.0);
+    for tx in transactions {
+        let tx = match tx {
+            Transaction::Declare(tx) if felt(&tx.version.0) == u64_as_felt(DECLARE_V2_VERSION) => {
+                tx
+            }
+            _ => continue,
+        };
+        let compiled_class_hash = cairo1_state_diff
+            .declared_classes
+            .get(&tx.class_hash)
+            .map(|(compiled_class_hash, _)| felt(&compiled_class_hash.0));
+        if !declare_hash_candidates(tx, chain_id, compiled_class_hash)
+            .contains(&felt(&tx.transaction_hash.0))
+        {
+            return Err(HashVerificationError::TransactionHash { tx_hash: tx.transaction_hash });
+        }
+    }
+    Ok(())
+}
+
+/// Calculates the hash of an invoke transaction by the current formula, as the sequencer would for
+/// a transaction sent to it.
+pub fn calculate_invoke_transaction_hash(
+    tx: &InvokeTransaction,
+    chain_id: &ChainId,
+) -> TransactionHash {
+    let tx_hash = invoke_hash_candidates(tx, ascii_as_felt(&chain_id.0))[0];
+    TransactionHash(StarkFelt::new(tx_hash.to_bytes_be()).expect("A field element is a felt."))
+}
+
+// Returns the hashes of the block by the current formula and by the formula before Starknet v0.7.
+fn block_hash_candidates(block: &Block, chain_id: FieldElement) -> [FieldElement; 2] {
+    let header = &block.header;
+    let transactions = &block.body.transactions;
+    let events: Vec<&Event> =
+        block.body.transaction_outputs.iter().flat_map(|output| output.events()).collect();
+    let block_number = u64_as_felt(header.block_number.0);
+    let state_root = felt(&header.state_root.0);
+    let tx_count = u64_as_felt(transactions.len() as u64);
+    let tx_commitment = calculate_commitment(
+        transactions
+            .iter()
+            .map(|tx| pedersen_hash(&felt(&tx.transaction_hash().0), &hash_array(&signature(tx)))),
+    );
+    let parent_hash = felt(&header.parent_hash.0);
+    [
+        hash_array(&[
+            block_number,
+            state_root,
+            felt(header.sequencer.0.key()),
+            u64_as_felt(header.timestamp.0),
+            tx_count,
+            tx_commitment,
+            u64_as_felt(events.len() as u64),
+            calculate_commitment(events.into_iter().map(event_hash)),
+            // Reserved for the protocol version and extra data.
+            FieldElement::ZERO,
+            FieldElement::ZERO,
+            parent_hash,
+        ]),
+        hash_array(&[
+            block_number,
+            state_root,
+            // The sequencer address, timestamp, event count, event commitment, protocol version
+            // and extra data weren't part of the hash.
+            FieldElement::ZERO,
+            FieldElement::ZERO,
+            tx_count,
+            tx_commitment,
+            FieldElement::ZERO,
+            FieldElement::ZERO,
+            FieldElement::ZERO,
+            FieldElement::ZERO,
+            chain_id,
+            parent_hash,
+        ]),
+    ]
+}
+
+// Only the signatures of invoke transactions are part of the transaction commitment.
+fn signature(tx: &Transaction) -> Vec<FieldElement> {
+    match tx {
+        Transaction::Invoke(tx) => tx.signature.0.iter().map(felt).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn event_hash(event: &Event) -> FieldElement {
+    hash_array(&[
+        felt(event.from_address.0.key()),
+        hash_array(&event.content.keys.iter().map(|key| felt(&key.0)).collect::<Vec<_>>()),
+        hash_array(&event.content.data.0.iter().map(felt).collect::<Vec<_>>()),
+    ])
+}
+
+// Returns whether the hash of the transaction matches its fields. Declare transactions of version 2
+// are accepted here, as their hash is verified with the state diff of the block.
+fn is_valid_transaction_hash(tx: &Transaction, chain_id: FieldElement) -> bool {
+    let tx_hash = felt(&tx.transaction_hash().0);
+    let candidates = match tx {
+        Transaction::Declare(tx) if felt(&tx.version.0) == u64_as_felt(DECLARE_V2_VERSION) => {
+            return true;
+        }
+        Transaction::Declare(tx) => declare_hash_candidates(tx, chain_id, None),
+        Transaction::Deploy(tx) => deploy_hash_candidates(tx, chain_id),
+        Transaction::DeployAccount(tx) => deploy_account_hash_candidates(tx, chain_id),
+        Transaction::Invoke(tx) => invoke_hash_candidates(tx, chain_id),
+        Transaction::L1Handler(tx) => l1_handler_hash_candidates(tx, chain_id),
+    };
+    candidates.contains(&tx_hash)
+}
+
+// Returns no candidates for an unknown version, or for version 2 without the compiled class hash.
+fn declare_hash_candidates(
+    tx: &DeclareTransaction,
+    chain_id: FieldElement,
+    compiled_class_hash: Option<FieldElement>,
+) -> Vec<FieldElement> {
+    let version = felt(&tx.version.0);
+    let prefix = ascii_as_felt("declare");
+    let sender_address = felt(tx.sender_address.0.key());
+    let max_fee = u128_as_felt(tx.max_fee.0);
+    let class_hash = felt(&tx.class_hash.0);
+    if version == FieldElement::ZERO {
+        vec![hash_array(&[
+            prefix,
+            version,
+            sender_address,
+            FieldElement::ZERO,
+            hash_array(&[]),
+            max_fee,
+            chain_id,
+            class_hash,
+        ])]
+    } else if version == FieldElement::ONE {
+        vec![hash_array(&[
+            prefix,
+            version,
+            sender_address,
+            FieldElement::ZERO,
+            hash_array(&[class_hash]),
+            max_fee,
+            chain_id,
+            felt(&tx.nonce.0),
+        ])]
+    } else if version == u64_as_felt(DECLARE_V2_VERSION) {
+        compiled_class_hash
+            .map(|compiled_class_hash| {
+                hash_array(&[
+                    prefix,
+                    version,
+                    sender_address,
+                    FieldElement::ZERO,
+                    hash_array(&[class_hash]),
+                    max_fee,
+                    chain_id,
+                    felt(&tx.nonce.0),
+                    compiled_class_hash,
+                ])
+            })
+            .into_iter()
+            .collect()
+    } else {
+        vec![]
+    }
+}
+
+fn deploy_hash_candidates(tx: &DeployTransaction, chain_id: FieldElement) -> Vec<FieldElement> {
+    let prefix = ascii_as_felt("deploy");
+    let contract_address = felt(tx.contract_address.0.key());
+    let constructor_selector = FieldElement::from_bytes_be(&CONSTRUCTOR_ENTRY_POINT_SELECTOR)
+        .expect("The constructor selector is a field element.");
+    let calldata_hash = hash_array(&tx.constructor_calldata.0.iter().map(felt).collect::<Vec<_>>());
+    vec![
+        hash_array(&[
+            prefix,
+            felt(&tx.version.0),
+            contract_address,
+            constructor_selector,
+            calldata_hash,
+            FieldElement::ZERO,
+            chain_id,
+        ]),
+        // Before the version and max fee were part of the hash.
+        hash_array(&[prefix, contract_address, constructor_selector, calldata_hash, chain_id]),
+    ]
+}
+
+fn deploy_account_hash_candidates(
+    tx: &DeployAccountTransaction,
+    chain_id: FieldElement,
+) -> Vec<FieldElement> {
+    let mut calldata = vec![felt(&tx.class_hash.0), felt(&tx.contract_address_salt.0)];
+    calldata.extend(tx.constructor_calldata.0.iter().map(felt));
+    vec![hash_array(&[
+        ascii_as_felt("deploy_account"),
+        felt(&tx.version.0),
+        felt(tx.contract_address.0.key()),
+        FieldElement::ZERO,
+        hash_array(&calldata),
+        u128_as_felt(tx.max_fee.0),
+        chain_id,
+        felt(&tx.nonce.0),
+    ])]
+}
+
+fn invoke_hash_candidates(tx: &InvokeTransaction, chain_id: FieldElement) -> Vec<FieldElement> {
+    let prefix = ascii_as_felt("invoke");
+    let version = felt(&tx.version.0);
+    let sender_address = felt(tx.sender_address.0.key());
+    let entry_point_selector =
+        tx.entry_point_selector.map_or(FieldElement::ZERO, |selector| felt(&selector.0));
+    let calldata_hash = hash_array(&tx.calldata.0.iter().map(felt).collect::<Vec<_>>());
+    let max_fee = u128_as_felt(tx.max_fee.0);
+    if version == FieldElement::ZERO {
+        vec![
+            hash_array(&[
+                prefix,
+                version,
+                sender_address,
+                entry_point_selector,
+                calldata_hash,
+                max_fee,
+                chain_id,
+            ]),
+            // Before the version and max fee were part of the hash.
+            hash_array(&[prefix, sender_address, entry_point_selector, calldata_hash, chain_id]),
+        ]
+    } else {
+        vec![hash_array(&[
+            prefix,
+            version,
+            sender_address,
+            FieldElement::ZERO,
+            calldata_hash,
+            max_fee,
+            chain_id,
+            felt(&tx.nonce.0),
+        ])]
+    }
+}
+
+fn l1_handler_hash_candidates(
+    tx: &L1HandlerTransaction,
+    chain_id: FieldElement,
+) -> Vec<FieldElement> {
+    let prefix = ascii_as_felt("l1_handler");
+    let version = felt(&tx.version.0);
+    let contract_address = felt(tx.contract_address.0.key());
+    let entry_point_selector = felt(&tx.entry_point_selector.0);
+    let calldata_hash = hash_array(&tx.calldata.0.iter().map(felt).collect::<Vec<_>>());
+    vec![
+        hash_array(&[
+            prefix,
+            version,
+            contract_address,
+            entry_point_selector,
+            calldata_hash,
+            FieldElement::ZERO,
+            chain_id,
+            felt(&tx.nonce.0),
+        ]),
+        // Before the nonce was part of the hash.
+        hash_array(&[
+            prefix,
+            version,
+            contract_address,
+            entry_point_selector,
+            calldata_hash,
+            FieldElement::ZERO,
+            chain_id,
+        ]),
+        // Before L1 handler transactions had their own prefix.
+        hash_array(&[
+            ascii_as_felt("invoke"),
+            contract_address,
+            entry_point_selector,
+            calldata_hash,
+            chain_id,
+        ]),
+    ]
+}
+
+// The root of a Patricia-Merkle tree of height COMMITMENT_TREE_HEIGHT, with the given leaves at
+// indices 0, 1, 2, ... Zero leaves are considered empty.
+fn calculate_commitment(leaves: impl Iterator<Item = FieldElement>) -> FieldElement {
+    let leaves: Vec<(u64, FieldElement)> = leaves
+        .enumerate()
+        .map(|(index, leaf)| (index as u64, leaf))
+        .filter(|(_, leaf)| *leaf != FieldElement::ZERO)
+        .collect();
+    calculate_subtree(&leaves, COMMITMENT_TREE_HEIGHT)
+        .map_or(FieldElement::ZERO, |node| node.hash())
+}
+
+// A node of a Patricia-Merkle tree: an edge of `length` bits along `path` down to a node with hash
+// `bottom`, or just the bottom node if the length is 0.
+struct Node {
+    bottom: FieldElement,
+    path: u64,
+    length: u8,
+}
+
+impl Node {
+    fn hash(&self) -> FieldElement {
+        if self.length == 0 {
+            return self.bottom;
+        }
+        pedersen_hash(&self.bottom, &u64_as_felt(self.path)) + u64_as_felt(self.length.into())
+    }
+}
+
+// Returns the root of the subtree of the given height with the given sorted leaves, whose indices
+// are relative to the subtree. Returns None if the subtree is empty.
+fn calculate_subtree(leaves: &[(u64, FieldElement)], height: u8) -> Option<Node> {
+    if height == 0 {
+        return leaves.first().map(|(_, leaf)| Node { bottom: *leaf, path: 0, length: 0 });
+    }
+    let bit = 1u64 << (height - 1);
+    let split = leaves.partition_point(|(index, _)| index & bit == 0);
+    let (left, right) = leaves.split_at(split);
+    let right: Vec<(u64, FieldElement)> =
+        right.iter().map(|(index, leaf)| (index & !bit, *leaf)).collect();
+    match (calculate_subtree(left, height - 1), calculate_subtree(&right, height - 1)) {
+        (None, None) => None,
+        (Some(child), None) => Some(Node { length: child.length + 1, ..child }),
+        (None, Some(child)) => {
+            Some(Node { path: (1 << child.length) | child.path, length: child.length + 1, ..child })
+        }
+        (Some(left), Some(right)) => {
+            Some(Node { bottom: pedersen_hash(&left.hash(), &right.hash()), path: 0, length: 0 })
+        }
+    }
+}
+
+// The Pedersen hash chain of the elements, followed by their number.
+fn hash_array(elements: &[FieldElement]) -> FieldElement {
+    let hash =
+        elements.iter().fold(FieldElement::ZERO, |hash, element| pedersen_hash(&hash, element));
+    pedersen_hash(&hash, &u64_as_felt(elements.len() as u64))
+}
+
+fn felt(felt: &StarkFelt) -> FieldElement {
+    let bytes: [u8; 32] = felt.bytes().try_into().expect("A felt has 32 bytes.");
+    FieldElement::from_bytes_be(&bytes).expect("A felt is a field element.")
+}
+
+fn u64_as_felt(value: u64) -> FieldElement {
+    u128_as_felt(value.into())
+}
+
+fn u128_as_felt(value: u128) -> FieldElement {
+    let mut bytes = [0u8; 32];
+    bytes[16..].copy_from_slice(&value.to_be_bytes());
+    FieldElement::from_bytes_be(&bytes).expect("A u128 is a field element.")
+}
+
+// The big-endian number of the ASCII bytes, as in Cairo short strings. The chain id is checked to be
+// a short string when the config of the node is loaded.
+fn ascii_as_felt(ascii: &str) -> FieldElement {
+    FieldElement::from_byte_slice_be(ascii.as_bytes()).expect("A short string is a field element.")
+}
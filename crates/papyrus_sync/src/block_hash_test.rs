@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use indexmap::IndexMap;
+use papyrus_storage::state::data::{Cairo1StateDiff, CompiledClassHash, SierraContractClass};
+use starknet_api::block::{Block, BlockBody, BlockHash, BlockHeader, BlockNumber, BlockTimestamp};
+use starknet_api::core::{ChainId, ClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::StarkFelt;
+use starknet_api::transaction::{
+    Calldata, DeclareTransaction, Event, EventContent, EventData, EventKey, Fee, InvokeTransaction,
+    InvokeTransactionOutput, Transaction, TransactionHash, TransactionOutput, TransactionSignature,
+    TransactionVersion,
+};
+use starknet_api::{patricia_key, stark_felt};
+use starknet_crypto::{pedersen_hash, FieldElement};
+
+use super::{
+    ascii_as_felt, block_hash_candidates, calculate_commitment, declare_hash_candidates, felt,
+    invoke_hash_candidates, u64_as_felt, verify_block_hashes, verify_declare_v2_hashes,
+    HashVerificationError,
+};
+
+fn as_stark_felt(felt: FieldElement) -> StarkFelt {
+    StarkFelt::new(felt.to_bytes_be()).unwrap()
+}
+
+// Returns a block with an invoke transaction that emits an event, with valid hashes.
+fn get_valid_block(chain_id: &ChainId) -> Block {
+    let mut tx = InvokeTransaction {
+        transaction_hash: TransactionHash::default(),
+        max_fee: Fee(100),
+        version: TransactionVersion(stark_felt!("0x1")),
+        signature: TransactionSignature(vec![stark_felt!("0x2"), stark_felt!("0x3")]),
+        nonce: Nonce(stark_felt!("0x4")),
+        sender_address: ContractAddress(patricia_key!("0x5")),
+        entry_point_selector: None,
+        calldata: Calldata(Arc::new(vec![stark_felt!("0x6")])),
+    };
+    tx.transaction_hash =
+        TransactionHash(as_stark_felt(invoke_hash_candidates(&tx, ascii_as_felt(&chain_id.0))[0]));
+    let event = Event {
+        from_address: ContractAddress(patricia_key!("0x5")),
+        content: EventContent {
+            keys: vec![EventKey(stark_felt!("0x7"))],
+            data: EventData(vec![stark_felt!("0x8")]),
+        },
+    };
+    let mut block = Block {
+        header: BlockHeader {
+            parent_hash: BlockHash(stark_felt!("0x9")),
+            block_number: BlockNumber(1),
+            timestamp: BlockTimestamp(10),
+            ..BlockHeader::default()
+        },
+        body: BlockBody {
+            transactions: vec![Transaction::Invoke(tx)],
+            transaction_outputs: vec![TransactionOutput::Invoke(InvokeTransactionOutput {
+                actual_fee: Fee(50),
+                messages_sent: vec![],
+                events: vec![event],
+            })],
+        },
+    };
+    block.header.block_hash =
+        BlockHash(as_stark_felt(block_hash_candidates(&block, ascii_as_felt(&chain_id.0))[0]));
+    block
+}
+
+#[test]
+fn commitment() {
+    let leaf_0 = u64_as_felt(1);
+    let leaf_1 = u64_as_felt(2);
+    assert_eq!(calculate_commitment(std::iter::empty()), FieldElement::ZERO);
+    // A single leaf is an edge of the full height from the root.
+    assert_eq!(
+        calculate_commitment([leaf_0].into_iter()),
+        pedersen_hash(&leaf_0, &FieldElement::ZERO) + u64_as_felt(64)
+    );
+    // Two adjacent leaves are a binary node under an edge from the root.
+    assert_eq!(
+        calculate_commitment([leaf_0, leaf_1].into_iter()),
+        pedersen_hash(&pedersen_hash(&leaf_0, &leaf_1), &FieldElement::ZERO) + u64_as_felt(63)
+    );
+    // Zero leaves are empty.
+    assert_eq!(
+        calculate_commitment([FieldElement::ZERO, leaf_1].into_iter()),
+        pedersen_hash(&leaf_1, &FieldElement::ONE) + u64_as_felt(64)
+    );
+}
+
+#[test]
+fn verify_valid_block() {
+    let chain_id = ChainId("SN_TEST".to_owned());
+    let block = get_valid_block(&chain_id);
+    verify_block_hashes(&block, &chain_id).unwrap();
+}
+
+#[test]
+fn verify_invalid_block() {
+    let chain_id = ChainId("SN_TEST".to_owned());
+
+    // The transaction hashes depend on the chain.
+    let block = get_valid_block(&chain_id);
+    let tx_hash = block.body.transactions[0].transaction_hash();
+    assert_matches!(
+        verify_block_hashes(&block, &ChainId("SN_OTHER".to_owned())),
+        Err(HashVerificationError::TransactionHash { tx_hash: hash }) if hash == tx_hash
+    );
+
+    let mut block = get_valid_block(&chain_id);
+    block.header.timestamp = BlockTimestamp(11);
+    assert_matches!(
+        verify_block_hashes(&block, &chain_id),
+        Err(HashVerificationError::BlockHash { block_number: BlockNumber(1) })
+    );
+
+    let mut block = get_valid_block(&chain_id);
+    if let TransactionOutput::Invoke(output) = &mut block.body.transaction_outputs[0] {
+        output.events[0].content.data = EventData(vec![stark_felt!("0x9")]);
+    }
+    assert_matches!(
+        verify_block_hashes(&block, &chain_id),
+        Err(HashVerificationError::BlockHash { block_number: BlockNumber(1) })
+    );
+}
+
+#[test]
+fn verify_declare_v2() {
+    let chain_id = ChainId("SN_TEST".to_owned());
+    let class_hash = ClassHash(stark_felt!("0x1"));
+    let compiled_class_hash = CompiledClassHash(stark_felt!("0x2"));
+    let mut tx = DeclareTransaction {
+        transaction_hash: TransactionHash::default(),
+        max_fee: Fee(100),
+        version: TransactionVersion(stark_felt!("0x2")),
+        nonce: Nonce(stark_felt!("0x3")),
+        class_hash,
+        signature: TransactionSignature::default(),
+        sender_address: ContractAddress(patricia_key!("0x4")),
+    };
+    tx.transaction_hash = TransactionHash(as_stark_felt(
+        declare_hash_candidates(
+            &tx,
+            ascii_as_felt(&chain_id.0),
+            Some(felt(&compiled_class_hash.0)),
+        )[0],
+    ));
+    let transactions = vec![Transaction::Declare(tx.clone())];
+    let cairo1_state_diff = |compiled_class_hash| Cairo1StateDiff {
+        declared_classes: IndexMap::from([(
+            class_hash,
+            (compiled_class_hash, SierraContractClass::default()),
+        )]),
+        replaced_classes: IndexMap::new(),
+    };
+
+    verify_declare_v2_hashes(&transactions, &cairo1_state_diff(compiled_class_hash), &chain_id)
+        .unwrap();
+    // The hash depends on the compiled class hash.
+    assert_matches!(
+        verify_declare_v2_hashes(
+            &transactions,
+            &cairo1_state_diff(CompiledClassHash(stark_felt!("0x5"))),
+            &chain_id
+        ),
+        Err(HashVerificationError::TransactionHash { tx_hash }) if tx_hash == tx.transaction_hash
+    );
+    assert_matches!(
+        verify_declare_v2_hashes(&transactions, &Cairo1StateDiff::default(), &chain_id),
+        Err(HashVerificationError::TransactionHash { .. })
+    );
+
+    // Declare transactions of later versions are rejected with the block.
+    let mut block = get_valid_block(&chain_id);
+    tx.version = TransactionVersion(stark_felt!("0x3"));
+    block.body.transactions.push(Transaction::Declare(tx.clone()));
+    assert_matches!(
+        verify_block_hashes(&block, &chain_id),
+        Err(HashVerificationError::TransactionHash { tx_hash }) if tx_hash == tx.transaction_hash
+    );
+}
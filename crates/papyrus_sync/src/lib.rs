@@ -2,39 +2,124 @@
 #[path = "sync_test.rs"]
 mod sync_test;
 
+mod block_hash;
+mod metrics;
 mod sources;
 
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 use async_stream::try_stream;
-use futures_util::{pin_mut, select, Stream, StreamExt};
+use futures_util::{pin_mut, select, stream, Stream, StreamExt};
 use indexmap::IndexMap;
-use papyrus_storage::body::BodyStorageWriter;
+use papyrus_storage::base_layer::{BaseLayerStorageReader, BaseLayerStorageWriter};
+use papyrus_storage::body::{BodyStorageReader, BodyStorageWriter};
 use papyrus_storage::header::{HeaderStorageReader, HeaderStorageWriter};
 use papyrus_storage::ommer::{OmmerStorageReader, OmmerStorageWriter};
+use papyrus_storage::pruning::{PruningConfig, PruningStorageReader, PruningStorageWriter};
+use papyrus_storage::state::data::{Cairo1StateDiff, ThinStateDiff};
 use papyrus_storage::state::{StateStorageReader, StateStorageWriter};
 use papyrus_storage::{StorageError, StorageReader, StorageWriter};
 use serde::{Deserialize, Serialize};
-use starknet_api::block::{Block, BlockHash, BlockNumber};
-use starknet_api::core::ClassHash;
+use starknet_api::block::{Block, BlockBody, BlockHash, BlockNumber, BlockTimestamp, GasPrice};
+use starknet_api::core::{ChainId, ClassHash, ContractAddress, GlobalRoot};
 use starknet_api::state::{ContractClass, StateDiff};
+use tokio::sync::broadcast;
 use tracing::{debug, error, info, trace, warn};
 
-pub use self::sources::{CentralError, CentralSource, CentralSourceConfig, CentralSourceTrait};
+pub use self::block_hash::{calculate_invoke_transaction_hash, HashVerificationError};
+pub use self::sources::{
+    BaseLayerSourceError, BaseLayerSourceTrait, CentralError, CentralSource, CentralSourceConfig,
+    CentralSourceKind, CentralSourceTrait, EthereumBaseLayerSource, OfflineCentralSource,
+    PeerCentralSource,
+};
+use crate::block_hash::{verify_block_hashes, verify_declare_v2_hashes};
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SyncConfig {
     pub block_propagation_sleep_duration: Duration,
     pub recoverable_error_sleep_duration: Duration,
+    pub pending_sleep_duration: Duration,
+    pub base_layer_propagation_sleep_duration: Duration,
+    /// Whether to recalculate the hashes of the received blocks and their transactions, and reject
+    /// blocks that don't match, instead of trusting the central source.
+    pub verify_hashes: bool,
+    /// Whether to stop syncing on a state diff after which the state root computed by the storage
+    /// doesn't match the state root in the header of the block. The sync doesn't start on a
+    /// storage without the state tries of its blocks.
+    pub verify_state_root: bool,
+    /// The chain of the transaction hashes.
+    pub chain_id: ChainId,
+}
+
+/// The header of the block that is currently being constructed by the sequencer.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PendingBlockHeader {
+    pub parent_hash: BlockHash,
+    pub gas_price: GasPrice,
+    pub sequencer: ContractAddress,
+    pub timestamp: BlockTimestamp,
+}
+
+/// The data of the block that is currently being constructed by the sequencer. Kept in memory and
+/// shared with the gateway, which layers it on top of the latest block in the storage.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PendingData {
+    pub header: PendingBlockHeader,
+    pub body: BlockBody,
+    pub state_diff: StateDiff,
+    pub cairo1_state_diff: Cairo1StateDiff,
+    // Class definitions of deployed contracts with classes that were not declared in this state
+    // diff.
+    pub deployed_contract_class_definitions: IndexMap<ClassHash, ContractClass>,
+}
+
+/// A change in the chain stored by the sync, published to the subscribers of the sync
+/// notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncNotification {
+    /// The header and body of a block were added to the storage.
+    BlockAdded { block_number: BlockNumber, block_hash: BlockHash },
+    /// A block was reverted and moved to the ommer tables.
+    BlockReverted { block_number: BlockNumber, block_hash: BlockHash },
+}
+
+/// The progress of the sync, shared with the health probes of the monitoring gateway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncStatus {
+    /// The block marker of the central source, as of the last time the sync checked it.
+    pub central_block_marker: Option<BlockNumber>,
+    /// The last time the sync stored new data or found that it's up to date with the central
+    /// source.
+    pub last_progress_time: Instant,
+    /// Whether the sync stopped on an unrecoverable error.
+    pub stopped: bool,
+}
+
+impl Default for SyncStatus {
+    // The progress time starts when the status is created, so that a sync that never makes
+    // progress is detected.
+    fn default() -> Self {
+        Self { central_block_marker: None, last_progress_time: Instant::now(), stopped: false }
+    }
 }
 
 // Orchestrates specific network interfaces (e.g. central, p2p, l1) and writes to Storage.
-pub struct GenericStateSync<TCentralSource: CentralSourceTrait + Sync + Send> {
+pub struct GenericStateSync<
+    TCentralSource: CentralSourceTrait + Sync + Send,
+    TBaseLayerSource: BaseLayerSourceTrait + Sync + Send,
+> {
     config: SyncConfig,
     central_source: Arc<TCentralSource>,
+    // None if the blocks proved on the base layer shouldn't be tracked.
+    base_layer_source: Option<Arc<TBaseLayerSource>>,
     reader: StorageReader,
     writer: StorageWriter,
+    // None if the storage keeps the full history of the state.
+    pruning_config: Option<PruningConfig>,
+    pending_data: Arc<RwLock<Option<PendingData>>>,
+    notifications_sender: broadcast::Sender<SyncNotification>,
+    sync_status: Arc<RwLock<SyncStatus>>,
 }
 
 pub type StateSyncResult = Result<(), StateSyncError>;
@@ -59,6 +144,19 @@ pub enum StateSyncError {
          matching header (neither in the ommer headers)."
     )]
     StateDiffWithoutMatchingHeader { block_number: BlockNumber, block_hash: BlockHash },
+    #[error("Received an invalid block from the central source: {0}")]
+    InvalidBlockHash(#[from] HashVerificationError),
+    #[error(
+        "The state root of block {block_number} is {expected:?}, but the state diff leads to \
+         {computed:?}."
+    )]
+    StateRootMismatch { block_number: BlockNumber, expected: GlobalRoot, computed: GlobalRoot },
+    #[error(
+        "The state root of block {block_number} wasn't computed, as the storage was synced without \
+         the state tries. Resync the storage from the genesis block or turn off \
+         verify_state_root."
+    )]
+    StateRootNotComputed { block_number: BlockNumber },
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -71,16 +169,36 @@ pub enum SyncEvent {
         block_number: BlockNumber,
         block_hash: BlockHash,
         state_diff: StateDiff,
+        cairo1_state_diff: Cairo1StateDiff,
         // TODO(anatg): Remove once there are no more deployed contracts with undeclared classes.
         // Class definitions of deployed contracts with classes that were not declared in this
         // state diff.
         deployed_contract_class_definitions: IndexMap<ClassHash, ContractClass>,
     },
+    PendingDataAvailable {
+        pending_data: PendingData,
+    },
+    NewBaseLayerBlock {
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+    },
+    PruningAvailable,
 }
 
-impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSync<TCentralSource> {
+impl<
+        TCentralSource: CentralSourceTrait + Sync + Send + 'static,
+        TBaseLayerSource: BaseLayerSourceTrait + Sync + Send + 'static,
+    > GenericStateSync<TCentralSource, TBaseLayerSource>
+{
     pub async fn run(&mut self) -> StateSyncResult {
         info!("State sync started.");
+        if self.config.verify_state_root {
+            if let Err(err) = self.verify_state_root_computed() {
+                error!("{}", err);
+                update_sync_status(&self.sync_status, |status| status.stopped = true);
+                return Err(err);
+            }
+        }
         loop {
             match self.sync_while_ok().await {
                 Err(StateSyncError::ParentBlockHashMismatch {
@@ -105,6 +223,7 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
                 // Unrecoverable errors.
                 Err(err) => {
                     error!("{}", err);
+                    update_sync_status(&self.sync_status, |status| status.stopped = true);
                     return Err(err);
                 }
                 Ok(_) => {
@@ -133,13 +252,16 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
 
     // Sync until encountering an error:
     //  1. If needed, revert blocks from the end of the chain.
-    //  2. Create infinite block and state diff streams to fetch data from the central source.
+    //  2. Create infinite block, state diff and pending data streams to fetch data from the central
+    //     source, a stream of the blocks proved on the base layer and a stream of the pruning of
+    //     the storage.
     //  3. Fetch data from the streams with unblocking wait while there is no new data.
     async fn sync_while_ok(&mut self) -> StateSyncResult {
         self.handle_block_reverts().await?;
         let block_stream = stream_new_blocks(
             self.reader.clone(),
             self.central_source.clone(),
+            self.sync_status.clone(),
             self.config.block_propagation_sleep_duration,
         )
         .fuse();
@@ -149,17 +271,55 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
             self.config.block_propagation_sleep_duration,
         )
         .fuse();
-        pin_mut!(block_stream, state_diff_stream);
+        let pending_data_stream = stream_pending_data(
+            self.reader.clone(),
+            self.central_source.clone(),
+            self.config.pending_sleep_duration,
+        )
+        .fuse();
+        let base_layer_block_stream = match &self.base_layer_source {
+            Some(base_layer_source) => stream_new_base_layer_block(
+                self.reader.clone(),
+                base_layer_source.clone(),
+                self.config.base_layer_propagation_sleep_duration,
+            )
+            .boxed(),
+            None => stream::pending().boxed(),
+        }
+        .fuse();
+        let pruning_stream = match &self.pruning_config {
+            Some(pruning_config) => {
+                stream_pruning(self.reader.clone(), pruning_config.clone()).boxed()
+            }
+            None => stream::pending().boxed(),
+        }
+        .fuse();
+        pin_mut!(
+            block_stream,
+            state_diff_stream,
+            pending_data_stream,
+            base_layer_block_stream,
+            pruning_stream
+        );
 
         loop {
-            debug!("Selecting between block sync and state diff sync.");
+            debug!(
+                "Selecting between block sync, state diff sync, pending data sync, base layer sync \
+                 and pruning."
+            );
             let sync_event = select! {
               res = block_stream.next() => res,
               res = state_diff_stream.next() => res,
+              res = pending_data_stream.next() => res,
+              res = base_layer_block_stream.next() => res,
+              res = pruning_stream.next() => res,
               complete => break,
             }
             .expect("Received None as a sync event.")?;
             self.process_sync_event(sync_event).await?;
+            update_sync_status(&self.sync_status, |status| {
+                status.last_progress_time = Instant::now()
+            });
             debug!("Finished processing sync event.");
         }
         unreachable!("Fetching data loop should never return.");
@@ -176,6 +336,7 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
                 block_number,
                 block_hash,
                 state_diff,
+                cairo1_state_diff,
                 deployed_contract_class_definitions,
             } => {
                 debug!("Got state diff sync event.");
@@ -183,24 +344,42 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
                     block_number,
                     block_hash,
                     state_diff,
+                    cairo1_state_diff,
                     deployed_contract_class_definitions,
                 )
             }
+            SyncEvent::PendingDataAvailable { pending_data } => {
+                debug!("Got pending data sync event.");
+                self.store_pending_data(pending_data)
+            }
+            SyncEvent::NewBaseLayerBlock { block_number, block_hash } => {
+                debug!("Got base layer sync event.");
+                self.store_base_layer_block(block_number, block_hash)
+            }
+            SyncEvent::PruningAvailable => {
+                debug!("Got pruning sync event.");
+                self.prune_storage()
+            }
         }
     }
 
     fn store_block(&mut self, block_number: BlockNumber, block: Block) -> StateSyncResult {
+        if self.config.verify_hashes {
+            verify_block_hashes(&block, &self.config.chain_id)?;
+        }
         // Assuming the central source is trusted, detect reverts by comparing the incoming block's
         // parent hash to the current hash.
         self.verify_parent_block_hash(block_number, &block)?;
 
-        debug!("Storing block {block_number} with hash {}.", block.header.block_hash);
+        let block_hash = block.header.block_hash;
+        debug!("Storing block {block_number} with hash {block_hash}.");
         trace!("Block data: {block:#?}");
         self.writer
             .begin_rw_txn()?
             .append_header(block_number, &block.header)?
             .append_body(block_number, block.body)?
             .commit()?;
+        self.notify(SyncNotification::BlockAdded { block_number, block_hash });
         Ok(())
     }
 
@@ -209,15 +388,43 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
         block_number: BlockNumber,
         block_hash: BlockHash,
         state_diff: StateDiff,
+        cairo1_state_diff: Cairo1StateDiff,
         deployed_contract_class_definitions: IndexMap<ClassHash, ContractClass>,
     ) -> StateSyncResult {
         if !self.is_reverted_state_diff(block_number, block_hash)? {
+            if self.config.verify_hashes {
+                let transactions = self
+                    .reader
+                    .begin_ro_txn()?
+                    .get_block_transactions(block_number)?
+                    .expect("A state diff is stored after its block.");
+                verify_declare_v2_hashes(&transactions, &cairo1_state_diff, &self.config.chain_id)?;
+            }
             debug!("Storing state diff of block {block_number} with hash {block_hash}.");
-            trace!("StateDiff data: {state_diff:#?}");
-            self.writer
-                .begin_rw_txn()?
-                .append_state_diff(block_number, state_diff, deployed_contract_class_definitions)?
-                .commit()?;
+            trace!("StateDiff data: {state_diff:#?}, Cairo 1 data: {cairo1_state_diff:#?}");
+            let txn = self.writer.begin_rw_txn()?.append_state_diff_with_cairo1(
+                block_number,
+                state_diff,
+                cairo1_state_diff,
+                deployed_contract_class_definitions,
+            )?;
+            if self.config.verify_state_root {
+                let computed = txn
+                    .get_state_root(block_number)?
+                    .ok_or(StateSyncError::StateRootNotComputed { block_number })?;
+                let expected = txn
+                    .get_block_header(block_number)?
+                    .expect("A state diff is stored after its header.")
+                    .state_root;
+                if computed != expected {
+                    return Err(StateSyncError::StateRootMismatch {
+                        block_number,
+                        expected,
+                        computed,
+                    });
+                }
+            }
+            txn.commit()?;
 
             // Info the user on syncing the block once all the data is stored.
             info!("Added block {} with hash {}.", block_number, block_hash);
@@ -226,11 +433,14 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
                 "Storing ommer state diff of block {} with hash {:?}.",
                 block_number, block_hash
             );
+            // The ommer tables don't keep the definitions of the Sierra classes.
+            let mut thin_state_diff = ThinStateDiff::from(state_diff);
+            thin_state_diff.add_cairo1_state_diff(cairo1_state_diff);
             self.writer
                 .begin_rw_txn()?
                 .insert_ommer_state_diff(
                     block_hash,
-                    &state_diff.into(),
+                    &thin_state_diff,
                     &deployed_contract_class_definitions,
                 )?
                 .commit()?;
@@ -238,6 +448,74 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
         Ok(())
     }
 
+    // The state root of a block is computed with the state tries of the previous block, which a
+    // storage that was synced before the tries were kept doesn't have.
+    fn verify_state_root_computed(&self) -> StateSyncResult {
+        let txn = self.reader.begin_ro_txn()?;
+        if let Some(block_number) = txn.get_state_marker()?.prev() {
+            if txn.get_state_root(block_number)?.is_none() {
+                return Err(StateSyncError::StateRootNotComputed { block_number });
+            }
+        }
+        Ok(())
+    }
+
+    // Marks the block and its ancestors as proved on the base layer, as long as the stored block is
+    // the proved one.
+    fn store_base_layer_block(
+        &mut self,
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+    ) -> StateSyncResult {
+        let txn = self.writer.begin_rw_txn()?;
+        let stored_block_hash = txn.get_block_header(block_number)?.map(|header| header.block_hash);
+        if stored_block_hash != Some(block_hash) {
+            warn!(
+                "Block {block_number} with hash {block_hash} was proved on the base layer, but the \
+                 stored block hash is {stored_block_hash:?}."
+            );
+            return Ok(());
+        }
+        debug!("Block {block_number} with hash {block_hash} was proved on the base layer.");
+        txn.update_base_layer_block_marker(&block_number.next())?.commit()?;
+        Ok(())
+    }
+
+    // Prunes the storage in a single write transaction, bounded by the pruning configuration.
+    fn prune_storage(&mut self) -> StateSyncResult {
+        if let Some(pruning_config) = &self.pruning_config {
+            let (txn, _) = self.writer.begin_rw_txn()?.prune(pruning_config)?;
+            txn.commit()?;
+        }
+        Ok(())
+    }
+
+    // Keeps the pending data in memory, as long as it extends the latest block in the storage.
+    fn store_pending_data(&mut self, pending_data: PendingData) -> StateSyncResult {
+        let txn = self.reader.begin_ro_txn()?;
+        let latest_block_hash = match txn.get_header_marker()?.prev() {
+            None => BlockHash::default(),
+            Some(block_number) => {
+                txn.get_block_header(block_number)?
+                    .ok_or(StorageError::DBInconsistency {
+                        msg: format!("Missing block {block_number} in the storage."),
+                    })?
+                    .block_hash
+            }
+        };
+        if pending_data.header.parent_hash != latest_block_hash {
+            debug!(
+                "Ignoring pending data with parent hash {}, the latest block hash is {}.",
+                pending_data.header.parent_hash, latest_block_hash
+            );
+            return Ok(());
+        }
+
+        trace!("Pending data: {pending_data:#?}");
+        *self.pending_data.write().expect("Pending data lock is poisoned.") = Some(pending_data);
+        Ok(())
+    }
+
     // Compares the block's parent hash to the stored block.
     fn verify_parent_block_hash(
         &self,
@@ -278,15 +556,21 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
 
         // Revert last blocks if needed.
         let mut last_block_in_storage = header_marker.prev();
+        let mut reverted_blocks = 0;
         while let Some(block_number) = last_block_in_storage {
             if self.should_revert_block(block_number).await? {
                 info!("Reverting block {}.", block_number);
                 self.revert_block(block_number)?;
+                reverted_blocks += 1;
                 last_block_in_storage = block_number.prev();
             } else {
                 break;
             }
         }
+        if reverted_blocks > 0 {
+            metrics::REVERTS.inc();
+            metrics::REVERTED_BLOCKS.inc_by(reverted_blocks);
+        }
         Ok(())
     }
 
@@ -297,8 +581,11 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
 
         let res = txn.revert_header(block_number)?;
         txn = res.0;
+        let reverted_block_hash = res.1.as_ref().map(|header| header.block_hash);
         if let Some(header) = res.1 {
-            txn = txn.insert_ommer_header(header.block_hash, &header)?;
+            txn = txn
+                .try_revert_base_layer_marker(block_number)?
+                .insert_ommer_header(header.block_hash, &header)?;
 
             let res = txn.revert_body(block_number)?;
             txn = res.0;
@@ -323,9 +610,19 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
         }
 
         txn.commit()?;
+        if let Some(block_hash) = reverted_block_hash {
+            self.notify(SyncNotification::BlockReverted { block_number, block_hash });
+        }
         Ok(())
     }
 
+    // Publishes a notification to the subscribers. Sending fails only when there are no
+    // subscribers, in which case the notification is dropped.
+    fn notify(&self, notification: SyncNotification) {
+        trace!("Sync notification: {notification:?}.");
+        let _ = self.notifications_sender.send(notification);
+    }
+
     /// Checks if centrals block hash at the block number is different from ours (or doesn't exist).
     /// If so, a revert is required.
     async fn should_revert_block(&self, block_number: BlockNumber) -> Result<bool, StateSyncError> {
@@ -369,12 +666,22 @@ impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static> GenericStateSyn
 fn stream_new_blocks<TCentralSource: CentralSourceTrait + Sync + Send>(
     reader: StorageReader,
     central_source: Arc<TCentralSource>,
+    sync_status: Arc<RwLock<SyncStatus>>,
     block_propation_sleep_duration: Duration,
 ) -> impl Stream<Item = Result<SyncEvent, StateSyncError>> {
     try_stream! {
         loop {
             let header_marker = reader.begin_ro_txn()?.get_header_marker()?;
             let last_block_number = central_source.get_block_marker().await?;
+            metrics::CENTRAL_BLOCK_MARKER.set(last_block_number.0 as i64);
+            metrics::BLOCKS_BEHIND_CENTRAL
+                .set(last_block_number.0.saturating_sub(header_marker.0) as i64);
+            update_sync_status(&sync_status, |status| {
+                status.central_block_marker = Some(last_block_number);
+                if header_marker == last_block_number {
+                    status.last_progress_time = Instant::now();
+                }
+            });
             if header_marker == last_block_number {
                 debug!("Waiting for more blocks.");
                 tokio::time::sleep(block_propation_sleep_duration).await;
@@ -418,13 +725,16 @@ fn stream_new_state_diffs<TCentralSource: CentralSourceTrait + Sync + Send>(
                     block_number,
                     block_hash,
                     mut state_diff,
+                    mut cairo1_state_diff,
                     deployed_contract_class_definitions,
                 ) = maybe_state_diff?;
                 sort_state_diff(&mut state_diff);
+                sort_cairo1_state_diff(&mut cairo1_state_diff);
                 yield SyncEvent::StateDiffAvailable {
                     block_number,
                     block_hash,
                     state_diff,
+                    cairo1_state_diff,
                     deployed_contract_class_definitions,
                 };
             }
@@ -432,6 +742,99 @@ fn stream_new_state_diffs<TCentralSource: CentralSourceTrait + Sync + Send>(
     }
 }
 
+fn update_sync_status(sync_status: &RwLock<SyncStatus>, update: impl FnOnce(&mut SyncStatus)) {
+    update(&mut sync_status.write().expect("Sync status lock is poisoned."));
+}
+
+// Polls the central source for the pending block once the storage is synced with it.
+fn stream_pending_data<TCentralSource: CentralSourceTrait + Sync + Send>(
+    reader: StorageReader,
+    central_source: Arc<TCentralSource>,
+    pending_sleep_duration: Duration,
+) -> impl Stream<Item = Result<SyncEvent, StateSyncError>> {
+    try_stream! {
+        loop {
+            let txn = reader.begin_ro_txn()?;
+            let header_marker = txn.get_header_marker()?;
+            let state_marker = txn.get_state_marker()?;
+            drop(txn);
+            if state_marker == header_marker
+                && header_marker == central_source.get_block_marker().await?
+            {
+                if let Some(mut pending_data) =
+                    central_source.get_pending_data(header_marker).await?
+                {
+                    sort_state_diff(&mut pending_data.state_diff);
+                    sort_cairo1_state_diff(&mut pending_data.cairo1_state_diff);
+                    yield SyncEvent::PendingDataAvailable { pending_data };
+                }
+            } else {
+                debug!("Waiting for the storage to be synced before polling the pending block.");
+            }
+            tokio::time::sleep(pending_sleep_duration).await;
+        }
+    }
+}
+
+// Polls the base layer for the latest proved block, once it's in the storage and wasn't marked as
+// proved yet. Failures to reach the base layer don't stop the sync of the other data, so they are
+// only logged.
+fn stream_new_base_layer_block<TBaseLayerSource: BaseLayerSourceTrait + Sync + Send>(
+    reader: StorageReader,
+    base_layer_source: Arc<TBaseLayerSource>,
+    base_layer_propagation_sleep_duration: Duration,
+) -> impl Stream<Item = Result<SyncEvent, StateSyncError>> {
+    try_stream! {
+        loop {
+            match base_layer_source.latest_proved_block().await {
+                Err(err) => {
+                    warn!("Failed getting the latest block proved on the base layer: {err}.")
+                }
+                Ok(None) => debug!("No block was proved on the base layer yet."),
+                Ok(Some((block_number, block_hash))) => {
+                    let (base_layer_block_marker, header_marker) = {
+                        let txn = reader.begin_ro_txn()?;
+                        (txn.get_base_layer_block_marker()?, txn.get_header_marker()?)
+                    };
+                    if block_number < base_layer_block_marker {
+                        debug!("No new block was proved on the base layer.");
+                    } else if block_number >= header_marker {
+                        debug!(
+                            "Waiting for block {block_number} to be synced before marking it as \
+                             proved on the base layer."
+                        );
+                    } else {
+                        yield SyncEvent::NewBaseLayerBlock { block_number, block_hash };
+                    }
+                }
+            }
+            tokio::time::sleep(base_layer_propagation_sleep_duration).await;
+        }
+    }
+}
+
+// Triggers the pruning of the storage as long as the state marker is ahead of the pruned state
+// marker by more than the history length.
+fn stream_pruning(
+    reader: StorageReader,
+    pruning_config: PruningConfig,
+) -> impl Stream<Item = Result<SyncEvent, StateSyncError>> {
+    try_stream! {
+        loop {
+            let txn = reader.begin_ro_txn()?;
+            let state_marker = txn.get_state_marker()?;
+            let pruned_state_marker = txn.get_pruned_state_marker()?;
+            drop(txn);
+            if pruned_state_marker.0 + pruning_config.history_length < state_marker.0 {
+                yield SyncEvent::PruningAvailable;
+            } else {
+                debug!("Waiting for the state to advance before pruning the storage.");
+                tokio::time::sleep(pruning_config.sleep_duration).await;
+            }
+        }
+    }
+}
+
 pub fn sort_state_diff(diff: &mut StateDiff) {
     diff.declared_classes.sort_unstable_keys();
     diff.deployed_contracts.sort_unstable_keys();
@@ -442,15 +845,38 @@ pub fn sort_state_diff(diff: &mut StateDiff) {
     }
 }
 
-pub type StateSync = GenericStateSync<CentralSource>;
+pub fn sort_cairo1_state_diff(diff: &mut Cairo1StateDiff) {
+    diff.declared_classes.sort_unstable_keys();
+    diff.replaced_classes.sort_unstable_keys();
+}
 
-impl StateSync {
+pub type StateSync = GenericStateSync<CentralSource, EthereumBaseLayerSource>;
+
+impl<TCentralSource: CentralSourceTrait + Sync + Send + 'static>
+    GenericStateSync<TCentralSource, EthereumBaseLayerSource>
+{
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         config: SyncConfig,
-        central_source: CentralSource,
+        central_source: TCentralSource,
+        base_layer_source: Option<EthereumBaseLayerSource>,
         reader: StorageReader,
         writer: StorageWriter,
+        pruning_config: Option<PruningConfig>,
+        pending_data: Arc<RwLock<Option<PendingData>>>,
+        notifications_sender: broadcast::Sender<SyncNotification>,
+        sync_status: Arc<RwLock<SyncStatus>>,
     ) -> Self {
-        Self { config, central_source: Arc::new(central_source), reader, writer }
+        Self {
+            config,
+            central_source: Arc::new(central_source),
+            base_layer_source: base_layer_source.map(Arc::new),
+            reader,
+            writer,
+            pruning_config,
+            pending_data,
+            notifications_sender,
+            sync_status,
+        }
     }
 }
@@ -0,0 +1,36 @@
+//! Prometheus metrics of the sync, registered in the default registry on first use.
+
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter, register_int_gauge, IntCounter, IntGauge};
+
+pub(crate) static CENTRAL_BLOCK_MARKER: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "papyrus_sync_central_block_marker",
+        "The block marker of the central source, i.e. the number of its next block."
+    )
+    .expect("Failed to register the central block marker metric.")
+});
+
+pub(crate) static BLOCKS_BEHIND_CENTRAL: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "papyrus_sync_blocks_behind_central",
+        "The number of blocks of the central source that are not in the storage yet."
+    )
+    .expect("Failed to register the blocks behind central metric.")
+});
+
+pub(crate) static REVERTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "papyrus_sync_reverts_total",
+        "The number of reverts handled by the sync, each of one or more blocks."
+    )
+    .expect("Failed to register the reverts metric.")
+});
+
+pub(crate) static REVERTED_BLOCKS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "papyrus_sync_reverted_blocks_total",
+        "The number of blocks reverted by the sync."
+    )
+    .expect("Failed to register the reverted blocks metric.")
+});
@@ -0,0 +1,35 @@
+use async_trait::async_trait;
+#[cfg(test)]
+use mockall::automock;
+use papyrus_base_layer::ethereum_base_layer_contract::{
+    EthereumBaseLayerContract, EthereumBaseLayerError,
+};
+use papyrus_base_layer::BaseLayerContract;
+use starknet_api::block::{BlockHash, BlockNumber};
+
+pub type EthereumBaseLayerSource = EthereumBaseLayerContract;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BaseLayerSourceError {
+    #[error(transparent)]
+    EthereumBaseLayerError(#[from] EthereumBaseLayerError),
+}
+
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait BaseLayerSourceTrait {
+    // Returns the latest Starknet block that is proved on the base layer, or None if there is no
+    // such block.
+    async fn latest_proved_block(
+        &self,
+    ) -> Result<Option<(BlockNumber, BlockHash)>, BaseLayerSourceError>;
+}
+
+#[async_trait]
+impl BaseLayerSourceTrait for EthereumBaseLayerSource {
+    async fn latest_proved_block(
+        &self,
+    ) -> Result<Option<(BlockNumber, BlockHash)>, BaseLayerSourceError> {
+        Ok(BaseLayerContract::latest_proved_block(self, None).await?)
+    }
+}
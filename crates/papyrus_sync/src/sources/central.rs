@@ -9,27 +9,50 @@ use futures_util::StreamExt;
 use indexmap::IndexMap;
 #[cfg(test)]
 use mockall::automock;
+use papyrus_storage::state::data::{
+    Cairo1StateDiff, CompiledClassHash, SierraContractClass, SierraEntryPoint,
+};
 use serde::{Deserialize, Serialize};
 use starknet_api::block::{Block, BlockHash, BlockNumber};
 use starknet_api::core::ClassHash;
 use starknet_api::state::{ContractClass, StateDiff};
 use starknet_api::StarknetApiError;
 use starknet_client::{
-    ClientCreationError, ClientError, RetryConfig, StarknetClient, StarknetClientTrait, StateUpdate,
+    ClientCreationError, ClientError, GenericContractClass, OfflineStarknetClient, RetryConfig,
+    StarknetClient, StarknetClientTrait, StateUpdate,
 };
 use tokio_stream::Stream;
 use tracing::{debug, trace};
 
 use super::stream_utils::MyStreamExt;
+use crate::{PendingBlockHeader, PendingData};
 
 pub type CentralResult<T> = Result<T, CentralError>;
 #[derive(Clone, Serialize, Deserialize)]
 pub struct CentralSourceConfig {
+    pub source: CentralSourceKind,
     pub concurrent_requests: usize,
+    /// The url of the starknet feeder gateway, of the JSON-RPC server of the peer node, or the
+    /// path of the directory of recorded responses.
     pub url: String,
     pub http_headers: Option<HashMap<String, String>>,
     pub retry_config: RetryConfig,
 }
+
+/// Where the sync reads the blocks and state updates from.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CentralSourceKind {
+    /// The starknet feeder gateway.
+    #[default]
+    FeederGateway,
+    /// The JSON-RPC server of a trusted papyrus node.
+    Peer,
+    /// A directory of feeder gateway responses recorded with
+    /// [`starknet_client::record_responses`].
+    Offline,
+}
+
 pub struct GenericCentralSource<TStarknetClient: StarknetClientTrait + Send + Sync> {
     pub concurrent_requests: usize,
     pub starknet_client: Arc<TStarknetClient>,
@@ -49,6 +72,21 @@ pub enum CentralError {
     BlockNotFound { block_number: BlockNumber },
     #[error(transparent)]
     StarknetApiError(#[from] Arc<StarknetApiError>),
+    #[error(transparent)]
+    RpcError(#[from] Arc<jsonrpsee::core::Error>),
+    #[error("Could not decode the program of class {:?}.", class_hash)]
+    ClassDecodingError { class_hash: ClassHash },
+    #[error(
+        "Class {:?} was declared as a Cairo 0 class but is a Sierra class, or vice versa.",
+        class_hash
+    )]
+    ClassKindMismatch { class_hash: ClassHash },
+}
+
+impl From<jsonrpsee::core::Error> for CentralError {
+    fn from(err: jsonrpsee::core::Error) -> Self {
+        CentralError::RpcError(Arc::new(err))
+    }
 }
 
 #[cfg_attr(test, automock)]
@@ -70,10 +108,18 @@ pub trait CentralSourceTrait {
         &self,
         block_number: BlockNumber,
     ) -> Result<Option<BlockHash>, CentralError>;
+
+    // Returns the data of the block that is currently being constructed, or None if there is no
+    // such block. pending_block_number is the number the pending block will get once accepted.
+    async fn get_pending_data(
+        &self,
+        pending_block_number: BlockNumber,
+    ) -> Result<Option<PendingData>, CentralError>;
 }
 
 pub(crate) type BlocksStream<'a> = BoxStream<'a, Result<(BlockNumber, Block), CentralError>>;
-type CentralStateUpdate = (BlockNumber, BlockHash, StateDiff, IndexMap<ClassHash, ContractClass>);
+pub(crate) type CentralStateUpdate =
+    (BlockNumber, BlockHash, StateDiff, Cairo1StateDiff, IndexMap<ClassHash, ContractClass>);
 pub(crate) type StateUpdatesStream<'a> = BoxStream<'a, CentralResult<CentralStateUpdate>>;
 
 #[async_trait]
@@ -159,41 +205,179 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static> CentralSource
         }
         .boxed()
     }
+
+    async fn get_pending_data(
+        &self,
+        pending_block_number: BlockNumber,
+    ) -> Result<Option<PendingData>, CentralError> {
+        let block = match self.starknet_client.pending_block().await.map_err(Arc::new)? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        let state_update =
+            match self.starknet_client.pending_state_update().await.map_err(Arc::new)? {
+                Some(state_update) => state_update,
+                None => return Ok(None),
+            };
+
+        // Get the declared, deployed and declared Sierra classes.
+        let class_hashes = state_update.state_diff.class_hashes();
+        let classes: Vec<_> = futures_util::stream::iter(class_hashes)
+            .map(|class_hash| async move {
+                (class_hash, self.starknet_client.class_by_hash(class_hash).await)
+            })
+            .buffered(self.concurrent_requests)
+            .collect()
+            .await;
+        let mut class_definitions = IndexMap::new();
+        for (class_hash, class) in classes {
+            match class.map_err(Arc::new)? {
+                Some(class) => class_definitions.insert(class_hash, class),
+                None => return Err(CentralError::ClassNotFound),
+            };
+        }
+
+        let (state_diff, cairo1_state_diff, deployed_contract_class_definitions) =
+            client_to_central_state_diff(state_update.state_diff, class_definitions)?;
+        let header = PendingBlockHeader {
+            parent_hash: block.parent_block_hash,
+            gas_price: block.gas_price,
+            sequencer: block.sequencer_address,
+            timestamp: block.timestamp,
+        };
+        let body = block
+            .to_starknet_api_block_body(pending_block_number)
+            .map_err(|err| CentralError::ClientError(Arc::new(err)))?;
+        debug!(
+            "Received pending data with {} transactions on top of block {}.",
+            body.transactions.len(),
+            header.parent_hash
+        );
+        Ok(Some(PendingData {
+            header,
+            body,
+            state_diff,
+            cairo1_state_diff,
+            deployed_contract_class_definitions,
+        }))
+    }
+}
+
+// Converts a client state diff to a starknet_api state diff and its Cairo 1 part, given the
+// definitions of the declared classes, followed by the definitions of the deployed classes that
+// were not declared and by the definitions of the declared Sierra classes. Returns the state diffs
+// and the definitions of the deployed classes.
+fn client_to_central_state_diff(
+    client_state_diff: starknet_client::StateDiff,
+    mut classes: IndexMap<ClassHash, GenericContractClass>,
+) -> CentralResult<(StateDiff, Cairo1StateDiff, IndexMap<ClassHash, ContractClass>)> {
+    let sierra_classes =
+        classes.split_off(classes.len() - client_state_diff.declared_classes.len());
+    // The classes of contracts deployed with Sierra classes were declared in earlier blocks.
+    let deployed_contract_class_definitions = classes
+        .split_off(client_state_diff.declared_contracts.len())
+        .into_iter()
+        .filter_map(|(class_hash, class)| match class {
+            GenericContractClass::Cairo0ContractClass(class) => Some((class_hash, class.into())),
+            GenericContractClass::Cairo1ContractClass(_) => None,
+        })
+        .collect();
+    let declared_classes = classes
+        .into_iter()
+        .map(|(class_hash, class)| match class {
+            GenericContractClass::Cairo0ContractClass(class) => Ok((class_hash, class.into())),
+            GenericContractClass::Cairo1ContractClass(_) => {
+                Err(CentralError::ClassKindMismatch { class_hash })
+            }
+        })
+        .collect::<CentralResult<_>>()?;
+    let declared_sierra_classes = client_state_diff
+        .declared_classes
+        .into_iter()
+        .zip(sierra_classes)
+        .map(|(entry, (class_hash, class))| match class {
+            GenericContractClass::Cairo1ContractClass(class) => Ok((
+                class_hash,
+                (
+                    CompiledClassHash(entry.compiled_class_hash),
+                    client_to_central_sierra_class(class),
+                ),
+            )),
+            GenericContractClass::Cairo0ContractClass(_) => {
+                Err(CentralError::ClassKindMismatch { class_hash })
+            }
+        })
+        .collect::<CentralResult<_>>()?;
+    let cairo1_state_diff = Cairo1StateDiff {
+        declared_classes: declared_sierra_classes,
+        replaced_classes: client_state_diff
+            .replaced_classes
+            .into_iter()
+            .map(|replaced_class| (replaced_class.address, replaced_class.class_hash))
+            .collect(),
+    };
+    let state_diff = StateDiff {
+        deployed_contracts: IndexMap::from_iter(
+            client_state_diff.deployed_contracts.iter().map(|dc| (dc.address, dc.class_hash)),
+        ),
+        storage_diffs: IndexMap::from_iter(client_state_diff.storage_diffs.into_iter().map(
+            |(address, entries)| {
+                (address, entries.into_iter().map(|se| (se.key, se.value)).collect())
+            },
+        )),
+        declared_classes,
+        nonces: client_state_diff.nonces,
+    };
+    Ok((state_diff, cairo1_state_diff, deployed_contract_class_definitions))
+}
+
+fn client_to_central_sierra_class(
+    class: starknet_client::SierraContractClass,
+) -> SierraContractClass {
+    SierraContractClass {
+        sierra_program: class.sierra_program,
+        contract_class_version: class.contract_class_version,
+        entry_points_by_type: class
+            .entry_points_by_type
+            .into_iter()
+            .map(|(entry_point_type, entry_points)| {
+                let entry_points = entry_points.into_iter().map(|entry_point| SierraEntryPoint {
+                    function_idx: entry_point.function_idx,
+                    selector: entry_point.selector,
+                });
+                (entry_point_type, entry_points.collect())
+            })
+            .collect(),
+        abi: class.abi,
+    }
 }
 
 fn client_to_central_state_update(
     current_block_number: BlockNumber,
-    maybe_client_state_update: CentralResult<(StateUpdate, IndexMap<ClassHash, ContractClass>)>,
+    maybe_client_state_update: CentralResult<(
+        StateUpdate,
+        IndexMap<ClassHash, GenericContractClass>,
+    )>,
 ) -> CentralResult<CentralStateUpdate> {
     match maybe_client_state_update {
-        Ok((state_update, mut classes)) => {
+        Ok((state_update, classes)) => {
             let block_hash = state_update.block_hash;
-            let deployed_contract_class_definitions =
-                classes.split_off(state_update.state_diff.declared_contracts.len());
-            let state_diff = StateDiff {
-                deployed_contracts: IndexMap::from_iter(
-                    state_update
-                        .state_diff
-                        .deployed_contracts
-                        .iter()
-                        .map(|dc| (dc.address, dc.class_hash)),
-                ),
-                storage_diffs: IndexMap::from_iter(
-                    state_update.state_diff.storage_diffs.into_iter().map(|(address, entries)| {
-                        (address, entries.into_iter().map(|se| (se.key, se.value)).collect())
-                    }),
-                ),
-                declared_classes: classes,
-                nonces: state_update.state_diff.nonces,
-            };
+            let (state_diff, cairo1_state_diff, deployed_contract_class_definitions) =
+                client_to_central_state_diff(state_update.state_diff, classes)?;
             debug!(
                 "Received new state update of block {current_block_number} with hash {block_hash}."
             );
             trace!(
-                "State diff: {state_diff:?}, deployed_contract_class_definitions: \
-                 {deployed_contract_class_definitions:?}."
+                "State diff: {state_diff:?}, Cairo 1 state diff: {cairo1_state_diff:?}, \
+                 deployed_contract_class_definitions: {deployed_contract_class_definitions:?}."
             );
-            Ok((current_block_number, block_hash, state_diff, deployed_contract_class_definitions))
+            Ok((
+                current_block_number,
+                block_hash,
+                state_diff,
+                cairo1_state_diff,
+                deployed_contract_class_definitions,
+            ))
         }
         Err(err) => {
             debug!("Received error for state diff {}: {:?}.", current_block_number, err);
@@ -230,7 +414,8 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static>
     fn state_update_stream(
         &self,
         block_number_stream: impl Stream<Item = BlockNumber> + Send + Sync + 'static,
-    ) -> impl Stream<Item = CentralResult<(StateUpdate, IndexMap<ClassHash, ContractClass>)>> {
+    ) -> impl Stream<Item = CentralResult<(StateUpdate, IndexMap<ClassHash, GenericContractClass>)>>
+    {
         // Stream the state updates.
         let starknet_client = self.starknet_client.clone();
         let (state_updates0, mut state_updates1) = block_number_stream
@@ -243,7 +428,7 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static>
             .map_err(Arc::new)
             .fanout(self.concurrent_requests);
 
-        // Stream the declared and deployed classes.
+        // Stream the declared, deployed and declared Sierra classes.
         let starknet_client = self.starknet_client.clone();
         let mut flat_classes = state_updates0
             // In case state_updates1 contains a ClientError, we yield it and break - without
@@ -274,13 +459,13 @@ impl<TStarknetClient: StarknetClientTrait + Send + Sync + 'static>
                     }
                 };
 
-                // Get the next state declared and deployed classes.
+                // Get the next state declared, deployed and declared Sierra classes.
                 let len = state_update.state_diff.class_hashes().len();
-                let classes: Option<Result<IndexMap<ClassHash, ContractClass>, _>> =
+                let classes: Option<Result<IndexMap<ClassHash, GenericContractClass>, _>> =
                     flat_classes.take_n(len).await.map(|v| {
                         v.into_iter()
                             .map(|(class_hash, class)| match class {
-                                Ok(Some(class)) => Ok((class_hash, class.into())),
+                                Ok(Some(class)) => Ok((class_hash, class)),
                                 Ok(None) => Err(CentralError::StateUpdateNotFound),
                                 Err(err) => Err(CentralError::ClientError(err)),
                             })
@@ -309,3 +494,15 @@ impl CentralSource {
         })
     }
 }
+
+pub type OfflineCentralSource = GenericCentralSource<OfflineStarknetClient>;
+
+impl OfflineCentralSource {
+    pub fn new(config: CentralSourceConfig) -> OfflineCentralSource {
+        let dir = config.url.strip_prefix("file://").unwrap_or(&config.url);
+        OfflineCentralSource {
+            concurrent_requests: config.concurrent_requests,
+            starknet_client: Arc::new(OfflineStarknetClient::new(dir)),
+        }
+    }
+}
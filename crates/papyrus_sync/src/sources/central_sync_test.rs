@@ -1,28 +1,37 @@
-use std::sync::Arc;
+use std::cmp::Ordering;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use async_stream::stream;
 use async_trait::async_trait;
 use futures::StreamExt;
 use indexmap::IndexMap;
+use papyrus_storage::base_layer::BaseLayerStorageReader;
 use papyrus_storage::header::HeaderStorageReader;
+use papyrus_storage::state::data::Cairo1StateDiff;
 use papyrus_storage::state::StateStorageReader;
 use papyrus_storage::test_utils::get_test_storage;
 use papyrus_storage::{StorageReader, StorageWriter};
 use starknet_api::block::{Block, BlockBody, BlockHash, BlockHeader, BlockNumber};
+use starknet_api::core::ChainId;
 use starknet_api::hash::StarkFelt;
 use starknet_api::stark_felt;
 use starknet_api::state::StateDiff;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 use tracing::{debug, error};
 
 use super::central::BlocksStream;
 use crate::sources::central::{MockCentralSourceTrait, StateUpdatesStream};
-use crate::{CentralError, CentralSourceTrait, GenericStateSync, StateSyncResult, SyncConfig};
+use crate::sources::MockBaseLayerSourceTrait;
+use crate::{
+    CentralError, CentralSourceTrait, GenericStateSync, PendingBlockHeader, PendingData,
+    StateSyncResult, SyncConfig, SyncNotification, SyncStatus,
+};
 
 const SYNC_SLEEP_DURATION: Duration = Duration::new(0, 1000 * 1000 * 100); // 100ms
 const DURATION_BEFORE_CHECKING_STORAGE: Duration = Duration::new(0, 1000 * 1000 * 100); // 100ms
 const MAX_CHECK_STORAGE_ITERATIONS: u8 = 3;
+const NOTIFICATIONS_CAPACITY: usize = 100;
 
 enum CheckStoragePredicateResult {
     InProgress,
@@ -66,15 +75,49 @@ async fn run_sync(
     reader: StorageReader,
     writer: StorageWriter,
     central: impl CentralSourceTrait + Send + Sync + 'static,
+) -> StateSyncResult {
+    let (notifications_sender, _) = broadcast::channel(NOTIFICATIONS_CAPACITY);
+    run_sync_with_shared_state(
+        reader,
+        writer,
+        central,
+        None,
+        Arc::new(RwLock::new(None)),
+        notifications_sender,
+        Arc::new(RwLock::new(SyncStatus::default())),
+    )
+    .await
+}
+
+// Runs sync loop with a mocked central and optionally a mocked base layer, exposing the pending data
+// the sync keeps in memory, the notifications it publishes and its status.
+async fn run_sync_with_shared_state(
+    reader: StorageReader,
+    writer: StorageWriter,
+    central: impl CentralSourceTrait + Send + Sync + 'static,
+    base_layer: Option<MockBaseLayerSourceTrait>,
+    pending_data: Arc<RwLock<Option<PendingData>>>,
+    notifications_sender: broadcast::Sender<SyncNotification>,
+    sync_status: Arc<RwLock<SyncStatus>>,
 ) -> StateSyncResult {
     let mut state_sync = GenericStateSync {
         config: SyncConfig {
             block_propagation_sleep_duration: SYNC_SLEEP_DURATION,
             recoverable_error_sleep_duration: SYNC_SLEEP_DURATION,
+            pending_sleep_duration: SYNC_SLEEP_DURATION,
+            base_layer_propagation_sleep_duration: SYNC_SLEEP_DURATION,
+            verify_hashes: false,
+            verify_state_root: false,
+            chain_id: ChainId("SN_TEST".to_owned()),
         },
         central_source: Arc::new(central),
+        base_layer_source: base_layer.map(Arc::new),
         reader,
         writer,
+        pruning_config: None,
+        pending_data,
+        notifications_sender,
+        sync_status,
     };
 
     state_sync.run().await?;
@@ -88,16 +131,30 @@ async fn sync_empty_chain() {
     // Mock central without any block.
     let mut mock = MockCentralSourceTrait::new();
     mock.expect_get_block_marker().returning(|| Ok(BlockNumber(0)));
+    mock.expect_get_pending_data().returning(|_| Ok(None));
     let (reader, writer) = get_test_storage();
-    let sync_future = run_sync(reader.clone(), writer, mock);
+    let sync_status = Arc::new(RwLock::new(SyncStatus::default()));
+    let sync_future = run_sync_with_shared_state(
+        reader.clone(),
+        writer,
+        mock,
+        None,
+        Arc::new(RwLock::new(None)),
+        broadcast::channel(NOTIFICATIONS_CAPACITY).0,
+        sync_status.clone(),
+    );
 
-    // Check that the header marker is 0.
+    // Check that the header marker is 0 and that the sync reports the marker of the central.
     let check_storage_future = check_storage(reader.clone(), Duration::from_millis(50), |reader| {
         let marker = reader.begin_ro_txn().unwrap().get_header_marker().unwrap();
-        if marker == BlockNumber(0) {
-            return CheckStoragePredicateResult::Passed;
+        if marker != BlockNumber(0) {
+            return CheckStoragePredicateResult::Error;
+        }
+        match sync_status.read().unwrap().central_block_marker {
+            None => CheckStoragePredicateResult::InProgress,
+            Some(BlockNumber(0)) => CheckStoragePredicateResult::Passed,
+            Some(_) => CheckStoragePredicateResult::Error,
         }
-        CheckStoragePredicateResult::Error
     });
 
     tokio::select! {
@@ -143,6 +200,7 @@ async fn sync_happy_flow() {
                     block_number,
                     create_block_hash(block_number, false),
                     StateDiff::default(),
+                    Cairo1StateDiff::default(),
                     IndexMap::new(),
                 ));
             }
@@ -151,6 +209,7 @@ async fn sync_happy_flow() {
         state_stream
     });
     mock.expect_get_block_hash().returning(|bn| Ok(Some(create_block_hash(bn, false))));
+    mock.expect_get_pending_data().returning(|_| Ok(None));
     let (reader, writer) = get_test_storage();
     let sync_future = run_sync(reader.clone(), writer, mock);
 
@@ -184,6 +243,163 @@ async fn sync_happy_flow() {
     }
 }
 
+#[tokio::test]
+async fn sync_base_layer_block() {
+    const N_BLOCKS: u64 = 3;
+    const PROVED_BLOCK_NUMBER: BlockNumber = BlockNumber(1);
+    const MAX_TIME_TO_SYNC_MS: u64 = 300;
+    let _ = simple_logger::init_with_env();
+
+    // Mock having N_BLOCKS chain in central, and one of its blocks proved on the base layer.
+    let mut mock = MockCentralSourceTrait::new();
+    mock.expect_get_block_marker().returning(|| Ok(BlockNumber(N_BLOCKS)));
+    mock.expect_stream_new_blocks().returning(move |initial, up_to| {
+        let blocks_stream: BlocksStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                let header = BlockHeader {
+                    block_number,
+                    block_hash: create_block_hash(block_number, false),
+                    parent_hash: create_block_hash(block_number.prev().unwrap_or_default(), false),
+                    ..BlockHeader::default()
+                };
+                yield Ok((block_number, Block { header, body: BlockBody::default() }));
+            }
+        }
+        .boxed();
+        blocks_stream
+    });
+    mock.expect_stream_state_updates().returning(move |initial, up_to| {
+        let state_stream: StateUpdatesStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                yield Ok((
+                    block_number,
+                    create_block_hash(block_number, false),
+                    StateDiff::default(),
+                    Cairo1StateDiff::default(),
+                    IndexMap::new(),
+                ));
+            }
+        }
+        .boxed();
+        state_stream
+    });
+    mock.expect_get_block_hash().returning(|bn| Ok(Some(create_block_hash(bn, false))));
+    mock.expect_get_pending_data().returning(|_| Ok(None));
+    let mut base_layer_mock = MockBaseLayerSourceTrait::new();
+    base_layer_mock.expect_latest_proved_block().returning(|| {
+        Ok(Some((PROVED_BLOCK_NUMBER, create_block_hash(PROVED_BLOCK_NUMBER, false))))
+    });
+
+    let (reader, writer) = get_test_storage();
+    let sync_future = run_sync_with_shared_state(
+        reader.clone(),
+        writer,
+        mock,
+        Some(base_layer_mock),
+        Arc::new(RwLock::new(None)),
+        broadcast::channel(NOTIFICATIONS_CAPACITY).0,
+        Arc::new(RwLock::new(SyncStatus::default())),
+    );
+
+    // Check that the proved block and its ancestors are marked as proved on the base layer.
+    let check_storage_future =
+        check_storage(reader, Duration::from_millis(MAX_TIME_TO_SYNC_MS), |reader| {
+            let marker = reader.begin_ro_txn().unwrap().get_base_layer_block_marker().unwrap();
+            match marker.cmp(&PROVED_BLOCK_NUMBER.next()) {
+                Ordering::Less => CheckStoragePredicateResult::InProgress,
+                Ordering::Equal => CheckStoragePredicateResult::Passed,
+                Ordering::Greater => CheckStoragePredicateResult::Error,
+            }
+        });
+
+    tokio::select! {
+        sync_result = sync_future => sync_result.unwrap(),
+        storage_check_result = check_storage_future => assert!(storage_check_result),
+    }
+}
+
+#[tokio::test]
+async fn sync_pending_data() {
+    const N_BLOCKS: u64 = 2;
+    const MAX_TIME_TO_SYNC_MS: u64 = 300;
+    let _ = simple_logger::init_with_env();
+
+    // Mock having N_BLOCKS chain in central with a pending block on top of it.
+    let mut mock = MockCentralSourceTrait::new();
+    mock.expect_get_block_marker().returning(|| Ok(BlockNumber(N_BLOCKS)));
+    mock.expect_stream_new_blocks().returning(move |initial, up_to| {
+        let blocks_stream: BlocksStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                let header = BlockHeader {
+                    block_number,
+                    block_hash: create_block_hash(block_number, false),
+                    parent_hash: create_block_hash(block_number.prev().unwrap_or_default(), false),
+                    ..BlockHeader::default()
+                };
+                yield Ok((block_number, Block { header, body: BlockBody::default() }));
+            }
+        }
+        .boxed();
+        blocks_stream
+    });
+    mock.expect_stream_state_updates().returning(move |initial, up_to| {
+        let state_stream: StateUpdatesStream<'_> = stream! {
+            for block_number in initial.iter_up_to(up_to) {
+                yield Ok((
+                    block_number,
+                    create_block_hash(block_number, false),
+                    StateDiff::default(),
+                    Cairo1StateDiff::default(),
+                    IndexMap::new(),
+                ));
+            }
+        }
+        .boxed();
+        state_stream
+    });
+    mock.expect_get_block_hash().returning(|bn| Ok(Some(create_block_hash(bn, false))));
+    let expected_pending_data = PendingData {
+        header: PendingBlockHeader {
+            parent_hash: create_block_hash(BlockNumber(N_BLOCKS - 1), false),
+            ..PendingBlockHeader::default()
+        },
+        ..PendingData::default()
+    };
+    let pending_data_clone = expected_pending_data.clone();
+    mock.expect_get_pending_data()
+        .withf(|pending_block_number| *pending_block_number == BlockNumber(N_BLOCKS))
+        .returning(move |_| Ok(Some(pending_data_clone.clone())));
+
+    let (reader, writer) = get_test_storage();
+    let pending_data = Arc::new(RwLock::new(None));
+    let sync_future = run_sync_with_shared_state(
+        reader.clone(),
+        writer,
+        mock,
+        None,
+        pending_data.clone(),
+        broadcast::channel(NOTIFICATIONS_CAPACITY).0,
+        Arc::new(RwLock::new(SyncStatus::default())),
+    );
+
+    // Check that the pending data is kept once the storage is synced.
+    let check_storage_future =
+        check_storage(reader, Duration::from_millis(MAX_TIME_TO_SYNC_MS), |_reader| {
+            match &*pending_data.read().unwrap() {
+                None => CheckStoragePredicateResult::InProgress,
+                Some(pending_data) if *pending_data == expected_pending_data => {
+                    CheckStoragePredicateResult::Passed
+                }
+                Some(_) => CheckStoragePredicateResult::Error,
+            }
+        });
+
+    tokio::select! {
+        sync_result = sync_future => sync_result.unwrap(),
+        storage_check_result = check_storage_future => assert!(storage_check_result),
+    }
+}
+
 #[tokio::test]
 async fn sync_with_revert() {
     let _ = simple_logger::init_with_env();
@@ -197,7 +413,17 @@ async fn sync_with_revert() {
     // Prepare sync thread with mocked central source that will perform a revert once the
     // reverted_mutex is true.
     let mock = MockedCentralWithRevert { reverted: reverted_mutex.clone() };
-    let sync_future = run_sync(reader.clone(), writer, mock);
+    let (notifications_sender, mut notifications_receiver) =
+        broadcast::channel(NOTIFICATIONS_CAPACITY);
+    let sync_future = run_sync_with_shared_state(
+        reader.clone(),
+        writer,
+        mock,
+        None,
+        Arc::new(RwLock::new(None)),
+        notifications_sender,
+        Arc::new(RwLock::new(SyncStatus::default())),
+    );
 
     // Prepare functions that check that the sync worked up to N_BLOCKS_BEFORE_REVERT and then
     // reacted correctly to the revert.
@@ -300,6 +526,19 @@ async fn sync_with_revert() {
         assert!(check_storage_before_revert_future.await);
         signal_revert.await;
         assert!(check_storage_after_revert_future.await);
+
+        // Check that the reverted blocks were published, from the last one backwards.
+        let mut reverted_blocks = vec![];
+        while let Ok(notification) = notifications_receiver.try_recv() {
+            if let SyncNotification::BlockReverted { block_number, block_hash } = notification {
+                reverted_blocks.push((block_number, block_hash));
+            }
+        }
+        let expected_reverted_blocks: Vec<_> = (CHAIN_FORK_BLOCK_NUMBER..N_BLOCKS_BEFORE_REVERT)
+            .rev()
+            .map(|bn| (BlockNumber(bn), create_block_hash(BlockNumber(bn), false)))
+            .collect();
+        assert_eq!(reverted_blocks, expected_reverted_blocks);
     };
 
     tokio::select! {
@@ -354,6 +593,13 @@ async fn sync_with_revert() {
             }
         }
 
+        async fn get_pending_data(
+            &self,
+            _pending_block_number: BlockNumber,
+        ) -> Result<Option<PendingData>, CentralError> {
+            Ok(None)
+        }
+
         fn stream_new_blocks(
             &self,
             initial_block_number: BlockNumber,
@@ -402,7 +648,13 @@ async fn sync_with_revert() {
                         if i.0 >= N_BLOCKS_BEFORE_REVERT {
                             yield Err(CentralError::BlockNotFound { block_number: i });
                         }
-                        yield Ok((i, create_block_hash(i, false), StateDiff::default(), IndexMap::new()));
+                        yield Ok((
+                            i,
+                            create_block_hash(i, false),
+                            StateDiff::default(),
+                            Cairo1StateDiff::default(),
+                            IndexMap::new(),
+                        ));
                     }
                 }
                 .boxed(),
@@ -416,6 +668,7 @@ async fn sync_with_revert() {
                             i,
                             create_block_hash(i, is_reverted_state_diff),
                             StateDiff::default(),
+                            Cairo1StateDiff::default(),
                             IndexMap::new(),
                         ));
                     }
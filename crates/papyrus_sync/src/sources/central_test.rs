@@ -4,15 +4,17 @@ use assert_matches::assert_matches;
 use futures_util::pin_mut;
 use indexmap::IndexMap;
 use mockall::predicate;
+use papyrus_storage::state::data::{CompiledClassHash, SierraContractClass};
 use reqwest::StatusCode;
-use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::block::{BlockHash, BlockNumber, BlockTimestamp};
 use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
 use starknet_api::hash::{StarkFelt, StarkHash};
 use starknet_api::state::StorageKey;
 use starknet_api::{patricia_key, stark_felt};
 use starknet_client::{
-    Block, ClientError, ContractClass, DeployedContract, GlobalRoot, MockStarknetClientTrait,
-    StateUpdate, StorageEntry,
+    Block, ClientError, ContractClass, DeclaredClassHashEntry, DeployedContract,
+    GenericContractClass, GlobalRoot, MockStarknetClientTrait, PendingBlock, PendingStateUpdate,
+    ReplacedClass, StateUpdate, StorageEntry,
 };
 use tokio_stream::StreamExt;
 
@@ -167,6 +169,8 @@ async fn stream_state_updates() {
     let class_hash1 = ClassHash(stark_felt!("0x123"));
     let class_hash2 = ClassHash(stark_felt!("0x456"));
     let class_hash3 = ClassHash(stark_felt!("0x789"));
+    let sierra_class_hash = ClassHash(stark_felt!("0x999"));
+    let compiled_class_hash = stark_felt!("0x888");
     let contract_address1 = ContractAddress(patricia_key!("0xabc"));
     let contract_address2 = ContractAddress(patricia_key!("0xdef"));
     let nonce1 = Nonce(stark_felt!("0x123456789abcdef"));
@@ -181,6 +185,10 @@ async fn stream_state_updates() {
     let contract_class1 = ContractClass::default();
     let contract_class2 = ContractClass::default();
     let contract_class3 = ContractClass::default();
+    let sierra_class = starknet_client::SierraContractClass {
+        contract_class_version: "0.1.0".to_owned(),
+        ..starknet_client::SierraContractClass::default()
+    };
 
     let client_state_diff1 = starknet_client::StateDiff {
         storage_diffs: IndexMap::from([(contract_address1, vec![StorageEntry { key, value }])]),
@@ -190,6 +198,14 @@ async fn stream_state_updates() {
         ],
         declared_contracts: vec![class_hash1, class_hash3],
         nonces: IndexMap::from([(contract_address1, nonce1)]),
+        declared_classes: vec![DeclaredClassHashEntry {
+            class_hash: sierra_class_hash,
+            compiled_class_hash,
+        }],
+        replaced_classes: vec![ReplacedClass {
+            address: contract_address2,
+            class_hash: sierra_class_hash,
+        }],
     };
     let client_state_diff2 = starknet_client::StateDiff::default();
 
@@ -218,20 +234,21 @@ async fn stream_state_updates() {
         .times(1)
         .returning(move |_x| Ok(Some(block_state_update2_clone.clone())));
     let contract_class1_clone = contract_class1.clone();
-    mock.expect_class_by_hash()
-        .with(predicate::eq(class_hash1))
-        .times(1)
-        .returning(move |_x| Ok(Some(contract_class1_clone.clone())));
+    mock.expect_class_by_hash().with(predicate::eq(class_hash1)).times(1).returning(move |_x| {
+        Ok(Some(GenericContractClass::Cairo0ContractClass(contract_class1_clone.clone())))
+    });
     let contract_class2_clone = contract_class2.clone();
-    mock.expect_class_by_hash()
-        .with(predicate::eq(class_hash2))
-        .times(1)
-        .returning(move |_x| Ok(Some(contract_class2_clone.clone())));
+    mock.expect_class_by_hash().with(predicate::eq(class_hash2)).times(1).returning(move |_x| {
+        Ok(Some(GenericContractClass::Cairo0ContractClass(contract_class2_clone.clone())))
+    });
     let contract_class3_clone = contract_class3.clone();
-    mock.expect_class_by_hash()
-        .with(predicate::eq(class_hash3))
-        .times(1)
-        .returning(move |_x| Ok(Some(contract_class3_clone.clone())));
+    mock.expect_class_by_hash().with(predicate::eq(class_hash3)).times(1).returning(move |_x| {
+        Ok(Some(GenericContractClass::Cairo0ContractClass(contract_class3_clone.clone())))
+    });
+    let sierra_class_clone = sierra_class.clone();
+    mock.expect_class_by_hash().with(predicate::eq(sierra_class_hash)).times(1).returning(
+        move |_x| Ok(Some(GenericContractClass::Cairo1ContractClass(sierra_class_clone.clone()))),
+    );
 
     let central_source = GenericCentralSource {
         concurrent_requests: TEST_CONCURRENT_REQUESTS,
@@ -243,12 +260,17 @@ async fn stream_state_updates() {
         central_source.stream_state_updates(initial_block_num, BlockNumber(END_BLOCK_NUMBER));
     pin_mut!(stream);
 
-    let (current_block_num, current_block_hash, state_diff, deployed_contract_class_definitions) =
-        if let Some(Ok(state_diff_tuple)) = stream.next().await {
-            state_diff_tuple
-        } else {
-            panic!("Match of streamed state_update failed!");
-        };
+    let (
+        current_block_num,
+        current_block_hash,
+        state_diff,
+        cairo1_state_diff,
+        deployed_contract_class_definitions,
+    ) = if let Some(Ok(state_diff_tuple)) = stream.next().await {
+        state_diff_tuple
+    } else {
+        panic!("Match of streamed state_update failed!");
+    };
     assert_eq!(initial_block_num, current_block_num);
     assert_eq!(block_hash1, current_block_hash);
     assert_eq!(
@@ -272,8 +294,25 @@ async fn stream_state_updates() {
         state_diff.declared_classes,
     );
     assert_eq!(IndexMap::from([(contract_address1, nonce1)]), state_diff.nonces);
+    assert_eq!(
+        IndexMap::from([(
+            sierra_class_hash,
+            (
+                CompiledClassHash(compiled_class_hash),
+                SierraContractClass {
+                    contract_class_version: "0.1.0".to_owned(),
+                    ..SierraContractClass::default()
+                }
+            )
+        )]),
+        cairo1_state_diff.declared_classes,
+    );
+    assert_eq!(
+        IndexMap::from([(contract_address2, sierra_class_hash)]),
+        cairo1_state_diff.replaced_classes
+    );
 
-    let (current_block_num, current_block_hash, state_diff, _deployed_classes) =
+    let (current_block_num, current_block_hash, state_diff, _cairo1_state_diff, _deployed_classes) =
         if let Some(Ok(state_diff_tuple)) = stream.next().await {
             state_diff_tuple
         } else {
@@ -285,3 +324,59 @@ async fn stream_state_updates() {
 
     assert!(stream.next().await.is_none());
 }
+
+#[tokio::test]
+async fn get_pending_data() {
+    let parent_hash = BlockHash(stark_felt!("0x123"));
+    let timestamp = BlockTimestamp(1234);
+    let class_hash = ClassHash(stark_felt!("0x456"));
+    let contract_address = ContractAddress(patricia_key!("0xabc"));
+    let client_state_diff = starknet_client::StateDiff {
+        deployed_contracts: vec![DeployedContract { address: contract_address, class_hash }],
+        ..starknet_client::StateDiff::default()
+    };
+
+    let mut mock = MockStarknetClientTrait::new();
+    mock.expect_pending_block().times(1).returning(move || {
+        Ok(Some(PendingBlock {
+            parent_block_hash: parent_hash,
+            timestamp,
+            ..PendingBlock::default()
+        }))
+    });
+    let client_state_diff_clone = client_state_diff.clone();
+    mock.expect_pending_state_update().times(1).returning(move || {
+        Ok(Some(PendingStateUpdate {
+            old_root: GlobalRoot::default(),
+            state_diff: client_state_diff_clone.clone(),
+        }))
+    });
+    mock.expect_class_by_hash().with(predicate::eq(class_hash)).times(1).returning(|_x| {
+        Ok(Some(GenericContractClass::Cairo0ContractClass(ContractClass::default())))
+    });
+    let central_source = GenericCentralSource {
+        concurrent_requests: TEST_CONCURRENT_REQUESTS,
+        starknet_client: Arc::new(mock),
+    };
+
+    let pending_data = central_source.get_pending_data(BlockNumber(1)).await.unwrap().unwrap();
+    assert_eq!(pending_data.header.parent_hash, parent_hash);
+    assert_eq!(pending_data.header.timestamp, timestamp);
+    assert_eq!(
+        IndexMap::from([(contract_address, class_hash)]),
+        pending_data.state_diff.deployed_contracts
+    );
+    assert_eq!(
+        IndexMap::from([(class_hash, starknet_api::state::ContractClass::default())]),
+        pending_data.deployed_contract_class_definitions
+    );
+
+    // No pending block.
+    let mut mock = MockStarknetClientTrait::new();
+    mock.expect_pending_block().times(1).returning(|| Ok(None));
+    let central_source = GenericCentralSource {
+        concurrent_requests: TEST_CONCURRENT_REQUESTS,
+        starknet_client: Arc::new(mock),
+    };
+    assert!(central_source.get_pending_data(BlockNumber(1)).await.unwrap().is_none());
+}
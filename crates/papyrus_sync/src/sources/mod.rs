@@ -1,10 +1,19 @@
+mod base_layer;
 mod central;
 #[cfg(test)]
 mod central_sync_test;
 #[cfg(test)]
 mod central_test;
+mod peer;
+#[cfg(test)]
+mod peer_test;
 mod stream_utils;
 
+#[cfg(test)]
+pub(crate) use base_layer::MockBaseLayerSourceTrait;
+pub use base_layer::{BaseLayerSourceError, BaseLayerSourceTrait, EthereumBaseLayerSource};
 pub use central::{
-    CentralError, CentralResult, CentralSource, CentralSourceConfig, CentralSourceTrait,
+    CentralError, CentralResult, CentralSource, CentralSourceConfig, CentralSourceKind,
+    CentralSourceTrait, OfflineCentralSource,
 };
+pub use peer::PeerCentralSource;
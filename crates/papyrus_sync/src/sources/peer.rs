@@ -0,0 +1,580 @@
+//! A central source that reads the blocks and state updates from the JSON-RPC server of a trusted
+//! peer papyrus node. The gas price of the blocks, which the Starknet specification doesn't expose,
+//! is read with the papyrus_getBlockGasPrice method of the peer. The pending data isn't synced from
+//! a peer.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use async_stream::stream;
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use futures::future::try_join_all;
+use futures_util::StreamExt;
+use indexmap::IndexMap;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::core::Error;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::rpc_params;
+use jsonrpsee::types::error::CallError;
+use papyrus_storage::state::data::{Cairo1StateDiff, CompiledClassHash, SierraContractClass};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use starknet_api::block::{
+    Block, BlockBody, BlockHash, BlockHeader, BlockNumber, BlockTimestamp, GasPrice,
+};
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector, GlobalRoot, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::{ContractClass, EntryPoint, EntryPointType, StateDiff, StorageKey};
+use starknet_api::transaction::{
+    Calldata, DeclareTransaction, DeclareTransactionOutput, DeployAccountTransaction,
+    DeployAccountTransactionOutput, DeployTransaction, DeployTransactionOutput, Event, Fee,
+    InvokeTransaction, InvokeTransactionOutput, L1HandlerTransaction, L1HandlerTransactionOutput,
+    MessageToL1, Transaction, TransactionHash, TransactionOutput, TransactionSignature,
+    TransactionVersion,
+};
+use tracing::{debug, trace};
+
+use super::central::{
+    BlocksStream, CentralError, CentralResult, CentralSourceConfig, CentralSourceTrait,
+    CentralStateUpdate, StateUpdatesStream,
+};
+use crate::PendingData;
+
+// The JSON-RPC error codes of the papyrus gateway.
+const NO_BLOCKS_ERROR_CODE: i32 = 0;
+const BLOCK_NOT_FOUND_ERROR_CODE: i32 = 24;
+const CLASS_HASH_NOT_FOUND_ERROR_CODE: i32 = 28;
+
+pub struct PeerCentralSource {
+    pub concurrent_requests: usize,
+    pub client: HttpClient,
+}
+
+impl PeerCentralSource {
+    pub fn new(config: CentralSourceConfig) -> Result<PeerCentralSource, CentralError> {
+        let client = HttpClientBuilder::default().build(&config.url).map_err(CentralError::from)?;
+        Ok(PeerCentralSource { concurrent_requests: config.concurrent_requests, client })
+    }
+}
+
+#[async_trait]
+impl CentralSourceTrait for PeerCentralSource {
+    async fn get_block_marker(&self) -> Result<BlockNumber, CentralError> {
+        let block_number: Option<BlockNumber> = none_on_error_code(
+            self.client.request("starknet_blockNumber", None).await,
+            NO_BLOCKS_ERROR_CODE,
+        )?;
+        Ok(block_number.map_or(BlockNumber::default(), |block_number| block_number.next()))
+    }
+
+    fn stream_new_blocks(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> BlocksStream<'_> {
+        stream! {
+            let mut res =
+                futures_util::stream::iter(initial_block_number.iter_up_to(up_to_block_number))
+                    .map(|block_number| async move {
+                        (block_number, self.get_block(block_number).await)
+                    })
+                    .buffered(self.concurrent_requests);
+            while let Some((block_number, maybe_block)) = res.next().await {
+                match maybe_block {
+                    Ok(Some(block)) => {
+                        debug!(
+                            "Received new block {block_number} with hash {}.",
+                            block.header.block_hash
+                        );
+                        trace!("Block: {block:#?}.");
+                        yield Ok((block_number, block));
+                    }
+                    Ok(None) => {
+                        yield Err(CentralError::BlockNotFound { block_number });
+                        return;
+                    }
+                    Err(err) => {
+                        debug!("Received error for block {block_number}: {err:?}.");
+                        yield Err(err);
+                        return;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn stream_state_updates(
+        &self,
+        initial_block_number: BlockNumber,
+        up_to_block_number: BlockNumber,
+    ) -> StateUpdatesStream<'_> {
+        stream! {
+            let mut res =
+                futures_util::stream::iter(initial_block_number.iter_up_to(up_to_block_number))
+                    .map(|block_number| async move {
+                        (block_number, self.get_state_update(block_number).await)
+                    })
+                    .buffered(self.concurrent_requests);
+            while let Some((block_number, maybe_state_update)) = res.next().await {
+                match maybe_state_update {
+                    Ok(Some(state_update)) => {
+                        debug!(
+                            "Received new state update of block {block_number} with hash {}.",
+                            state_update.1
+                        );
+                        yield Ok(state_update);
+                    }
+                    Ok(None) => {
+                        yield Err(CentralError::StateUpdateNotFound);
+                        return;
+                    }
+                    Err(err) => {
+                        debug!("Received error for state diff {block_number}: {err:?}.");
+                        yield Err(err);
+                        return;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    async fn get_block_hash(
+        &self,
+        block_number: BlockNumber,
+    ) -> Result<Option<BlockHash>, CentralError> {
+        let block: Option<PeerBlockWithTxHashes> = none_on_error_code(
+            self.client
+                .request("starknet_getBlockWithTxHashes", rpc_params![PeerBlockId { block_number }])
+                .await,
+            BLOCK_NOT_FOUND_ERROR_CODE,
+        )?;
+        Ok(block.map(|block| block.block_hash))
+    }
+
+    async fn get_pending_data(
+        &self,
+        _pending_block_number: BlockNumber,
+    ) -> Result<Option<PendingData>, CentralError> {
+        Ok(None)
+    }
+}
+
+impl PeerCentralSource {
+    // Returns the block with its transaction outputs, which are read from the transaction
+    // receipts, and its gas price.
+    async fn get_block(&self, block_number: BlockNumber) -> CentralResult<Option<Block>> {
+        let block: PeerBlock = match none_on_error_code(
+            self.client
+                .request("starknet_getBlockWithTxs", rpc_params![PeerBlockId { block_number }])
+                .await,
+            BLOCK_NOT_FOUND_ERROR_CODE,
+        )? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+
+        let receipts: Vec<PeerTransactionReceipt> =
+            try_join_all(block.transactions.iter().map(|transaction| {
+                self.client.request(
+                    "starknet_getTransactionReceipt",
+                    rpc_params![transaction.transaction_hash()],
+                )
+            }))
+            .await?;
+        let gas_price: GasPrice = self
+            .client
+            .request("papyrus_getBlockGasPrice", rpc_params![PeerBlockId { block_number }])
+            .await?;
+        let transactions: Vec<Transaction> =
+            block.transactions.into_iter().map(Transaction::from).collect();
+        let transaction_outputs =
+            receipts.into_iter().map(PeerTransactionReceipt::into_transaction_output).collect();
+
+        Ok(Some(Block {
+            header: BlockHeader {
+                block_hash: block.header.block_hash,
+                parent_hash: block.header.parent_hash,
+                block_number: block.header.block_number,
+                gas_price,
+                state_root: block.header.new_root,
+                sequencer: block.header.sequencer_address,
+                timestamp: block.header.timestamp,
+            },
+            body: BlockBody { transactions, transaction_outputs },
+        }))
+    }
+
+    // Returns the state update of the block with the definitions of its declared classes and its
+    // Cairo 1 part, followed by the definitions of the deployed classes that weren't declared in
+    // the block.
+    async fn get_state_update(
+        &self,
+        block_number: BlockNumber,
+    ) -> CentralResult<Option<CentralStateUpdate>> {
+        let state_update: PeerStateUpdate = match none_on_error_code(
+            self.client
+                .request("starknet_getStateUpdate", rpc_params![PeerBlockId { block_number }])
+                .await,
+            BLOCK_NOT_FOUND_ERROR_CODE,
+        )? {
+            Some(state_update) => state_update,
+            None => return Ok(None),
+        };
+        let diff = state_update.state_diff;
+
+        let declared_class_hashes = diff.declared_contract_hashes;
+        let sierra_class_hashes: Vec<ClassHash> =
+            diff.declared_classes.iter().map(|declared_class| declared_class.class_hash).collect();
+        let deployed_class_hashes: Vec<ClassHash> = diff
+            .deployed_contracts
+            .iter()
+            .map(|contract| contract.class_hash)
+            .filter(|class_hash| {
+                !declared_class_hashes.contains(class_hash)
+                    && !sierra_class_hashes.contains(class_hash)
+            })
+            .collect();
+        let mut declared_classes = IndexMap::new();
+        for (class_hash, class) in self.get_classes(block_number, declared_class_hashes).await? {
+            match class {
+                PeerClass::Cairo0(class) => {
+                    declared_classes.insert(class_hash, class.decode(class_hash)?)
+                }
+                PeerClass::Sierra(_) => return Err(CentralError::ClassKindMismatch { class_hash }),
+            };
+        }
+        // The classes of contracts deployed with Sierra classes were declared in earlier blocks.
+        let mut deployed_contract_class_definitions = IndexMap::new();
+        for (class_hash, class) in self.get_classes(block_number, deployed_class_hashes).await? {
+            if let PeerClass::Cairo0(class) = class {
+                deployed_contract_class_definitions.insert(class_hash, class.decode(class_hash)?);
+            }
+        }
+        let sierra_classes = self.get_classes(block_number, sierra_class_hashes).await?;
+        let mut declared_sierra_classes = IndexMap::new();
+        for (declared_class, (class_hash, class)) in
+            diff.declared_classes.iter().zip(sierra_classes)
+        {
+            match class {
+                PeerClass::Sierra(class) => declared_sierra_classes
+                    .insert(class_hash, (declared_class.compiled_class_hash, class)),
+                PeerClass::Cairo0(_) => return Err(CentralError::ClassKindMismatch { class_hash }),
+            };
+        }
+        let cairo1_state_diff = Cairo1StateDiff {
+            declared_classes: declared_sierra_classes,
+            replaced_classes: diff
+                .replaced_classes
+                .into_iter()
+                .map(|replaced_class| (replaced_class.contract_address, replaced_class.class_hash))
+                .collect(),
+        };
+
+        let state_diff = StateDiff {
+            deployed_contracts: diff
+                .deployed_contracts
+                .into_iter()
+                .map(|contract| (contract.address, contract.class_hash))
+                .collect(),
+            storage_diffs: diff
+                .storage_diffs
+                .into_iter()
+                .map(|diff| {
+                    let entries =
+                        diff.storage_entries.into_iter().map(|entry| (entry.key, entry.value));
+                    (diff.address, entries.collect())
+                })
+                .collect(),
+            declared_classes,
+            nonces: diff
+                .nonces
+                .into_iter()
+                .map(|nonce| (nonce.contract_address, nonce.nonce))
+                .collect(),
+        };
+        trace!(
+            "State diff: {state_diff:?}, Cairo 1 state diff: {cairo1_state_diff:?}, \
+             deployed_contract_class_definitions: {deployed_contract_class_definitions:?}."
+        );
+        Ok(Some((
+            block_number,
+            state_update.block_hash,
+            state_diff,
+            cairo1_state_diff,
+            deployed_contract_class_definitions,
+        )))
+    }
+
+    async fn get_classes(
+        &self,
+        block_number: BlockNumber,
+        class_hashes: Vec<ClassHash>,
+    ) -> CentralResult<Vec<(ClassHash, PeerClass)>> {
+        try_join_all(class_hashes.into_iter().map(|class_hash| async move {
+            let class: Option<PeerClass> = none_on_error_code(
+                self.client
+                    .request(
+                        "starknet_getClass",
+                        rpc_params![PeerBlockId { block_number }, class_hash],
+                    )
+                    .await,
+                CLASS_HASH_NOT_FOUND_ERROR_CODE,
+            )?;
+            let class = class.ok_or(CentralError::ClassNotFound)?;
+            Ok((class_hash, class))
+        }))
+        .await
+    }
+}
+
+// Returns None if the peer responded with the given JSON-RPC error code.
+fn none_on_error_code<T>(result: Result<T, Error>, error_code: i32) -> CentralResult<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::Call(CallError::Custom(err))) if err.code() == error_code => Ok(None),
+        Err(err) => Err(CentralError::from(err)),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PeerBlockId {
+    block_number: BlockNumber,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerBlockHeader {
+    block_hash: BlockHash,
+    parent_hash: BlockHash,
+    block_number: BlockNumber,
+    sequencer_address: ContractAddress,
+    new_root: GlobalRoot,
+    timestamp: BlockTimestamp,
+}
+
+// Only the block hash is read from the blocks with transaction hashes.
+#[derive(Debug, Deserialize)]
+struct PeerBlockWithTxHashes {
+    block_hash: BlockHash,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerBlock {
+    #[serde(flatten)]
+    header: PeerBlockHeader,
+    transactions: Vec<PeerTransaction>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+enum PeerTransactionType {
+    #[serde(rename = "DECLARE")]
+    Declare,
+    #[serde(rename = "DEPLOY")]
+    Deploy,
+    #[serde(rename = "DEPLOY_ACCOUNT")]
+    DeployAccount,
+    #[serde(rename = "INVOKE")]
+    Invoke,
+    #[serde(rename = "L1_HANDLER")]
+    L1Handler,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum PeerTransaction {
+    #[serde(rename = "DECLARE")]
+    Declare(DeclareTransaction),
+    #[serde(rename = "DEPLOY")]
+    Deploy(DeployTransaction),
+    #[serde(rename = "DEPLOY_ACCOUNT")]
+    DeployAccount(DeployAccountTransaction),
+    #[serde(rename = "INVOKE")]
+    Invoke(PeerInvokeTransaction),
+    #[serde(rename = "L1_HANDLER")]
+    L1Handler(L1HandlerTransaction),
+}
+
+impl PeerTransaction {
+    fn transaction_hash(&self) -> TransactionHash {
+        match self {
+            PeerTransaction::Declare(tx) => tx.transaction_hash,
+            PeerTransaction::Deploy(tx) => tx.transaction_hash,
+            PeerTransaction::DeployAccount(tx) => tx.transaction_hash,
+            PeerTransaction::Invoke(tx) => tx.transaction_hash,
+            PeerTransaction::L1Handler(tx) => tx.transaction_hash,
+        }
+    }
+}
+
+impl From<PeerTransaction> for Transaction {
+    fn from(tx: PeerTransaction) -> Self {
+        match tx {
+            PeerTransaction::Declare(tx) => Transaction::Declare(tx),
+            PeerTransaction::Deploy(tx) => Transaction::Deploy(tx),
+            PeerTransaction::DeployAccount(tx) => Transaction::DeployAccount(tx),
+            PeerTransaction::Invoke(tx) => Transaction::Invoke(InvokeTransaction {
+                transaction_hash: tx.transaction_hash,
+                max_fee: tx.max_fee,
+                version: tx.version,
+                signature: tx.signature,
+                nonce: tx.nonce,
+                sender_address: tx.sender_address,
+                entry_point_selector: tx.entry_point_selector,
+                calldata: tx.calldata,
+            }),
+            PeerTransaction::L1Handler(tx) => Transaction::L1Handler(tx),
+        }
+    }
+}
+
+// Invoke transactions of version 0 have a contract address and an entry point selector, and those
+// of version 1 have a sender address.
+#[derive(Debug, Deserialize)]
+struct PeerInvokeTransaction {
+    transaction_hash: TransactionHash,
+    max_fee: Fee,
+    version: TransactionVersion,
+    signature: TransactionSignature,
+    nonce: Nonce,
+    #[serde(alias = "contract_address")]
+    sender_address: ContractAddress,
+    entry_point_selector: Option<EntryPointSelector>,
+    calldata: Calldata,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerTransactionReceipt {
+    r#type: PeerTransactionType,
+    actual_fee: Fee,
+    messages_sent: Vec<MessageToL1>,
+    events: Vec<Event>,
+}
+
+impl PeerTransactionReceipt {
+    fn into_transaction_output(self) -> TransactionOutput {
+        let PeerTransactionReceipt { r#type, actual_fee, messages_sent, events } = self;
+        match r#type {
+            PeerTransactionType::Declare => TransactionOutput::Declare(DeclareTransactionOutput {
+                actual_fee,
+                messages_sent,
+                events,
+            }),
+            PeerTransactionType::Deploy => TransactionOutput::Deploy(DeployTransactionOutput {
+                actual_fee,
+                messages_sent,
+                events,
+            }),
+            PeerTransactionType::DeployAccount => {
+                TransactionOutput::DeployAccount(DeployAccountTransactionOutput {
+                    actual_fee,
+                    messages_sent,
+                    events,
+                })
+            }
+            PeerTransactionType::Invoke => TransactionOutput::Invoke(InvokeTransactionOutput {
+                actual_fee,
+                messages_sent,
+                events,
+            }),
+            PeerTransactionType::L1Handler => {
+                TransactionOutput::L1Handler(L1HandlerTransactionOutput {
+                    actual_fee,
+                    messages_sent,
+                    events,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerStateUpdate {
+    block_hash: BlockHash,
+    state_diff: PeerStateDiff,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerStateDiff {
+    deployed_contracts: Vec<PeerDeployedContract>,
+    storage_diffs: Vec<PeerStorageDiff>,
+    declared_contract_hashes: Vec<ClassHash>,
+    nonces: Vec<PeerContractNonce>,
+    #[serde(default)]
+    declared_classes: Vec<PeerDeclaredClass>,
+    #[serde(default)]
+    replaced_classes: Vec<PeerReplacedClass>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerDeclaredClass {
+    class_hash: ClassHash,
+    compiled_class_hash: CompiledClassHash,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerReplacedClass {
+    contract_address: ContractAddress,
+    class_hash: ClassHash,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerDeployedContract {
+    address: ContractAddress,
+    class_hash: ClassHash,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerStorageDiff {
+    address: ContractAddress,
+    storage_entries: Vec<PeerStorageEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerStorageEntry {
+    key: StorageKey,
+    value: StarkFelt,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerContractNonce {
+    contract_address: ContractAddress,
+    nonce: Nonce,
+}
+
+// A Cairo 0 class doesn't have a Sierra program, and a Sierra class doesn't have a program.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PeerClass {
+    Cairo0(PeerContractClass),
+    Sierra(SierraContractClass),
+}
+
+#[derive(Debug, Deserialize)]
+struct PeerContractClass {
+    abi: serde_json::Value,
+    /// A base64 encoding of the gzip-compressed JSON representation of the program.
+    program: String,
+    entry_points_by_type: HashMap<EntryPointType, Vec<EntryPoint>>,
+}
+
+impl PeerContractClass {
+    fn decode(self, class_hash: ClassHash) -> CentralResult<ContractClass> {
+        let program =
+            decode_program(&self.program).ok_or(CentralError::ClassDecodingError { class_hash })?;
+        Ok(starknet_client::ContractClass {
+            abi: self.abi,
+            program,
+            entry_points_by_type: self.entry_points_by_type,
+        }
+        .into())
+    }
+}
+
+fn decode_program<T: DeserializeOwned>(program: &str) -> Option<T> {
+    let compressed = base64::decode(program).ok()?;
+    let mut decompressed = Vec::new();
+    GzDecoder::new(compressed.as_slice()).read_to_end(&mut decompressed).ok()?;
+    serde_json::from_slice(&decompressed).ok()
+}
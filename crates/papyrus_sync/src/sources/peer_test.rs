@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use assert_matches::assert_matches;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures_util::pin_mut;
+use indexmap::IndexMap;
+use jsonrpsee::core::Error;
+use jsonrpsee::http_server::{HttpServerBuilder, HttpServerHandle, RpcModule};
+use jsonrpsee::types::error::{CallError, ErrorObject};
+use papyrus_storage::state::data::{Cairo1StateDiff, CompiledClassHash, SierraContractClass};
+use serde::Deserialize;
+use serde_json::json;
+use starknet_api::block::{Block, BlockBody, BlockHash, BlockHeader, BlockNumber, GasPrice};
+use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::{ContractClass, Program, StateDiff, StorageKey};
+use starknet_api::transaction::{
+    InvokeTransaction, InvokeTransactionOutput, Transaction, TransactionOutput,
+};
+use starknet_api::{patricia_key, stark_felt};
+use test_utils::{get_rng, GetTestInstance};
+use tokio_stream::StreamExt;
+
+use crate::sources::central::{CentralError, CentralSourceTrait};
+use crate::sources::peer::PeerCentralSource;
+
+const TEST_CONCURRENT_REQUESTS: usize = 2;
+const BLOCK_NOT_FOUND_ERROR_CODE: i32 = 24;
+
+#[derive(Deserialize)]
+struct TestBlockId {
+    block_number: BlockNumber,
+}
+
+// The peer has a single block with an invoke transaction. Its state diff declares one class and
+// deploys a contract of another class, and declares a Sierra class that replaces the class of the
+// contract.
+struct TestPeerData {
+    block: Block,
+    state_diff: StateDiff,
+    cairo1_state_diff: Cairo1StateDiff,
+    deployed_contract_class_definitions: IndexMap<ClassHash, ContractClass>,
+}
+
+fn get_test_peer_data() -> TestPeerData {
+    let mut rng = get_rng();
+    let transaction = InvokeTransaction::get_test_instance(&mut rng);
+    let output = InvokeTransactionOutput::get_test_instance(&mut rng);
+    let block = Block {
+        header: BlockHeader {
+            block_hash: BlockHash(stark_felt!("0x1")),
+            gas_price: GasPrice(100),
+            ..BlockHeader::default()
+        },
+        body: BlockBody {
+            transactions: vec![Transaction::Invoke(transaction)],
+            transaction_outputs: vec![TransactionOutput::Invoke(output)],
+        },
+    };
+
+    let class = ContractClass {
+        abi: None,
+        program: Program::default(),
+        entry_points_by_type: HashMap::new(),
+    };
+    let address = ContractAddress(patricia_key!("0x11"));
+    let state_diff = StateDiff {
+        deployed_contracts: IndexMap::from([(address, ClassHash(stark_felt!("0x3")))]),
+        storage_diffs: IndexMap::from([(
+            address,
+            IndexMap::from([(StorageKey(patricia_key!("0x5")), stark_felt!("0x6"))]),
+        )]),
+        declared_classes: IndexMap::from([(ClassHash(stark_felt!("0x2")), class.clone())]),
+        nonces: IndexMap::from([(address, Nonce(stark_felt!("0x1")))]),
+    };
+    let sierra_class = SierraContractClass {
+        sierra_program: vec![stark_felt!("0x7")],
+        contract_class_version: "0.1.0".to_owned(),
+        entry_points_by_type: HashMap::new(),
+        abi: "[]".to_owned(),
+    };
+    let cairo1_state_diff = Cairo1StateDiff {
+        declared_classes: IndexMap::from([(
+            ClassHash(stark_felt!("0x4")),
+            (CompiledClassHash(stark_felt!("0x8")), sierra_class),
+        )]),
+        replaced_classes: IndexMap::from([(address, ClassHash(stark_felt!("0x4")))]),
+    };
+    let deployed_contract_class_definitions =
+        IndexMap::from([(ClassHash(stark_felt!("0x3")), class)]);
+    TestPeerData { block, state_diff, cairo1_state_diff, deployed_contract_class_definitions }
+}
+
+// Runs a JSON-RPC server that responds like a papyrus node that has the given data.
+async fn run_test_peer(data: &TestPeerData) -> (PeerCentralSource, HttpServerHandle) {
+    let header = &data.block.header;
+    let (transaction, output) =
+        match (&data.block.body.transactions[0], &data.block.body.transaction_outputs[0]) {
+            (Transaction::Invoke(transaction), TransactionOutput::Invoke(output)) => {
+                (transaction, output)
+            }
+            _ => panic!("Expected an invoke transaction."),
+        };
+    let mut transaction_json = serde_json::to_value(transaction).unwrap();
+    transaction_json["type"] = json!("INVOKE");
+    let block_json = json!({
+        "status": "ACCEPTED_ON_L2",
+        "block_hash": header.block_hash,
+        "parent_hash": header.parent_hash,
+        "block_number": header.block_number,
+        "sequencer_address": header.sequencer,
+        "new_root": header.state_root,
+        "timestamp": header.timestamp,
+        "transactions": [transaction_json],
+    });
+    let mut receipt_json = serde_json::to_value(output).unwrap();
+    receipt_json["type"] = json!("INVOKE");
+    receipt_json["transaction_hash"] = json!(transaction.transaction_hash);
+
+    let diff = &data.state_diff;
+    let (address, class_hash) = diff.deployed_contracts.get_index(0).unwrap();
+    let (key, value) = diff.storage_diffs[address].get_index(0).unwrap();
+    let declared_classes_json: Vec<_> = data
+        .cairo1_state_diff
+        .declared_classes
+        .iter()
+        .map(|(class_hash, (compiled_class_hash, _))| {
+            json!({"class_hash": class_hash, "compiled_class_hash": compiled_class_hash})
+        })
+        .collect();
+    let replaced_classes_json: Vec<_> = data
+        .cairo1_state_diff
+        .replaced_classes
+        .iter()
+        .map(|(address, class_hash)| json!({"contract_address": address, "class_hash": class_hash}))
+        .collect();
+    let state_update_json = json!({
+        "block_hash": header.block_hash,
+        "new_root": header.state_root,
+        "old_root": header.state_root,
+        "state_diff": {
+            "deployed_contracts": [{"address": address, "class_hash": class_hash}],
+            "storage_diffs": [
+                {"address": address, "storage_entries": [{"key": key, "value": value}]}
+            ],
+            "declared_contract_hashes": diff.declared_classes.keys().collect::<Vec<_>>(),
+            "nonces": [{"contract_address": address, "nonce": diff.nonces[address]}],
+            "declared_classes": declared_classes_json,
+            "replaced_classes": replaced_classes_json,
+        },
+    });
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, &Program::default()).unwrap();
+    let class_json = json!({
+        "abi": null,
+        "program": base64::encode(encoder.finish().unwrap()),
+        "entry_points_by_type": {},
+    });
+    let known_class_hashes: Vec<ClassHash> = diff
+        .declared_classes
+        .keys()
+        .chain(data.deployed_contract_class_definitions.keys())
+        .copied()
+        .collect();
+    let sierra_classes: HashMap<ClassHash, serde_json::Value> = data
+        .cairo1_state_diff
+        .declared_classes
+        .iter()
+        .map(|(class_hash, (_, class))| (*class_hash, serde_json::to_value(class).unwrap()))
+        .collect();
+
+    let block_not_found = || {
+        Error::Call(CallError::Custom(ErrorObject::owned(
+            BLOCK_NOT_FOUND_ERROR_CODE,
+            "Block not found",
+            None::<()>,
+        )))
+    };
+    let mut module = RpcModule::new(());
+    module.register_method("starknet_blockNumber", |_, _| Ok(BlockNumber(0))).unwrap();
+    let block_hash = header.block_hash;
+    module
+        .register_method("starknet_getBlockWithTxHashes", move |params, _| {
+            match params.one::<TestBlockId>()?.block_number {
+                BlockNumber(0) => Ok(json!({"block_hash": block_hash})),
+                _ => Err(block_not_found()),
+            }
+        })
+        .unwrap();
+    module
+        .register_method("starknet_getBlockWithTxs", move |params, _| {
+            match params.one::<TestBlockId>()?.block_number {
+                BlockNumber(0) => Ok(block_json.clone()),
+                _ => Err(block_not_found()),
+            }
+        })
+        .unwrap();
+    module
+        .register_method("starknet_getTransactionReceipt", move |_, _| Ok(receipt_json.clone()))
+        .unwrap();
+    let gas_price = header.gas_price;
+    module
+        .register_method("papyrus_getBlockGasPrice", move |params, _| {
+            match params.one::<TestBlockId>()?.block_number {
+                BlockNumber(0) => Ok(gas_price),
+                _ => Err(block_not_found()),
+            }
+        })
+        .unwrap();
+    module
+        .register_method("starknet_getStateUpdate", move |params, _| {
+            match params.one::<TestBlockId>()?.block_number {
+                BlockNumber(0) => Ok(state_update_json.clone()),
+                _ => Err(block_not_found()),
+            }
+        })
+        .unwrap();
+    module
+        .register_method("starknet_getClass", move |params, _| {
+            let (_block_id, class_hash) = params.parse::<(TestBlockId, ClassHash)>()?;
+            if let Some(sierra_class) = sierra_classes.get(&class_hash) {
+                return Ok(sierra_class.clone());
+            }
+            assert!(known_class_hashes.contains(&class_hash));
+            Ok(class_json.clone())
+        })
+        .unwrap();
+
+    let server = HttpServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.start(module).unwrap();
+    let source = PeerCentralSource::new(crate::CentralSourceConfig {
+        source: crate::CentralSourceKind::Peer,
+        concurrent_requests: TEST_CONCURRENT_REQUESTS,
+        url: format!("http://{addr}"),
+        http_headers: None,
+        retry_config: starknet_client::RetryConfig {
+            retry_base_millis: 30,
+            retry_max_delay_millis: 30000,
+            max_retries: 0,
+        },
+    })
+    .unwrap();
+    (source, handle)
+}
+
+#[tokio::test]
+async fn get_block_marker() {
+    let data = get_test_peer_data();
+    let (source, _handle) = run_test_peer(&data).await;
+    assert_eq!(source.get_block_marker().await.unwrap(), BlockNumber(1));
+    assert_eq!(
+        source.get_block_hash(BlockNumber(0)).await.unwrap(),
+        Some(data.block.header.block_hash)
+    );
+    assert!(source.get_pending_data(BlockNumber(1)).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn stream_new_blocks() {
+    let data = get_test_peer_data();
+    let (source, _handle) = run_test_peer(&data).await;
+
+    let stream = source.stream_new_blocks(BlockNumber(0), BlockNumber(2));
+    pin_mut!(stream);
+    let (block_number, block) = stream.next().await.unwrap().unwrap();
+    assert_eq!(block_number, BlockNumber(0));
+    assert_eq!(block, data.block);
+    assert_matches!(
+        stream.next().await.unwrap(),
+        Err(CentralError::BlockNotFound { block_number: BlockNumber(1) })
+    );
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn stream_state_updates() {
+    let data = get_test_peer_data();
+    let (source, _handle) = run_test_peer(&data).await;
+
+    let stream = source.stream_state_updates(BlockNumber(0), BlockNumber(2));
+    pin_mut!(stream);
+    let (
+        block_number,
+        block_hash,
+        state_diff,
+        cairo1_state_diff,
+        deployed_contract_class_definitions,
+    ) = stream.next().await.unwrap().unwrap();
+    assert_eq!(block_number, BlockNumber(0));
+    assert_eq!(block_hash, data.block.header.block_hash);
+    assert_eq!(state_diff, data.state_diff);
+    assert_eq!(cairo1_state_diff, data.cairo1_state_diff);
+    assert_eq!(deployed_contract_class_definitions, data.deployed_contract_class_definitions);
+    assert_matches!(stream.next().await.unwrap(), Err(CentralError::StateUpdateNotFound));
+    assert!(stream.next().await.is_none());
+}
@@ -3,6 +3,7 @@
 //! [`starknet`]: https://starknet.io/
 
 mod objects;
+mod offline;
 pub mod retry;
 #[cfg(test)]
 mod starknet_client_test;
@@ -23,10 +24,18 @@ use starknet_api::StarknetApiError;
 use tracing::debug;
 use url::Url;
 
+pub use self::objects::add_transaction::{
+    AddDeclareTransaction, AddDeployAccountTransaction, AddInvokeTransaction, AddTransaction,
+    AddTransactionResponse, CompressedCairo0ContractClass, CompressedContractClass,
+    CompressedSierraContractClass,
+};
 pub use self::objects::block::{
-    Block, ContractClass, DeployedContract, GlobalRoot, StateDiff, StateUpdate, StorageEntry,
+    Block, BlockStatus, ContractClass, DeclaredClassHashEntry, DeployedContract,
+    GenericContractClass, GlobalRoot, PendingBlock, PendingStateUpdate, ReplacedClass,
+    SierraContractClass, SierraEntryPoint, StateDiff, StateUpdate, StorageEntry,
     TransactionReceiptsError,
 };
+pub use self::offline::{record_responses, OfflineStarknetClient};
 use self::retry::Retry;
 pub use self::retry::RetryConfig;
 #[cfg(doc)]
@@ -45,10 +54,20 @@ pub trait StarknetClientTrait {
     /// Returns a [`Block`] corresponding to `block_number`, returning [`None`] in case no such
     /// block exists in the system.
     async fn block(&self, block_number: BlockNumber) -> ClientResult<Option<Block>>;
-    /// Returns a [`ContractClass`] corresponding to `class_hash`.
-    async fn class_by_hash(&self, class_hash: ClassHash) -> ClientResult<Option<ContractClass>>;
+    /// Returns a [`GenericContractClass`] corresponding to `class_hash`, either a Cairo 0
+    /// [`ContractClass`] or a [`SierraContractClass`].
+    async fn class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> ClientResult<Option<GenericContractClass>>;
     /// Returns a [`starknet_clinet`][`StateUpdate`] corresponding to `block_number`.
     async fn state_update(&self, block_number: BlockNumber) -> ClientResult<Option<StateUpdate>>;
+    /// Returns the [`PendingBlock`], returning [`None`] in case there is no pending block in the
+    /// system.
+    async fn pending_block(&self) -> ClientResult<Option<PendingBlock>>;
+    /// Returns the [`PendingStateUpdate`] of the pending block, returning [`None`] in case there
+    /// is no pending block in the system.
+    async fn pending_state_update(&self) -> ClientResult<Option<PendingStateUpdate>>;
 }
 
 /// A starknet client.
@@ -61,6 +80,7 @@ pub struct StarknetClient {
 
 #[derive(Clone, Debug)]
 struct StarknetUrls {
+    add_transaction: Url,
     get_block: Url,
     get_contract_by_hash: Url,
     get_state_update: Url,
@@ -77,6 +97,28 @@ pub enum StarknetErrorCode {
     MalformedRequest = 32,
     #[serde(rename = "StarknetErrorCode.UNDECLARED_CLASS")]
     UndeclaredClass = 44,
+    #[serde(rename = "StarknetErrorCode.CLASS_ALREADY_DECLARED")]
+    ClassAlreadyDeclared,
+    #[serde(rename = "StarknetErrorCode.COMPILATION_FAILED")]
+    CompilationFailed,
+    #[serde(rename = "StarknetErrorCode.CONTRACT_BYTECODE_SIZE_TOO_LARGE")]
+    ContractBytecodeSizeTooLarge,
+    #[serde(rename = "StarknetErrorCode.DUPLICATED_TRANSACTION")]
+    DuplicatedTransaction,
+    #[serde(rename = "StarknetErrorCode.INSUFFICIENT_ACCOUNT_BALANCE")]
+    InsufficientAccountBalance,
+    #[serde(rename = "StarknetErrorCode.INSUFFICIENT_MAX_FEE")]
+    InsufficientMaxFee,
+    #[serde(rename = "StarknetErrorCode.INVALID_COMPILED_CLASS_HASH")]
+    InvalidCompiledClassHash,
+    #[serde(rename = "StarknetErrorCode.INVALID_CONTRACT_CLASS_VERSION")]
+    InvalidContractClassVersion,
+    #[serde(rename = "StarknetErrorCode.INVALID_TRANSACTION_NONCE")]
+    InvalidTransactionNonce,
+    #[serde(rename = "StarknetErrorCode.INVALID_TRANSACTION_VERSION")]
+    InvalidTransactionVersion,
+    #[serde(rename = "StarknetErrorCode.VALIDATE_FAILURE")]
+    ValidateFailure,
 }
 
 /// A client error wrapping error codes returned by the starknet gateway.
@@ -113,6 +155,9 @@ pub enum ClientError {
     /// A client error representing bad status http responses.
     #[error("Bad response status code: {:?} message: {:?}.", code, message)]
     BadResponseStatus { code: StatusCode, message: String },
+    /// A client error representing errors while reading or writing recorded responses.
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
     /// A client error representing http request errors.
     #[error(transparent)]
     RequestError(#[from] reqwest::Error),
@@ -133,16 +178,19 @@ pub enum ClientError {
     TransactionReceiptsError(#[from] TransactionReceiptsError),
 }
 
+const ADD_TRANSACTION_URL: &str = "gateway/add_transaction";
 const GET_BLOCK_URL: &str = "feeder_gateway/get_block";
 const GET_CONTRACT_BY_HASH_URL: &str = "feeder_gateway/get_class_by_hash";
 const GET_STATE_UPDATE_URL: &str = "feeder_gateway/get_state_update";
 const BLOCK_NUMBER_QUERY: &str = "blockNumber";
 const CLASS_HASH_QUERY: &str = "classHash";
+const PENDING_BLOCK_ID: &str = "pending";
 
 impl StarknetUrls {
     fn new(url_str: &str) -> Result<Self, ClientCreationError> {
         let base_url = Url::parse(url_str)?;
         Ok(StarknetUrls {
+            add_transaction: base_url.join(ADD_TRANSACTION_URL)?,
             get_block: base_url.join(GET_BLOCK_URL)?,
             get_contract_by_hash: base_url.join(GET_CONTRACT_BY_HASH_URL)?,
             get_state_update: base_url.join(GET_STATE_UPDATE_URL)?,
@@ -175,6 +223,34 @@ impl StarknetClient {
         })
     }
 
+    /// Sends the transaction to the starknet gateway, to be added to the mempool of the sequencer.
+    /// The request isn't retried, so that the transaction isn't sent twice.
+    pub async fn add_transaction(
+        &self,
+        tx: &AddTransaction,
+    ) -> ClientResult<AddTransactionResponse> {
+        let response = self
+            .internal_client
+            .post(self.urls.add_transaction.clone())
+            .headers(self.http_headers.clone())
+            .json(tx)
+            .send()
+            .await?;
+        let code = response.status();
+        let message = response.text().await?;
+        match code {
+            StatusCode::OK => Ok(serde_json::from_str(&message)?),
+            // The gateway rejects invalid transactions with a starknet error.
+            StatusCode::BAD_REQUEST | StatusCode::INTERNAL_SERVER_ERROR => {
+                match serde_json::from_str::<StarknetError>(&message) {
+                    Ok(starknet_error) => Err(ClientError::StarknetError(starknet_error)),
+                    Err(_) => Err(ClientError::BadResponseStatus { code, message }),
+                }
+            }
+            _ => Err(ClientError::BadResponseStatus { code, message }),
+        }
+    }
+
     fn get_retry_error_code(err: &ClientError) -> Option<RetryErrorCode> {
         match err {
             ClientError::BadResponseStatus { code, message: _ } => match *code {
@@ -274,7 +350,10 @@ impl StarknetClientTrait for StarknetClient {
         self.request_block(Some(block_number)).await
     }
 
-    async fn class_by_hash(&self, class_hash: ClassHash) -> ClientResult<Option<ContractClass>> {
+    async fn class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> ClientResult<Option<GenericContractClass>> {
         let mut url = self.urls.get_contract_by_hash.clone();
         let class_hash = serde_json::to_string(&class_hash)?;
         url.query_pairs_mut()
@@ -314,4 +393,44 @@ impl StarknetClientTrait for StarknetClient {
             }
         }
     }
+
+    async fn pending_block(&self) -> ClientResult<Option<PendingBlock>> {
+        let mut url = self.urls.get_block.clone();
+        url.query_pairs_mut().append_pair(BLOCK_NUMBER_QUERY, PENDING_BLOCK_ID);
+        let response = self.request_with_retry(url).await;
+        match response {
+            Ok(raw_block) => {
+                let block: PendingBlock = serde_json::from_str(&raw_block)?;
+                Ok(Some(block))
+            }
+            Err(ClientError::StarknetError(StarknetError {
+                code: StarknetErrorCode::BlockNotFound,
+                message: _,
+            })) => Ok(None),
+            Err(err) => {
+                debug!("Failed to get the pending block from starknet server.");
+                Err(err)
+            }
+        }
+    }
+
+    async fn pending_state_update(&self) -> ClientResult<Option<PendingStateUpdate>> {
+        let mut url = self.urls.get_state_update.clone();
+        url.query_pairs_mut().append_pair(BLOCK_NUMBER_QUERY, PENDING_BLOCK_ID);
+        let response = self.request_with_retry(url).await;
+        match response {
+            Ok(raw_state_update) => {
+                let state_update: PendingStateUpdate = serde_json::from_str(&raw_state_update)?;
+                Ok(Some(state_update))
+            }
+            Err(ClientError::StarknetError(StarknetError {
+                code: StarknetErrorCode::BlockNotFound,
+                message: _,
+            })) => Ok(None),
+            Err(err) => {
+                debug!("Failed to get the pending state update from starknet server.");
+                Err(err)
+            }
+        }
+    }
 }
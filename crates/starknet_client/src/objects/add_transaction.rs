@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkHash;
+use starknet_api::state::{EntryPoint, EntryPointType};
+use starknet_api::transaction::{
+    Calldata, ContractAddressSalt, Fee, TransactionHash, TransactionSignature, TransactionVersion,
+};
+
+use crate::objects::block::SierraEntryPoint;
+
+/// A transaction sent to the starknet gateway, to be added to the mempool of the sequencer.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum AddTransaction {
+    #[serde(rename = "DECLARE")]
+    Declare(AddDeclareTransaction),
+    #[serde(rename = "DEPLOY_ACCOUNT")]
+    DeployAccount(AddDeployAccountTransaction),
+    #[serde(rename = "INVOKE_FUNCTION")]
+    Invoke(AddInvokeTransaction),
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct AddInvokeTransaction {
+    pub sender_address: ContractAddress,
+    pub calldata: Calldata,
+    pub max_fee: Fee,
+    pub signature: TransactionSignature,
+    pub nonce: Nonce,
+    pub version: TransactionVersion,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct AddDeployAccountTransaction {
+    pub class_hash: ClassHash,
+    pub contract_address_salt: ContractAddressSalt,
+    pub constructor_calldata: Calldata,
+    pub max_fee: Fee,
+    pub signature: TransactionSignature,
+    pub nonce: Nonce,
+    pub version: TransactionVersion,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct AddDeclareTransaction {
+    pub contract_class: CompressedContractClass,
+    pub sender_address: ContractAddress,
+    pub max_fee: Fee,
+    pub signature: TransactionSignature,
+    pub nonce: Nonce,
+    pub version: TransactionVersion,
+    // Only in declare transactions of version 2, of Sierra classes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiled_class_hash: Option<StarkHash>,
+}
+
+/// The class of a declare transaction, with its program as a base64 encoding of its
+/// gzip-compressed JSON representation.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum CompressedContractClass {
+    Cairo0(CompressedCairo0ContractClass),
+    Sierra(CompressedSierraContractClass),
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct CompressedCairo0ContractClass {
+    pub abi: serde_json::Value,
+    pub program: String,
+    pub entry_points_by_type: HashMap<EntryPointType, Vec<EntryPoint>>,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct CompressedSierraContractClass {
+    pub sierra_program: String,
+    pub contract_class_version: String,
+    pub entry_points_by_type: HashMap<EntryPointType, Vec<SierraEntryPoint>>,
+    pub abi: String,
+}
+
+/// The response of the starknet gateway to a transaction it accepted.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct AddTransactionResponse {
+    pub code: String,
+    pub transaction_hash: TransactionHash,
+    /// The address of the deployed account, only in responses to deploy account transactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<ContractAddress>,
+    /// The hash of the declared class, only in responses to declare transactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_hash: Option<ClassHash>,
+}
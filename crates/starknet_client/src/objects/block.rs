@@ -2,11 +2,11 @@ use std::collections::HashMap;
 use std::ops::Index;
 
 use indexmap::IndexMap;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 #[cfg(doc)]
 use starknet_api::block::Block as starknet_api_block;
 use starknet_api::block::{BlockHash, BlockNumber, BlockTimestamp, GasPrice};
-use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector, Nonce};
 use starknet_api::hash::{StarkFelt, StarkHash};
 use starknet_api::serde_utils::NonPrefixedBytesAsHex;
 use starknet_api::state::{EntryPoint, EntryPointType, Program, StorageKey};
@@ -20,9 +20,7 @@ use crate::objects::transaction::{
 };
 use crate::{ClientError, ClientResult};
 
-#[derive(
-    Debug, Copy, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord,
-)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash, Deserialize, PartialOrd, Ord)]
 #[serde(try_from = "NonPrefixedBytesAsHex<32_usize>")]
 pub struct GlobalRoot(pub StarkHash);
 
@@ -39,6 +37,18 @@ impl From<GlobalRoot> for starknet_api::core::GlobalRoot {
         Self(val.0)
     }
 }
+impl From<starknet_api::core::GlobalRoot> for GlobalRoot {
+    fn from(val: starknet_api::core::GlobalRoot) -> Self {
+        Self(val.0)
+    }
+}
+
+// Serialized the same way the Starknet sequencer does, so that it can be deserialized back.
+impl Serialize for GlobalRoot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(self.0.bytes()))
+    }
+}
 
 /// A block as returned by the starknet gateway.
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
@@ -114,85 +124,74 @@ pub enum TransactionReceiptsError {
     },
 }
 
-/// Converts the client representation of [`Block`] to a [`starknet_api`][`Block`].
-impl TryFrom<Block> for starknet_api::block::Block {
-    type Error = ClientError;
-
-    fn try_from(block: Block) -> ClientResult<Self> {
-        // Check that the number of receipts is the same as the number of transactions.
-        let num_of_txs = block.transactions.len();
-        let num_of_receipts = block.transaction_receipts.len();
-        if num_of_txs != num_of_receipts {
-            return Err(ClientError::TransactionReceiptsError(
-                TransactionReceiptsError::WrongNumberOfReceipts {
-                    block_number: block.block_number,
-                    num_of_txs,
-                    num_of_receipts,
-                },
-            ));
-        }
-
-        // Get the transaction outputs.
-        let mut transaction_outputs = vec![];
-        for (i, receipt) in block.transaction_receipts.into_iter().enumerate() {
-            let transaction = block.transactions.index(i);
-
-            // Check that the transaction index that appears in the receipt is the same as the
-            // index of the transaction.
-            if i != receipt.transaction_index.0 {
-                return Err(ClientError::TransactionReceiptsError(
-                    TransactionReceiptsError::MismatchTransactionIndex {
-                        block_number: block.block_number,
-                        tx_index: TransactionOffsetInBlock(i),
-                        tx_hash: transaction.transaction_hash(),
-                        receipt_tx_index: receipt.transaction_index,
-                    },
-                ));
-            }
+/// A pending block as returned by the starknet gateway. The pending block is the block that is
+/// currently being constructed by the sequencer, hence it has no hash, number or state root yet.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct PendingBlock {
+    pub gas_price: GasPrice,
+    pub parent_block_hash: BlockHash,
+    #[serde(default)]
+    pub sequencer_address: ContractAddress,
+    pub status: BlockStatus,
+    #[serde(default)]
+    pub timestamp: BlockTimestamp,
+    pub transactions: Vec<Transaction>,
+    pub transaction_receipts: Vec<TransactionReceipt>,
+}
 
-            // Check that the transaction hash that appears in the receipt is the same as in the
-            // transaction.
-            if transaction.transaction_hash() != receipt.transaction_hash {
-                return Err(ClientError::TransactionReceiptsError(
-                    TransactionReceiptsError::MismatchTransactionHash {
-                        block_number: block.block_number,
-                        tx_index: TransactionOffsetInBlock(i),
-                        tx_hash: transaction.transaction_hash(),
-                        receipt_tx_hash: receipt.transaction_hash,
-                    },
-                ));
-            }
+impl PendingBlock {
+    /// Converts the transactions and receipts of the pending block to a [`starknet_api`] block
+    /// body. `block_number` is the number the pending block will get once it is accepted, and is
+    /// only used for reporting errors.
+    pub fn to_starknet_api_block_body(
+        self,
+        block_number: BlockNumber,
+    ) -> ClientResult<starknet_api::block::BlockBody> {
+        to_starknet_api_block_body(block_number, self.transactions, self.transaction_receipts)
+    }
+}
 
-            // Check that the receipt has the correct fields according to the transaction type.
-            if transaction.transaction_type() != TransactionType::L1Handler
-                && receipt.l1_to_l2_consumed_message != L1ToL2Message::default()
-            {
-                return Err(ClientError::TransactionReceiptsError(
-                    TransactionReceiptsError::MismatchFields {
-                        block_number: block.block_number,
-                        tx_index: TransactionOffsetInBlock(i),
-                        tx_hash: transaction.transaction_hash(),
-                        tx_type: transaction.transaction_type(),
-                    },
-                ));
-            }
+impl Block {
+    /// Converts a [`starknet_api`] block to its representation in the starknet gateway. See
+    /// [`TransactionReceipt::from_starknet_api_transaction_output`] for the receipt fields that
+    /// can't be recovered from [`starknet_api`].
+    pub fn from_starknet_api_block(block: starknet_api::block::Block, status: BlockStatus) -> Self {
+        let transaction_receipts = block
+            .body
+            .transactions
+            .iter()
+            .zip(block.body.transaction_outputs)
+            .enumerate()
+            .map(|(i, (transaction, output))| {
+                TransactionReceipt::from_starknet_api_transaction_output(
+                    TransactionOffsetInBlock(i),
+                    transaction,
+                    output,
+                )
+            })
+            .collect();
+        let transactions = block.body.transactions.into_iter().map(Transaction::from).collect();
 
-            let tx_output =
-                receipt.into_starknet_api_transaction_output(transaction.transaction_type());
-            transaction_outputs.push(tx_output);
+        Self {
+            block_hash: block.header.block_hash,
+            block_number: block.header.block_number,
+            gas_price: block.header.gas_price,
+            parent_block_hash: block.header.parent_hash,
+            sequencer_address: block.header.sequencer,
+            state_root: block.header.state_root.into(),
+            status,
+            timestamp: block.header.timestamp,
+            transactions,
+            transaction_receipts,
         }
+    }
+}
 
-        // Get the transactions.
-        // Note: This cannot happen before getting the transaction outputs since we need to borrow
-        // the block transactions inside the for loop for the transaction type (TransactionType is
-        // defined in starknet_client therefore starknet_api::Transaction cannot return it).
-        let transactions: Vec<starknet_api::transaction::Transaction> = block
-            .transactions
-            .into_iter()
-            .map(starknet_api::transaction::Transaction::from)
-            .collect();
+/// Converts the client representation of [`Block`] to a [`starknet_api`][`Block`].
+impl TryFrom<Block> for starknet_api::block::Block {
+    type Error = ClientError;
 
-        // Get the header.
+    fn try_from(block: Block) -> ClientResult<Self> {
         let header = starknet_api::block::BlockHeader {
             block_hash: block.block_hash,
             parent_hash: block.parent_block_hash,
@@ -202,13 +201,99 @@ impl TryFrom<Block> for starknet_api::block::Block {
             sequencer: block.sequencer_address,
             timestamp: block.timestamp,
         };
-
-        let body = starknet_api::block::BlockBody { transactions, transaction_outputs };
+        let body = to_starknet_api_block_body(
+            block.block_number,
+            block.transactions,
+            block.transaction_receipts,
+        )?;
 
         Ok(Self { header, body })
     }
 }
 
+// Checks that the transaction receipts match the transactions and converts them to a
+// [`starknet_api`] block body.
+fn to_starknet_api_block_body(
+    block_number: BlockNumber,
+    transactions: Vec<Transaction>,
+    transaction_receipts: Vec<TransactionReceipt>,
+) -> ClientResult<starknet_api::block::BlockBody> {
+    // Check that the number of receipts is the same as the number of transactions.
+    let num_of_txs = transactions.len();
+    let num_of_receipts = transaction_receipts.len();
+    if num_of_txs != num_of_receipts {
+        return Err(ClientError::TransactionReceiptsError(
+            TransactionReceiptsError::WrongNumberOfReceipts {
+                block_number,
+                num_of_txs,
+                num_of_receipts,
+            },
+        ));
+    }
+
+    // Get the transaction outputs.
+    let mut transaction_outputs = vec![];
+    for (i, receipt) in transaction_receipts.into_iter().enumerate() {
+        let transaction = transactions.index(i);
+
+        // Check that the transaction index that appears in the receipt is the same as the
+        // index of the transaction.
+        if i != receipt.transaction_index.0 {
+            return Err(ClientError::TransactionReceiptsError(
+                TransactionReceiptsError::MismatchTransactionIndex {
+                    block_number,
+                    tx_index: TransactionOffsetInBlock(i),
+                    tx_hash: transaction.transaction_hash(),
+                    receipt_tx_index: receipt.transaction_index,
+                },
+            ));
+        }
+
+        // Check that the transaction hash that appears in the receipt is the same as in the
+        // transaction.
+        if transaction.transaction_hash() != receipt.transaction_hash {
+            return Err(ClientError::TransactionReceiptsError(
+                TransactionReceiptsError::MismatchTransactionHash {
+                    block_number,
+                    tx_index: TransactionOffsetInBlock(i),
+                    tx_hash: transaction.transaction_hash(),
+                    receipt_tx_hash: receipt.transaction_hash,
+                },
+            ));
+        }
+
+        // Check that the receipt has the correct fields according to the transaction type.
+        if transaction.transaction_type() != TransactionType::L1Handler
+            && receipt
+                .l1_to_l2_consumed_message
+                .as_ref()
+                .map_or(false, |message| *message != L1ToL2Message::default())
+        {
+            return Err(ClientError::TransactionReceiptsError(
+                TransactionReceiptsError::MismatchFields {
+                    block_number,
+                    tx_index: TransactionOffsetInBlock(i),
+                    tx_hash: transaction.transaction_hash(),
+                    tx_type: transaction.transaction_type(),
+                },
+            ));
+        }
+
+        let tx_output =
+            receipt.into_starknet_api_transaction_output(transaction.transaction_type());
+        transaction_outputs.push(tx_output);
+    }
+
+    // Get the transactions.
+    // Note: This cannot happen before getting the transaction outputs since we need to borrow
+    // the block transactions inside the for loop for the transaction type (TransactionType is
+    // defined in starknet_client therefore starknet_api::Transaction cannot return it).
+    let transactions: Vec<starknet_api::transaction::Transaction> =
+        transactions.into_iter().map(starknet_api::transaction::Transaction::from).collect();
+
+    Ok(starknet_api::block::BlockBody { transactions, transaction_outputs })
+}
+
 /// A state update derived from a single block as returned by the starknet gateway.
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
 pub struct StateUpdate {
@@ -218,6 +303,13 @@ pub struct StateUpdate {
     pub state_diff: StateDiff,
 }
 
+/// The state update of the pending block as returned by the starknet gateway.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct PendingStateUpdate {
+    pub old_root: GlobalRoot,
+    pub state_diff: StateDiff,
+}
+
 #[derive(
     Debug, Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord, Default,
 )]
@@ -253,26 +345,50 @@ pub struct StateDiff {
     // IndexMap is serialized as a mapping in json, keeps ordering and is efficiently iterable.
     pub storage_diffs: IndexMap<ContractAddress, Vec<StorageEntry>>,
     pub deployed_contracts: Vec<DeployedContract>,
-    #[serde(default)]
+    // The Cairo 0 declared classes, renamed once the Sierra classes were added.
+    #[serde(default, alias = "old_declared_contracts")]
     pub declared_contracts: Vec<ClassHash>,
     pub nonces: IndexMap<ContractAddress, Nonce>,
+    #[serde(default)]
+    pub declared_classes: Vec<DeclaredClassHashEntry>,
+    #[serde(default)]
+    pub replaced_classes: Vec<ReplacedClass>,
 }
 impl StateDiff {
-    // Returns the declared class hashes and after them the deployed class hashes that weren't in
-    // the declared.
+    // Returns the declared class hashes, after them the deployed class hashes that weren't in the
+    // declared, and after them the hashes of the declared Sierra classes.
     pub fn class_hashes(&self) -> Vec<ClassHash> {
+        let mut sierra_class_hashes: Vec<ClassHash> =
+            self.declared_classes.iter().map(|entry| entry.class_hash).collect();
         let mut deployed_class_hashes = self
             .deployed_contracts
             .iter()
             .map(|contract| contract.class_hash)
-            .filter(|hash| !self.declared_contracts.contains(hash))
+            .filter(|hash| {
+                !self.declared_contracts.contains(hash) && !sierra_class_hashes.contains(hash)
+            })
             .collect();
         let mut declared_class_hashes = self.declared_contracts.clone();
         declared_class_hashes.append(&mut deployed_class_hashes);
+        declared_class_hashes.append(&mut sierra_class_hashes);
         declared_class_hashes
     }
 }
 
+/// A declared Sierra class with the hash of its compiled class.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct DeclaredClassHashEntry {
+    pub class_hash: ClassHash,
+    pub compiled_class_hash: StarkHash,
+}
+
+/// A deployed contract whose class was replaced.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct ReplacedClass {
+    pub address: ContractAddress,
+    pub class_hash: ClassHash,
+}
+
 /// A deployed contract in StarkNet.
 #[derive(Debug, Default, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
 pub struct DeployedContract {
@@ -287,6 +403,30 @@ pub struct StorageEntry {
     pub value: StarkFelt,
 }
 
+/// A contract class as returned by the starknet gateway, either a Cairo 0 class or a Sierra class.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum GenericContractClass {
+    Cairo0ContractClass(ContractClass),
+    Cairo1ContractClass(SierraContractClass),
+}
+
+/// A Cairo 1 contract class, as a Sierra program.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct SierraContractClass {
+    pub sierra_program: Vec<StarkFelt>,
+    pub contract_class_version: String,
+    /// The selector of each entry point is a unique identifier in the program.
+    pub entry_points_by_type: HashMap<EntryPointType, Vec<SierraEntryPoint>>,
+    pub abi: String,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct SierraEntryPoint {
+    pub function_idx: usize,
+    pub selector: EntryPointSelector,
+}
+
 #[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
 pub struct ContractClass {
     pub abi: serde_json::Value,
@@ -306,6 +446,20 @@ impl From<ContractClass> for starknet_api::state::ContractClass {
     }
 }
 
+impl From<starknet_api::state::ContractClass> for ContractClass {
+    fn from(class: starknet_api::state::ContractClass) -> Self {
+        // An abi that couldn't be parsed is returned as null, and is parsed back to None.
+        let abi = class.abi.map(|entries| {
+            entries.into_iter().map(ContractClassAbiEntry::from).collect::<Vec<_>>()
+        });
+        Self {
+            abi: serde_json::to_value(abi).unwrap_or_default(),
+            program: class.program,
+            entry_points_by_type: class.entry_points_by_type,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum ContractClassAbiEntry {
@@ -330,6 +484,30 @@ impl ContractClassAbiEntry {
     }
 }
 
+impl From<starknet_api::state::ContractClassAbiEntry> for ContractClassAbiEntry {
+    fn from(entry: starknet_api::state::ContractClassAbiEntry) -> Self {
+        match entry {
+            starknet_api::state::ContractClassAbiEntry::Event(entry) => {
+                ContractClassAbiEntry::Event(EventAbiEntry { r#type: "event".to_owned(), entry })
+            }
+            starknet_api::state::ContractClassAbiEntry::Function(entry) => {
+                let r#type = match entry.r#type {
+                    starknet_api::state::FunctionAbiEntryType::Constructor => "constructor",
+                    starknet_api::state::FunctionAbiEntryType::Regular => "function",
+                    starknet_api::state::FunctionAbiEntryType::L1Handler => "l1_handler",
+                };
+                ContractClassAbiEntry::Function(FunctionAbiEntry {
+                    r#type: r#type.to_owned(),
+                    entry: entry.entry,
+                })
+            }
+            starknet_api::state::ContractClassAbiEntry::Struct(entry) => {
+                ContractClassAbiEntry::Struct(StructAbiEntry { r#type: "struct".to_owned(), entry })
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize)]
 pub struct EventAbiEntry {
     pub r#type: String,
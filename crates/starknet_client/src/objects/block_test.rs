@@ -1,7 +1,7 @@
 use assert::assert_ok;
 use assert_matches::assert_matches;
 use indexmap::IndexMap;
-use starknet_api::block::BlockHash;
+use starknet_api::block::{BlockHash, BlockNumber};
 use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
 use starknet_api::hash::{StarkFelt, StarkHash};
 use starknet_api::serde_utils::bytes_from_hex_str;
@@ -10,8 +10,9 @@ use starknet_api::transaction::{TransactionHash, TransactionOffsetInBlock};
 use starknet_api::{patricia_key, stark_felt};
 
 use super::block::{
-    Block, ContractClass, ContractClassAbiEntry, DeployedContract, GlobalRoot, StateDiff,
-    StateUpdate, StorageEntry, TransactionReceiptsError,
+    Block, BlockStatus, ContractClass, ContractClassAbiEntry, DeclaredClassHashEntry,
+    DeployedContract, GlobalRoot, PendingBlock, ReplacedClass, StateDiff, StateUpdate,
+    StorageEntry, TransactionReceiptsError,
 };
 use super::transaction::TransactionReceipt;
 use crate::test_utils::read_resource::read_resource_file;
@@ -22,6 +23,11 @@ fn load_block_succeeds() {
     assert_ok!(serde_json::from_str::<Block>(&read_resource_file("block.json")));
 }
 
+#[test]
+fn load_pending_block_succeeds() {
+    assert_ok!(serde_json::from_str::<PendingBlock>(&read_resource_file("pending_block.json")));
+}
+
 #[test]
 fn load_block_state_update_succeeds() {
     let expected_state_update = StateUpdate {
@@ -81,6 +87,8 @@ fn load_block_state_update_succeeds() {
                 )),
                 Nonce(stark_felt!("0x12")),
             )]),
+            declared_classes: vec![],
+            replaced_classes: vec![],
         },
     };
     assert_eq!(
@@ -90,6 +98,33 @@ fn load_block_state_update_succeeds() {
     )
 }
 
+#[test]
+fn load_block_state_update_with_cairo1_succeeds() {
+    let state_update: StateUpdate =
+        serde_json::from_str(&read_resource_file("block_state_update_with_cairo1.json")).unwrap();
+    let state_diff = state_update.state_diff;
+    assert_eq!(state_diff.declared_contracts, vec![ClassHash(stark_felt!("0x100"))]);
+    assert_eq!(
+        state_diff.declared_classes,
+        vec![DeclaredClassHashEntry {
+            class_hash: ClassHash(stark_felt!("0x101")),
+            compiled_class_hash: stark_felt!("0x102"),
+        }]
+    );
+    assert_eq!(
+        state_diff.replaced_classes,
+        vec![ReplacedClass {
+            address: ContractAddress(patricia_key!("0x103")),
+            class_hash: ClassHash(stark_felt!("0x101")),
+        }]
+    );
+    // The Sierra classes are fetched after the Cairo 0 classes.
+    assert_eq!(
+        state_diff.class_hashes(),
+        vec![ClassHash(stark_felt!("0x100")), ClassHash(stark_felt!("0x101"))]
+    );
+}
+
 #[tokio::test]
 async fn try_into_starknet_api() {
     let raw_block = read_resource_file("block.json");
@@ -154,6 +189,46 @@ async fn try_into_starknet_api() {
     );
 }
 
+#[tokio::test]
+async fn from_starknet_api_block() {
+    let block: Block = serde_json::from_str(&read_resource_file("block.json")).unwrap();
+    let starknet_api_block = starknet_api::block::Block::try_from(block).unwrap();
+
+    let block =
+        Block::from_starknet_api_block(starknet_api_block.clone(), BlockStatus::AcceptedOnL1);
+    assert_eq!(block.status, BlockStatus::AcceptedOnL1);
+    // The fields that aren't part of starknet_api are left out rather than made up.
+    for receipt in &block.transaction_receipts {
+        assert!(receipt.l1_to_l2_consumed_message.is_none());
+        assert!(receipt.execution_resources.is_none());
+        assert!(receipt.l2_to_l1_messages.iter().all(|message| message.from_address.is_none()));
+    }
+    let raw_block = serde_json::to_string(&block).unwrap();
+    let block: Block = serde_json::from_str(&raw_block).unwrap();
+    assert_eq!(starknet_api::block::Block::try_from(block).unwrap(), starknet_api_block);
+}
+
+#[tokio::test]
+async fn pending_block_to_starknet_api_block_body() {
+    let raw_block = read_resource_file("pending_block.json");
+    let block: PendingBlock = serde_json::from_str(&raw_block).unwrap();
+    let expected_num_of_tx_outputs = block.transactions.len();
+    let body = block.to_starknet_api_block_body(BlockNumber(1)).unwrap();
+    assert_eq!(expected_num_of_tx_outputs, body.transaction_outputs.len());
+
+    let mut err_block: PendingBlock = serde_json::from_str(&raw_block).unwrap();
+    err_block.transaction_receipts.pop();
+    let err = err_block.to_starknet_api_block_body(BlockNumber(1)).unwrap_err();
+    assert_matches!(
+        err,
+        ClientError::TransactionReceiptsError(TransactionReceiptsError::WrongNumberOfReceipts {
+            block_number: BlockNumber(1),
+            num_of_txs: _,
+            num_of_receipts: _,
+        })
+    );
+}
+
 #[tokio::test]
 async fn abi_into_starknet_api_full() {
     // TODO(anatg): Find an abi json with event entries.
@@ -173,3 +248,18 @@ async fn abi_into_starknet_api_none() {
     let starknet_api_class = starknet_api::state::ContractClass::from(class);
     assert!(starknet_api_class.abi.is_none())
 }
+
+#[tokio::test]
+async fn abi_from_starknet_api() {
+    let raw_abi: serde_json::Value = serde_json::from_str(&read_resource_file("abi.json")).unwrap();
+    let class = ContractClass { abi: raw_abi, ..ContractClass::default() };
+    let starknet_api_class = starknet_api::state::ContractClass::from(class);
+
+    let class = ContractClass::from(starknet_api_class.clone());
+    assert_eq!(starknet_api::state::ContractClass::from(class), starknet_api_class);
+
+    let starknet_api_class = starknet_api::state::ContractClass::default();
+    let class = ContractClass::from(starknet_api_class);
+    assert!(class.abi.is_null());
+    assert!(starknet_api::state::ContractClass::from(class).abi.is_none());
+}
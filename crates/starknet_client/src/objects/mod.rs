@@ -1,3 +1,4 @@
+pub mod add_transaction;
 pub mod block;
 #[cfg(test)]
 mod block_test;
@@ -44,6 +44,28 @@ impl From<Transaction> for starknet_api::transaction::Transaction {
     }
 }
 
+impl From<starknet_api::transaction::Transaction> for Transaction {
+    fn from(tx: starknet_api::transaction::Transaction) -> Self {
+        match tx {
+            starknet_api::transaction::Transaction::Declare(declare_tx) => {
+                Transaction::Declare(declare_tx.into())
+            }
+            starknet_api::transaction::Transaction::Deploy(deploy_tx) => {
+                Transaction::Deploy(deploy_tx.into())
+            }
+            starknet_api::transaction::Transaction::DeployAccount(deploy_acc_tx) => {
+                Transaction::DeployAccount(deploy_acc_tx.into())
+            }
+            starknet_api::transaction::Transaction::Invoke(invoke_tx) => {
+                Transaction::Invoke(invoke_tx.into())
+            }
+            starknet_api::transaction::Transaction::L1Handler(l1_handler_tx) => {
+                Transaction::L1Handler(l1_handler_tx.into())
+            }
+        }
+    }
+}
+
 impl Transaction {
     pub fn transaction_hash(&self) -> TransactionHash {
         match self {
@@ -91,6 +113,20 @@ impl From<L1HandlerTransaction> for starknet_api::transaction::L1HandlerTransact
     }
 }
 
+impl From<starknet_api::transaction::L1HandlerTransaction> for L1HandlerTransaction {
+    fn from(l1_handler_tx: starknet_api::transaction::L1HandlerTransaction) -> Self {
+        Self {
+            transaction_hash: l1_handler_tx.transaction_hash,
+            version: l1_handler_tx.version,
+            nonce: l1_handler_tx.nonce,
+            contract_address: l1_handler_tx.contract_address,
+            entry_point_selector: l1_handler_tx.entry_point_selector,
+            calldata: l1_handler_tx.calldata,
+            r#type: TransactionType::L1Handler,
+        }
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
 pub struct DeclareTransaction {
     pub class_hash: ClassHash,
@@ -102,6 +138,10 @@ pub struct DeclareTransaction {
     pub transaction_hash: TransactionHash,
     pub signature: TransactionSignature,
     pub r#type: TransactionType,
+    // Only in declare transactions of version 2, of Sierra classes. The compiled class hash is
+    // kept by the state diff of the block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiled_class_hash: Option<StarkHash>,
 }
 
 impl From<DeclareTransaction> for starknet_api::transaction::DeclareTransaction {
@@ -118,6 +158,22 @@ impl From<DeclareTransaction> for starknet_api::transaction::DeclareTransaction
     }
 }
 
+impl From<starknet_api::transaction::DeclareTransaction> for DeclareTransaction {
+    fn from(declare_tx: starknet_api::transaction::DeclareTransaction) -> Self {
+        Self {
+            class_hash: declare_tx.class_hash,
+            sender_address: declare_tx.sender_address,
+            nonce: declare_tx.nonce,
+            max_fee: declare_tx.max_fee,
+            version: declare_tx.version,
+            transaction_hash: declare_tx.transaction_hash,
+            signature: declare_tx.signature,
+            r#type: TransactionType::Declare,
+            compiled_class_hash: None,
+        }
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
 pub struct DeployTransaction {
     pub contract_address: ContractAddress,
@@ -143,6 +199,20 @@ impl From<DeployTransaction> for starknet_api::transaction::DeployTransaction {
     }
 }
 
+impl From<starknet_api::transaction::DeployTransaction> for DeployTransaction {
+    fn from(deploy_tx: starknet_api::transaction::DeployTransaction) -> Self {
+        Self {
+            contract_address: deploy_tx.contract_address,
+            contract_address_salt: deploy_tx.contract_address_salt,
+            class_hash: deploy_tx.class_hash,
+            constructor_calldata: deploy_tx.constructor_calldata,
+            transaction_hash: deploy_tx.transaction_hash,
+            version: deploy_tx.version,
+            r#type: TransactionType::Deploy,
+        }
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
 pub struct DeployAccountTransaction {
     pub contract_address: ContractAddress,
@@ -174,6 +244,23 @@ impl From<DeployAccountTransaction> for starknet_api::transaction::DeployAccount
     }
 }
 
+impl From<starknet_api::transaction::DeployAccountTransaction> for DeployAccountTransaction {
+    fn from(deploy_tx: starknet_api::transaction::DeployAccountTransaction) -> Self {
+        Self {
+            contract_address: deploy_tx.contract_address,
+            contract_address_salt: deploy_tx.contract_address_salt,
+            class_hash: deploy_tx.class_hash,
+            constructor_calldata: deploy_tx.constructor_calldata,
+            nonce: deploy_tx.nonce,
+            max_fee: deploy_tx.max_fee,
+            signature: deploy_tx.signature,
+            transaction_hash: deploy_tx.transaction_hash,
+            version: deploy_tx.version,
+            r#type: TransactionType::DeployAccount,
+        }
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
 pub struct InvokeTransaction {
     pub calldata: Calldata,
@@ -203,20 +290,88 @@ impl From<InvokeTransaction> for starknet_api::transaction::InvokeTransaction {
     }
 }
 
+impl From<starknet_api::transaction::InvokeTransaction> for InvokeTransaction {
+    fn from(invoke_tx: starknet_api::transaction::InvokeTransaction) -> Self {
+        // Only the invoke transactions of version 0 have an entry point selector, and they don't
+        // have a nonce.
+        let nonce = match invoke_tx.entry_point_selector {
+            Some(_) => None,
+            None => Some(invoke_tx.nonce),
+        };
+        Self {
+            calldata: invoke_tx.calldata,
+            contract_address: invoke_tx.sender_address,
+            entry_point_selector: invoke_tx.entry_point_selector,
+            nonce,
+            max_fee: invoke_tx.max_fee,
+            signature: invoke_tx.signature,
+            transaction_hash: invoke_tx.transaction_hash,
+            version: invoke_tx.version,
+            r#type: TransactionType::InvokeFunction,
+        }
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
 pub struct TransactionReceipt {
     pub transaction_index: TransactionOffsetInBlock,
     pub transaction_hash: TransactionHash,
-    #[serde(default)]
-    pub l1_to_l2_consumed_message: L1ToL2Message,
+    // Only in the receipts of L1 handler transactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_to_l2_consumed_message: Option<L1ToL2Message>,
     pub l2_to_l1_messages: Vec<L2ToL1Message>,
     pub events: Vec<Event>,
-    #[serde(default)]
-    pub execution_resources: ExecutionResources,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_resources: Option<ExecutionResources>,
     pub actual_fee: Fee,
 }
 
 impl TransactionReceipt {
+    /// Creates the receipt of a transaction from its [`starknet_api`] output. The consumed L1 to L2
+    /// message, the execution resources and the senders of the L2 to L1 messages aren't part of
+    /// [`starknet_api`], so they are left out.
+    pub fn from_starknet_api_transaction_output(
+        transaction_index: TransactionOffsetInBlock,
+        transaction: &starknet_api::transaction::Transaction,
+        output: TransactionOutput,
+    ) -> Self {
+        let (actual_fee, messages_sent, events) = match output {
+            TransactionOutput::Declare(output) => {
+                (output.actual_fee, output.messages_sent, output.events)
+            }
+            TransactionOutput::Deploy(output) => {
+                (output.actual_fee, output.messages_sent, output.events)
+            }
+            TransactionOutput::DeployAccount(output) => {
+                (output.actual_fee, output.messages_sent, output.events)
+            }
+            TransactionOutput::Invoke(output) => {
+                (output.actual_fee, output.messages_sent, output.events)
+            }
+            TransactionOutput::L1Handler(output) => {
+                (output.actual_fee, output.messages_sent, output.events)
+            }
+        };
+        let l2_to_l1_messages = messages_sent
+            .into_iter()
+            .map(|message| L2ToL1Message {
+                from_address: None,
+                to_address: message.to_address,
+                payload: message.payload,
+            })
+            .collect();
+
+        Self {
+            transaction_index,
+            transaction_hash: transaction.transaction_hash(),
+            l1_to_l2_consumed_message: None,
+            l2_to_l1_messages,
+            events,
+            execution_resources: None,
+            actual_fee,
+        }
+    }
+
     pub fn into_starknet_api_transaction_output(
         self,
         tx_type: TransactionType,
@@ -303,7 +458,9 @@ impl From<L1ToL2Message> for starknet_api::transaction::MessageToL2 {
 
 #[derive(Debug, Default, Deserialize, Serialize, Clone, Eq, PartialEq)]
 pub struct L2ToL1Message {
-    pub from_address: ContractAddress,
+    // Left out of the messages of receipts created from [`starknet_api`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_address: Option<ContractAddress>,
     pub to_address: EthAddress,
     pub payload: L2ToL1Payload,
 }
@@ -0,0 +1,186 @@
+//! Reading and recording responses of the starknet feeder gateway from and into a local
+//! directory, so that a chain segment can be synced without network access.
+//!
+//! The directory holds a JSON file for every response, in the format the [`StarknetClient`]
+//! deserializes:
+//! * `blocks/<block number>.json`
+//! * `state_updates/<block number>.json`
+//! * `classes/<class hash>.json`
+//!
+//! [`StarknetClient`]: crate::StarknetClient
+
+#[cfg(test)]
+#[path = "offline_test.rs"]
+mod offline_test;
+
+use std::collections::BTreeSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use starknet_api::block::BlockNumber;
+use starknet_api::core::ClassHash;
+use tracing::debug;
+
+use crate::{
+    Block, ClientResult, GenericContractClass, PendingBlock, PendingStateUpdate,
+    StarknetClientTrait, StateUpdate,
+};
+
+const BLOCKS_DIR: &str = "blocks";
+const STATE_UPDATES_DIR: &str = "state_updates";
+const CLASSES_DIR: &str = "classes";
+const FILE_EXTENSION: &str = "json";
+
+/// A [`StarknetClientTrait`] implementation that reads the recorded responses from a directory.
+/// There is no pending block in the directory, and the latest block is the last one of the
+/// consecutive run of recorded blocks from the first one, so a gap in the recording ends it.
+pub struct OfflineStarknetClient {
+    dir: PathBuf,
+}
+
+impl OfflineStarknetClient {
+    /// Creates a new client that reads the responses recorded in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        OfflineStarknetClient { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl StarknetClientTrait for OfflineStarknetClient {
+    async fn block_number(&self) -> ClientResult<Option<BlockNumber>> {
+        let mut entries = match tokio::fs::read_dir(self.dir.join(BLOCKS_DIR)).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let mut block_numbers = BTreeSet::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(FILE_EXTENSION) {
+                continue;
+            }
+            if let Some(block_number) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok())
+            {
+                block_numbers.insert(block_number);
+            }
+        }
+
+        let mut block_numbers = block_numbers.into_iter();
+        let mut last_block_number = match block_numbers.next() {
+            Some(block_number) => block_number,
+            None => return Ok(None),
+        };
+        for block_number in block_numbers {
+            if block_number != last_block_number + 1 {
+                debug!(
+                    "Block {} wasn't recorded, ignoring the blocks after it.",
+                    last_block_number + 1
+                );
+                break;
+            }
+            last_block_number = block_number;
+        }
+        Ok(Some(BlockNumber(last_block_number)))
+    }
+
+    async fn block(&self, block_number: BlockNumber) -> ClientResult<Option<Block>> {
+        read_file(&block_path(&self.dir, block_number)).await
+    }
+
+    async fn class_by_hash(
+        &self,
+        class_hash: ClassHash,
+    ) -> ClientResult<Option<GenericContractClass>> {
+        read_file(&class_path(&self.dir, class_hash)?).await
+    }
+
+    async fn state_update(&self, block_number: BlockNumber) -> ClientResult<Option<StateUpdate>> {
+        read_file(&state_update_path(&self.dir, block_number)).await
+    }
+
+    async fn pending_block(&self) -> ClientResult<Option<PendingBlock>> {
+        Ok(None)
+    }
+
+    async fn pending_state_update(&self) -> ClientResult<Option<PendingStateUpdate>> {
+        Ok(None)
+    }
+}
+
+/// Records the blocks in the range `[initial_block_number, up_to_block_number)` from `client` into
+/// `dir`, with their state updates and the classes declared or deployed in them. Stops at the first
+/// block the client doesn't fully have, and returns the number of the block after the last recorded
+/// one.
+pub async fn record_responses<TStarknetClient: StarknetClientTrait + Sync>(
+    client: &TStarknetClient,
+    dir: &Path,
+    initial_block_number: BlockNumber,
+    up_to_block_number: BlockNumber,
+) -> ClientResult<BlockNumber> {
+    for sub_dir in [BLOCKS_DIR, STATE_UPDATES_DIR, CLASSES_DIR] {
+        tokio::fs::create_dir_all(dir.join(sub_dir)).await?;
+    }
+
+    for block_number in initial_block_number.iter_up_to(up_to_block_number) {
+        let (block, state_update) =
+            match (client.block(block_number).await?, client.state_update(block_number).await?) {
+                (Some(block), Some(state_update)) => (block, state_update),
+                _ => {
+                    debug!("Block {block_number} wasn't found, stopping the recording.");
+                    return Ok(block_number);
+                }
+            };
+        for class_hash in state_update.state_diff.class_hashes() {
+            let path = class_path(dir, class_hash)?;
+            if tokio::fs::metadata(&path).await.is_ok() {
+                continue;
+            }
+            match client.class_by_hash(class_hash).await? {
+                Some(class) => write_file(&path, &class).await?,
+                None => {
+                    debug!("Class {class_hash:?} wasn't found, stopping the recording.");
+                    return Ok(block_number);
+                }
+            }
+        }
+        write_file(&state_update_path(dir, block_number), &state_update).await?;
+        // The block is written last, so a recorded block always has its state update.
+        write_file(&block_path(dir, block_number), &block).await?;
+        debug!("Recorded block {block_number}.");
+    }
+    Ok(up_to_block_number)
+}
+
+fn block_path(dir: &Path, block_number: BlockNumber) -> PathBuf {
+    dir.join(BLOCKS_DIR).join(format!("{block_number}.{FILE_EXTENSION}"))
+}
+
+fn state_update_path(dir: &Path, block_number: BlockNumber) -> PathBuf {
+    dir.join(STATE_UPDATES_DIR).join(format!("{block_number}.{FILE_EXTENSION}"))
+}
+
+// The class files are named after the hex representation of the class hash, as it's serialized.
+fn class_path(dir: &Path, class_hash: ClassHash) -> ClientResult<PathBuf> {
+    let class_hash = serde_json::to_string(&class_hash)?;
+    let class_hash = class_hash.trim_matches('"');
+    Ok(dir.join(CLASSES_DIR).join(format!("{class_hash}.{FILE_EXTENSION}")))
+}
+
+// Returns None if the file doesn't exist.
+async fn read_file<T: DeserializeOwned>(path: &Path) -> ClientResult<Option<T>> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(raw) => Ok(Some(serde_json::from_str(&raw)?)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+async fn write_file<T: Serialize>(path: &Path, value: &T) -> ClientResult<()> {
+    Ok(tokio::fs::write(path, serde_json::to_string(value)?).await?)
+}
@@ -0,0 +1,84 @@
+use mockall::predicate;
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::core::{ClassHash, ContractAddress, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::{patricia_key, stark_felt};
+
+use super::{record_responses, OfflineStarknetClient};
+use crate::test_utils::read_resource::read_resource_file;
+use crate::{
+    Block, DeployedContract, GenericContractClass, MockStarknetClientTrait, StarknetClientTrait,
+    StateDiff, StateUpdate,
+};
+
+#[tokio::test]
+async fn record_and_read_responses() {
+    let dir = tempfile::tempdir().unwrap();
+    let block = Block { block_number: BlockNumber(0), ..Block::default() };
+    let state_update = StateUpdate {
+        block_hash: BlockHash(stark_felt!("0x1")),
+        state_diff: StateDiff {
+            deployed_contracts: vec![DeployedContract {
+                address: ContractAddress(patricia_key!("0x2")),
+                class_hash: ClassHash(stark_felt!("0x3")),
+            }],
+            declared_contracts: vec![ClassHash(stark_felt!("0x4"))],
+            ..StateDiff::default()
+        },
+        ..StateUpdate::default()
+    };
+    let class: GenericContractClass =
+        serde_json::from_str(&read_resource_file("contract_class.json")).unwrap();
+
+    let mut mock = MockStarknetClientTrait::new();
+    let block_clone = block.clone();
+    mock.expect_block()
+        .with(predicate::eq(BlockNumber(0)))
+        .times(1)
+        .returning(move |_| Ok(Some(block_clone.clone())));
+    mock.expect_block().with(predicate::eq(BlockNumber(1))).times(1).returning(|_| Ok(None));
+    let state_update_clone = state_update.clone();
+    mock.expect_state_update()
+        .with(predicate::eq(BlockNumber(0)))
+        .times(1)
+        .returning(move |_| Ok(Some(state_update_clone.clone())));
+    mock.expect_state_update().with(predicate::eq(BlockNumber(1))).times(1).returning(|_| Ok(None));
+    let class_clone = class.clone();
+    mock.expect_class_by_hash().times(2).returning(move |_| Ok(Some(class_clone.clone())));
+
+    let block_marker =
+        record_responses(&mock, dir.path(), BlockNumber(0), BlockNumber(3)).await.unwrap();
+    assert_eq!(block_marker, BlockNumber(1));
+
+    let offline_client = OfflineStarknetClient::new(dir.path());
+    assert_eq!(offline_client.block_number().await.unwrap(), Some(BlockNumber(0)));
+    assert_eq!(offline_client.block(BlockNumber(0)).await.unwrap(), Some(block));
+    assert!(offline_client.block(BlockNumber(1)).await.unwrap().is_none());
+    assert_eq!(offline_client.state_update(BlockNumber(0)).await.unwrap(), Some(state_update));
+    assert!(offline_client.state_update(BlockNumber(1)).await.unwrap().is_none());
+    for class_hash in [ClassHash(stark_felt!("0x3")), ClassHash(stark_felt!("0x4"))] {
+        assert_eq!(offline_client.class_by_hash(class_hash).await.unwrap(), Some(class.clone()));
+    }
+    assert!(offline_client.class_by_hash(ClassHash(stark_felt!("0x5"))).await.unwrap().is_none());
+    assert!(offline_client.pending_block().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn empty_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let offline_client = OfflineStarknetClient::new(dir.path());
+    assert!(offline_client.block_number().await.unwrap().is_none());
+    assert!(offline_client.block(BlockNumber(0)).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn gap_in_recorded_blocks() {
+    let dir = tempfile::tempdir().unwrap();
+    let blocks_dir = dir.path().join("blocks");
+    std::fs::create_dir(&blocks_dir).unwrap();
+    for block_number in [2, 3, 4, 6] {
+        std::fs::write(blocks_dir.join(format!("{block_number}.json")), "{}").unwrap();
+    }
+    let offline_client = OfflineStarknetClient::new(dir.path());
+    assert_eq!(offline_client.block_number().await.unwrap(), Some(BlockNumber(4)));
+}
@@ -1,22 +1,31 @@
 use std::collections::HashMap;
 
 use assert_matches::assert_matches;
-use mockito::mock;
+use mockito::{mock, Matcher};
 use reqwest::StatusCode;
 use starknet_api::block::BlockNumber;
 use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector, Nonce, PatriciaKey};
 use starknet_api::hash::{StarkFelt, StarkHash};
 use starknet_api::state::{EntryPoint, EntryPointOffset, EntryPointType, Program};
-use starknet_api::transaction::{Fee, TransactionHash, TransactionSignature, TransactionVersion};
+use starknet_api::transaction::{
+    Calldata, Fee, TransactionHash, TransactionSignature, TransactionVersion,
+};
 use starknet_api::{patricia_key, stark_felt};
 
-use super::objects::block::{ContractClass, StateUpdate};
+use super::objects::add_transaction::{
+    AddInvokeTransaction, AddTransaction, AddTransactionResponse,
+};
+use super::objects::block::{
+    ContractClass, GenericContractClass, PendingBlock, PendingStateUpdate, SierraContractClass,
+    SierraEntryPoint, StateUpdate,
+};
 use super::objects::transaction::{DeclareTransaction, TransactionType};
 use super::test_utils::read_resource::read_resource_file;
 use super::test_utils::retry::get_test_config;
 use super::{
-    Block, ClientError, RetryErrorCode, StarknetClient, StarknetClientTrait, BLOCK_NUMBER_QUERY,
-    CLASS_HASH_QUERY, GET_BLOCK_URL, GET_STATE_UPDATE_URL,
+    Block, ClientError, RetryErrorCode, StarknetClient, StarknetClientTrait, StarknetError,
+    StarknetErrorCode, ADD_TRANSACTION_URL, BLOCK_NUMBER_QUERY, CLASS_HASH_QUERY, GET_BLOCK_URL,
+    GET_STATE_UPDATE_URL, PENDING_BLOCK_ID,
 };
 
 #[test]
@@ -31,6 +40,10 @@ fn new_urls() {
         starknet_client.urls.get_state_update.as_str(),
         url_base_str.to_string() + "/" + GET_STATE_UPDATE_URL
     );
+    assert_eq!(
+        starknet_client.urls.add_transaction.as_str(),
+        url_base_str.to_string() + "/" + ADD_TRANSACTION_URL
+    );
 }
 
 #[tokio::test]
@@ -71,6 +84,7 @@ async fn declare_tx_serde() {
         )),
         signature: TransactionSignature(vec![]),
         r#type: TransactionType::Declare,
+        compiled_class_hash: None,
     };
     let raw_declare_tx = serde_json::to_string(&declare_tx).unwrap();
     assert_eq!(declare_tx, serde_json::from_str(&raw_declare_tx).unwrap());
@@ -160,15 +174,16 @@ async fn contract_class() {
             ),
         ]),
     };
-    let mock_by_hash =
-        mock(
-            "GET",
-            &format!("/feeder_gateway/get_class_by_hash?\
-         {CLASS_HASH_QUERY}=0x7af612493193c771c1b12f511a8b4d3b0c6d0648242af4680c7cd0d06186f17")[..],
-        )
-        .with_status(200)
-        .with_body(read_resource_file("contract_class.json"))
-        .create();
+    let mock_by_hash = mock(
+        "GET",
+        &format!(
+            "/feeder_gateway/get_class_by_hash?\
+         {CLASS_HASH_QUERY}=0x7af612493193c771c1b12f511a8b4d3b0c6d0648242af4680c7cd0d06186f17"
+        )[..],
+    )
+    .with_status(200)
+    .with_body(read_resource_file("contract_class.json"))
+    .create();
     let contract_class = starknet_client
         .class_by_hash(ClassHash(stark_felt!(
             "0x7af612493193c771c1b12f511a8b4d3b0c6d0648242af4680c7cd0d06186f17"
@@ -176,7 +191,44 @@ async fn contract_class() {
         .await
         .unwrap();
     mock_by_hash.assert();
-    assert_eq!(contract_class.unwrap(), expected_contract_class);
+    assert_eq!(
+        contract_class.unwrap(),
+        GenericContractClass::Cairo0ContractClass(expected_contract_class)
+    );
+
+    // Sierra class.
+    let expected_sierra_class = SierraContractClass {
+        sierra_program: vec![stark_felt!("0x302e312e30"), stark_felt!("0x1b"), stark_felt!("0x2")],
+        contract_class_version: "0.1.0".to_owned(),
+        entry_points_by_type: HashMap::from([
+            (EntryPointType::Constructor, vec![]),
+            (
+                EntryPointType::External,
+                vec![SierraEntryPoint {
+                    function_idx: 0,
+                    selector: EntryPointSelector(stark_felt!(
+                        "0x362398bec32bc0ebb411203221a35a0301193a96f317ebe5e40be9f60d15320"
+                    )),
+                }],
+            ),
+            (EntryPointType::L1Handler, vec![]),
+        ]),
+        abi: "[{\"type\": \"function\", \"name\": \"increase_balance\", \"inputs\": [{\"name\": \
+              \"amount\", \"type\": \"core::felt252\"}], \"outputs\": [], \"state_mutability\": \
+              \"external\"}]"
+            .to_owned(),
+    };
+    let mock_by_hash =
+        mock("GET", &format!("/feeder_gateway/get_class_by_hash?{CLASS_HASH_QUERY}=0x8")[..])
+            .with_status(200)
+            .with_body(read_resource_file("sierra_contract_class.json"))
+            .create();
+    let sierra_class = starknet_client.class_by_hash(ClassHash(stark_felt!("0x8"))).await.unwrap();
+    mock_by_hash.assert();
+    assert_eq!(
+        sierra_class.unwrap(),
+        GenericContractClass::Cairo1ContractClass(expected_sierra_class)
+    );
 
     // Undeclared class.
     let body = r#"{"code": "StarknetErrorCode.UNDECLARED_CLASS", "message": "Class with hash 0x7 is not declared."}"#;
@@ -216,6 +268,56 @@ async fn get_block() {
     assert!(block.is_none());
 }
 
+#[tokio::test]
+async fn get_pending_block() {
+    let starknet_client =
+        StarknetClient::new(&mockito::server_url(), None, get_test_config()).unwrap();
+    let raw_block = read_resource_file("pending_block.json");
+    let mock_block = mock(
+        "GET",
+        &format!("/feeder_gateway/get_block?{BLOCK_NUMBER_QUERY}={PENDING_BLOCK_ID}")[..],
+    )
+    .with_status(200)
+    .with_body(&raw_block)
+    .create();
+    let block = starknet_client.pending_block().await.unwrap().unwrap();
+    mock_block.assert();
+    let expected_block: PendingBlock = serde_json::from_str(&raw_block).unwrap();
+    assert_eq!(block, expected_block);
+
+    // No pending block.
+    let body = r#"{"code": "StarknetErrorCode.BLOCK_NOT_FOUND", "message": "Block pending was not found."}"#;
+    let mock_no_block = mock(
+        "GET",
+        &format!("/feeder_gateway/get_block?{BLOCK_NUMBER_QUERY}={PENDING_BLOCK_ID}")[..],
+    )
+    .with_status(500)
+    .with_body(body)
+    .create();
+    let block = starknet_client.pending_block().await.unwrap();
+    mock_no_block.assert();
+    assert!(block.is_none());
+}
+
+#[tokio::test]
+async fn pending_state_update() {
+    let starknet_client =
+        StarknetClient::new(&mockito::server_url(), None, get_test_config()).unwrap();
+    let raw_state_update = read_resource_file("pending_state_update.json");
+    let mock = mock(
+        "GET",
+        &format!("/feeder_gateway/get_state_update?{BLOCK_NUMBER_QUERY}={PENDING_BLOCK_ID}")[..],
+    )
+    .with_status(200)
+    .with_body(&raw_state_update)
+    .create();
+    let state_update = starknet_client.pending_state_update().await.unwrap();
+    mock.assert();
+    let expected_state_update: PendingStateUpdate =
+        serde_json::from_str(&raw_state_update).unwrap();
+    assert_eq!(state_update.unwrap(), expected_state_update);
+}
+
 #[tokio::test]
 async fn block_unserializable() {
     let starknet_client =
@@ -251,3 +353,52 @@ async fn retry_error_codes() {
         mock.assert();
     }
 }
+
+#[tokio::test]
+async fn add_transaction() {
+    let starknet_client =
+        StarknetClient::new(&mockito::server_url(), None, get_test_config()).unwrap();
+    let tx = AddTransaction::Invoke(AddInvokeTransaction {
+        sender_address: ContractAddress(patricia_key!("0x1")),
+        calldata: Calldata(vec![stark_felt!("0x2")].into()),
+        max_fee: Fee(100),
+        signature: TransactionSignature(vec![stark_felt!("0x3")]),
+        nonce: Nonce(stark_felt!("0x4")),
+        version: TransactionVersion(stark_felt!("0x1")),
+    });
+
+    // The transaction is accepted.
+    let expected_response = AddTransactionResponse {
+        code: "TRANSACTION_RECEIVED".to_owned(),
+        transaction_hash: TransactionHash(stark_felt!("0x5")),
+        ..AddTransactionResponse::default()
+    };
+    let mock_accepted = mock("POST", "/gateway/add_transaction")
+        .match_body(Matcher::PartialJson(serde_json::json!({"type": "INVOKE_FUNCTION"})))
+        .with_status(200)
+        .with_body(r#"{"code": "TRANSACTION_RECEIVED", "transaction_hash": "0x5"}"#)
+        .create();
+    let response = starknet_client.add_transaction(&tx).await.unwrap();
+    mock_accepted.assert();
+    assert_eq!(response, expected_response);
+
+    drop(mock_accepted);
+
+    // The transaction is rejected, and isn't sent again.
+    let body = r#"{"code": "StarknetErrorCode.INVALID_TRANSACTION_NONCE",
+        "message": "Invalid transaction nonce."}"#;
+    let mock_rejected = mock("POST", "/gateway/add_transaction")
+        .with_status(400)
+        .with_body(body)
+        .expect(1)
+        .create();
+    let error = starknet_client.add_transaction(&tx).await.unwrap_err();
+    mock_rejected.assert();
+    assert_matches!(
+        error,
+        ClientError::StarknetError(StarknetError {
+            code: StarknetErrorCode::InvalidTransactionNonce,
+            message: _,
+        })
+    );
+}